@@ -0,0 +1,37 @@
+//! The history of the instructions retired by the debugged hart.
+
+use brisc_emu::observer::Observer;
+use brisc_hw::{pipeline::PipelineRegister, DecodedInstruction, XWord, Xlen};
+use std::collections::VecDeque;
+
+/// The number of retired instructions kept by a [History].
+pub(crate) const HISTORY_LEN: usize = 256;
+
+/// An [Observer] keeping the last [HISTORY_LEN] instructions retired by the hart.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct History {
+    /// The program counter and decoding of the retired instructions, oldest first.
+    retired: VecDeque<(XWord, DecodedInstruction)>,
+}
+
+impl History {
+    /// Returns the last `count` retired instructions, oldest first.
+    pub(crate) fn last(
+        &self,
+        count: usize,
+    ) -> impl Iterator<Item = &(XWord, DecodedInstruction)> + '_ {
+        self.retired.iter().skip(self.retired.len().saturating_sub(count))
+    }
+}
+
+impl<X: Xlen> Observer<X> for History {
+    fn on_retire(&mut self, p_reg: &PipelineRegister<X>) {
+        let Some(decoded) = p_reg.instruction else {
+            return;
+        };
+        if self.retired.len() == HISTORY_LEN {
+            self.retired.pop_front();
+        }
+        self.retired.push_back((p_reg.pc, decoded));
+    }
+}
@@ -0,0 +1,740 @@
+//! An interactive debugger, driving a [StEmu] with commands read line by line.
+//!
+//! Commands are read from any [BufRead] and their output is written to any [Write], so that a
+//! session can be scripted. An empty line repeats the last command.
+
+use brisc_emu::{
+    cfg::EmuConfig,
+    st::{StEmu, StopReason},
+};
+use brisc_hw::{
+    memory::Memory, DisplayOptions, Instruction, XWord, Xlen, REGISTER_ABI_NAMES, REG_RA,
+    REG_S0_FP, REG_SP,
+};
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use thiserror::Error;
+
+mod history;
+pub(crate) use history::History;
+
+/// The prompt printed before every command.
+const PROMPT: &str = "(brisc) ";
+
+/// The help message printed by `help`.
+const HELP: &str = "\
+Commands:
+  s, step [n]              Execute n instructions (default: 1), stepping into calls
+  n, next [n]              Execute n instructions (default: 1), stepping over calls
+  c, continue              Run until a breakpoint, an ebreak, a trap, the exit or Ctrl-C
+  b, break [location]      Set a breakpoint, or list the breakpoints
+  d, delete [location]     Delete a breakpoint, or all of them
+  r, regs [register...]    Print the program counter and the named or all registers
+  set <register> <value>   Write a register
+  x <address> [length]     Hexdump memory (default: 64 bytes, at most 64 KiB)
+  disas [address] [count]  Disassemble instructions (default: 8, at the pc, at most 4096)
+  bt, backtrace            Print the call stack, reconstructed from ra and fp
+  hist, history [n]        Print the last n retired instructions (default: 10)
+  h, help                  Print this message
+  q, quit                  Quit the debugger
+
+Values are registers (a0, x10, pc), symbols, or decimal or 0x-prefixed hex numbers, optionally
+followed by +offset or -offset.
+";
+
+/// The maximum number of frames printed by `backtrace`.
+const MAX_FRAMES: usize = 64;
+
+/// The maximum number of bytes dumped by `x`.
+const MAX_HEXDUMP: XWord = 0x10000;
+
+/// The maximum number of instructions disassembled by `disas`.
+const MAX_DISASSEMBLY: XWord = 0x1000;
+
+/// Whether the session goes on after a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Flow {
+    /// Read the next command.
+    Continue,
+    /// End the session.
+    Quit,
+}
+
+/// An error raised by a debugger command.
+#[derive(Error, Debug)]
+enum CommandError {
+    /// The command is not known.
+    #[error("Unknown command `{0}`, try `help`")]
+    Unknown(String),
+    /// The command was given the wrong arguments.
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    /// A value could not be evaluated.
+    #[error("Cannot evaluate `{0}`")]
+    Value(String),
+    /// A register name is not known, or the register cannot be written.
+    #[error("Invalid register `{0}`")]
+    Register(String),
+    /// There is no breakpoint at the address.
+    #[error("No breakpoint at {0:#x}")]
+    NoBreakpoint(XWord),
+    /// The output could not be written.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// A register named in a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    /// The integer register with the given number.
+    X(usize),
+    /// The program counter.
+    Pc,
+}
+
+/// An interactive debugging session of a [StEmu], which records its retired instructions in a
+/// [History].
+pub(crate) struct Session<Config: EmuConfig<Observer = History>> {
+    /// The debugged hart.
+    pub(crate) emu: StEmu<Config>,
+    /// The function symbols of the program, by address.
+    symbols: BTreeMap<XWord, String>,
+    /// The last command run, repeated by an empty line.
+    last_command: String,
+    /// Set to interrupt a `continue`, as by the Ctrl-C handler of the session.
+    interrupt: Arc<AtomicBool>,
+}
+
+impl<Config: EmuConfig<Observer = History>> Session<Config> {
+    /// Creates a new [Session] debugging `emu`, which stops on guest `ebreak` instructions, with
+    /// the function `symbols` of its program.
+    pub(crate) fn new(mut emu: StEmu<Config>, symbols: BTreeMap<XWord, String>) -> Self {
+        emu.debugger.stop_on_ebreak = true;
+        Self { emu, symbols, last_command: String::new(), interrupt: Arc::default() }
+    }
+
+    /// Returns the flag that interrupts a `continue` once set, to be set by a Ctrl-C handler.
+    pub(crate) fn interrupt(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.interrupt)
+    }
+
+    /// Runs the commands read from `input` until it ends or a `quit` command, prompting for each.
+    pub(crate) fn repl(
+        &mut self,
+        mut input: impl BufRead,
+        output: &mut impl Write,
+    ) -> io::Result<()> {
+        self.print_instruction(self.emu.register.pc, output)?;
+        let mut line = String::new();
+        loop {
+            write!(output, "{PROMPT}")?;
+            output.flush()?;
+            line.clear();
+            if input.read_line(&mut line)? == 0 || self.execute(&line, output)? == Flow::Quit {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Runs a single command line, writing its output and any error to `output`.
+    pub(crate) fn execute(&mut self, line: &str, output: &mut impl Write) -> io::Result<Flow> {
+        let line = match line.trim() {
+            "" => self.last_command.clone(),
+            line => {
+                self.last_command = line.to_string();
+                self.last_command.clone()
+            }
+        };
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(Flow::Continue);
+        };
+        let args = words.collect::<Vec<_>>();
+
+        let result = match command {
+            "s" | "step" => self.step(&args, false, output),
+            "n" | "next" => self.step(&args, true, output),
+            "c" | "continue" => self.resume(output),
+            "b" | "break" => self.set_breakpoint(&args, output),
+            "d" | "delete" => self.delete_breakpoint(&args),
+            "r" | "regs" => self.print_registers(&args, output),
+            "set" => self.set_register(&args),
+            "x" => self.hexdump(&args, output),
+            "disas" => self.disassemble(&args, output),
+            "bt" | "backtrace" => self.backtrace(output),
+            "hist" | "history" => self.history(&args, output),
+            "h" | "help" => output.write_all(HELP.as_bytes()).map_err(Into::into),
+            "q" | "quit" => return Ok(Flow::Quit),
+            _ => Err(CommandError::Unknown(command.to_string())),
+        };
+        match result {
+            Ok(()) => {}
+            Err(CommandError::Io(e)) => return Err(e),
+            Err(e) => writeln!(output, "error: {e}")?,
+        }
+        Ok(Flow::Continue)
+    }
+
+    /// Executes `n` instructions, running each call to its return if `over` is set.
+    fn step(&mut self, args: &[&str], over: bool, output: &mut impl Write) -> CommandResult {
+        let count = match args {
+            [] => 1,
+            [count] => self.evaluate(count)?,
+            _ => return Err(CommandError::Usage("step [n]")),
+        };
+
+        let mut reason = StopReason::BudgetExhausted;
+        for _ in 0..count {
+            reason = match self.call_return() {
+                Some(ret) if over => {
+                    // The call has returned once the stack is back to its depth at the call.
+                    let sp = self.emu.register.registers[REG_SP as usize];
+                    self.emu
+                        .run_until(|emu| {
+                            emu.register.pc == ret && emu.register.registers[REG_SP as usize] >= sp
+                        })
+                        .reason
+                }
+                _ => self.emu.run_for(1).reason,
+            };
+            if !matches!(reason, StopReason::BudgetExhausted | StopReason::Predicate) {
+                break;
+            }
+        }
+        self.report(reason, output)
+    }
+
+    /// Runs until a breakpoint, a guest `ebreak`, a watchpoint, a trap, the exit or an interrupt.
+    fn resume(&mut self, output: &mut impl Write) -> CommandResult {
+        let interrupt = self.interrupt();
+        interrupt.store(false, Ordering::Relaxed);
+        let reason = self.emu.run_until(|_| interrupt.load(Ordering::Relaxed)).reason;
+        if matches!(reason, StopReason::Predicate) {
+            writeln!(output, "Interrupted")?;
+        }
+        self.report(reason, output)
+    }
+
+    /// Returns the return address of the instruction at the program counter, if it is a call.
+    fn call_return(&self) -> Option<XWord> {
+        let pc = self.emu.register.pc;
+        let decoded = self.emu.isa.decode(self.emu.memory.get_word(pc).ok()?).ok()?;
+        let call = matches!(decoded.instruction, Instruction::Jal(_) | Instruction::Jalr(_)) &&
+            decoded.instruction.rd() == Some(REG_RA as u8);
+        call.then(|| Config::Xlen::truncate(pc.wrapping_add(decoded.size() as XWord)))
+    }
+
+    /// Writes the reason that a run stopped, followed by the instruction at the program counter.
+    fn report(&self, reason: StopReason, output: &mut impl Write) -> CommandResult {
+        match reason {
+            StopReason::Exited(code) => {
+                writeln!(output, "Program exited with code {}", Config::Xlen::signed(code))?;
+                return Ok(());
+            }
+            StopReason::Breakpoint(pc) => writeln!(output, "Breakpoint at {}", self.location(pc))?,
+            StopReason::Ebreak(pc) => writeln!(output, "ebreak at {}", self.location(pc))?,
+            StopReason::Watchpoint(hit) => writeln!(
+                output,
+                "Watchpoint {} hit by a {}-byte {} at {}",
+                hit.id.0,
+                hit.access.bytes,
+                if hit.access.write { "write" } else { "read" },
+                self.address(hit.access.address)
+            )?,
+            StopReason::Trap(e) => writeln!(output, "Trap: {e}")?,
+            StopReason::BudgetExhausted | StopReason::Predicate => {}
+        }
+        self.print_instruction(self.emu.register.pc, output)?;
+        Ok(())
+    }
+
+    /// Sets a breakpoint at the location in `args`, or lists the breakpoints.
+    fn set_breakpoint(&mut self, args: &[&str], output: &mut impl Write) -> CommandResult {
+        match args {
+            [] => {
+                for pc in self.emu.debugger.breakpoints() {
+                    writeln!(output, "Breakpoint at {}", self.location(pc))?;
+                }
+            }
+            [location] => {
+                let pc = self.evaluate(location)?;
+                self.emu.debugger.set_breakpoint(pc);
+                writeln!(output, "Breakpoint at {}", self.location(pc))?;
+            }
+            _ => return Err(CommandError::Usage("break [location]")),
+        }
+        Ok(())
+    }
+
+    /// Deletes the breakpoint at the location in `args`, or all of them.
+    fn delete_breakpoint(&mut self, args: &[&str]) -> CommandResult {
+        match args {
+            [] => {
+                let debugger = &mut self.emu.debugger;
+                for pc in debugger.breakpoints().collect::<Vec<_>>() {
+                    debugger.clear_breakpoint(pc);
+                }
+            }
+            [location] => {
+                let pc = self.evaluate(location)?;
+                if !self.emu.debugger.clear_breakpoint(pc) {
+                    return Err(CommandError::NoBreakpoint(pc));
+                }
+            }
+            _ => return Err(CommandError::Usage("delete [location]")),
+        }
+        Ok(())
+    }
+
+    /// Prints the registers named in `args`, or the program counter and every register.
+    fn print_registers(&self, args: &[&str], output: &mut impl Write) -> CommandResult {
+        let registers = if args.is_empty() {
+            [Register::Pc].into_iter().chain((0..32).map(Register::X)).collect()
+        } else {
+            args.iter().map(|name| parse_register(name)).collect::<Result<Vec<_>, _>>()?
+        };
+        for register in registers {
+            let (name, value) = match register {
+                Register::Pc => ("pc", self.emu.register.pc),
+                Register::X(x) => (REGISTER_ABI_NAMES[x], self.emu.register.registers[x]),
+            };
+            writeln!(output, "{name:<4} {}", self.address(value))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the register named by the first argument with the value of the second.
+    fn set_register(&mut self, args: &[&str]) -> CommandResult {
+        let [name, value] = args else {
+            return Err(CommandError::Usage("set <register> <value>"));
+        };
+        let value = self.evaluate(value)?;
+        match parse_register(name)? {
+            Register::Pc => self.emu.register.pc = value,
+            Register::X(0) => return Err(CommandError::Register(name.to_string())),
+            Register::X(x) => self.emu.register.registers[x] = value,
+        }
+        Ok(())
+    }
+
+    /// Prints the memory at the address in `args` as hex and ASCII, 16 bytes per line, up to
+    /// [MAX_HEXDUMP] bytes.
+    fn hexdump(&self, args: &[&str], output: &mut impl Write) -> CommandResult {
+        let (address, length) = match args {
+            [address] => (self.evaluate(address)?, 64),
+            [address, length] => (self.evaluate(address)?, self.evaluate(length)?),
+            _ => return Err(CommandError::Usage("x <address> [length]")),
+        };
+
+        for line in (0..length.min(MAX_HEXDUMP)).step_by(16) {
+            let chunk = (line..length.min(line + 16))
+                .map(|offset| self.read_byte(address.wrapping_add(offset)))
+                .collect::<Vec<_>>();
+            let hex = chunk.iter().map(|byte| format!("{byte:02x}")).collect::<Vec<_>>();
+            let ascii = chunk
+                .iter()
+                .map(
+                    |&byte| {
+                        if byte.is_ascii_graphic() || byte == b' ' {
+                            byte as char
+                        } else {
+                            '.'
+                        }
+                    },
+                )
+                .collect::<String>();
+            let line_address = address.wrapping_add(line);
+            writeln!(output, "{}: {:<47}  |{ascii}|", self.address(line_address), hex.join(" "))?;
+        }
+        if length > MAX_HEXDUMP {
+            writeln!(output, "(truncated to {MAX_HEXDUMP} bytes)")?;
+        }
+        Ok(())
+    }
+
+    /// Disassembles the instructions at the address in `args`, or at the program counter, up to
+    /// [MAX_DISASSEMBLY] instructions.
+    fn disassemble(&self, args: &[&str], output: &mut impl Write) -> CommandResult {
+        let (mut address, count) = match args {
+            [] => (self.emu.register.pc, 8),
+            [address] => (self.evaluate(address)?, 8),
+            [address, count] => (self.evaluate(address)?, self.evaluate(count)?),
+            _ => return Err(CommandError::Usage("disas [address] [count]")),
+        };
+        for _ in 0..count.min(MAX_DISASSEMBLY) {
+            address = self.print_instruction(address, output)?;
+        }
+        if count > MAX_DISASSEMBLY {
+            writeln!(output, "(truncated to {MAX_DISASSEMBLY} instructions)")?;
+        }
+        Ok(())
+    }
+
+    /// Prints the call stack, reconstructed from `ra` and the frame records that `fp` points to.
+    ///
+    /// A frame record holds the return address at `fp - XLEN / 8` and the caller's frame pointer
+    /// at `fp - 2 * XLEN / 8`. A function stopped before it saves its frame record, or one that
+    /// does not save it, still has its return address in `ra`, which is used as the caller's frame
+    /// unless it is the saved return address or lies within the current function.
+    fn backtrace(&self, output: &mut impl Write) -> CommandResult {
+        let word = (Config::Xlen::X_LEN / 8) as XWord;
+        let registers = &self.emu.register.registers;
+        let (pc, ra) = (self.emu.register.pc, registers[REG_RA as usize]);
+        let mut fp = registers[REG_S0_FP as usize];
+
+        let mut frames = vec![pc];
+        let saved_ra = (fp != 0).then(|| self.read_word(fp.wrapping_sub(word)));
+        if ra != 0 && saved_ra != Some(ra) && self.function(ra) != self.function(pc) {
+            frames.push(ra);
+        }
+        while fp != 0 && fp.is_multiple_of(word) && frames.len() < MAX_FRAMES {
+            let (ret, caller_fp) =
+                (self.read_word(fp.wrapping_sub(word)), self.read_word(fp.wrapping_sub(2 * word)));
+            if ret == 0 {
+                break;
+            }
+            frames.push(ret);
+            // The stack grows down, so the caller's frame is above this one.
+            if caller_fp <= fp {
+                break;
+            }
+            fp = caller_fp;
+        }
+
+        for (depth, frame) in frames.into_iter().enumerate() {
+            writeln!(output, "#{depth:<2} {}", self.location(frame))?;
+        }
+        Ok(())
+    }
+
+    /// Prints the last retired instructions, oldest first.
+    fn history(&self, args: &[&str], output: &mut impl Write) -> CommandResult {
+        let count = match args {
+            [] => 10,
+            [count] => self.evaluate(count)? as usize,
+            _ => return Err(CommandError::Usage("history [n]")),
+        };
+        for &(pc, decoded) in self.emu.observer.last(count) {
+            let text = decoded.instruction.disassemble(DisplayOptions::new().with_pc(pc));
+            writeln!(output, "   {}:  {text}", self.location(pc))?;
+        }
+        Ok(())
+    }
+
+    /// Prints the instruction at `address`, marking the program counter, and returns the address
+    /// of the next one.
+    fn print_instruction(&self, address: XWord, output: &mut impl Write) -> io::Result<XWord> {
+        let marker = if address == self.emu.register.pc { "=>" } else { "  " };
+        let raw = (0..4)
+            .rev()
+            .fold(0, |raw, i| raw << 8 | self.read_byte(address.wrapping_add(i)) as u32);
+        let (size, text) = match self.emu.isa.decode(raw) {
+            Ok(decoded) => (
+                decoded.size(),
+                decoded.instruction.disassemble(DisplayOptions::new().with_pc(address)).to_string(),
+            ),
+            Err(e) => (if raw & 0b11 == 0b11 { 4 } else { 2 }, format!("<{e}>")),
+        };
+        let encoding = match size {
+            2 => format!("{:04x}    ", raw & 0xffff),
+            _ => format!("{raw:08x}"),
+        };
+        writeln!(output, "{marker} {}:  {encoding}  {text}", self.location(address))?;
+        Ok(Config::Xlen::truncate(address.wrapping_add(size as XWord)))
+    }
+
+    /// Evaluates a value: a register, a symbol or a number, with an optional offset.
+    fn evaluate(&self, value: &str) -> Result<XWord, CommandError> {
+        let error = || CommandError::Value(value.to_string());
+        let (base, offset) = match value.char_indices().skip(1).find(|&(_, c)| c == '+' || c == '-')
+        {
+            Some((at, sign)) => {
+                let offset = parse_number(&value[at + 1..]).ok_or_else(error)?;
+                (&value[..at], if sign == '+' { offset } else { offset.wrapping_neg() })
+            }
+            None => (value, 0),
+        };
+
+        let base = if let Some(number) = parse_number(base) {
+            number
+        } else if let Ok(register) = parse_register(base) {
+            match register {
+                Register::Pc => self.emu.register.pc,
+                Register::X(x) => self.emu.register.registers[x],
+            }
+        } else {
+            let symbol = self.symbols.iter().find(|(_, name)| *name == base);
+            *symbol.ok_or_else(error)?.0
+        };
+        Ok(Config::Xlen::truncate(base.wrapping_add(offset)))
+    }
+
+    /// Returns the name and address of the function containing `address`, if any.
+    fn function(&self, address: XWord) -> Option<(&XWord, &String)> {
+        self.symbols.range(..=address).next_back()
+    }
+
+    /// Renders `address` with the function that contains it, as in `0x00010078 <main+8>`.
+    fn location(&self, address: XWord) -> String {
+        match self.function(address) {
+            Some((start, name)) if *start == address => {
+                format!("{} <{name}>", self.address(address))
+            }
+            Some((start, name)) => {
+                format!("{} <{name}+{}>", self.address(address), address - start)
+            }
+            None => self.address(address),
+        }
+    }
+
+    /// Renders `address` in hex, padded to the register width.
+    fn address(&self, address: XWord) -> String {
+        format!("{address:#0width$x}", width = Config::Xlen::X_LEN / 4 + 2)
+    }
+
+    /// Reads the byte at `address`.
+    fn read_byte(&self, address: XWord) -> u8 {
+        self.emu.memory.get_byte(Config::Xlen::truncate(address)).unwrap_or_default()
+    }
+
+    /// Reads the little-endian register-sized word at `address`.
+    fn read_word(&self, address: XWord) -> XWord {
+        (0..(Config::Xlen::X_LEN / 8) as XWord)
+            .rev()
+            .fold(0, |word, i| word << 8 | self.read_byte(address.wrapping_add(i)) as XWord)
+    }
+}
+
+/// The result of a debugger command.
+type CommandResult = Result<(), CommandError>;
+
+/// Parses a register name: an ABI name, `fp`, `x0` to `x31` or `pc`.
+fn parse_register(name: &str) -> Result<Register, CommandError> {
+    let x = match name {
+        "pc" => return Ok(Register::Pc),
+        "fp" => Some(REG_S0_FP as usize),
+        _ => REGISTER_ABI_NAMES
+            .iter()
+            .position(|abi| *abi == name)
+            .or_else(|| name.strip_prefix('x')?.parse::<usize>().ok().filter(|&x| x < 32)),
+    };
+    x.map(Register::X).ok_or_else(|| CommandError::Register(name.to_string()))
+}
+
+/// Parses a decimal or `0x`-prefixed hex number.
+fn parse_number(number: &str) -> Option<XWord> {
+    number
+        .strip_prefix("0x")
+        .map_or_else(|| number.parse().ok(), |hex| XWord::from_str_radix(hex, 16).ok())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{History, Session, MAX_DISASSEMBLY, MAX_HEXDUMP};
+    use crate::kernel::HostConfig;
+    use brisc_emu::{elf::function_symbols, st::StEmu, test_utils::loaded};
+    use brisc_hw::{NativeXlen, XWord, Xlen};
+    use brisc_isa::asm::{assemble, Program};
+    use std::{
+        io::Cursor,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    /// The size of a register, in bytes.
+    const WORD: usize = NativeXlen::X_LEN / 8;
+
+    /// Returns a program in which `main` saves a frame record and calls `leaf`, returning 6.
+    fn program() -> Program {
+        let (store, load) = match WORD {
+            4 => ("sw", "lw"),
+            8 => ("sd", "ld"),
+            _ => ("sq", "lq"),
+        };
+        let frame = 2 * WORD;
+        assemble(&format!(
+            r#"
+            .data
+            .align 4
+            stack: .zero 256
+            stack_top:
+
+            .text
+            .globl _start
+            _start:
+                la sp, stack_top
+                li s0, 0
+                call main
+                li a7, 93
+                ecall
+            main:
+                addi sp, sp, -{frame}
+                {store} ra, {WORD}(sp)
+                {store} s0, 0(sp)
+                addi s0, sp, {frame}
+                li a0, 5
+                call leaf
+                {load} ra, {WORD}(sp)
+                {load} s0, 0(sp)
+                addi sp, sp, {frame}
+                ret
+            leaf:
+                addi a0, a0, 1
+                ret
+            "#
+        ))
+        .unwrap()
+    }
+
+    /// Returns a session debugging `program`.
+    fn session(program: &Program) -> Session<HostConfig<NativeXlen, History>> {
+        let elf = program.to_elf();
+        let emu = loaded::<StEmu<_>, HostConfig<NativeXlen, History>>(&elf).build();
+        Session::new(emu, function_symbols(&elf).unwrap())
+    }
+
+    /// Runs the `script` of commands against `session`, and returns the output.
+    fn run_session(session: &mut Session<HostConfig<NativeXlen, History>>, script: &str) -> String {
+        let mut output = Vec::new();
+        session.repl(Cursor::new(script), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    /// Runs the `script` of commands against a session debugging `program`, and returns the
+    /// output.
+    fn run(program: &Program, script: &str) -> String {
+        run_session(&mut session(program), script)
+    }
+
+    /// Renders `address` as the session does.
+    fn address(address: XWord) -> String {
+        format!("{address:#0width$x}", width = NativeXlen::X_LEN / 4 + 2)
+    }
+
+    #[test]
+    fn test_breakpoints_and_inspection() {
+        let program = program();
+        let symbol = |name| program.symbol(name).unwrap().address;
+        let (main, leaf) = (symbol("main"), symbol("leaf"));
+        let output = run(
+            &program,
+            "b leaf\nc\nbt\nr a0 pc\nset a0 41\ns\nr a0\nhist 2\nx main 4\ndisas main 2\nd leaf\nd \
+             leaf\nfoo\nq\nr\n",
+        );
+
+        // The session starts at the entry point, and runs to the breakpoint.
+        assert!(output.starts_with(&format!("=> {} <_start>:", address(program.entry))));
+        assert!(output.contains(&format!(
+            "Breakpoint at {} <leaf>\n=> {} <leaf>:",
+            address(leaf),
+            address(leaf)
+        )));
+
+        // `leaf` has no frame record, so its caller is found from `ra`, and the rest of the stack
+        // from the frame record of `main`.
+        let frames = output
+            .lines()
+            .map(|line| line.trim_start_matches("(brisc) "))
+            .filter(|line| line.starts_with('#'))
+            .collect::<Vec<_>>();
+        assert_eq!(frames.len(), 3, "{output}");
+        assert!(frames[0].ends_with("<leaf>"));
+        assert!(frames[1].ends_with(&format!("<main+{}>", 4 * 4 + 4 + 8)), "{frames:?}");
+        assert!(frames[2].ends_with("<_start+20>"), "{frames:?}");
+
+        assert!(output.contains(&format!("a0   {}\npc   {}", address(5), address(leaf))));
+        assert!(output.contains(&format!("a0   {}", address(42))));
+        let history = format!("{} <leaf>:  addi a0, a0, 1\n(brisc) ", address(leaf));
+        assert!(output.contains(&history), "{output}");
+
+        // Memory is dumped and disassembled from symbols.
+        let text = &program.text[(main - program.text_base) as usize..][..4];
+        let hex = text.iter().map(|byte| format!("{byte:02x}")).collect::<Vec<_>>().join(" ");
+        assert!(output.contains(&format!("{}: {hex} ", address(main))));
+        let disassembly = format!(
+            "   {} <main>:  {:02x}{:02x}{:02x}{:02x}  addi sp, sp, -{}",
+            address(main),
+            text[3],
+            text[2],
+            text[1],
+            text[0],
+            2 * WORD
+        );
+        assert!(output.contains(&disassembly), "{output}");
+        assert!(output.contains(&format!("   {} <main+4>:", address(main + 4))));
+
+        assert!(output.contains(&format!("error: No breakpoint at {leaf:#x}")));
+        assert!(output.contains("error: Unknown command `foo`"));
+        // `quit` ends the session before the last command.
+        assert!(output.ends_with("(brisc) "));
+    }
+
+    #[test]
+    fn test_next_steps_over_calls() {
+        let program = program();
+        let main = program.symbol("main").unwrap().address;
+        // The empty line repeats `n`, stepping over the `jalr` of the call to `leaf`.
+        let output = run(&program, "b main\nc\nn 5\nn\n\nr a0\nc\n");
+
+        assert!(output.contains(&format!("=> {} <main+20>:", address(main + 20))), "{output}");
+        assert!(output.contains(&format!("=> {} <main+24>:", address(main + 24))), "{output}");
+        assert!(output.contains(&format!("=> {} <main+28>:", address(main + 28))), "{output}");
+        assert!(output.contains(&format!("a0   {}", address(6))));
+        assert!(output.ends_with("Program exited with code 6\n(brisc) "), "{output}");
+    }
+
+    #[test]
+    fn test_disassembly_is_capped() {
+        let output = run(&program(), "disas pc 0xffffffff\n");
+        let lines = output
+            .lines()
+            .filter(|line| line.starts_with("=> ") || line.starts_with("   "))
+            .count();
+        assert_eq!(lines, MAX_DISASSEMBLY as usize);
+        assert!(
+            output.contains(&format!("(truncated to {MAX_DISASSEMBLY} instructions)\n")),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn test_hexdump_is_capped() {
+        let output = run(&program(), "x sp 0xffffffff\n");
+        let lines = output.lines().filter(|line| line.ends_with('|')).count();
+        assert_eq!(lines, MAX_HEXDUMP as usize / 16);
+        assert!(output.contains(&format!("(truncated to {MAX_HEXDUMP} bytes)\n")), "{output}");
+    }
+
+    #[test]
+    fn test_continue_is_interrupted() {
+        let program = assemble(".globl _start\n_start:\n j _start").unwrap();
+        let mut session = session(&program);
+
+        // Keep interrupting until the session is done, as the flag is cleared by `continue`.
+        let (interrupt, done) = (session.interrupt(), Arc::new(AtomicBool::new(false)));
+        let interrupter = thread::spawn({
+            let done = Arc::clone(&done);
+            move || {
+                while !done.load(Ordering::Relaxed) {
+                    interrupt.store(true, Ordering::Relaxed);
+                    thread::sleep(Duration::from_millis(10));
+                }
+            }
+        });
+        let output = run_session(&mut session, "c\nq\n");
+        done.store(true, Ordering::Relaxed);
+        interrupter.join().unwrap();
+
+        assert!(output.contains("Interrupted\n=> "), "{output}");
+    }
+}
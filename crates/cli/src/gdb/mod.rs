@@ -0,0 +1,590 @@
+//! A GDB Remote Serial Protocol stub, serving a [StEmu] to `gdb` or `lldb` over a socket.
+//!
+//! The stub presents the hart as a single thread. The `g` packet holds the 32 integer registers
+//! followed by the program counter, each in little-endian target byte order, as described by the
+//! target description in [target]. Execution breakpoints and watchpoints are those of the
+//! [Debugger], and a guest `ebreak` stops the hart as a breakpoint.
+//!
+//! [Debugger]: brisc_emu::st::Debugger
+
+use brisc_emu::{
+    cfg::EmuConfig,
+    st::{StEmu, StopReason, WatchKind, WatchpointId},
+};
+use brisc_hw::{errors::PipelineError, memory::Memory, XWord, Xlen};
+use std::{
+    collections::BTreeMap,
+    io::{self, ErrorKind, Read, Write},
+    net::TcpStream,
+};
+
+mod packet;
+use packet::{decode_hex, encode_hex, parse_hex, Connection, Message, INTERRUPT};
+
+mod target;
+use target::{target_xml, MISA_REGNUM, PC_REGNUM};
+
+/// The largest packet accepted from the debugger, advertised in reply to `qSupported`. Replies
+/// are kept within it too.
+const PACKET_SIZE: usize = 0x4000;
+
+/// The number of instructions executed between polls for an interrupt while continuing.
+const CONTINUE_CHUNK: u64 = 100_000;
+
+/// The signal reported when the hart stops at a breakpoint, watchpoint or after a step.
+const SIGTRAP: u8 = 5;
+
+/// The signal reported when the debugger interrupts the hart.
+const SIGINT: u8 = 2;
+
+/// The signal reported when the hart cannot decode an instruction.
+const SIGILL: u8 = 4;
+
+/// The signal reported when the hart faults on a memory access.
+const SIGSEGV: u8 = 11;
+
+/// The signal reported for other errors raised by the hart.
+const SIGABRT: u8 = 6;
+
+/// A byte stream to a debugger, which can be polled for an interrupt while the hart runs.
+pub(crate) trait Stream: Read + Write {
+    /// Consumes and returns `true` if the debugger has sent an interrupt, without blocking.
+    fn interrupted(&mut self) -> io::Result<bool>;
+}
+
+/// Implements [Stream] for a socket type with `set_nonblocking`. The debugger sends nothing but
+/// interrupts while the hart runs, so any other byte is discarded.
+macro_rules! impl_stream {
+    ($ty:ty) => {
+        impl Stream for $ty {
+            fn interrupted(&mut self) -> io::Result<bool> {
+                self.set_nonblocking(true)?;
+                let mut byte = [0];
+                let read = self.read(&mut byte);
+                self.set_nonblocking(false)?;
+                match read {
+                    Ok(0) => Err(ErrorKind::UnexpectedEof.into()),
+                    Ok(_) => Ok(byte[0] == INTERRUPT),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+                    Err(e) => Err(e),
+                }
+            }
+        }
+    };
+}
+
+impl_stream!(TcpStream);
+#[cfg(unix)]
+impl_stream!(std::os::unix::net::UnixStream);
+
+/// What to do after handling a packet.
+#[derive(Debug)]
+enum Reply {
+    /// Send the reply.
+    Send(Vec<u8>),
+    /// Send the reply, then close the connection.
+    Close(Vec<u8>),
+    /// Close the connection without replying.
+    Kill,
+}
+
+impl<T: Into<Vec<u8>>> From<T> for Reply {
+    fn from(data: T) -> Self {
+        Self::Send(data.into())
+    }
+}
+
+/// A GDB Remote Serial Protocol stub serving a [StEmu].
+pub(crate) struct GdbStub<Config: EmuConfig> {
+    /// The debugged hart.
+    pub(crate) emu: StEmu<Config>,
+    /// The watchpoints set by the debugger, by the type, address and length of their `Z` packet.
+    watchpoints: BTreeMap<(u8, XWord, XWord), WatchpointId>,
+    /// The reply to the last stop of the hart, sent again on `?`.
+    last_stop: Vec<u8>,
+}
+
+impl<Config: EmuConfig> GdbStub<Config> {
+    /// Creates a new [GdbStub] serving `emu`, which stops on guest `ebreak` instructions.
+    pub(crate) fn new(mut emu: StEmu<Config>) -> Self {
+        emu.debugger.stop_on_ebreak = true;
+        Self { emu, watchpoints: BTreeMap::new(), last_stop: stop_signal(SIGTRAP) }
+    }
+
+    /// Serves the debugger connected over `stream` until it detaches, kills the hart or closes
+    /// the connection.
+    pub(crate) fn serve<S: Stream>(&mut self, stream: S) -> io::Result<()> {
+        let mut connection = Connection::new(stream);
+        while let Some(message) = connection.receive()? {
+            // An interrupt while the hart is stopped has nothing to interrupt.
+            let Message::Packet(packet) = message else {
+                continue;
+            };
+            match self.handle(&packet, &mut connection)? {
+                Reply::Send(reply) => connection.send(&reply)?,
+                Reply::Close(reply) => return connection.send(&reply),
+                Reply::Kill => return Ok(()),
+            }
+            if packet == b"QStartNoAckMode" {
+                connection.no_ack = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles a packet, returning the reply to send. An empty reply marks an unsupported packet.
+    fn handle<S: Stream>(
+        &mut self,
+        packet: &[u8],
+        connection: &mut Connection<S>,
+    ) -> io::Result<Reply> {
+        let Some((&command, args)) = packet.split_first() else {
+            return Ok("".into());
+        };
+        let reply = match command {
+            b'?' => self.last_stop.clone().into(),
+            b'g' => self.read_registers().into(),
+            b'G' => ok_or_error(self.write_registers(args)),
+            b'p' => parse_hex(args)
+                .and_then(|regnum| self.read_register(regnum as usize))
+                .map_or_else(error, Into::into),
+            b'P' => ok_or_error(split(args, b'=').and_then(|(regnum, value)| {
+                self.write_register(parse_hex(regnum)? as usize, value)
+            })),
+            b'm' => self.read_memory(args).map_or_else(error, Into::into),
+            b'M' => ok_or_error(self.write_memory(args)),
+            b's' | b'c' => self.resume(command, args, connection)?.into(),
+            b'v' => self.handle_v(args, connection)?,
+            b'Z' | b'z' => self.handle_z(command == b'Z', args),
+            b'q' => self.handle_query(args),
+            b'Q' if args == b"StartNoAckMode" => "OK".into(),
+            b'H' | b'T' => "OK".into(),
+            b'D' => Reply::Close(b"OK".to_vec()),
+            b'k' => Reply::Kill,
+            _ => "".into(),
+        };
+        Ok(reply)
+    }
+
+    /// Handles the `v` packets, of which only `vCont` is supported.
+    fn handle_v<S: Stream>(
+        &mut self,
+        args: &[u8],
+        connection: &mut Connection<S>,
+    ) -> io::Result<Reply> {
+        if args == b"Cont?" {
+            return Ok("vCont;c;C;s;S".into());
+        }
+        let Some(actions) = args.strip_prefix(b"Cont;") else {
+            return Ok("".into());
+        };
+        // There is a single thread, so the first action applies to it. Signals are not delivered
+        // to the guest.
+        let action = match actions.first() {
+            Some(b's' | b'S') => b's',
+            Some(b'c' | b'C') => b'c',
+            _ => return Ok(error()),
+        };
+        Ok(self.resume(action, &[], connection)?.into())
+    }
+
+    /// Handles the `Z` and `z` packets, inserting or removing a breakpoint or watchpoint.
+    fn handle_z(&mut self, insert: bool, args: &[u8]) -> Reply {
+        let mut fields = args.splitn(3, |&byte| byte == b',');
+        let (Some(&[kind]), Some(address), Some(length)) =
+            (fields.next(), fields.next().and_then(parse_hex), fields.next())
+        else {
+            return error();
+        };
+        let address = Config::Xlen::truncate(address as XWord);
+        // The length of a breakpoint may be followed by conditions, which are not supported.
+        let Some(length) = parse_hex(length.split(|&byte| byte == b';').next().unwrap_or(length))
+        else {
+            return error();
+        };
+
+        let debugger = &mut self.emu.debugger;
+        let watch = match kind {
+            // Software and hardware breakpoints are both breakpoints of the hart.
+            b'0' | b'1' => {
+                if insert {
+                    debugger.set_breakpoint(address);
+                } else {
+                    debugger.clear_breakpoint(address);
+                }
+                return "OK".into();
+            }
+            b'2' => WatchKind::Write,
+            b'3' => WatchKind::Read,
+            b'4' => WatchKind::Access,
+            _ => return "".into(),
+        };
+        let key = (kind, address, length as XWord);
+        if insert {
+            let range = address..address.saturating_add(length as XWord);
+            let id = debugger.set_watchpoint(range, watch);
+            if let Some(previous) = self.watchpoints.insert(key, id) {
+                debugger.clear_watchpoint(previous);
+            }
+        } else if let Some(id) = self.watchpoints.remove(&key) {
+            debugger.clear_watchpoint(id);
+        }
+        "OK".into()
+    }
+
+    /// Handles the `q` packets.
+    fn handle_query(&self, args: &[u8]) -> Reply {
+        if args.starts_with(b"Supported") {
+            return format!("PacketSize={PACKET_SIZE:x};QStartNoAckMode+;qXfer:features:read+")
+                .into();
+        }
+        if let Some(args) = args.strip_prefix(b"Xfer:features:read:") {
+            let Some((annex, range)) = split(args, b':') else {
+                return error();
+            };
+            if annex != b"target.xml" {
+                return "E00".into();
+            }
+            let xml = target_xml(&self.emu.isa);
+            let Some((offset, length)) = split(range, b',')
+                .and_then(|(offset, length)| Some((parse_hex(offset)?, parse_hex(length)?)))
+            else {
+                return error();
+            };
+            let start = (offset as usize).min(xml.len());
+            let length = (length as usize).min(PACKET_SIZE - 1);
+            let end = start.saturating_add(length).min(xml.len());
+            let mut reply = vec![if end == xml.len() { b'l' } else { b'm' }];
+            reply.extend_from_slice(&xml.as_bytes()[start..end]);
+            return reply.into();
+        }
+        match args {
+            b"Attached" => "1".into(),
+            b"C" => "QC1".into(),
+            b"fThreadInfo" => "m1".into(),
+            b"sThreadInfo" => "l".into(),
+            _ => "".into(),
+        }
+    }
+
+    /// Resumes the hart with a step (`s`) or continue (`c`), from the address in `args` if any,
+    /// and returns the reply to its stop.
+    fn resume<S: Stream>(
+        &mut self,
+        action: u8,
+        args: &[u8],
+        connection: &mut Connection<S>,
+    ) -> io::Result<Vec<u8>> {
+        if let Some(pc) = parse_hex(args) {
+            self.emu.register.pc = Config::Xlen::truncate(pc as XWord);
+        }
+
+        let reason = if action == b's' {
+            self.emu.run_for(1).reason
+        } else {
+            loop {
+                let reason = self.emu.run_for(CONTINUE_CHUNK).reason;
+                if !matches!(reason, StopReason::BudgetExhausted) {
+                    break reason;
+                }
+                if connection.stream.interrupted()? {
+                    self.last_stop = stop_signal(SIGINT);
+                    return Ok(self.last_stop.clone());
+                }
+            }
+        };
+        self.last_stop = self.stop_reply(&reason);
+        Ok(self.last_stop.clone())
+    }
+
+    /// Returns the stop reply reporting `reason`.
+    fn stop_reply(&self, reason: &StopReason) -> Vec<u8> {
+        match reason {
+            StopReason::Exited(code) => format!("W{:02x}", *code as u8).into_bytes(),
+            StopReason::Watchpoint(hit) => {
+                let watchpoint = self.emu.debugger.watchpoint(hit.id);
+                let kind = match watchpoint.map(|watch| watch.kind) {
+                    Some(WatchKind::Read) => "rwatch",
+                    Some(WatchKind::Access) => "awatch",
+                    _ => "watch",
+                };
+                // An access may start below the watched range, at an address that gdb cannot
+                // match to any of its watchpoints, so the first watched address is reported.
+                let address = watchpoint
+                    .map_or(hit.access.address, |watch| hit.access.address.max(watch.range.start));
+                format!("T{SIGTRAP:02x}{kind}:{address:x};").into_bytes()
+            }
+            StopReason::Trap(PipelineError::InstructionDecodeError { .. }) => stop_signal(SIGILL),
+            StopReason::Trap(PipelineError::MemoryError(_)) => stop_signal(SIGSEGV),
+            StopReason::Trap(_) => stop_signal(SIGABRT),
+            StopReason::BudgetExhausted |
+            StopReason::Breakpoint(_) |
+            StopReason::Ebreak(_) |
+            StopReason::Predicate => stop_signal(SIGTRAP),
+        }
+    }
+
+    /// Returns the `g` packet: the integer registers, followed by the program counter.
+    fn read_registers(&self) -> String {
+        let register = &self.emu.register;
+        register
+            .registers
+            .iter()
+            .chain([&register.pc])
+            .map(|&value| encode_word::<Config::Xlen>(value))
+            .collect()
+    }
+
+    /// Writes the integer registers and the program counter from a `G` packet. Writes to `x0` are
+    /// ignored.
+    fn write_registers(&mut self, args: &[u8]) -> Option<()> {
+        let digits = Config::Xlen::X_LEN / 4;
+        if args.len() != (PC_REGNUM + 1) * digits {
+            return None;
+        }
+        for (regnum, value) in args.chunks(digits).enumerate() {
+            self.write_register(regnum, value)?;
+        }
+        Some(())
+    }
+
+    /// Returns the value of the register `regnum` for a `p` packet.
+    fn read_register(&self, regnum: usize) -> Option<String> {
+        let value = match regnum {
+            0..PC_REGNUM => self.emu.register.registers[regnum],
+            PC_REGNUM => self.emu.register.pc,
+            MISA_REGNUM => self.emu.isa.misa(),
+            _ => return None,
+        };
+        Some(encode_word::<Config::Xlen>(value))
+    }
+
+    /// Writes the register `regnum` from the value of a `P` packet. Writes to `x0` are ignored,
+    /// and `misa` is read-only.
+    fn write_register(&mut self, regnum: usize, value: &[u8]) -> Option<()> {
+        let value = decode_word::<Config::Xlen>(value)?;
+        let register = &mut self.emu.register;
+        match regnum {
+            0 => {}
+            1..PC_REGNUM => register.registers[regnum] = value,
+            PC_REGNUM => register.pc = value,
+            _ => return None,
+        }
+        Some(())
+    }
+
+    /// Reads memory for an `m addr,length` packet. The read is cut short to the bytes that fit in
+    /// a packet, and at the first byte that cannot be read, and fails if none can.
+    fn read_memory(&self, args: &[u8]) -> Option<String> {
+        let (address, length) = split(args, b',')?;
+        let address = parse_hex(address)? as XWord;
+        let length = parse_hex(length)?.min(PACKET_SIZE as u64 / 2);
+        let bytes = (0..length as XWord)
+            .map_while(|offset| {
+                let address = Config::Xlen::truncate(address.wrapping_add(offset));
+                self.emu.memory.get_byte(address).ok()
+            })
+            .collect::<Vec<_>>();
+        (!bytes.is_empty()).then(|| encode_hex(&bytes))
+    }
+
+    /// Writes memory for an `M addr,length:data` packet.
+    fn write_memory(&mut self, args: &[u8]) -> Option<()> {
+        let (range, data) = split(args, b':')?;
+        let (address, length) = split(range, b',')?;
+        let data = decode_hex(data)?;
+        if data.len() as u64 != parse_hex(length)? {
+            return None;
+        }
+        let address = Config::Xlen::truncate(parse_hex(address)? as XWord);
+        self.emu.memory.set_memory_range(address, &mut data.as_slice()).ok()
+    }
+}
+
+/// Returns the stop reply reporting `signal`.
+fn stop_signal(signal: u8) -> Vec<u8> {
+    format!("S{signal:02x}").into_bytes()
+}
+
+/// Encodes a register value as the hex digits of its `X::X_LEN / 8` little-endian bytes.
+fn encode_word<X: Xlen>(value: XWord) -> String {
+    encode_hex(&value.to_le_bytes()[..X::X_LEN / 8])
+}
+
+/// Decodes a register value from the hex digits of its `X::X_LEN / 8` little-endian bytes.
+fn decode_word<X: Xlen>(digits: &[u8]) -> Option<XWord> {
+    let bytes = decode_hex(digits).filter(|bytes| bytes.len() == X::X_LEN / 8)?;
+    let mut word = [0; size_of::<XWord>()];
+    word[..bytes.len()].copy_from_slice(&bytes);
+    Some(XWord::from_le_bytes(word))
+}
+
+/// Splits `data` at the first `separator`.
+fn split(data: &[u8], separator: u8) -> Option<(&[u8], &[u8])> {
+    let at = data.iter().position(|&byte| byte == separator)?;
+    Some((&data[..at], &data[at + 1..]))
+}
+
+/// Returns `OK` if `result` is set, or an error reply.
+fn ok_or_error(result: Option<()>) -> Reply {
+    result.map_or_else(error, |()| "OK".into())
+}
+
+/// Returns the error reply to a malformed or failed request.
+fn error() -> Reply {
+    "E01".into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        encode_word,
+        packet::{Connection, Message, INTERRUPT},
+        GdbStub, PACKET_SIZE,
+    };
+    use crate::kernel::HostConfig;
+    use brisc_emu::{st::StEmu, test_utils::loaded};
+    use brisc_hw::{NativeXlen, Xlen};
+    use brisc_isa::asm::{assemble, Program};
+    use std::{
+        io::Write,
+        net::{TcpListener, TcpStream},
+        thread::{self, JoinHandle},
+    };
+
+    const PROGRAM: &str = r#"
+        .data
+        value: .word 0
+
+        .text
+        .globl _start
+        _start:
+            la a1, value
+            li a0, 7
+        store:
+            sw a0, 0(a1)
+            lbu a0, 0(a1)
+            ebreak
+            li a7, 93
+            ecall
+    "#;
+
+    /// Serves `program` on a local TCP port, and returns a connection to it along with the server.
+    fn serve(program: &Program) -> (Connection<TcpStream>, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let elf = program.to_elf();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let emu = loaded::<StEmu<_>, HostConfig<NativeXlen>>(&elf).build();
+            GdbStub::new(emu).serve(stream).unwrap();
+        });
+        (Connection::new(TcpStream::connect(address).unwrap()), server)
+    }
+
+    /// Sends `packet` and returns the reply.
+    fn request(client: &mut Connection<TcpStream>, packet: &str) -> String {
+        client.send(packet.as_bytes()).unwrap();
+        reply(client)
+    }
+
+    /// Receives a reply.
+    fn reply(client: &mut Connection<TcpStream>) -> String {
+        match client.receive().unwrap() {
+            Some(Message::Packet(reply)) => String::from_utf8(reply).unwrap(),
+            other => panic!("Expected a reply, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_scripted_session() {
+        let program = assemble(PROGRAM).unwrap();
+        let store = program.symbol("store").unwrap().address;
+        let value = program.symbol("value").unwrap().address;
+        let word = encode_word::<NativeXlen>;
+        let (mut client, server) = serve(&program);
+
+        assert!(request(&mut client, "qSupported:xmlRegisters=riscv").contains("qXfer:features"));
+        assert_eq!(request(&mut client, "QStartNoAckMode"), "OK");
+        client.no_ack = true;
+        assert_eq!(request(&mut client, "?"), "S05");
+        assert_eq!(request(&mut client, "vMustReplyEmpty"), "");
+
+        // The target description is read in chunks.
+        let xml = request(&mut client, "qXfer:features:read:target.xml:0,a");
+        assert_eq!(xml, "m<?xml vers");
+        let xml = request(&mut client, "qXfer:features:read:target.xml:a,10000");
+        assert!(xml.starts_with('l') && xml.ends_with("</target>\n"), "{xml}");
+
+        // The `g` packet holds the integer registers, then the program counter.
+        let registers = request(&mut client, "g");
+        let digits = NativeXlen::X_LEN / 4;
+        assert_eq!(registers.len(), 33 * digits);
+        assert_eq!(registers[32 * digits..], word(program.entry));
+        assert_eq!(request(&mut client, "p20"), word(program.entry));
+
+        // Registers can be written one at a time or all at once, and `x0` stays zero.
+        assert_eq!(request(&mut client, &format!("P5={}", word(0x1234))), "OK");
+        assert_eq!(request(&mut client, "p5"), word(0x1234));
+        let written = registers.replacen(&word(0), &word(1), 1);
+        assert_eq!(request(&mut client, &format!("G{written}")), "OK");
+        assert_eq!(request(&mut client, "g"), registers);
+        assert_eq!(request(&mut client, &format!("P342={}", word(0))), "E01");
+
+        // Continue to a breakpoint, then to the watched store.
+        assert_eq!(request(&mut client, &format!("Z0,{store:x},4")), "OK");
+        assert_eq!(request(&mut client, "c"), "S05");
+        assert_eq!(request(&mut client, "p20"), word(store));
+        assert_eq!(request(&mut client, &format!("z0,{store:x},4")), "OK");
+        assert_eq!(request(&mut client, &format!("Z2,{value:x},4")), "OK");
+        assert_eq!(request(&mut client, "c"), format!("T05watch:{value:x};"));
+        assert_eq!(request(&mut client, &format!("z2,{value:x},4")), "OK");
+
+        // Memory is read and written through the hart's memory.
+        assert_eq!(request(&mut client, &format!("m{value:x},4")), "07000000");
+        assert_eq!(request(&mut client, &format!("M{value:x},1:ab")), "OK");
+        assert_eq!(request(&mut client, &format!("m{value:x},2")), "ab00");
+
+        // Reads are cut short to the bytes that fit in a packet.
+        let long = request(&mut client, &format!("m{value:x},ffffffff"));
+        assert_eq!((&long[..4], long.len()), ("ab00", PACKET_SIZE));
+
+        // Continue to `ebreak`, step over it and run to the exit, with the byte loaded as the code.
+        assert_eq!(request(&mut client, "c"), "S05");
+        assert_eq!(request(&mut client, "p20"), word(store + 8));
+        assert_eq!(request(&mut client, "vCont;s:1"), "S05");
+        assert_eq!(request(&mut client, "p20"), word(store + 12));
+        assert_eq!(request(&mut client, "c"), "Wab");
+
+        assert_eq!(request(&mut client, "D"), "OK");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_watchpoint_within_access() {
+        let program = assemble(PROGRAM).unwrap();
+        let value = program.symbol("value").unwrap().address;
+        let (mut client, server) = serve(&program);
+
+        // The word store starts below the watched half-word, which is reported instead.
+        assert_eq!(request(&mut client, &format!("Z2,{:x},2", value + 2)), "OK");
+        assert_eq!(request(&mut client, "c"), format!("T05watch:{:x};", value + 2));
+
+        client.send(b"k").unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let program = assemble(".globl _start\n_start:\n j _start").unwrap();
+        let (mut client, server) = serve(&program);
+
+        client.send(b"c").unwrap();
+        client.stream.write_all(&[INTERRUPT]).unwrap();
+        assert_eq!(reply(&mut client), "S02");
+        assert_eq!(request(&mut client, "p20"), encode_word::<NativeXlen>(program.entry));
+
+        client.send(b"k").unwrap();
+        server.join().unwrap();
+    }
+}
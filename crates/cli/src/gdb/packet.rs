@@ -0,0 +1,214 @@
+//! Framing of the packets of the GDB Remote Serial Protocol.
+//!
+//! Packets are sent as `$<data>#<checksum>`, where the checksum is the sum of the data bytes
+//! modulo 256 in two hex digits. Until no-acknowledgment mode is negotiated, each packet is
+//! acknowledged with `+`, or `-` to request retransmission. A lone `0x03` byte outside of a packet
+//! interrupts the target.
+
+use std::io::{self, ErrorKind, Read, Write};
+
+/// The byte that interrupts the target.
+pub(crate) const INTERRUPT: u8 = 0x03;
+
+/// A message received from the debugger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Message {
+    /// A packet, with its data unescaped.
+    Packet(Vec<u8>),
+    /// A request to interrupt the target.
+    Interrupt,
+}
+
+/// A connection to a debugger, framing the packets sent and received over `stream`.
+#[derive(Debug)]
+pub(crate) struct Connection<S> {
+    /// The underlying byte stream.
+    pub(crate) stream: S,
+    /// Whether packets are no longer acknowledged.
+    pub(crate) no_ack: bool,
+}
+
+impl<S: Read + Write> Connection<S> {
+    /// Creates a new [Connection] over `stream`, acknowledging packets.
+    pub(crate) const fn new(stream: S) -> Self {
+        Self { stream, no_ack: false }
+    }
+
+    /// Receives the next message, or `None` if the debugger closed the connection. Packets with a
+    /// bad checksum are rejected and received again.
+    pub(crate) fn receive(&mut self) -> io::Result<Option<Message>> {
+        loop {
+            let Some(byte) = self.read_byte()? else {
+                return Ok(None);
+            };
+            match byte {
+                INTERRUPT => return Ok(Some(Message::Interrupt)),
+                b'$' => {}
+                // Acknowledgments of our replies, and noise between packets.
+                _ => continue,
+            }
+
+            let mut data = Vec::new();
+            let mut sum = 0u8;
+            loop {
+                let Some(byte) = self.read_byte()? else {
+                    return Ok(None);
+                };
+                if byte == b'#' {
+                    break;
+                }
+                sum = sum.wrapping_add(byte);
+                data.push(byte);
+            }
+            let mut checksum = [0; 2];
+            self.stream.read_exact(&mut checksum)?;
+
+            let valid = parse_hex(&checksum) == Some(sum as u64);
+            if !self.no_ack {
+                self.stream.write_all(if valid { b"+" } else { b"-" })?;
+                self.stream.flush()?;
+            }
+            if valid {
+                return Ok(Some(Message::Packet(unescape(&data))));
+            }
+        }
+    }
+
+    /// Sends a packet holding `data`, escaping it as needed, and waits for its acknowledgment.
+    pub(crate) fn send(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut packet = Vec::with_capacity(data.len() + 4);
+        packet.push(b'$');
+        for &byte in data {
+            if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+                packet.extend([b'}', byte ^ 0x20]);
+            } else {
+                packet.push(byte);
+            }
+        }
+        let sum = packet[1..].iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+        packet.extend(format!("#{sum:02x}").bytes());
+
+        loop {
+            self.stream.write_all(&packet)?;
+            self.stream.flush()?;
+            if self.no_ack {
+                return Ok(());
+            }
+            match self.read_byte()? {
+                Some(b'+') => return Ok(()),
+                Some(b'-') => continue,
+                Some(_) => return Err(io::Error::new(ErrorKind::InvalidData, "Expected an ack")),
+                None => return Err(ErrorKind::UnexpectedEof.into()),
+            }
+        }
+    }
+
+    /// Reads a single byte, or `None` at the end of the stream.
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        match self.stream.read(&mut byte) {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(byte[0])),
+            Err(e) if e.kind() == ErrorKind::Interrupted => self.read_byte(),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Removes the `}` escapes from the data of a packet.
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(data.len());
+    let mut bytes = data.iter();
+    while let Some(&byte) = bytes.next() {
+        match byte {
+            b'}' => unescaped.extend(bytes.next().map(|byte| byte ^ 0x20)),
+            _ => unescaped.push(byte),
+        }
+    }
+    unescaped
+}
+
+/// Parses hex digits into an integer, or `None` if they are empty, invalid or overflow.
+pub(crate) fn parse_hex(digits: &[u8]) -> Option<u64> {
+    let digits = std::str::from_utf8(digits).ok().filter(|digits| !digits.is_empty())?;
+    u64::from_str_radix(digits, 16).ok()
+}
+
+/// Decodes pairs of hex digits into bytes.
+pub(crate) fn decode_hex(digits: &[u8]) -> Option<Vec<u8>> {
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits.chunks(2).map(|pair| parse_hex(pair).map(|byte| byte as u8)).collect()
+}
+
+/// Encodes bytes as pairs of hex digits.
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_hex, encode_hex, Connection, Message};
+    use rstest::rstest;
+    use std::io::{self, Cursor, Read, Write};
+
+    /// A stream reading scripted input and recording the output.
+    #[derive(Debug, Default)]
+    struct Script {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Script {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Script {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn script(input: &[u8]) -> Connection<Script> {
+        Connection::new(Script { input: Cursor::new(input.to_vec()), output: Vec::new() })
+    }
+
+    #[rstest]
+    #[case::packet(b"+$g#67", Some(Message::Packet(b"g".to_vec())), b"+")]
+    #[case::escaped(b"$X0,1:}]#f9", Some(Message::Packet(b"X0,1:}".to_vec())), b"+")]
+    #[case::retransmitted(b"$g#00$g#67", Some(Message::Packet(b"g".to_vec())), b"-+")]
+    #[case::interrupt(b"\x03", Some(Message::Interrupt), b"")]
+    #[case::closed(b"+", None, b"")]
+    fn test_receive(#[case] input: &[u8], #[case] expected: Option<Message>, #[case] acks: &[u8]) {
+        let mut connection = script(input);
+        assert_eq!(connection.receive().unwrap(), expected);
+        assert_eq!(connection.stream.output, acks);
+    }
+
+    #[test]
+    fn test_send() {
+        let mut connection = script(b"-+");
+        connection.send(b"OK").unwrap();
+        assert_eq!(connection.stream.output, b"$OK#9a$OK#9a");
+
+        let mut connection = script(b"");
+        connection.no_ack = true;
+        connection.send(b"a#b").unwrap();
+        assert_eq!(connection.stream.output, b"$a}\x03b#43");
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(encode_hex(&[0x00, 0xab, 0x10]), "00ab10");
+        assert_eq!(decode_hex(b"00ab10"), Some(vec![0x00, 0xab, 0x10]));
+        assert_eq!(decode_hex(b"0ab"), None);
+        assert_eq!(decode_hex(b"zz"), None);
+    }
+}
@@ -0,0 +1,78 @@
+//! The target description sent to the debugger, and the numbering of its registers.
+
+use brisc_hw::{IsaConfig, Xlen, REGISTER_ABI_NAMES};
+use std::fmt::Write;
+
+/// The register number of the program counter.
+pub(crate) const PC_REGNUM: usize = 32;
+
+/// The register number of the first CSR. The CSR at address `n` has the register number
+/// `CSR_REGNUM + n`.
+const CSR_REGNUM: usize = 65;
+
+/// The register number of the read-only `misa` CSR.
+pub(crate) const MISA_REGNUM: usize = CSR_REGNUM + 0x301;
+
+/// Returns the target description of a hart with the register width `X` and the extensions of
+/// `isa`.
+///
+/// The `org.gnu.gdb.riscv.cpu` feature holds the integer registers and the program counter, which
+/// make up the `g` packet. The `org.gnu.gdb.riscv.csr` feature holds `misa`, from which the
+/// debugger learns the enabled extensions.
+pub(crate) fn target_xml<X: Xlen>(isa: &IsaConfig<X>) -> String {
+    let bits = X::X_LEN;
+    let mut xml = format!(
+        "<?xml version=\"1.0\"?>\n\
+         <!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n\
+         <target version=\"1.0\">\n\
+         <architecture>riscv:rv{bits}</architecture>\n\
+         <feature name=\"org.gnu.gdb.riscv.cpu\">\n"
+    );
+    for (regnum, name) in REGISTER_ABI_NAMES.iter().enumerate() {
+        let kind = match regnum {
+            1 => "code_ptr",
+            2..=4 | 8 => "data_ptr",
+            _ => "int",
+        };
+        _ = writeln!(
+            xml,
+            "<reg name=\"{name}\" bitsize=\"{bits}\" type=\"{kind}\" regnum=\"{regnum}\"/>"
+        );
+    }
+    _ = write!(
+        xml,
+        "<reg name=\"pc\" bitsize=\"{bits}\" type=\"code_ptr\" regnum=\"{PC_REGNUM}\"/>\n\
+         </feature>\n\
+         <feature name=\"org.gnu.gdb.riscv.csr\">\n\
+         <!-- {extensions} -->\n\
+         <reg name=\"misa\" bitsize=\"{bits}\" type=\"int\" regnum=\"{MISA_REGNUM}\" \
+         group=\"csr\"/>\n\
+         </feature>\n\
+         </target>\n",
+        extensions = isa,
+    );
+    xml
+}
+
+#[cfg(test)]
+mod test {
+    use super::target_xml;
+    use brisc_hw::{Extension, IsaConfig, Rv32};
+
+    #[test]
+    fn test_target_xml() {
+        let xml = target_xml(&IsaConfig::<Rv32>::default());
+        assert!(xml.contains("<architecture>riscv:rv32</architecture>"));
+        assert!(xml.contains("<reg name=\"zero\" bitsize=\"32\" type=\"int\" regnum=\"0\"/>"));
+        assert!(xml.contains("<reg name=\"pc\" bitsize=\"32\" type=\"code_ptr\" regnum=\"32\"/>"));
+        assert!(xml.contains("regnum=\"834\""));
+        assert_eq!(xml.matches("<reg ").count(), 34);
+
+        assert!(xml.contains(&format!("<!-- {} -->", IsaConfig::<Rv32>::default())));
+
+        let xml = target_xml(&IsaConfig::<Rv32>::new().with_extension(Extension::M));
+        assert!(
+            xml.contains(&format!("<!-- rv32i{} -->", if cfg!(feature = "m") { "m" } else { "" }))
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! The host kernel and emulator configuration of the command line tools.
+
+use brisc_emu::{cfg::EmuConfig, observer::Observer};
+use brisc_hw::{
+    errors::{PipelineError, PipelineResult},
+    kernel::Kernel,
+    memory::{Memory, SimpleMemory},
+    pipeline::PipelineRegister,
+    XWord, Xlen, REG_A0, REG_A1, REG_A2,
+};
+use std::{
+    io::{self, Write},
+    marker::PhantomData,
+};
+
+/// The Linux system call number of `write`.
+const SYS_WRITE: XWord = 64;
+
+/// The Linux system call number of `exit`.
+const SYS_EXIT: XWord = 93;
+
+/// The Linux system call number of `exit_group`.
+const SYS_EXIT_GROUP: XWord = 94;
+
+/// The Linux error number returned for writes to file descriptors other than 1 and 2.
+const EBADF: XWord = 9;
+
+/// The Linux error number returned by unimplemented system calls.
+const ENOSYS: XWord = 38;
+
+/// A [Kernel] implementing the `exit`, `exit_group` and `write` system calls against the host,
+/// writing the standard output and error of the guest to those of the host.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct HostKernel;
+
+impl Kernel for HostKernel {
+    fn syscall<M: Memory, X: Xlen>(
+        &mut self,
+        syscall_no: XWord,
+        memory: &mut M,
+        p_reg: &mut PipelineRegister<X>,
+    ) -> PipelineResult<XWord> {
+        let [a0, a1, a2] = [REG_A0, REG_A1, REG_A2].map(|reg| p_reg.registers[reg as usize]);
+        let result = match syscall_no {
+            SYS_EXIT | SYS_EXIT_GROUP => {
+                p_reg.exit_code = a0;
+                p_reg.exit = true;
+                return Ok(0);
+            }
+            SYS_WRITE => {
+                let data = memory.read_memory_range(a1, a2).map_err(PipelineError::MemoryError)?;
+                let written = match a0 {
+                    1 => io::stdout().write_all(&data),
+                    2 => io::stderr().write_all(&data),
+                    _ => Err(io::ErrorKind::InvalidInput.into()),
+                };
+                written.map_or(X::truncate(EBADF.wrapping_neg()), |()| a2)
+            }
+            _ => X::truncate(ENOSYS.wrapping_neg()),
+        };
+        p_reg.registers[REG_A0 as usize] = result;
+        Ok(result)
+    }
+}
+
+/// The [EmuConfig] of the command line tools, running on the [HostKernel] and observed by `O`.
+#[derive(Debug, Default)]
+pub(crate) struct HostConfig<X, O = ()>(PhantomData<(X, O)>);
+
+impl<X: Xlen, O: Observer<X> + Default> EmuConfig for HostConfig<X, O> {
+    type Memory = SimpleMemory;
+
+    type Kernel = HostKernel;
+
+    type Xlen = X;
+
+    type Observer = O;
+}
@@ -0,0 +1,177 @@
+#![doc = include_str!("../README.md")]
+#![warn(missing_debug_implementations, unreachable_pub, rustdoc::all)]
+#![deny(unused_must_use, rust_2018_idioms)]
+
+use brisc_emu::{
+    elf::{elf_xlen, function_symbols},
+    st::StEmu,
+};
+use brisc_hw::{Rv32, Xlen};
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    net::TcpListener,
+    process::ExitCode,
+    sync::atomic::Ordering,
+};
+
+mod debug;
+use debug::{History, Session};
+
+mod gdb;
+use gdb::{GdbStub, Stream};
+
+mod kernel;
+use kernel::{HostConfig, HostKernel};
+
+/// The usage message printed by `--help`.
+const USAGE: &str = "\
+Usage: brisc <command> [options] <file>
+
+Runs and debugs RISC-V ELF executables.
+
+Commands:
+  debug <file>              Debug the program interactively, reading commands from stdin
+  gdb [options] <file>      Serve the program to a debugger over the GDB remote serial protocol
+
+Options for gdb:
+  --listen <address>        Listen for the debugger on a TCP address (default: 127.0.0.1:1234)
+  --unix <path>             Listen for the debugger on a Unix socket
+  -h, --help                Print this message
+";
+
+/// The TCP address that `brisc gdb` listens on by default.
+const DEFAULT_LISTEN: &str = "127.0.0.1:1234";
+
+fn main() -> ExitCode {
+    match run(env::args().skip(1)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("brisc: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Parses the command line arguments and runs the command.
+fn run(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    match args.next().as_deref() {
+        Some("debug") => debug(args),
+        Some("gdb") => gdb(args),
+        Some("-h" | "--help") => {
+            print!("{USAGE}");
+            Ok(())
+        }
+        Some(other) => Err(format!("Unrecognized command: {other}\n\n{USAGE}")),
+        None => Err(format!("No command\n\n{USAGE}")),
+    }
+}
+
+/// Runs `brisc debug`, reading debugger commands from stdin until it ends or `quit`.
+fn debug(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let file = match (args.next(), args.next()) {
+        (Some(flag), _) if flag == "-h" || flag == "--help" => {
+            print!("{USAGE}");
+            return Ok(());
+        }
+        (Some(file), None) => file,
+        (Some(_), Some(arg)) => return Err(format!("Unexpected argument: {arg}")),
+        (None, _) => return Err(format!("No input file\n\n{USAGE}")),
+    };
+    let raw = fs::read(&file).map_err(|e| format!("{file}: {e}"))?;
+    match elf_xlen(&raw)? {
+        32 => debug_with::<Rv32>(&raw),
+        #[cfg(feature = "64-bit")]
+        64 => debug_with::<brisc_hw::Rv64>(&raw),
+        xlen => Err(format!("Unsupported XLEN {xlen}")),
+    }
+    .map_err(|e| format!("{file}: {e}"))
+}
+
+/// Debugs the ELF file `raw` on a hart with the register width `X`.
+fn debug_with<X: Xlen>(raw: &[u8]) -> Result<(), String> {
+    let emu =
+        StEmu::<HostConfig<X, History>>::builder().with_kernel(HostKernel).with_elf(raw)?.build();
+    let mut session = Session::new(emu, function_symbols(raw)?);
+    let interrupt = session.interrupt();
+    ctrlc::set_handler(move || interrupt.store(true, Ordering::Relaxed))
+        .map_err(|e| e.to_string())?;
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        eprintln!("Type `help` for a list of commands.");
+    }
+    session.repl(stdin.lock(), &mut io::stdout().lock()).map_err(|e| e.to_string())
+}
+
+/// The socket that `brisc gdb` listens on.
+enum Listen {
+    /// A TCP address.
+    Tcp(String),
+    /// The path of a Unix socket.
+    #[cfg(unix)]
+    Unix(String),
+}
+
+/// Runs `brisc gdb`, serving the program to a single debugger connection.
+fn gdb(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut listen = Listen::Tcp(DEFAULT_LISTEN.to_string());
+    let mut file = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            "--listen" => {
+                listen = Listen::Tcp(args.next().ok_or("Missing address after --listen")?)
+            }
+            #[cfg(unix)]
+            "--unix" => listen = Listen::Unix(args.next().ok_or("Missing path after --unix")?),
+            flag if flag.starts_with('-') => return Err(format!("Unrecognized option: {flag}")),
+            _ if file.is_some() => return Err(format!("Unexpected argument: {arg}")),
+            _ => file = Some(arg),
+        }
+    }
+    let file = file.ok_or_else(|| format!("No input file\n\n{USAGE}"))?;
+    let raw = fs::read(&file).map_err(|e| format!("{file}: {e}"))?;
+
+    match listen {
+        Listen::Tcp(address) => {
+            let listener = TcpListener::bind(&address).map_err(|e| format!("{address}: {e}"))?;
+            eprintln!(
+                "Listening for a debugger on {}",
+                listener.local_addr().map_err(|e| e.to_string())?
+            );
+            let (stream, peer) = listener.accept().map_err(|e| e.to_string())?;
+            eprintln!("Debugger connected from {peer}");
+            serve(&raw, stream)
+        }
+        #[cfg(unix)]
+        Listen::Unix(path) => {
+            let listener = std::os::unix::net::UnixListener::bind(&path)
+                .map_err(|e| format!("{path}: {e}"))?;
+            eprintln!("Listening for a debugger on {path}");
+            let accepted = listener.accept().map_err(|e| e.to_string());
+            // The socket file outlives the listener, so remove it once the debugger connects.
+            _ = fs::remove_file(&path);
+            serve(&raw, accepted?.0)
+        }
+    }
+    .map_err(|e| format!("{file}: {e}"))
+}
+
+/// Serves the ELF file `raw` over `stream`, on a hart with the register width of the file.
+fn serve(raw: &[u8], stream: impl Stream) -> Result<(), String> {
+    match elf_xlen(raw)? {
+        32 => serve_with::<Rv32>(raw, stream),
+        #[cfg(feature = "64-bit")]
+        64 => serve_with::<brisc_hw::Rv64>(raw, stream),
+        xlen => Err(format!("Unsupported XLEN {xlen}")),
+    }
+}
+
+/// Serves the ELF file `raw` over `stream`, on a hart with the register width `X`.
+fn serve_with<X: Xlen>(raw: &[u8], stream: impl Stream) -> Result<(), String> {
+    let emu = StEmu::<HostConfig<X>>::builder().with_kernel(HostKernel).with_elf(raw)?.build();
+    GdbStub::new(emu).serve(stream).map_err(|e| e.to_string())
+}
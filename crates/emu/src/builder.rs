@@ -0,0 +1,162 @@
+//! The configuration shared by the builders of every hart.
+
+use crate::{cfg::EmuConfig, elf::load_elf_checked};
+use alloc::string::String;
+use brisc_hw::{predictor::BranchUnit, IsaConfig, XWord};
+use core::fmt::{self, Debug, Formatter};
+
+/// The configuration shared by the builders of every hart: the program, the system call
+/// interface, the enabled extensions, the branch predictor and the observer.
+pub struct BuilderCore<Config>
+where
+    Config: EmuConfig,
+{
+    /// The starting program counter.
+    pub pc: XWord,
+    /// The initial memory for the emulator.
+    pub memory: Option<Config::Memory>,
+    /// The system call interface for the emulator.
+    pub kernel: Option<Config::Kernel>,
+    /// The ISA extensions enabled on the hart. Defaults to every extension compiled in.
+    pub isa: IsaConfig<Config::Xlen>,
+    /// The branch predictor of the hart, if any. Harts without one predict every branch not taken.
+    pub branch_unit: Option<BranchUnit>,
+    /// The observer of the execution of the hart.
+    pub observer: Config::Observer,
+}
+
+impl<Config> Default for BuilderCore<Config>
+where
+    Config: EmuConfig,
+{
+    fn default() -> Self {
+        Self {
+            pc: 0,
+            memory: None,
+            kernel: None,
+            isa: IsaConfig::default(),
+            branch_unit: None,
+            observer: Default::default(),
+        }
+    }
+}
+
+impl<Config> Debug for BuilderCore<Config>
+where
+    Config: EmuConfig,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BuilderCore")
+            .field("pc", &self.pc)
+            .field("memory", &self.memory.is_some())
+            .field("kernel", &self.kernel.is_some())
+            .field("isa", &self.isa)
+            .field("branch_unit", &self.branch_unit)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Config> BuilderCore<Config>
+where
+    Config: EmuConfig,
+{
+    /// Loads an ELF file into a new memory, starting the hart at its entry point. Fails if the ELF
+    /// file cannot be loaded into the hart, see [load_elf_checked].
+    pub fn load_elf(&mut self, elf_bytes: &[u8]) -> Result<(), String>
+    where
+        Config::Memory: Default,
+    {
+        let (memory, entry_pc) = load_elf_checked::<Config::Xlen, Config::Memory>(elf_bytes)?;
+        self.pc = entry_pc;
+        self.memory = Some(memory);
+        Ok(())
+    }
+
+    /// Returns the memory and the kernel of the hart.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the memory or kernel is not set.
+    pub(crate) const fn memory_and_kernel(&mut self) -> (Config::Memory, Config::Kernel) {
+        (
+            self.memory.take().expect("Memory not instantiated"),
+            self.kernel.take().expect("Kernel not instantiated"),
+        )
+    }
+}
+
+/// Implements the setters of a hart builder with a [BuilderCore] in its `hart` field: those of
+/// the [BuilderCore] for `core`, and those of the `caches` and `kanata` fields of the builder for
+/// each of them that is listed.
+macro_rules! builder_setters {
+    ($($group:ident),* $(,)?) => {
+        $($crate::builder::builder_setters!(@$group);)*
+    };
+    (@core) => {
+        /// Loads an elf file into the emulator builder, initializing the program counter and
+        /// memory. Fails if the class of the ELF file does not match the register width of the
+        /// hart.
+        pub fn with_elf(mut self, elf_bytes: &[u8]) -> Result<Self, alloc::string::String>
+        where
+            Config::Memory: Default,
+        {
+            self.hart.load_elf(elf_bytes)?;
+            Ok(self)
+        }
+
+        /// Assigns the entry point of the program.
+        pub const fn with_pc(mut self, pc: brisc_hw::XWord) -> Self {
+            self.hart.pc = pc;
+            self
+        }
+
+        /// Assigns a pre-created memory instance to the emulator.
+        pub fn with_memory(mut self, memory: Config::Memory) -> Self {
+            self.hart.memory = Some(memory);
+            self
+        }
+
+        /// Assigns the kernel to the emulator.
+        pub fn with_kernel(mut self, kernel: Config::Kernel) -> Self {
+            self.hart.kernel = Some(kernel);
+            self
+        }
+
+        /// Assigns the ISA extensions enabled on the hart.
+        pub const fn with_isa(mut self, isa: brisc_hw::IsaConfig<Config::Xlen>) -> Self {
+            self.hart.isa = isa;
+            self
+        }
+
+        /// Assigns the branch predictor of the hart, and records the outcome of its predictions.
+        pub fn with_predictor(
+            mut self,
+            predictor: impl brisc_hw::predictor::BranchPredictor + 'static,
+        ) -> Self {
+            self.hart.branch_unit = Some(brisc_hw::predictor::BranchUnit::new(predictor));
+            self
+        }
+
+        /// Assigns the observer of the execution of the hart.
+        pub fn with_observer(mut self, observer: Config::Observer) -> Self {
+            self.hart.observer = observer;
+            self
+        }
+    };
+    (@caches) => {
+        /// Assigns the cache hierarchy that observes the fetches and memory accesses of the hart.
+        pub fn with_caches(mut self, caches: brisc_hw::cache::CacheHierarchy) -> Self {
+            self.caches = Some(caches);
+            self
+        }
+    };
+    (@kanata) => {
+        /// Records the stages occupied by the instructions of the hart in `log`, cycle by cycle.
+        pub fn with_kanata(mut self, log: $crate::kanata::KanataLog) -> Self {
+            self.kanata = Some(log);
+            self
+        }
+    };
+}
+
+pub(crate) use builder_setters;
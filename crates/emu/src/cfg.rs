@@ -1,6 +1,7 @@
 //! Emulator type configuration trait
 
-use brisc_hw::{kernel::Kernel, memory::Memory};
+use crate::observer::Observer;
+use brisc_hw::{kernel::Kernel, memory::Memory, Xlen};
 
 /// The [`EmuConfig`] trait defines the type configuration for the emulator.
 pub trait EmuConfig {
@@ -9,4 +10,10 @@ pub trait EmuConfig {
 
     /// The kernel used by the emulator.
     type Kernel: Kernel;
+
+    /// The register width of the emulated hart.
+    type Xlen: Xlen;
+
+    /// The [Observer] of the execution of the hart. `()` observes nothing, at no cost.
+    type Observer: Observer<Self::Xlen> + Default;
 }
@@ -3,45 +3,94 @@
 use alloc::{
     format,
     string::{String, ToString},
+    vec::Vec,
 };
 use brisc_hw::{
     memory::{Address, Memory},
-    XWord,
+    XWord, Xlen,
 };
-use elf::{abi::PT_LOAD, endian::AnyEndian, ElfBytes};
+use elf::{abi::PT_LOAD, endian::AnyEndian, file::Class, ElfBytes};
 
-/// Load a raw ELF file into a fresh instance of [`Memory`].
+/// The `PT_RISCV_ATTRIBUTES` program header type, which is never loaded.
+const PT_RISCV_ATTRIBUTES: u32 = 0x70000003;
+
+/// A program segment that [load_elf] copies into memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadSegment<'a> {
+    /// The index of the segment's program header.
+    pub index: usize,
+    /// The program header type.
+    pub p_type: u32,
+    /// The program header flags.
+    pub flags: u32,
+    /// The virtual address the segment is loaded at.
+    pub vaddr: u64,
+    /// The size of the segment in memory. Any bytes past [Self::data] are zero-filled.
+    pub mem_size: u64,
+    /// The contents of the segment in the file.
+    pub data: &'a [u8],
+}
+
+/// Returns the register width that a raw ELF file targets, determined by its class.
 ///
 /// ### Takes
-/// - `raw`: The raw contents of the ELF file to load.
+/// - `raw`: The raw contents of the ELF file.
 ///
 /// ### Returns
-/// - `Ok((memory, entry_pc))` if the ELF file was loaded successfully
-/// - `Err(_)` if the ELF file could not be loaded
-pub fn load_elf<M>(raw: &[u8]) -> Result<(M, XWord), String>
-where
-    M: Memory + Default,
-{
+/// - `Ok(32)` or `Ok(64)` for `ELFCLASS32` and `ELFCLASS64` files
+/// - `Err(_)` if the ELF file could not be parsed
+pub fn elf_xlen(raw: &[u8]) -> Result<usize, String> {
     let elf = ElfBytes::<AnyEndian>::minimal_parse(raw)
         .map_err(|e| format!("Failed to parse ELF file: {e}"))?;
-    let mut memory = M::default();
+    Ok(match elf.ehdr.class {
+        Class::ELF32 => 32,
+        Class::ELF64 => 64,
+    })
+}
 
+/// Checks that a raw ELF file can be loaded into a hart with the register width `X`. There is no
+/// ELF class for RV128, so 128-bit harts load `ELFCLASS64` files.
+///
+/// ### Takes
+/// - `raw`: The raw contents of the ELF file.
+///
+/// ### Returns
+/// - `Ok(())` if the class of the ELF file matches the register width of the hart
+/// - `Err(_)` if the ELF file could not be parsed, or targets a different register width
+pub fn check_elf_xlen<X: Xlen>(raw: &[u8]) -> Result<(), String> {
+    let xlen = elf_xlen(raw)?;
+    if xlen != X::X_LEN && !(xlen == 64 && X::X_LEN == 128) {
+        return Err(format!("Cannot load a {xlen}-bit ELF file into a {}-bit hart", X::X_LEN));
+    }
+    Ok(())
+}
+
+/// Parses the program segments of a raw ELF file that [load_elf] loads into memory.
+///
+/// ### Takes
+/// - `raw`: The raw contents of the ELF file.
+///
+/// ### Returns
+/// - `Ok((segments, entry_pc))` if the ELF file's segments are valid
+/// - `Err(_)` if the ELF file could not be parsed, or a segment cannot be loaded
+pub fn load_segments(raw: &[u8]) -> Result<(Vec<LoadSegment<'_>>, XWord), String> {
+    let elf = ElfBytes::<AnyEndian>::minimal_parse(raw)
+        .map_err(|e| format!("Failed to parse ELF file: {e}"))?;
+
+    let mut segments = Vec::new();
     let headers = elf.segments().ok_or("Failed to load section headers")?;
     for (i, header) in headers.iter().enumerate() {
-        if header.p_type == 0x70000003 {
+        if header.p_type == PT_RISCV_ATTRIBUTES {
             continue;
         }
 
         let segment_data =
             elf.segment_data(&header).map_err(|e| format!("Failed to fetch section data: {e}"))?;
         let section_data = &segment_data[..header.p_filesz as usize];
-        let mut data = section_data.to_vec();
 
         if header.p_filesz != header.p_memsz {
             if header.p_type == PT_LOAD {
-                if header.p_filesz < header.p_memsz {
-                    data.resize(data.len() + (header.p_memsz - header.p_filesz) as usize, 0);
-                } else {
+                if header.p_filesz > header.p_memsz {
                     return Err(format!(
                         "Invalid PT_LOAD program segment {}, file size ({}) > mem size ({})",
                         i, header.p_filesz, header.p_memsz
@@ -67,10 +116,60 @@ where
             ));
         }
 
+        segments.push(LoadSegment {
+            index: i,
+            p_type: header.p_type,
+            flags: header.p_flags,
+            vaddr: header.p_vaddr,
+            mem_size: header.p_memsz,
+            data: section_data,
+        });
+    }
+
+    Ok((segments, elf.ehdr.e_entry as XWord))
+}
+
+/// Load a raw ELF file into a fresh instance of [`Memory`].
+///
+/// ### Takes
+/// - `raw`: The raw contents of the ELF file to load.
+///
+/// ### Returns
+/// - `Ok((memory, entry_pc))` if the ELF file was loaded successfully
+/// - `Err(_)` if the ELF file could not be loaded
+pub fn load_elf<M>(raw: &[u8]) -> Result<(M, XWord), String>
+where
+    M: Memory + Default,
+{
+    let (segments, entry_pc) = load_segments(raw)?;
+    let mut memory = M::default();
+
+    for segment in segments {
+        let mut data = segment.data.to_vec();
+        data.resize(segment.mem_size as usize, 0);
+
         memory
-            .set_memory_range(header.p_vaddr as Address, &mut data.as_slice())
+            .set_memory_range(segment.vaddr as Address, &mut data.as_slice())
             .map_err(|e| e.to_string())?;
     }
 
-    Ok((memory, elf.ehdr.e_entry as XWord))
+    Ok((memory, entry_pc))
+}
+
+/// Load a raw ELF file into a fresh instance of [`Memory`], for a hart with the register width
+/// `X`. This is [load_elf], after [check_elf_xlen].
+///
+/// ### Takes
+/// - `raw`: The raw contents of the ELF file to load.
+///
+/// ### Returns
+/// - `Ok((memory, entry_pc))` if the ELF file was loaded successfully
+/// - `Err(_)` if the ELF file could not be loaded, or targets a different register width
+pub fn load_elf_checked<X, M>(raw: &[u8]) -> Result<(M, XWord), String>
+where
+    X: Xlen,
+    M: Memory + Default,
+{
+    check_elf_xlen::<X>(raw)?;
+    load_elf(raw)
 }
@@ -1,4 +1,12 @@
 //! Utilities for ELF file parsing and tracing.
 
 mod load;
-pub use load::load_elf;
+pub use load::{check_elf_xlen, elf_xlen, load_elf, load_elf_checked, load_segments, LoadSegment};
+
+mod symbols;
+pub use symbols::function_symbols;
+
+#[cfg(feature = "c")]
+mod rvc;
+#[cfg(feature = "c")]
+pub use rvc::{compression_report, CompressionReport};
@@ -0,0 +1,137 @@
+//! Compressed instruction usage statistics for ELF files.
+
+use super::elf_xlen;
+use alloc::{format, string::String};
+use brisc_hw::{is_compressed, Instruction, Rv32, Word, Xlen};
+use elf::{abi::SHF_EXECINSTR, endian::AnyEndian, ElfBytes};
+
+/// A summary of how a program's executable sections use the `c` extension.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionReport {
+    /// The total number of instructions.
+    pub instructions: usize,
+    /// The number of instructions already encoded in their 16-bit form.
+    pub compressed: usize,
+    /// The number of 32-bit instructions that have an equivalent 16-bit form.
+    pub compressible: usize,
+    /// The number of 32-bit words that could not be decoded, such as data within `.text`.
+    pub undecodable: usize,
+}
+
+impl CompressionReport {
+    /// Returns the number of bytes that would be saved by compressing every compressible
+    /// instruction.
+    pub const fn bytes_saved(&self) -> usize {
+        self.compressible * 2
+    }
+}
+
+/// Walks the executable sections of a raw ELF file, counting the instructions that are compressed
+/// and the ones that could have been. Instructions are decoded and compressed for the register
+/// width of the ELF class.
+pub fn compression_report(raw: &[u8]) -> Result<CompressionReport, String> {
+    match elf_xlen(raw)? {
+        32 => compression_report_for::<Rv32>(raw),
+        #[cfg(feature = "64-bit")]
+        64 => compression_report_for::<brisc_hw::Rv64>(raw),
+        xlen => Err(format!("Unsupported {xlen}-bit ELF file")),
+    }
+}
+
+/// Builds the [CompressionReport] of a raw ELF file for a hart with the register width `X`.
+fn compression_report_for<X: Xlen>(raw: &[u8]) -> Result<CompressionReport, String> {
+    let elf = ElfBytes::<AnyEndian>::minimal_parse(raw)
+        .map_err(|e| format!("Failed to parse ELF file: {e}"))?;
+    let headers = elf.section_headers().ok_or("Failed to load section headers")?;
+
+    let mut report = CompressionReport::default();
+    for header in headers.iter().filter(|h| h.sh_flags & SHF_EXECINSTR as u64 != 0) {
+        let (data, _) =
+            elf.section_data(&header).map_err(|e| format!("Failed to fetch section data: {e}"))?;
+
+        let mut offset = 0;
+        while offset + 2 <= data.len() {
+            let low = u16::from_le_bytes([data[offset], data[offset + 1]]);
+            report.instructions += 1;
+
+            if is_compressed(low as Word) {
+                report.compressed += 1;
+                offset += 2;
+                continue;
+            }
+
+            let Some(bytes) = data.get(offset..offset + 4) else {
+                report.undecodable += 1;
+                break;
+            };
+            let word = Word::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            match Instruction::decode_for::<X>(word) {
+                Ok(instruction) if instruction.compress_for::<X>().is_some() => {
+                    report.compressible += 1
+                }
+                Ok(_) => {}
+                Err(_) => report.undecodable += 1,
+            }
+            offset += 4;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use brisc_isa::asm::{assemble, assemble_for};
+
+    #[test]
+    fn test_compression_report_assembled() {
+        let program = assemble(
+            "
+            _start:
+                li a0, 1
+                mv a1, a0
+                li a0, 0x12345
+                ecall
+            ",
+        )
+        .unwrap();
+
+        // `li a0, 1` and `mv` have 16-bit forms, as does the `lui` of the wide `li`.
+        let report = compression_report(&program.to_elf()).unwrap();
+        assert_eq!(
+            report,
+            CompressionReport { instructions: 5, compressed: 0, compressible: 3, undecodable: 0 }
+        );
+        assert_eq!(report.bytes_saved(), 6);
+    }
+
+    #[test]
+    fn test_compression_report_rv32() {
+        // `jal ra` only has a 16-bit form (`c.jal`) on RV32, whatever the widest XLEN of the build.
+        const SOURCE: &str = "_start: jal ra, _start\nslli a0, a0, 31";
+        let report = compression_report(&assemble_for::<Rv32>(SOURCE).unwrap().to_elf()).unwrap();
+        assert_eq!(
+            report,
+            CompressionReport { instructions: 2, compressed: 0, compressible: 2, undecodable: 0 }
+        );
+
+        #[cfg(feature = "64-bit")]
+        {
+            let report = compression_report(&assemble(SOURCE).unwrap().to_elf()).unwrap();
+            assert_eq!(report.compressible, 1);
+        }
+    }
+
+    #[test]
+    fn test_compression_report_compressed_elf() {
+        let path = if cfg!(feature = "64-bit") {
+            "../../rv-tests/bin/rs-64bit-fib"
+        } else {
+            "../../rv-tests/bin/rs-32bit-fib"
+        };
+        let report = compression_report(&std::fs::read(path).unwrap()).unwrap();
+        assert!(report.compressed > 0);
+        assert!(report.compressed + report.compressible <= report.instructions);
+    }
+}
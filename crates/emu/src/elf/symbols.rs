@@ -0,0 +1,49 @@
+//! ELF symbol table utilities.
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+};
+use brisc_hw::XWord;
+use elf::{
+    abi::{SHN_UNDEF, STT_FUNC},
+    endian::AnyEndian,
+    ElfBytes,
+};
+
+/// Collects the function symbols of a raw ELF file, keyed by address. Where several functions
+/// share an address, the first one in the symbol table is kept.
+///
+/// ### Takes
+/// - `raw`: The raw contents of the ELF file.
+///
+/// ### Returns
+/// - `Ok(symbols)` with the named `STT_FUNC` symbols, which is empty if the file is stripped
+/// - `Err(_)` if the ELF file or its symbol table could not be parsed
+pub fn function_symbols(raw: &[u8]) -> Result<BTreeMap<XWord, String>, String> {
+    let elf = ElfBytes::<AnyEndian>::minimal_parse(raw)
+        .map_err(|e| format!("Failed to parse ELF file: {e}"))?;
+
+    let mut symbols = BTreeMap::new();
+    let Some((table, strtab)) =
+        elf.symbol_table().map_err(|e| format!("Failed to parse symbol table: {e}"))?
+    else {
+        return Ok(symbols);
+    };
+
+    for symbol in table.iter() {
+        if symbol.st_shndx == SHN_UNDEF || symbol.st_symtype() != STT_FUNC {
+            continue;
+        }
+
+        let name = strtab
+            .get(symbol.st_name as usize)
+            .map_err(|e| format!("Failed to read symbol name: {e}"))?;
+        if !name.is_empty() {
+            symbols.entry(symbol.st_value as XWord).or_insert_with(|| name.to_string());
+        }
+    }
+
+    Ok(symbols)
+}
@@ -0,0 +1,276 @@
+//! Export of the pipeline occupancy of a run in the Kanata log format, for the [Konata] viewer.
+//!
+//! A Kanata log is a sequence of tab-separated commands, where `I` introduces an instruction, `L`
+//! labels it, `S` starts one of its stages at the current cycle, `R` retires or flushes it, and
+//! `C` advances the current cycle.
+//!
+//! [Konata]: https://github.com/shioyadan/Konata
+
+use alloc::{boxed::Box, vec::Vec};
+use brisc_hw::{pipeline::PipelineRegister, Xlen};
+use core::fmt::{self, Debug, Display, Formatter, Write};
+
+pub use crate::observer::Stage;
+
+/// A Kanata log, recorded cycle by cycle and streamed to a [Write] sink as the hart runs.
+///
+/// Instructions are identified by the sequence number returned by [Self::start]. Retirements and
+/// flushes take effect at the end of the current cycle, so that the last stage of an instruction
+/// is shown as occupied for that cycle.
+pub struct KanataLog {
+    /// The sink that the commands of the log are written to.
+    sink: Box<dyn Write>,
+    /// Whether writing to the sink failed, after which nothing more is written.
+    failed: bool,
+    /// The sequence number of the next instruction.
+    next_id: u64,
+    /// The number of instructions retired so far.
+    retired: u64,
+    /// The instructions leaving the pipeline at the end of the current cycle, and whether they
+    /// were flushed.
+    leaving: Vec<(u64, bool)>,
+}
+
+impl Debug for KanataLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KanataLog")
+            .field("failed", &self.failed)
+            .field("next_id", &self.next_id)
+            .field("retired", &self.retired)
+            .field("leaving", &self.leaving)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KanataLog {
+    /// Creates a new [KanataLog] starting at cycle 0, writing its header to `sink`.
+    pub fn new(sink: impl Write + 'static) -> Self {
+        let mut log = Self {
+            sink: Box::new(sink),
+            failed: false,
+            next_id: 0,
+            retired: 0,
+            leaving: Vec::new(),
+        };
+        log.command(format_args!("Kanata\t0004"));
+        log.command(format_args!("C=\t0"));
+        log
+    }
+
+    /// Returns `true` if writing to the sink failed. The log stops at the first failure, so the
+    /// sink holds a truncated log.
+    pub const fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Introduces a new instruction with the given label, and returns its sequence number.
+    pub fn start(&mut self, label: impl Display) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.command(format_args!("I\t{id}\t{id}\t0"));
+        self.command(format_args!("L\t{id}\t0\t{label}"));
+        id
+    }
+
+    /// Introduces the instruction fetched into `p_reg`, labelled with its address and disassembly,
+    /// and returns its sequence number.
+    pub fn start_instruction<X: Xlen>(&mut self, p_reg: &PipelineRegister<X>) -> u64 {
+        let pc = p_reg.pc;
+        match p_reg.instruction {
+            Some(decoded) => self.start(format_args!("{pc:08x}: {decoded}")),
+            None => self.start(format_args!("{pc:08x}: ?")),
+        }
+    }
+
+    /// Starts `stage` of the instruction `id` at the current cycle, ending its previous stage.
+    pub fn stage(&mut self, id: u64, stage: Stage) {
+        self.command(format_args!("S\t{id}\t0\t{}", stage.name()));
+    }
+
+    /// Retires the instruction `id` at the end of the current cycle.
+    pub fn retire(&mut self, id: u64) {
+        self.leaving.push((id, false));
+    }
+
+    /// Flushes the instruction `id` at the end of the current cycle.
+    pub fn flush(&mut self, id: u64) {
+        self.leaving.push((id, true));
+    }
+
+    /// Ends the current cycle, removing the instructions that retired or were flushed during it.
+    pub fn cycle(&mut self) {
+        self.command(format_args!("C\t1"));
+        for (id, flushed) in core::mem::take(&mut self.leaving) {
+            if flushed {
+                self.command(format_args!("R\t{id}\t0\t1"));
+            } else {
+                let retire_id = self.retired;
+                self.retired += 1;
+                self.command(format_args!("R\t{id}\t{retire_id}\t0"));
+            }
+        }
+    }
+
+    /// Records the instruction in `p_reg`, once it has passed through every stage of a
+    /// single-cycle hart, as occupying each stage for one cycle in turn.
+    pub fn record<X: Xlen>(&mut self, p_reg: &PipelineRegister<X>) {
+        let id = self.start_instruction(p_reg);
+        for stage in Stage::ALL {
+            self.stage(id, stage);
+            if stage == Stage::Writeback {
+                self.retire(id);
+            }
+            self.cycle();
+        }
+    }
+
+    /// Writes a command to the sink, unless a previous write failed.
+    fn command(&mut self, command: fmt::Arguments<'_>) {
+        if !self.failed {
+            self.failed = writeln!(self.sink, "{command}").is_err();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::KanataLog;
+    use crate::{
+        pipelined::PipelinedEmu,
+        st::StEmu,
+        test_utils::{assembled, SharedString, TestConfig},
+    };
+
+    const PROGRAM: &str = r#"
+        .text
+        .globl _start
+        _start:
+            li a0, 1
+            j exit
+            li a0, 2
+        exit:
+            li a7, 93
+            ecall
+    "#;
+
+    /// Returns the commands written to `sink`, with their fields separated by spaces.
+    fn commands(sink: &SharedString) -> Vec<String> {
+        sink.text().lines().map(|command| command.replace('\t', " ")).collect()
+    }
+
+    #[test]
+    fn test_sink_failure() {
+        /// A sink that rejects every write.
+        struct Full;
+
+        impl core::fmt::Write for Full {
+            fn write_str(&mut self, _: &str) -> core::fmt::Result {
+                Err(core::fmt::Error)
+            }
+        }
+
+        let mut log = KanataLog::new(Full);
+        assert!(log.failed());
+        log.cycle();
+        assert!(log.failed());
+        assert!(!KanataLog::new(SharedString::default()).failed());
+    }
+
+    #[test]
+    fn test_single_cycle_log() {
+        let sink = SharedString::default();
+        let mut hart = assembled::<StEmu<_>, TestConfig>(PROGRAM)
+            .with_kanata(KanataLog::new(sink.clone()))
+            .build();
+        hart.run().unwrap();
+        assert!(!hart.kanata.unwrap().failed());
+        let commands = commands(&sink);
+
+        // Every instruction occupies each stage for a cycle, and retires before the next starts.
+        assert_eq!(
+            commands[..16],
+            [
+                "Kanata 0004",
+                "C= 0",
+                "I 0 0 0",
+                "L 0 0 00010000: li a0, 1",
+                "S 0 0 IF",
+                "C 1",
+                "S 0 0 ID",
+                "C 1",
+                "S 0 0 EX",
+                "C 1",
+                "S 0 0 MEM",
+                "C 1",
+                "S 0 0 WB",
+                "C 1",
+                "R 0 0 0",
+                "I 1 1 0",
+            ]
+        );
+        assert_eq!(commands.iter().filter(|command| *command == "C 1").count(), 4 * 5);
+        assert_eq!(commands.last().unwrap(), "R 3 3 0");
+    }
+
+    #[test]
+    fn test_pipelined_log() {
+        let sink = SharedString::default();
+        let mut hart = assembled::<PipelinedEmu<_>, TestConfig>(PROGRAM)
+            .with_kanata(KanataLog::new(sink.clone()))
+            .build();
+        hart.run().unwrap();
+
+        // The instructions overlap. `li a0, 2` is fetched after the jump and flushed from ID, and
+        // `ecall` waits in ID until `li a7` retires, alongside it.
+        assert_eq!(
+            commands(&sink),
+            [
+                "Kanata 0004",
+                "C= 0",
+                "I 0 0 0",
+                "L 0 0 00010000: li a0, 1",
+                "S 0 0 IF",
+                "C 1",
+                "S 0 0 ID",
+                "I 1 1 0",
+                "L 1 0 00010004: j .+8",
+                "S 1 0 IF",
+                "C 1",
+                "S 0 0 EX",
+                "S 1 0 ID",
+                "I 2 2 0",
+                "L 2 0 00010008: li a0, 2",
+                "S 2 0 IF",
+                "C 1",
+                "S 0 0 MEM",
+                "S 1 0 EX",
+                "S 2 0 ID",
+                "C 1",
+                "R 2 0 1",
+                "S 0 0 WB",
+                "S 1 0 MEM",
+                "I 3 3 0",
+                "L 3 0 0001000c: li a7, 93",
+                "S 3 0 IF",
+                "C 1",
+                "R 0 0 0",
+                "S 1 0 WB",
+                "S 3 0 ID",
+                "I 4 4 0",
+                "L 4 0 00010010: ecall",
+                "S 4 0 IF",
+                "C 1",
+                "R 1 1 0",
+                "S 3 0 EX",
+                "S 4 0 ID",
+                "C 1",
+                "S 3 0 MEM",
+                "C 1",
+                "S 3 0 WB",
+                "C 1",
+                "R 3 2 0",
+                "R 4 3 0",
+            ]
+        );
+    }
+}
@@ -7,9 +7,15 @@
 
 extern crate alloc;
 
+pub mod builder;
 pub mod cfg;
 pub mod elf;
+pub mod kanata;
+pub mod observer;
+pub mod ooo;
+pub mod pipelined;
 pub mod st;
+pub mod vcd;
 
 #[cfg(any(test, feature = "test-utils"))]
 pub mod test_utils;
@@ -0,0 +1,379 @@
+//! Hooks observing the execution of a hart, stage by stage.
+//!
+//! Every hart calls the [Observer] of its [EmuConfig] as instructions pass through the stages.
+//! All of its methods do nothing by default, and are statically dispatched, and the memory
+//! accesses of an instruction are only recorded for an observer that sets
+//! [Observer::OBSERVES_MEMORY], so the `()` observer is removed entirely by the compiler.
+//!
+//! [EmuConfig]: crate::cfg::EmuConfig
+
+use alloc::vec::Vec;
+use brisc_hw::{
+    errors::PipelineResult,
+    kernel::Kernel,
+    memory::{Address, Memory, MemoryResult, Page, PageIndex},
+    pipeline::{mem_access, PipelineRegister},
+    Byte, DoubleWord, HalfWord, Word, XWord, Xlen,
+};
+use core::cell::RefCell;
+
+#[cfg(feature = "128-bit")]
+use brisc_hw::QuadWord;
+
+/// A stage of the 5-stage pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Instruction fetch.
+    Fetch,
+    /// Instruction decode and register read.
+    Decode,
+    /// Execution.
+    Execute,
+    /// Memory access.
+    Memory,
+    /// Register writeback.
+    Writeback,
+}
+
+impl Stage {
+    /// Every stage, in pipeline order.
+    pub const ALL: [Self; 5] =
+        [Self::Fetch, Self::Decode, Self::Execute, Self::Memory, Self::Writeback];
+
+    /// Returns the name of the stage.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Fetch => "IF",
+            Self::Decode => "ID",
+            Self::Execute => "EX",
+            Self::Memory => "MEM",
+            Self::Writeback => "WB",
+        }
+    }
+}
+
+/// Observes the execution of a hart.
+///
+/// The single-cycle and out-of-order harts run each instruction through the stages in turn, and
+/// the pipelined hart calls the hooks of every instruction in flight as its stages complete,
+/// including those of the instructions flushed down a mispredicted path. System calls stop in ID,
+/// where they are handled by the kernel, and retire without passing through the later stages.
+pub trait Observer<X: Xlen> {
+    /// Whether the observer implements [Self::on_memory_read] and [Self::on_memory_write]. The
+    /// harts only record the memory accesses of instructions for the observers that set it.
+    const OBSERVES_MEMORY: bool = false;
+
+    /// Called once `stage` has completed for the instruction in `p_reg`.
+    #[inline(always)]
+    fn on_stage(&mut self, stage: Stage, p_reg: &PipelineRegister<X>) {
+        _ = (stage, p_reg);
+    }
+
+    /// Called when the instruction in `p_reg` retires, with its result written back.
+    #[inline(always)]
+    fn on_retire(&mut self, p_reg: &PipelineRegister<X>) {
+        _ = p_reg;
+    }
+
+    /// Called before the kernel handles the system call `syscall_no`, with the architectural
+    /// state in `p_reg`.
+    #[inline(always)]
+    fn on_syscall(&mut self, syscall_no: XWord, p_reg: &PipelineRegister<X>) {
+        _ = (syscall_no, p_reg);
+    }
+
+    /// Called when the instruction in `p_reg` has read `bytes` bytes at `address`, or the kernel
+    /// has during the system call in `p_reg`. The value read by a load is in
+    /// [PipelineRegister::memory].
+    #[inline(always)]
+    fn on_memory_read(&mut self, p_reg: &PipelineRegister<X>, address: XWord, bytes: usize) {
+        _ = (p_reg, address, bytes);
+    }
+
+    /// Called when the instruction in `p_reg` has written `bytes` bytes at `address`, or the
+    /// kernel has during the system call in `p_reg`. The value written by a store is in
+    /// [PipelineRegister::rs2_value].
+    #[inline(always)]
+    fn on_memory_write(&mut self, p_reg: &PipelineRegister<X>, address: XWord, bytes: usize) {
+        _ = (p_reg, address, bytes);
+    }
+
+    /// Called at the end of every cycle of the hart, with the architectural register file in
+    /// `registers`. The single-cycle hart ends a cycle with every instruction that it retires.
+    #[inline(always)]
+    fn on_cycle(&mut self, registers: &[XWord; 32]) {
+        _ = registers;
+    }
+}
+
+/// The default [Observer], which observes nothing.
+impl<X: Xlen> Observer<X> for () {}
+
+/// A memory access made through an [ObservedMemory].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MemoryAccess {
+    /// The address of the first byte accessed.
+    pub(crate) address: Address,
+    /// The number of bytes accessed.
+    pub(crate) bytes: usize,
+    /// Whether the access was a write.
+    pub(crate) write: bool,
+}
+
+/// A [Memory] recording the accesses made through it, for the harts to run MEM and system calls
+/// through when their accesses are needed, with [mem_access_recorded] and [syscall_recorded]. The
+/// accesses are reported once the instruction has left MEM, or the kernel has handled its system
+/// call, with [observe_accesses].
+///
+/// Only the accesses that succeed are recorded. The pages of the memory are reached directly, and
+/// their accesses are not recorded.
+#[derive(Debug)]
+pub(crate) struct ObservedMemory<'a, M> {
+    /// The memory of the hart.
+    memory: &'a mut M,
+    /// The accesses made, in order.
+    accesses: RefCell<&'a mut Vec<MemoryAccess>>,
+}
+
+impl<'a, M: Memory> ObservedMemory<'a, M> {
+    /// Creates a new [ObservedMemory] over `memory`, recording the accesses made in `accesses`.
+    pub(crate) const fn new(memory: &'a mut M, accesses: &'a mut Vec<MemoryAccess>) -> Self {
+        Self { memory, accesses: RefCell::new(accesses) }
+    }
+
+    /// Records an access of `bytes` bytes at `address`, if `res` succeeded, and returns it.
+    fn record<T>(
+        &self,
+        res: MemoryResult<T>,
+        address: Address,
+        bytes: usize,
+        write: bool,
+    ) -> MemoryResult<T> {
+        if res.is_ok() {
+            self.accesses.borrow_mut().push(MemoryAccess { address, bytes, write });
+        }
+        res
+    }
+}
+
+/// Implements the getter and setter of `$ty` on an [ObservedMemory], recording their accesses.
+macro_rules! observed_accessors {
+    ($($(#[$attr:meta])* $get:ident, $set:ident: $ty:ty;)+) => {
+        $(
+            $(#[$attr])*
+            fn $get(&self, address: Address) -> MemoryResult<$ty> {
+                let res = self.memory.$get(address);
+                self.record(res, address, size_of::<$ty>(), false)
+            }
+
+            $(#[$attr])*
+            fn $set(&mut self, address: Address, value: $ty) -> MemoryResult<()> {
+                let res = self.memory.$set(address, value);
+                self.record(res, address, size_of::<$ty>(), true)
+            }
+        )+
+    };
+}
+
+impl<M: Memory> Memory for ObservedMemory<'_, M> {
+    fn page_count(&self) -> usize {
+        self.memory.page_count()
+    }
+
+    fn alloc(&mut self, page_index: PageIndex) -> MemoryResult<&mut Page> {
+        self.memory.alloc(page_index)
+    }
+
+    fn page(&self, page_index: PageIndex) -> Option<&Page> {
+        self.memory.page(page_index)
+    }
+
+    fn page_mut(&mut self, page_index: PageIndex) -> Option<&mut Page> {
+        self.memory.page_mut(page_index)
+    }
+
+    observed_accessors! {
+        get_byte, set_byte: Byte;
+        get_halfword, set_halfword: HalfWord;
+        get_word, set_word: Word;
+        get_doubleword, set_doubleword: DoubleWord;
+        #[cfg(feature = "128-bit")]
+        get_quadword, set_quadword: QuadWord;
+    }
+
+    fn set_memory_range(&mut self, address: Address, data: &mut &[u8]) -> MemoryResult<()> {
+        let bytes = data.len();
+        let res = self.memory.set_memory_range(address, data);
+        self.record(res, address, bytes, true)
+    }
+
+    fn read_memory_range(&mut self, address: Address, len: XWord) -> MemoryResult<Vec<u8>> {
+        let res = self.memory.read_memory_range(address, len);
+        self.record(res, address, len as usize, false)
+    }
+}
+
+/// Runs MEM for the instruction in `p_reg`, recording its accesses in `accesses` if it is [Some].
+/// Otherwise, the memory is accessed directly.
+#[inline(always)]
+pub(crate) fn mem_access_recorded<M: Memory, X: Xlen>(
+    p_reg: &mut PipelineRegister<X>,
+    memory: &mut M,
+    accesses: Option<&mut Vec<MemoryAccess>>,
+) -> PipelineResult<()> {
+    match accesses {
+        Some(accesses) => mem_access(p_reg, &mut ObservedMemory::new(memory, accesses)),
+        None => mem_access(p_reg, memory),
+    }
+}
+
+/// Has `kernel` handle the system call `syscall_no` of the instruction in `p_reg`, recording its
+/// accesses in `accesses` if it is [Some]. Otherwise, the memory is accessed directly.
+#[inline(always)]
+pub(crate) fn syscall_recorded<K: Kernel, M: Memory, X: Xlen>(
+    kernel: &mut K,
+    syscall_no: XWord,
+    memory: &mut M,
+    p_reg: &mut PipelineRegister<X>,
+    accesses: Option<&mut Vec<MemoryAccess>>,
+) -> PipelineResult<XWord> {
+    match accesses {
+        Some(accesses) => {
+            kernel.syscall(syscall_no, &mut ObservedMemory::new(memory, accesses), p_reg)
+        }
+        None => kernel.syscall(syscall_no, memory, p_reg),
+    }
+}
+
+/// Calls the memory hooks of `observer` for the `accesses` made by the instruction in `p_reg`, or
+/// by the kernel during its system call.
+#[inline(always)]
+pub(crate) fn observe_accesses<X: Xlen>(
+    observer: &mut impl Observer<X>,
+    p_reg: &PipelineRegister<X>,
+    accesses: &[MemoryAccess],
+) {
+    for access in accesses {
+        if access.write {
+            observer.on_memory_write(p_reg, access.address, access.bytes);
+        } else {
+            observer.on_memory_read(p_reg, access.address, access.bytes);
+        }
+    }
+}
+
+/// Calls the [Stage::Memory] hook of `observer` for the instruction in `p_reg`, after those of the
+/// memory `accesses` that it made.
+#[inline(always)]
+pub(crate) fn observe_memory<X: Xlen>(
+    observer: &mut impl Observer<X>,
+    p_reg: &PipelineRegister<X>,
+    accesses: &[MemoryAccess],
+) {
+    observe_accesses(observer, p_reg, accesses);
+    observer.on_stage(Stage::Memory, p_reg);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Observer, Stage};
+    use crate::{
+        ooo::OooEmu,
+        pipelined::PipelinedEmu,
+        st::StEmu,
+        test_utils::{loaded, TestConfig},
+    };
+    use brisc_hw::{pipeline::PipelineRegister, NativeXlen, XWord};
+    use brisc_isa::asm::assemble;
+
+    const PROGRAM: &str = r#"
+        .data
+        value: .word 0
+
+        .text
+        .globl _start
+        _start:
+            la a1, value
+            li a0, 42
+            sw a0, 0(a1)
+            lw a0, 0(a1)
+            li a0, 1
+            li a2, 4
+            li a7, 64
+            ecall
+            li a7, 93
+            ecall
+    "#;
+
+    /// An [Observer] counting the events of a run.
+    #[derive(Debug, Default, PartialEq, Eq)]
+    struct Counter {
+        stages: [u64; 5],
+        retired: u64,
+        syscalls: Vec<XWord>,
+        reads: Vec<(XWord, usize, Option<XWord>)>,
+        writes: Vec<(XWord, usize, XWord)>,
+    }
+
+    impl Observer<NativeXlen> for Counter {
+        const OBSERVES_MEMORY: bool = true;
+
+        fn on_stage(&mut self, stage: Stage, _: &PipelineRegister<NativeXlen>) {
+            self.stages[stage as usize] += 1;
+        }
+
+        fn on_retire(&mut self, _: &PipelineRegister<NativeXlen>) {
+            self.retired += 1;
+        }
+
+        fn on_syscall(&mut self, syscall_no: XWord, _: &PipelineRegister<NativeXlen>) {
+            self.syscalls.push(syscall_no);
+        }
+
+        fn on_memory_read(
+            &mut self,
+            p_reg: &PipelineRegister<NativeXlen>,
+            address: XWord,
+            bytes: usize,
+        ) {
+            self.reads.push((address, bytes, p_reg.memory));
+        }
+
+        fn on_memory_write(
+            &mut self,
+            p_reg: &PipelineRegister<NativeXlen>,
+            address: XWord,
+            bytes: usize,
+        ) {
+            self.writes.push((address, bytes, p_reg.rs2_value.unwrap()));
+        }
+    }
+
+    /// The [TestConfig] of a hart observed by a [Counter].
+    type CountingConfig = TestConfig<NativeXlen, Counter>;
+
+    #[test]
+    fn test_observers_agree() {
+        let elf = assemble(PROGRAM).unwrap().to_elf();
+        let mut st = loaded::<StEmu<_>, CountingConfig>(&elf).build();
+        st.run().unwrap();
+        let mut pipelined = loaded::<PipelinedEmu<_>, CountingConfig>(&elf).build();
+        pipelined.run().unwrap();
+        let mut ooo = loaded::<OooEmu<_>, CountingConfig>(&elf).build();
+        ooo.run().unwrap();
+
+        // `ecall` stops in ID, so it is fetched but never completes a later stage. The kernel
+        // reads the buffer passed to `write`.
+        let counter = st.observer;
+        assert_eq!((counter.stages, counter.retired), ([11, 9, 9, 9, 9], 11));
+        assert_eq!(counter.syscalls, [64, 93]);
+        let (address, bytes, value) = counter.writes[0];
+        assert_eq!((bytes, value), (4, 42));
+        assert_eq!(counter.reads, [(address, 4, Some(42)), (address, 4, None)]);
+
+        // The pipelined hart keeps fetching behind `ecall` until it runs.
+        assert_eq!(pipelined.observer.stages[1..], counter.stages[1..]);
+        assert_eq!(Counter { stages: counter.stages, ..pipelined.observer }, counter);
+        assert_eq!(ooo.observer, counter);
+    }
+}
@@ -0,0 +1,70 @@
+//! A builder for the [`OooEmu`] emulator.
+
+use super::OooEmu;
+use crate::{
+    builder::{builder_setters, BuilderCore},
+    cfg::EmuConfig,
+};
+use alloc::vec::Vec;
+use brisc_hw::{
+    ooo::{OooConfig, OooCore},
+    pipeline::PipelineRegister,
+};
+
+/// A builder for the [`OooEmu`] emulator.
+#[derive(Debug)]
+pub struct OooEmuBuilder<Config>
+where
+    Config: EmuConfig,
+{
+    /// The configuration shared by the builders of every hart.
+    pub hart: BuilderCore<Config>,
+    /// The out-of-order core. Defaults to a 4-wide core with a 64-entry reorder buffer and a
+    /// 16-entry load/store queue.
+    pub core: OooCore,
+}
+
+impl<Config> Default for OooEmuBuilder<Config>
+where
+    Config: EmuConfig,
+{
+    fn default() -> Self {
+        Self {
+            hart: BuilderCore::default(),
+            core: OooCore::new(OooConfig::new(4, 64, 16)).expect("Invalid default configuration"),
+        }
+    }
+}
+
+impl<Config> OooEmuBuilder<Config>
+where
+    Config: EmuConfig,
+{
+    builder_setters!(core);
+
+    /// Assigns the out-of-order core that times the instructions executed by the hart.
+    pub fn with_core(mut self, core: OooCore) -> Self {
+        self.core = core;
+        self
+    }
+
+    /// Builds the emulator with the current configuration.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the memory or kernel is not set.
+    pub fn build(mut self) -> OooEmu<Config> {
+        let (memory, kernel) = self.hart.memory_and_kernel();
+        OooEmu {
+            register: PipelineRegister::new(self.hart.pc),
+            core: self.core,
+            memory,
+            kernel,
+            isa: self.hart.isa,
+            branch_unit: self.hart.branch_unit,
+            observer: self.hart.observer,
+            pending: None,
+            accesses: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Out-of-order RISC-V core model, executing instructions in order and timing them out of order.
+
+use crate::{
+    cfg::EmuConfig,
+    observer::{
+        mem_access_recorded, observe_accesses, observe_memory, syscall_recorded, MemoryAccess,
+        Observer, Stage,
+    },
+};
+use alloc::vec::Vec;
+use brisc_hw::{
+    errors::{PipelineError, PipelineResult},
+    ooo::OooCore,
+    pipeline::{decode_instruction, execute, instruction_fetch, writeback, PipelineRegister},
+    predictor::BranchUnit,
+    IsaConfig,
+};
+
+mod builder;
+pub use builder::OooEmuBuilder;
+
+/// Out-of-order RISC-V processor emulator.
+///
+/// The [OooEmu] executes every instruction through the five stages in program order, as the
+/// [StEmu] does, and hands it to an [OooCore] which models its dispatch, issue and retirement.
+/// Instructions are only executed once the core has room to dispatch them, so a run takes as many
+/// [cycle]s as the core needs to retire the program.
+///
+/// Branches are predicted by the [Self::branch_unit], or not taken if there is none, and resolved
+/// once executed. The core stops dispatch behind a mispredicted branch until it completes.
+///
+/// [StEmu]: crate::st::StEmu
+/// [cycle]: Self::cycle
+#[derive(Debug)]
+pub struct OooEmu<Config>
+where
+    Config: EmuConfig,
+{
+    /// The architectural state of the hart, after the last instruction executed.
+    pub register: PipelineRegister<Config::Xlen>,
+    /// The timing model of the out-of-order core.
+    pub core: OooCore,
+    /// The device memory.
+    pub memory: Config::Memory,
+    /// The system call interface.
+    pub kernel: Config::Kernel,
+    /// The ISA extensions enabled on the hart.
+    pub isa: IsaConfig<Config::Xlen>,
+    /// The branch predictor, if any.
+    pub branch_unit: Option<BranchUnit>,
+    /// The observer of the execution of the hart. Instructions are observed as they execute in
+    /// program order, and retire from the observer's point of view before they are dispatched.
+    pub observer: Config::Observer,
+    /// The executed instruction that the core could not dispatch on the last cycle, if any.
+    pending: Option<PipelineRegister<Config::Xlen>>,
+    /// The memory accesses made by the last instruction executed, or by the kernel if it was a
+    /// system call.
+    accesses: Vec<MemoryAccess>,
+}
+
+impl<Config> OooEmu<Config>
+where
+    Config: EmuConfig,
+{
+    /// Creates a new [`OooEmuBuilder`].
+    pub fn builder() -> OooEmuBuilder<Config> {
+        OooEmuBuilder::default()
+    }
+
+    /// Executes the program until it exits and every instruction has retired, returning the final
+    /// architectural state.
+    pub fn run(&mut self) -> PipelineResult<PipelineRegister<Config::Xlen>> {
+        while !self.register.exit || self.pending.is_some() || !self.core.is_empty() {
+            self.cycle()?;
+        }
+
+        Ok(self.register)
+    }
+
+    /// Advances the core by one cycle, executing the instructions that it dispatches.
+    pub fn cycle(&mut self) -> PipelineResult<()> {
+        self.core.begin_cycle();
+        while self.core.can_dispatch() {
+            let r = match self.pending.take() {
+                Some(r) => r,
+                None if self.register.exit => break,
+                None => self.execute()?,
+            };
+            if self.core.dispatch(&r).is_err() {
+                self.pending = Some(r);
+            }
+        }
+        self.core.end_cycle();
+        self.observer.on_cycle(&self.register.registers);
+
+        Ok(())
+    }
+
+    /// Executes the next instruction in full, and returns its pipeline register.
+    fn execute(&mut self) -> PipelineResult<PipelineRegister<Config::Xlen>> {
+        let r = &mut self.register;
+        let branch_unit = &mut self.branch_unit;
+        let observer = &mut self.observer;
+        let accesses = &mut self.accesses;
+        accesses.clear();
+        let recording = <Config::Observer as Observer<Config::Xlen>>::OBSERVES_MEMORY;
+
+        let res = instruction_fetch(r, &self.memory, &self.isa)
+            .map(|_| branch_unit.as_mut().map(|unit| unit.predict(r)))
+            .map(|_| observer.on_stage(Stage::Fetch, r))
+            .and_then(|_| decode_instruction(r))
+            .map(|_| observer.on_stage(Stage::Decode, r))
+            .and_then(|_| execute(r))
+            .map(|_| branch_unit.as_mut().map(|unit| unit.resolve(r)))
+            .map(|_| observer.on_stage(Stage::Execute, r))
+            .and_then(|_| {
+                mem_access_recorded(r, &mut self.memory, recording.then_some(&mut *accesses))
+            })
+            .map(|_| observe_memory(observer, r, accesses))
+            .and_then(|_| writeback(r))
+            .map(|_| observer.on_stage(Stage::Writeback, r));
+
+        // Handle system calls.
+        match res {
+            Ok(()) => {}
+            Err(PipelineError::SyscallException(syscall_no)) => {
+                observer.on_syscall(syscall_no, r);
+                let recorded = recording.then_some(&mut *accesses);
+                syscall_recorded(&mut self.kernel, syscall_no, &mut self.memory, r, recorded)?;
+                observe_accesses(observer, r, accesses);
+            }
+            Err(e) => return Err(e),
+        }
+        observer.on_retire(r);
+
+        let executed = *r;
+        if !r.exit {
+            r.advance();
+        }
+        Ok(executed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OooEmu;
+    use crate::{
+        st::StEmu,
+        test_utils::{loaded, TestConfig},
+    };
+    use brisc_hw::{
+        ooo::{FunctionalUnit, OooConfig, OooConfigError, OooCore, StallReason, UnitConfig},
+        predictor::{Bimodal, BranchPredictor},
+    };
+    use brisc_isa::asm::assemble;
+    use rstest::rstest;
+
+    /// Runs `source` on an [OooEmu] with the given configuration, and checks that it agrees with
+    /// the [StEmu].
+    fn run(
+        source: &str,
+        config: OooConfig,
+        predictor: Option<Box<dyn BranchPredictor>>,
+    ) -> OooEmu<TestConfig> {
+        let elf = assemble(source).unwrap().to_elf();
+        let mut builder =
+            loaded::<OooEmu<_>, TestConfig>(&elf).with_core(OooCore::new(config).unwrap());
+        if let Some(predictor) = predictor {
+            builder = builder.with_predictor(predictor);
+        }
+        let mut hart = builder.build();
+        let register = hart.run().unwrap();
+
+        let mut st = loaded::<StEmu<_>, TestConfig>(&elf).build();
+        let mut executed = 0;
+        while !st.register.exit {
+            st.cycle().unwrap();
+            executed += 1;
+        }
+        assert_eq!(register, st.register);
+        assert_eq!(hart.core.stats.retired, executed);
+        hart
+    }
+
+    const INDEPENDENT: &str = r#"
+        .text
+        .globl _start
+        _start:
+            li a1, 1
+            li a2, 2
+            li a3, 3
+            li a4, 4
+            add a1, a1, a1
+            add a2, a2, a2
+            add a3, a3, a3
+            add a4, a4, a4
+            add a0, a1, a2
+            li a7, 93
+            ecall
+    "#;
+
+    const DEPENDENT: &str = r#"
+        .text
+        .globl _start
+        _start:
+            li a0, 1
+            add a0, a0, a0
+            add a0, a0, a0
+            add a0, a0, a0
+            add a0, a0, a0
+            add a0, a0, a0
+            add a0, a0, a0
+            add a0, a0, a0
+            add a0, a0, a0
+            li a7, 93
+            ecall
+    "#;
+
+    #[rstest]
+    #[case::independent_wide(INDEPENDENT, 4, 9)]
+    #[case::dependent_wide(DEPENDENT, 4, 13)]
+    #[case::independent_narrow(INDEPENDENT, 1, 14)]
+    #[case::dependent_narrow(DEPENDENT, 1, 14)]
+    fn test_ilp(#[case] source: &str, #[case] width: usize, #[case] cycles: u64) {
+        // The two ALUs run the independent additions two by two, while the dependent ones run
+        // one after the other. `ecall` waits for the additions to retire.
+        let hart = run(source, OooConfig::new(width, 64, 16), None);
+        let stats = &hart.core.stats;
+        assert_eq!((stats.retired, stats.cycles), (11, cycles));
+        assert!(stats.stalls[&StallReason::Serialize] > 0);
+    }
+
+    #[test]
+    #[cfg(feature = "m")]
+    fn test_store_to_load_forwarding() {
+        use brisc_hw::timing::Latencies;
+
+        let source = r#"
+            .data
+            buffer: .word 0, 0
+
+            .text
+            .globl _start
+            _start:
+                la a1, buffer
+                li a2, 8
+            loop:
+                mul t1, a2, a2
+                sw a2, 0(a1)
+                lw a0, 0(a1)
+                lh t0, 4(a1)
+                addi a2, a2, -1
+                bnez a2, loop
+
+                li a7, 93
+                ecall
+            "#;
+        let config = OooConfig::new(4, 64, 16).with_latencies(Latencies::ROCKET);
+        let hart = run(source, config, Some(Box::new(Bimodal::new(4))));
+
+        // The store cannot retire before the multiplication ahead of it, so every `lw` takes its
+        // data from the store queue, while `lh` reads the other word from memory.
+        assert_eq!(hart.core.stats.forwarded_loads, 8);
+    }
+
+    #[test]
+    #[cfg(feature = "m")]
+    fn test_rob_full() {
+        use brisc_hw::timing::Latencies;
+
+        let source = r#"
+            .text
+            .globl _start
+            _start:
+                li a1, 1000
+                li a2, 7
+                div a0, a1, a2
+                addi a3, a3, 1
+                addi a3, a3, 1
+                addi a3, a3, 1
+                addi a3, a3, 1
+                addi a3, a3, 1
+                addi a3, a3, 1
+                addi a3, a3, 1
+                addi a3, a3, 1
+                li a7, 93
+                ecall
+            "#;
+        let latencies = Latencies { div_early_out: false, ..Latencies::ROCKET };
+        let config = OooConfig::new(4, 8, 4).with_latencies(latencies);
+        let hart = run(source, config, None);
+
+        // The additions complete while the 64-cycle division runs, but cannot retire before it, so
+        // the reorder buffer fills up behind it.
+        let stats = &hart.core.stats;
+        assert_eq!((stats.retired, stats.cycles), (13, 75));
+        assert_eq!(stats.stalls[&StallReason::RobFull], 64);
+        assert_eq!(stats.rob_occupancy[8], 65);
+    }
+
+    #[test]
+    fn test_mispredictions() {
+        let source = r#"
+            .text
+            .globl _start
+            _start:
+                li a0, 0
+                li a2, 16
+            loop:
+                addi a0, a0, 2
+                addi a2, a2, -1
+                bnez a2, loop
+
+                li a7, 93
+                ecall
+            "#;
+        let config = OooConfig::new(4, 64, 16);
+        let not_taken = run(source, config, None);
+        let predicted = run(source, config, Some(Box::new(Bimodal::new(4))));
+
+        // Without a predictor, every taken branch stops dispatch until it completes and the
+        // front end refills. The predictor only misses the first iterations, and the exit.
+        let (not_taken, predicted) = (&not_taken.core.stats, &predicted.core.stats);
+        assert_eq!((not_taken.mispredictions, not_taken.cycles), (15, 97));
+        assert_eq!((predicted.mispredictions, predicted.cycles), (2, 32));
+        assert_eq!(not_taken.stalls[&StallReason::Mispredict], 90);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config =
+            OooConfig::new(4, 64, 16).with_unit(FunctionalUnit::Branch, UnitConfig::new(8, 0));
+        assert_eq!(OooCore::new(config).unwrap_err(), OooConfigError::Empty("number of units"));
+    }
+}
@@ -0,0 +1,67 @@
+//! A builder for the [`PipelinedEmu`] emulator.
+
+use super::{PipelineStats, PipelinedEmu};
+use crate::{
+    builder::{builder_setters, BuilderCore},
+    cfg::EmuConfig,
+    kanata::KanataLog,
+};
+use alloc::vec::Vec;
+use brisc_hw::{cache::CacheHierarchy, pipeline::PipelineRegister};
+
+/// A builder for the [`PipelinedEmu`] emulator.
+#[derive(Debug)]
+pub struct PipelinedEmuBuilder<Config>
+where
+    Config: EmuConfig,
+{
+    /// The configuration shared by the builders of every hart. The branch predictor steers fetch.
+    pub hart: BuilderCore<Config>,
+    /// The cache hierarchy of the hart, if any.
+    pub caches: Option<CacheHierarchy>,
+    /// The Kanata log of the run, if any.
+    pub kanata: Option<KanataLog>,
+}
+
+impl<Config> Default for PipelinedEmuBuilder<Config>
+where
+    Config: EmuConfig,
+{
+    fn default() -> Self {
+        Self { hart: BuilderCore::default(), caches: None, kanata: None }
+    }
+}
+
+impl<Config> PipelinedEmuBuilder<Config>
+where
+    Config: EmuConfig,
+{
+    builder_setters!(core, caches, kanata);
+
+    /// Builds the emulator with the current configuration.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the memory or kernel is not set.
+    pub fn build(mut self) -> PipelinedEmu<Config> {
+        let (memory, kernel) = self.hart.memory_and_kernel();
+        PipelinedEmu {
+            register: PipelineRegister::new(self.hart.pc),
+            fetch_pc: self.hart.pc,
+            if_id: None,
+            id_ex: None,
+            ex_mem: None,
+            mem_wb: None,
+            stats: PipelineStats::default(),
+            memory,
+            kernel,
+            isa: self.hart.isa,
+            branch_unit: self.hart.branch_unit,
+            caches: self.caches,
+            kanata: self.kanata,
+            observer: self.hart.observer,
+            latch_ids: Default::default(),
+            accesses: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,441 @@
+//! Cycle-level 5-stage RISC-V pipeline, with one instruction in flight per stage.
+
+use crate::{
+    cfg::EmuConfig,
+    kanata::KanataLog,
+    observer::{
+        mem_access_recorded, observe_accesses, observe_memory, syscall_recorded, MemoryAccess,
+        Observer, Stage,
+    },
+};
+use alloc::vec::Vec;
+use brisc_hw::{
+    cache::CacheHierarchy,
+    errors::{PipelineError, PipelineResult},
+    pipeline::{decode_instruction, execute, instruction_fetch, writeback, PipelineRegister},
+    predictor::BranchUnit,
+    Instruction, IsaConfig, XWord, Xlen,
+};
+
+mod builder;
+pub use builder::PipelinedEmuBuilder;
+
+mod stats;
+pub use stats::PipelineStats;
+
+/// Cycle-level pipelined RISC-V processor emulator.
+///
+/// Where [StEmu] runs each instruction through all five stages before fetching the next, the
+/// [PipelinedEmu] holds one [PipelineRegister] per stage latch, and advances every instruction in
+/// flight by one stage per [cycle]. The stages run from the back of the pipeline to the front, so
+/// that each stage consumes the latch that the stage before it filled on the previous cycle.
+///
+/// - RAW hazards are resolved by forwarding the results of the two older instructions from the
+///   EX/MEM and MEM/WB latches into EX.
+/// - An instruction that reads the result of the load ahead of it is stalled in ID for a cycle, as
+///   the loaded value is not available until the end of MEM.
+/// - Branches are predicted at fetch by the [Self::branch_unit], or not taken if there is none, and
+///   resolved in EX. Mispredicted branches and fences flush the two younger instructions in IF and
+///   ID.
+/// - System calls wait in ID until every older instruction has retired, and run against the
+///   architectural state in [Self::register].
+///
+/// [StEmu]: crate::st::StEmu
+/// [cycle]: Self::cycle
+#[derive(Debug, Default)]
+pub struct PipelinedEmu<Config>
+where
+    Config: EmuConfig,
+{
+    /// The architectural state of the hart: the register file, the exit status, the load
+    /// reservation, and the program counter of the next instruction to retire.
+    pub register: PipelineRegister<Config::Xlen>,
+    /// The program counter of the next instruction to fetch.
+    pub fetch_pc: XWord,
+    /// The IF/ID latch. Fetch errors are held in the latch until the instruction reaches ID, as
+    /// an instruction fetched down a mispredicted path is flushed without effect.
+    pub if_id: Option<PipelineResult<PipelineRegister<Config::Xlen>>>,
+    /// The ID/EX latch.
+    pub id_ex: Option<PipelineRegister<Config::Xlen>>,
+    /// The EX/MEM latch.
+    pub ex_mem: Option<PipelineRegister<Config::Xlen>>,
+    /// The MEM/WB latch.
+    pub mem_wb: Option<PipelineRegister<Config::Xlen>>,
+    /// The performance counters of the pipeline.
+    pub stats: PipelineStats,
+    /// The device memory.
+    pub memory: Config::Memory,
+    /// The system call interface.
+    pub kernel: Config::Kernel,
+    /// The ISA extensions enabled on the hart.
+    pub isa: IsaConfig<Config::Xlen>,
+    /// The branch predictor that steers fetch, if any.
+    pub branch_unit: Option<BranchUnit>,
+    /// The cache hierarchy, if any, which observes the addresses of fetches, including those
+    /// down a mispredicted path, and of memory accesses.
+    pub caches: Option<CacheHierarchy>,
+    /// The Kanata log of the run, if any, recording the stage that every instruction occupies on
+    /// each cycle, including the instructions flushed down a mispredicted path.
+    pub kanata: Option<KanataLog>,
+    /// The observer of the execution of the hart.
+    pub observer: Config::Observer,
+    /// The sequence numbers in [Self::kanata] of the instructions in the IF/ID, ID/EX, EX/MEM and
+    /// MEM/WB latches, along with the last stage logged for each.
+    latch_ids: [Option<(u64, Stage)>; 4],
+    /// The memory accesses made by the instruction in MEM, or by the kernel during a system call.
+    accesses: Vec<MemoryAccess>,
+}
+
+impl<Config> PipelinedEmu<Config>
+where
+    Config: EmuConfig,
+{
+    /// Creates a new [`PipelinedEmuBuilder`].
+    pub fn builder() -> PipelinedEmuBuilder<Config> {
+        PipelinedEmuBuilder::default()
+    }
+
+    /// Executes the program until it exits, returning the final architectural state.
+    pub fn run(&mut self) -> PipelineResult<PipelineRegister<Config::Xlen>> {
+        while !self.register.exit {
+            self.cycle()?;
+        }
+
+        Ok(self.register)
+    }
+
+    /// Advances every instruction in the pipeline by one stage.
+    pub fn cycle(&mut self) -> PipelineResult<()> {
+        self.stats.cycles += 1;
+        let res = self.step();
+        if let Some(log) = self.kanata.as_mut() {
+            log.cycle();
+        }
+        self.observer.on_cycle(&self.register.registers);
+        res
+    }
+
+    /// Runs the stages of the pipeline for the current cycle.
+    fn step(&mut self) -> PipelineResult<()> {
+        let [id_id, ex_id, mem_id, wb_id] = core::mem::take(&mut self.latch_ids);
+        let retiring = self.mem_wb.take();
+        let in_mem = self.ex_mem.take();
+        let in_ex = self.id_ex.take();
+        let in_id = self.if_id.take();
+
+        // WB: write the result of the oldest instruction to the register file.
+        if let Some(r) = retiring {
+            if let Some(((id, _), log)) =
+                self.log_stage(wb_id, Stage::Writeback).zip(self.kanata.as_mut())
+            {
+                log.retire(id);
+            }
+            self.retire(r)?;
+        }
+
+        // MEM: the load reservation is architectural state, shared by all instructions.
+        let mem_out = match in_mem {
+            Some(mut r) => {
+                #[cfg(feature = "a")]
+                {
+                    r.reservation = self.register.reservation;
+                }
+                self.accesses.clear();
+                let recorded = <Config::Observer as Observer<Config::Xlen>>::OBSERVES_MEMORY
+                    .then_some(&mut self.accesses);
+                mem_access_recorded(&mut r, &mut self.memory, recorded)?;
+                if let Some(caches) = self.caches.as_mut() {
+                    caches.record_mem_access(&r);
+                }
+                observe_memory(&mut self.observer, &r, &self.accesses);
+                #[cfg(feature = "a")]
+                {
+                    self.register.reservation = r.reservation;
+                }
+                self.latch_ids[3] = self.log_stage(mem_id, Stage::Memory);
+                Some(r)
+            }
+            None => None,
+        };
+
+        // EX: resolve the operands against the older instructions, and the branch prediction.
+        let mut redirect = None;
+        let ex_out = match in_ex {
+            Some(mut r) => {
+                self.forward(&mut r, [mem_out.as_ref(), retiring.as_ref()]);
+                execute(&mut r)?;
+
+                let instruction =
+                    r.instruction.ok_or(PipelineError::MissingState("instruction"))?;
+                let fallthrough = Config::Xlen::truncate(r.pc + instruction.size() as XWord);
+                let predicted = r.predicted_pc.unwrap_or(fallthrough);
+                r.next_pc = Config::Xlen::truncate(r.next_pc);
+                if let Some(unit) = self.branch_unit.as_mut() {
+                    unit.resolve(&r);
+                }
+                self.observer.on_stage(Stage::Execute, &r);
+                if r.next_pc != predicted || matches!(instruction.instruction, Instruction::Fence) {
+                    redirect = Some(r.next_pc);
+                }
+                self.latch_ids[2] = self.log_stage(ex_id, Stage::Execute);
+                Some(r)
+            }
+            None => None,
+        };
+        self.mem_wb = mem_out;
+        self.ex_mem = ex_out;
+
+        // The instructions in IF and ID were fetched down the wrong path, or before a fence.
+        if let Some(target) = redirect {
+            self.fetch_pc = target;
+            self.stats.flushes += 1;
+            if let Some(((id, _), log)) =
+                in_id.and(self.log_stage(id_id, Stage::Decode)).zip(self.kanata.as_mut())
+            {
+                log.flush(id);
+            }
+            return Ok(());
+        }
+
+        // ID: read the register file, or hold the instruction in the IF/ID latch on a hazard.
+        if let Some(fetched) = in_id {
+            let entry = self.log_stage(id_id, Stage::Decode);
+            let mut r = fetched?;
+            let instruction = r.instruction.ok_or(PipelineError::MissingState("instruction"))?;
+            let draining = instruction.instruction.is_system_call() &&
+                (self.ex_mem.is_some() || self.mem_wb.is_some());
+            if draining || self.is_load_use(&instruction.instruction) {
+                self.if_id = Some(Ok(r));
+                self.latch_ids[0] = entry;
+                self.stats.stalls += 1;
+                return Ok(());
+            }
+
+            r.registers = self.register.registers;
+            match decode_instruction(&mut r) {
+                Ok(()) => {
+                    self.observer.on_stage(Stage::Decode, &r);
+                    self.id_ex = Some(r);
+                    self.latch_ids[1] = entry;
+                }
+                Err(PipelineError::SyscallException(syscall_no)) => {
+                    // Every older instruction has retired, so the kernel observes the same state
+                    // as on a single-cycle hart.
+                    self.observer.on_syscall(syscall_no, &r);
+                    self.register.pc = r.pc;
+                    self.accesses.clear();
+                    let recorded = <Config::Observer as Observer<Config::Xlen>>::OBSERVES_MEMORY
+                        .then_some(&mut self.accesses);
+                    syscall_recorded(
+                        &mut self.kernel,
+                        syscall_no,
+                        &mut self.memory,
+                        &mut self.register,
+                        recorded,
+                    )?;
+                    self.register.pc = Config::Xlen::truncate(r.next_pc);
+                    self.stats.retired += 1;
+                    r.registers = self.register.registers;
+                    (r.exit, r.exit_code) = (self.register.exit, self.register.exit_code);
+                    observe_accesses(&mut self.observer, &r, &self.accesses);
+                    self.observer.on_retire(&r);
+                    if let Some(((id, _), log)) = entry.zip(self.kanata.as_mut()) {
+                        log.retire(id);
+                    }
+
+                    // Exit emulation if the syscall terminated the program.
+                    if self.register.exit {
+                        return Ok(());
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        // IF: fetch the next instruction, following the predicted path.
+        let pc = self.fetch_pc;
+        let mut r = PipelineRegister::new(pc);
+        let fetched = instruction_fetch(&mut r, &self.memory, &self.isa).map(|()| {
+            if let Some(caches) = self.caches.as_mut() {
+                caches.record_fetch(&r);
+            }
+            if let Some(unit) = self.branch_unit.as_mut() {
+                unit.predict(&mut r);
+            }
+            self.observer.on_stage(Stage::Fetch, &r);
+            self.fetch_pc = r.predicted_pc.unwrap_or(r.next_pc);
+            r
+        });
+        if let Some(log) = self.kanata.as_mut() {
+            let id = match &fetched {
+                Ok(r) => log.start_instruction(r),
+                Err(_) => log.start_instruction(&PipelineRegister::<Config::Xlen>::new(pc)),
+            };
+            log.stage(id, Stage::Fetch);
+            self.latch_ids[0] = Some((id, Stage::Fetch));
+        }
+        self.if_id = Some(fetched);
+
+        Ok(())
+    }
+
+    /// Logs the instruction `entry` of a latch as occupying `stage`, unless it already did on the
+    /// previous cycle, and returns its updated entry.
+    fn log_stage(&mut self, entry: Option<(u64, Stage)>, stage: Stage) -> Option<(u64, Stage)> {
+        let log = self.kanata.as_mut()?;
+        let (id, last) = entry?;
+        if last != stage {
+            log.stage(id, stage);
+        }
+        Some((id, stage))
+    }
+
+    /// Writes back the result of an instruction leaving the MEM/WB latch.
+    fn retire(&mut self, mut r: PipelineRegister<Config::Xlen>) -> PipelineResult<()> {
+        r.registers = self.register.registers;
+        writeback(&mut r)?;
+        self.observer.on_stage(Stage::Writeback, &r);
+        self.register.registers = r.registers;
+        self.register.pc = r.next_pc;
+        self.stats.retired += 1;
+        self.observer.on_retire(&r);
+        Ok(())
+    }
+
+    /// Replaces the source operands of the instruction entering EX with the results of the older
+    /// instructions in `producers`, youngest first, that write them.
+    fn forward(
+        &mut self,
+        r: &mut PipelineRegister<Config::Xlen>,
+        producers: [Option<&PipelineRegister<Config::Xlen>>; 2],
+    ) {
+        let Some(instruction) = r.instruction.map(|decoded| decoded.instruction) else {
+            return;
+        };
+
+        for (source, value) in
+            [(instruction.rs1(), &mut r.rs1_value), (instruction.rs2(), &mut r.rs2_value)]
+        {
+            let Some(source) = source.filter(|&source| source != 0) else {
+                continue;
+            };
+            let result = producers
+                .into_iter()
+                .flatten()
+                .find(|producer| producer.rd == Some(source))
+                .and_then(|producer| producer.memory.or(producer.alu_result));
+            if let Some(result) = result {
+                *value = Some(Config::Xlen::truncate(result));
+                self.stats.forwards += 1;
+            }
+        }
+    }
+
+    /// Returns `true` if `instruction` reads the result of the load in the EX/MEM latch, which is
+    /// only available once it leaves MEM.
+    fn is_load_use(&self, instruction: &Instruction) -> bool {
+        let Some(producer) = self.ex_mem.as_ref() else {
+            return false;
+        };
+        let loads = match producer.instruction.map(|decoded| decoded.instruction) {
+            Some(Instruction::MemoryLoad(_, _)) => true,
+            #[cfg(feature = "a")]
+            Some(Instruction::Amo(_, _)) => true,
+            _ => false,
+        };
+
+        loads &&
+            producer.rd.is_some_and(|rd| {
+                rd != 0 && (instruction.rs1() == Some(rd) || instruction.rs2() == Some(rd))
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PipelineStats, PipelinedEmu};
+    use crate::test_utils::{assembled, TestConfig};
+    use brisc_hw::{
+        predictor::{Bimodal, BranchPredictor, ReturnStack},
+        REG_A0,
+    };
+
+    fn run(source: &str, predictor: Option<Box<dyn BranchPredictor>>) -> PipelinedEmu<TestConfig> {
+        let mut builder = assembled::<PipelinedEmu<_>, TestConfig>(source);
+        if let Some(predictor) = predictor {
+            builder = builder.with_predictor(predictor);
+        }
+        let mut hart = builder.build();
+        hart.run().unwrap();
+        hart
+    }
+
+    #[test]
+    fn test_hazards() {
+        let hart = run(
+            r#"
+            .data
+            value: .word 40
+
+            .text
+            .globl _start
+            _start:
+                la a1, value
+                li a0, 1
+                addi a0, a0, 1
+                lw a2, 0(a1)
+                add a0, a0, a2
+                j exit
+                .word 0x0000000b
+            exit:
+                li a7, 93
+                ecall
+            "#,
+            None,
+        );
+
+        assert_eq!(hart.register.exit_code, 42);
+        assert_eq!(hart.register.registers[REG_A0 as usize], 42);
+        // `addi a1` and `addi a0` forward from EX/MEM, and `add` forwards the load from MEM/WB
+        // after a load-use stall. `ecall` stalls for two cycles until `li a7` retires, and the
+        // illegal word after `j` is flushed.
+        assert_eq!(
+            hart.stats,
+            PipelineStats { cycles: 15, retired: 9, stalls: 3, flushes: 1, forwards: 3 }
+        );
+    }
+
+    #[test]
+    fn test_branch_prediction() {
+        let source = r#"
+            .text
+            .globl _start
+            _start:
+                li a0, 0
+                li a2, 16
+            loop:
+                jal f
+                addi a2, a2, -1
+                bnez a2, loop
+
+                li a7, 93
+                ecall
+            f:
+                addi a0, a0, 2
+                ret
+            "#;
+        let not_taken = run(source, None);
+        let predicted = run(source, Some(Box::new(ReturnStack::new(Bimodal::new(8), 4))));
+
+        assert_eq!(not_taken.register.exit_code, 32);
+        assert_eq!(predicted.register, not_taken.register);
+        assert_eq!(predicted.stats.retired, not_taken.stats.retired);
+
+        // Without a predictor, every call, return and taken branch flushes. The predictor only
+        // misses the first iteration of the loop, and its exit.
+        assert_eq!(not_taken.stats.flushes, 16 * 3 - 1);
+        assert_eq!(predicted.stats.flushes, 2);
+        assert_eq!(predicted.stats.cycles, not_taken.stats.cycles - 2 * 45);
+        assert_eq!(predicted.branch_unit.unwrap().total().mispredictions, 2);
+    }
+}
@@ -0,0 +1,44 @@
+//! Performance counters for the [`PipelinedEmu`].
+//!
+//! [`PipelinedEmu`]: super::PipelinedEmu
+
+/// Performance counters of a [PipelinedEmu]. Cycles are counted separately from retired
+/// instructions, as hazards and flushes leave stages of the pipeline empty.
+///
+/// [PipelinedEmu]: super::PipelinedEmu
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineStats {
+    /// The number of cycles executed.
+    pub cycles: u64,
+    /// The number of instructions retired, including system calls.
+    pub retired: u64,
+    /// The number of cycles that an instruction was held in ID, either by a load-use hazard or
+    /// by a system call waiting for the older instructions to retire.
+    pub stalls: u64,
+    /// The number of times that a mispredicted branch or a fence flushed IF and ID.
+    pub flushes: u64,
+    /// The number of source operands forwarded from the EX/MEM or MEM/WB latch.
+    pub forwards: u64,
+}
+
+impl PipelineStats {
+    /// Returns the average number of cycles per retired instruction, or 0 before the first
+    /// instruction retires.
+    pub fn cpi(&self) -> f64 {
+        if self.retired == 0 {
+            return 0.0;
+        }
+        self.cycles as f64 / self.retired as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PipelineStats;
+
+    #[test]
+    fn test_cpi() {
+        assert_eq!(PipelineStats::default().cpi(), 0.0);
+        assert_eq!(PipelineStats { cycles: 6, retired: 4, ..Default::default() }.cpi(), 1.5);
+    }
+}
@@ -1,9 +1,13 @@
 //! A builder for the [`StEmu`] emulator.
 
-use super::StEmu;
-use crate::{cfg::EmuConfig, elf::load_elf};
-use alloc::string::String;
-use brisc_hw::{pipeline::PipelineRegister, XWord};
+use super::{Debugger, StEmu};
+use crate::{
+    builder::{builder_setters, BuilderCore},
+    cfg::EmuConfig,
+    kanata::KanataLog,
+};
+use alloc::vec::Vec;
+use brisc_hw::{cache::CacheHierarchy, pipeline::PipelineRegister, timing::TimingModel};
 
 /// A builder for the [`StEmu`] emulator.
 #[derive(Debug)]
@@ -11,12 +15,16 @@ pub struct StEmuBuilder<Config>
 where
     Config: EmuConfig,
 {
-    /// The starting program counter.
-    pub pc: XWord,
-    /// The initial memory for the emulator.
-    pub memory: Option<Config::Memory>,
-    /// The system call interface for the emulator.
-    pub kernel: Option<Config::Kernel>,
+    /// The configuration shared by the builders of every hart.
+    pub hart: BuilderCore<Config>,
+    /// The cache hierarchy of the hart, if any.
+    pub caches: Option<CacheHierarchy>,
+    /// The timing model of the hart, if any.
+    pub timing: Option<TimingModel>,
+    /// The Kanata log of the run, if any.
+    pub kanata: Option<KanataLog>,
+    /// The breakpoints and watchpoints of the hart.
+    pub debugger: Debugger,
 }
 
 impl<Config> Default for StEmuBuilder<Config>
@@ -24,21 +32,13 @@ where
     Config: EmuConfig,
 {
     fn default() -> Self {
-        Self { pc: 0, memory: None, kernel: None }
-    }
-}
-
-impl<Config> StEmuBuilder<Config>
-where
-    Config: EmuConfig,
-    Config::Memory: Default,
-{
-    /// Loads an elf file into the emulator builder, initializing the program counter and memory.
-    pub fn with_elf(mut self, elf_bytes: &[u8]) -> Result<Self, String> {
-        let (memory, entry_pc) = load_elf::<Config::Memory>(elf_bytes)?;
-        self.pc = entry_pc;
-        self.memory = Some(memory);
-        Ok(self)
+        Self {
+            hart: BuilderCore::default(),
+            caches: None,
+            timing: None,
+            kanata: None,
+            debugger: Debugger::default(),
+        }
     }
 }
 
@@ -46,21 +46,17 @@ impl<Config> StEmuBuilder<Config>
 where
     Config: EmuConfig,
 {
-    /// Assigns the entry point of the program.
-    pub const fn with_pc(mut self, pc: XWord) -> Self {
-        self.pc = pc;
-        self
-    }
+    builder_setters!(core, caches, kanata);
 
-    /// Assigns a pre-created memory instance to the emulator.
-    pub fn with_memory(mut self, memory: Config::Memory) -> Self {
-        self.memory = Some(memory);
+    /// Assigns the timing model that estimates the cycles of the instructions retired by the hart.
+    pub fn with_timing(mut self, timing: TimingModel) -> Self {
+        self.timing = Some(timing);
         self
     }
 
-    /// Assigns the kernel to the emulator.
-    pub fn with_kernel(mut self, kernel: Config::Kernel) -> Self {
-        self.kernel = Some(kernel);
+    /// Assigns the breakpoints and watchpoints that stop the runs of the hart.
+    pub fn with_debugger(mut self, debugger: Debugger) -> Self {
+        self.debugger = debugger;
         self
     }
 
@@ -69,11 +65,20 @@ where
     /// ## Panics
     ///
     /// Panics if the memory or kernel is not set.
-    pub fn build(self) -> StEmu<Config> {
+    pub fn build(mut self) -> StEmu<Config> {
+        let (memory, kernel) = self.hart.memory_and_kernel();
         StEmu {
-            register: PipelineRegister::new(self.pc),
-            memory: self.memory.expect("Memory not instantiated"),
-            kernel: self.kernel.expect("Kernel not instantiated"),
+            register: PipelineRegister::new(self.hart.pc),
+            memory,
+            kernel,
+            isa: self.hart.isa,
+            branch_unit: self.hart.branch_unit,
+            caches: self.caches,
+            timing: self.timing,
+            kanata: self.kanata,
+            observer: self.hart.observer,
+            debugger: self.debugger,
+            accesses: Vec::new(),
         }
     }
 }
@@ -0,0 +1,404 @@
+//! Breakpoints, watchpoints and `ebreak` stops for the bounded runs of a [StEmu].
+//!
+//! [StEmu]: super::StEmu
+
+use super::StopReason;
+use crate::observer::MemoryAccess;
+use alloc::{collections::BTreeSet, vec::Vec};
+use brisc_hw::{
+    memory::Address, pipeline::PipelineRegister, DecodedInstruction, EnvironmentFunction,
+    Instruction, XWord, Xlen,
+};
+use core::ops::Range;
+
+/// The accesses that trigger a watchpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    /// Reads of the watched range.
+    Read,
+    /// Writes to the watched range.
+    Write,
+    /// Reads of and writes to the watched range.
+    Access,
+}
+
+impl WatchKind {
+    /// Returns `true` if an access, writing if `write` is set, triggers the watchpoint.
+    const fn triggers(&self, write: bool) -> bool {
+        match self {
+            Self::Read => !write,
+            Self::Write => write,
+            Self::Access => true,
+        }
+    }
+}
+
+/// A watchpoint on a range of addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    /// The watched addresses.
+    pub range: Range<Address>,
+    /// The accesses that trigger the watchpoint.
+    pub kind: WatchKind,
+}
+
+/// The identifier of a watchpoint, returned by [Debugger::set_watchpoint].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WatchpointId(pub usize);
+
+/// An access to memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Access {
+    /// The program counter of the instruction that made the access. For a system call, that of
+    /// the `ecall`.
+    pub pc: XWord,
+    /// The address of the first byte accessed.
+    pub address: Address,
+    /// The number of bytes accessed.
+    pub bytes: usize,
+    /// Whether the access was a write.
+    pub write: bool,
+}
+
+/// A watchpoint triggered by an access to memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchpointHit {
+    /// The watchpoint that was triggered.
+    pub id: WatchpointId,
+    /// The access that triggered it.
+    pub access: Access,
+}
+
+/// The breakpoints and watchpoints of a hart, which stop its runs.
+///
+/// A bounded run stops before the instruction at a breakpoint, and before a guest `ebreak` if
+/// [Self::stop_on_ebreak] is set, so that the instruction has not executed. Resuming a run from the
+/// instruction that it stopped at executes it. Watchpoints are checked as every instruction
+/// retires, whichever way the hart is driven: every run stops after the instruction whose access
+/// triggered one, and [Self::hit] reports it until the next instruction executes.
+///
+/// Watchpoints observe the loads, stores and atomic memory operations of the hart, and the
+/// accesses of the kernel to the memory of the hart during system calls.
+#[derive(Debug, Default, Clone)]
+pub struct Debugger {
+    /// Whether a guest `ebreak` stops the run, rather than executing as a no-op.
+    pub stop_on_ebreak: bool,
+    /// The addresses of the execution breakpoints.
+    breakpoints: BTreeSet<XWord>,
+    /// The watchpoints, indexed by [WatchpointId]. Cleared watchpoints leave an empty slot.
+    watchpoints: Vec<Option<Watchpoint>>,
+    /// The program counter of the instruction that the last run stopped before, if any.
+    pub(crate) stopped_at: Option<XWord>,
+    /// The watchpoint triggered by the last instruction executed, if any.
+    pub(crate) hit: Option<WatchpointHit>,
+}
+
+impl Debugger {
+    /// Creates a new [Debugger], without any breakpoint or watchpoint.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the stop on guest `ebreak` instructions.
+    pub const fn with_stop_on_ebreak(mut self, stop: bool) -> Self {
+        self.stop_on_ebreak = stop;
+        self
+    }
+
+    /// Sets an execution breakpoint at `pc`. Returns `false` if there already was one.
+    pub fn set_breakpoint(&mut self, pc: XWord) -> bool {
+        self.breakpoints.insert(pc)
+    }
+
+    /// Clears the execution breakpoint at `pc`. Returns `false` if there was none.
+    pub fn clear_breakpoint(&mut self, pc: XWord) -> bool {
+        self.breakpoints.remove(&pc)
+    }
+
+    /// Returns the addresses of the execution breakpoints, in order.
+    pub fn breakpoints(&self) -> impl Iterator<Item = XWord> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Sets a watchpoint on the accesses of `kind` to `range`, and returns its identifier.
+    pub fn set_watchpoint(&mut self, range: Range<Address>, kind: WatchKind) -> WatchpointId {
+        self.watchpoints.push(Some(Watchpoint { range, kind }));
+        WatchpointId(self.watchpoints.len() - 1)
+    }
+
+    /// Clears the watchpoint `id`, and returns it if it was set.
+    pub fn clear_watchpoint(&mut self, id: WatchpointId) -> Option<Watchpoint> {
+        self.watchpoints.get_mut(id.0)?.take()
+    }
+
+    /// Returns the watchpoint `id`, if it is set.
+    pub fn watchpoint(&self, id: WatchpointId) -> Option<&Watchpoint> {
+        self.watchpoints.get(id.0)?.as_ref()
+    }
+
+    /// Returns whether any watchpoint is set, for which the memory accesses of every instruction
+    /// are checked.
+    pub(crate) fn watches(&self) -> bool {
+        self.watchpoints.iter().any(Option::is_some)
+    }
+
+    /// Returns the watchpoint triggered by the last instruction executed, if any.
+    pub const fn hit(&self) -> Option<WatchpointHit> {
+        self.hit
+    }
+
+    /// Returns the reason to stop before the instruction at `pc`, whose encoding is read by `raw`,
+    /// if any.
+    pub(crate) fn stop_before<X: Xlen>(
+        &self,
+        pc: XWord,
+        raw: impl FnOnce() -> Option<u32>,
+    ) -> Option<StopReason> {
+        if self.breakpoints.contains(&pc) {
+            return Some(StopReason::Breakpoint(pc));
+        }
+        let ebreak = self.stop_on_ebreak &&
+            raw().and_then(|raw| DecodedInstruction::decode_for::<X>(raw).ok()).is_some_and(
+                |decoded| {
+                    matches!(
+                        decoded.instruction,
+                        Instruction::Environment(_, EnvironmentFunction::Ebreak)
+                    )
+                },
+            );
+        ebreak.then_some(StopReason::Ebreak(pc))
+    }
+
+    /// Returns the first watchpoint triggered by the memory `accesses` of the instruction in
+    /// `p_reg`, once it has retired, or of the kernel if it was a system call.
+    pub(crate) fn check_watchpoints<X: Xlen>(
+        &self,
+        p_reg: &PipelineRegister<X>,
+        accesses: &[MemoryAccess],
+    ) -> Option<WatchpointHit> {
+        accesses.iter().find_map(|&MemoryAccess { address, bytes, write }| {
+            let end = address.saturating_add(bytes.max(1) as Address);
+            self.watchpoints.iter().enumerate().find_map(|(id, watchpoint)| {
+                let watchpoint = watchpoint.as_ref()?;
+                (watchpoint.kind.triggers(write) &&
+                    address < watchpoint.range.end &&
+                    watchpoint.range.start < end)
+                    .then_some(WatchpointHit {
+                        id: WatchpointId(id),
+                        access: Access { pc: p_reg.pc, address, bytes, write },
+                    })
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Access, Debugger, WatchKind, WatchpointHit};
+    use crate::{
+        st::{StEmu, StopReason},
+        test_utils::{assembled, RiscvTestKernel, TestConfig},
+    };
+    use brisc_hw::{
+        errors::PipelineResult, kernel::Kernel, memory::Memory, pipeline::PipelineRegister,
+        NativeXlen, XWord, Xlen, REG_A0, REG_A1, REG_A2,
+    };
+
+    const PROGRAM: &str = r#"
+        .data
+        value: .word 0
+
+        .text
+        .globl _start
+        _start:
+            la a1, value
+            li a0, 2
+        loop:
+            sw a0, 0(a1)
+            lw a2, 0(a1)
+            addi a0, a0, -1
+            bnez a0, loop
+            ebreak
+            li a7, 93
+            ecall
+    "#;
+
+    #[test]
+    fn test_breakpoints() {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(PROGRAM).build();
+        hart.run_for(3);
+        let head = hart.register.pc;
+        assert!(hart.debugger.set_breakpoint(head));
+        assert!(!hart.debugger.set_breakpoint(head));
+
+        // A run stops before the instruction at a breakpoint, and executes it when resumed.
+        let summary = hart.run_for(100);
+        assert!(matches!(summary.reason, StopReason::Breakpoint(pc) if pc == head));
+        assert_eq!(summary.stats.instructions, 0);
+        let summary = hart.run_for(100);
+        assert!(matches!(summary.reason, StopReason::Breakpoint(pc) if pc == head));
+        assert_eq!((summary.stats.instructions, hart.register.registers[REG_A0 as usize]), (4, 1));
+
+        // `ebreak` is a no-op unless the debugger stops on it.
+        assert!(hart.debugger.clear_breakpoint(head));
+        hart.debugger.stop_on_ebreak = true;
+        let summary = hart.run_for(100);
+        let StopReason::Ebreak(pc) = summary.reason else {
+            panic!("Expected an ebreak stop, got {:?}", summary.reason);
+        };
+        assert_eq!((pc, summary.stats.instructions), (hart.register.pc, 4));
+        let summary = hart.run_for(100);
+        assert!(matches!(summary.reason, StopReason::Exited(0)));
+        assert_eq!(summary.stats.instructions, 3);
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(PROGRAM).build();
+        hart.run_for(3);
+        let (head, value) = (hart.register.pc, hart.register.registers[REG_A1 as usize]);
+
+        // The store to the last byte of `value` overlaps the watched range.
+        let write = hart.debugger.set_watchpoint(value + 3..value + 8, WatchKind::Write);
+        let summary = hart.run_for(100);
+        let access = Access { pc: head, address: value, bytes: 4, write: true };
+        assert!(matches!(
+            summary.reason,
+            StopReason::Watchpoint(hit) if hit == WatchpointHit { id: write, access }
+        ));
+        assert_eq!(summary.stats.instructions, 1);
+
+        assert!(hart.debugger.clear_watchpoint(write).is_some());
+        assert!(hart.debugger.watchpoint(write).is_none());
+        let read = hart.debugger.set_watchpoint(value..value + 4, WatchKind::Read);
+        let summary = hart.run_for(100);
+        let StopReason::Watchpoint(hit) = summary.reason else {
+            panic!("Expected a watchpoint stop, got {:?}", summary.reason);
+        };
+        assert_eq!((hit.id, hit.access.address, hit.access.write), (read, value, false));
+        assert_eq!(hart.register.registers[REG_A0 as usize + 2], 2);
+
+        // An access watchpoint is triggered by the store of the next iteration.
+        hart.debugger.clear_watchpoint(read);
+        let access = hart.debugger.set_watchpoint(value..value + 1, WatchKind::Access);
+        let summary = hart.run_for(100);
+        assert!(matches!(
+            summary.reason,
+            StopReason::Watchpoint(hit) if hit.id == access && hit.access.write
+        ));
+        assert_eq!(summary.stats.instructions, 3);
+    }
+
+    #[test]
+    fn test_unbounded_watchpoints() {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(PROGRAM).build();
+        hart.run_for(3);
+        let (head, value) = (hart.register.pc, hart.register.registers[REG_A1 as usize]);
+        let id = hart.debugger.set_watchpoint(value..value + 4, WatchKind::Write);
+
+        // `run` stops after the store, and resumes from the instruction after it.
+        let register = hart.run().unwrap();
+        let access = Access { pc: head, address: value, bytes: 4, write: true };
+        assert_eq!(hart.debugger.hit(), Some(WatchpointHit { id, access }));
+        assert_eq!(register.pc, head + 4);
+        assert!(!register.exit);
+
+        // `cycle` reports the hit of the instruction that it executed.
+        hart.cycle().unwrap();
+        assert_eq!(hart.debugger.hit(), None);
+        while hart.debugger.hit().is_none() {
+            hart.cycle().unwrap();
+        }
+        assert_eq!(hart.debugger.hit().map(|hit| hit.access.pc), Some(head));
+
+        hart.debugger.clear_watchpoint(id);
+        assert_eq!(hart.run().unwrap().exit_code, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "a")]
+    fn test_amo_watchpoint() {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(
+            r#"
+            .data
+            value: .word 0
+
+            .text
+            .globl _start
+            _start:
+                la a1, value
+                li a0, 1
+                amoadd.w a2, a0, (a1)
+                li a7, 93
+                ecall
+            "#,
+        )
+        .build();
+        hart.run_for(2);
+        let (amo, value) = (hart.register.pc + 4, hart.register.registers[REG_A1 as usize]);
+        hart.debugger.set_watchpoint(value..value + 4, WatchKind::Write);
+
+        let summary = hart.run_for(100);
+        let StopReason::Watchpoint(hit) = summary.reason else {
+            panic!("Expected a watchpoint stop, got {:?}", summary.reason);
+        };
+        assert_eq!(hit.access, Access { pc: amo, address: value, bytes: 4, write: true });
+    }
+
+    /// A [Kernel] whose `read` system call fills the buffer with `0xAB`.
+    #[derive(Debug, Default)]
+    struct FillKernel;
+
+    impl Kernel for FillKernel {
+        fn syscall<M: Memory, X: Xlen>(
+            &mut self,
+            syscall_no: XWord,
+            memory: &mut M,
+            p_reg: &mut PipelineRegister<X>,
+        ) -> PipelineResult<XWord> {
+            if syscall_no != 63 {
+                return RiscvTestKernel.syscall(syscall_no, memory, p_reg);
+            }
+            let [ptr, len] = [REG_A1, REG_A2].map(|reg| p_reg.registers[reg as usize]);
+            let data = alloc::vec![0xAB; len as usize];
+            memory.set_memory_range(ptr, &mut data.as_slice()).unwrap();
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test_kernel_write_watchpoint() {
+        let mut hart = assembled::<StEmu<_>, TestConfig<NativeXlen, (), FillKernel>>(
+            r#"
+            .data
+            buffer: .zero 16
+
+            .text
+            .globl _start
+            _start:
+                la a1, buffer
+                li a2, 16
+                li a7, 63
+                ecall
+                lbu a0, 12(a1)
+                li a7, 93
+                ecall
+            "#,
+        )
+        .with_debugger(Debugger::new())
+        .build();
+        hart.run_for(4);
+        let buffer = hart.register.registers[REG_A1 as usize];
+        let ecall = hart.register.pc;
+        let id = hart.debugger.set_watchpoint(buffer + 12..buffer + 16, WatchKind::Write);
+
+        let summary = hart.run_for(100);
+        let access = Access { pc: ecall, address: buffer, bytes: 16, write: true };
+        assert!(matches!(
+            summary.reason,
+            StopReason::Watchpoint(hit) if hit == WatchpointHit { id, access }
+        ));
+        let summary = hart.run_for(100);
+        assert!(matches!(summary.reason, StopReason::Exited(0xAB)));
+    }
+}
@@ -1,17 +1,36 @@
 //! Single-threaded, single-cycle 5-stage RISC-V pipeline.
 
-use crate::cfg::EmuConfig;
+use crate::{
+    cfg::EmuConfig,
+    kanata::KanataLog,
+    observer::{
+        mem_access_recorded, observe_accesses, observe_memory, syscall_recorded, MemoryAccess,
+        Observer, Stage,
+    },
+};
+use alloc::vec::Vec;
 use brisc_hw::{
+    cache::CacheHierarchy,
     errors::{PipelineError, PipelineResult},
-    kernel::Kernel,
-    pipeline::{
-        decode_instruction, execute, instruction_fetch, mem_access, writeback, PipelineRegister,
-    },
+    memory::Memory,
+    pipeline::{decode_instruction, execute, instruction_fetch, writeback, PipelineRegister},
+    predictor::BranchUnit,
+    timing::TimingModel,
+    IsaConfig, XWord,
 };
 
 mod builder;
 pub use builder::StEmuBuilder;
 
+mod stop;
+pub use stop::{RunStats, RunSummary, StopReason};
+
+mod debug;
+pub use debug::{Access, Debugger, WatchKind, Watchpoint, WatchpointHit, WatchpointId};
+
+mod snapshot;
+pub use snapshot::{SnapshotError, SNAPSHOT_VERSION};
+
 /// Single-cycle RISC-V processor emulator.
 #[derive(Debug, Default)]
 pub struct StEmu<Config>
@@ -19,11 +38,32 @@ where
     Config: EmuConfig,
 {
     /// The pipeline register.
-    pub register: PipelineRegister,
+    pub register: PipelineRegister<Config::Xlen>,
     /// The device memory.
     pub memory: Config::Memory,
     /// The system call interface.
     pub kernel: Config::Kernel,
+    /// The ISA extensions enabled on the hart.
+    pub isa: IsaConfig<Config::Xlen>,
+    /// The branch predictor, if any. A single-cycle hart has no use for predictions, but checks
+    /// them against the resolved program counter to measure the accuracy of the predictor.
+    pub branch_unit: Option<BranchUnit>,
+    /// The cache hierarchy, if any, which observes the addresses of fetches and memory accesses
+    /// without affecting their results.
+    pub caches: Option<CacheHierarchy>,
+    /// The timing model, if any, which estimates the cycles that the program would take on a
+    /// multi-cycle core.
+    pub timing: Option<TimingModel>,
+    /// The Kanata log of the run, if any, in which every instruction occupies each stage for a
+    /// cycle in turn.
+    pub kanata: Option<KanataLog>,
+    /// The observer of the execution of the hart.
+    pub observer: Config::Observer,
+    /// The breakpoints and watchpoints that stop the runs of the hart. Breakpoints only stop its
+    /// bounded runs.
+    pub debugger: Debugger,
+    /// The memory accesses made by the last instruction, or by the kernel if it was a system call.
+    accesses: Vec<MemoryAccess>,
 }
 
 impl<Config> StEmu<Config>
@@ -35,49 +75,176 @@ where
         StEmuBuilder::default()
     }
 
-    /// Executes the program until it exits, returning the final [PipelineRegister].
-    pub fn run(&mut self) -> PipelineResult<PipelineRegister> {
+    /// Executes the program until it exits, or an instruction triggers a watchpoint of
+    /// [Self::debugger], returning the final [PipelineRegister].
+    pub fn run(&mut self) -> PipelineResult<PipelineRegister<Config::Xlen>> {
         while !self.register.exit {
             self.cycle()?;
+            if self.debugger.hit.is_some() {
+                break;
+            }
         }
 
         Ok(self.register)
     }
 
-    /// Execute a single cycle of the processor in full.
+    /// Executes at most `budget` instructions, stopping early if the program exits, or at a
+    /// breakpoint or watchpoint of [Self::debugger].
+    pub fn run_for(&mut self, budget: u64) -> RunSummary {
+        self.run_bounded(budget, |_| None)
+    }
+
+    /// Executes the program until the program counter reaches `pc`, before the instruction at
+    /// `pc` executes, or the program exits. Returns at once if the program counter is at `pc`.
+    pub fn run_until_pc(&mut self, pc: XWord) -> RunSummary {
+        self.run_bounded(u64::MAX, |emu| {
+            (emu.register.pc == pc).then_some(StopReason::Breakpoint(pc))
+        })
+    }
+
+    /// Executes the program until `predicate` holds, checking it before every instruction, or the
+    /// program exits.
+    pub fn run_until(&mut self, mut predicate: impl FnMut(&Self) -> bool) -> RunSummary {
+        self.run_bounded(u64::MAX, |emu| predicate(emu).then_some(StopReason::Predicate))
+    }
+
+    /// Executes at most `budget` instructions, until the program exits, an instruction raises an
+    /// error, or `stop` returns a reason to stop before the next instruction.
+    fn run_bounded(
+        &mut self,
+        budget: u64,
+        mut stop: impl FnMut(&Self) -> Option<StopReason>,
+    ) -> RunSummary {
+        let mut stats = RunStats::default();
+        // Resuming from a breakpoint executes the instruction that the last run stopped before.
+        let mut resuming = self.debugger.stopped_at.take() == Some(self.register.pc);
+        let reason = loop {
+            if self.register.exit {
+                break StopReason::Exited(self.register.exit_code);
+            }
+            if let Some(reason) = stop(self) {
+                break reason;
+            }
+            let pc = self.register.pc;
+            if !core::mem::take(&mut resuming) {
+                let memory = &self.memory;
+                let raw = || memory.get_word(pc).ok();
+                if let Some(reason) = self.debugger.stop_before::<Config::Xlen>(pc, raw) {
+                    self.debugger.stopped_at = Some(pc);
+                    break reason;
+                }
+            }
+            if stats.instructions == budget {
+                break StopReason::BudgetExhausted;
+            }
+            if let Err(e) = self.step(true) {
+                break StopReason::Trap(e);
+            }
+
+            stats.record(&self.register, &self.accesses);
+            let hit = self.debugger.hit;
+            if !self.register.exit {
+                self.register.advance();
+            }
+            if let Some(hit) = hit {
+                break StopReason::Watchpoint(hit);
+            }
+        };
+        RunSummary { reason, stats }
+    }
+
+    /// Execute a single cycle of the processor in full. A watchpoint triggered by the instruction
+    /// is reported by [Debugger::hit].
     #[inline(always)]
     pub fn cycle(&mut self) -> PipelineResult<()> {
+        self.step(false)?;
+
+        // Exit emulation if a syscall terminated the program.
+        if !self.register.exit {
+            self.register.advance();
+        }
+        Ok(())
+    }
+
+    /// Runs the instruction at the program counter through every stage, leaving its results in
+    /// [Self::register]. Its memory accesses are recorded if `collecting` is set, as a bounded run
+    /// accounts for them in its [RunStats], or if the observer or a watchpoint needs them.
+    #[inline(always)]
+    fn step(&mut self, collecting: bool) -> PipelineResult<()> {
         let r = &mut self.register;
+        let branch_unit = &mut self.branch_unit;
+        let caches = &mut self.caches;
+        let observer = &mut self.observer;
+        let accesses = &mut self.accesses;
+        accesses.clear();
+        self.debugger.hit = None;
+        let recording = collecting ||
+            <Config::Observer as Observer<Config::Xlen>>::OBSERVES_MEMORY ||
+            self.debugger.watches();
 
-        // Execute all pipeline stages sequentially.
-        let cycle_res = instruction_fetch(r, &self.memory)
+        // Execute all pipeline stages sequentially, predicting branches at fetch and resolving
+        // them once executed.
+        let cycle_res = instruction_fetch(r, &self.memory, &self.isa)
+            .map(|_| caches.as_mut().map(|caches| caches.record_fetch(r)))
+            .map(|_| branch_unit.as_mut().map(|unit| unit.predict(r)))
+            .map(|_| observer.on_stage(Stage::Fetch, r))
             .and_then(|_| decode_instruction(r))
+            .map(|_| observer.on_stage(Stage::Decode, r))
             .and_then(|_| execute(r))
-            .and_then(|_| mem_access(r, &mut self.memory))
-            .and_then(|_| writeback(r));
+            .map(|_| branch_unit.as_mut().map(|unit| unit.resolve(r)))
+            .map(|_| observer.on_stage(Stage::Execute, r))
+            .and_then(|_| {
+                mem_access_recorded(r, &mut self.memory, recording.then_some(&mut *accesses))
+            })
+            .map(|_| caches.as_mut().map(|caches| caches.record_mem_access(r)))
+            .map(|_| observe_memory(observer, r, accesses))
+            .and_then(|_| writeback(r))
+            .map(|_| observer.on_stage(Stage::Writeback, r));
 
         // Handle system calls.
         match cycle_res {
             Ok(()) => {}
             Err(PipelineError::SyscallException(syscall_no)) => {
-                self.kernel.syscall(syscall_no, &mut self.memory, r)?;
-
-                // Exit emulation if the syscall terminated the program.
-                if r.exit {
-                    return Ok(());
-                }
+                observer.on_syscall(syscall_no, r);
+                let recorded = recording.then_some(&mut *accesses);
+                syscall_recorded(&mut self.kernel, syscall_no, &mut self.memory, r, recorded)?;
+                observe_accesses(observer, r, accesses);
             }
             Err(e) => return Err(e),
         }
+        observer.on_retire(r);
+        self.debugger.hit = self.debugger.check_watchpoints(r, accesses);
 
-        r.advance();
+        if let Some(timing) = self.timing.as_mut() {
+            timing.retire(r);
+        }
+        if let Some(log) = self.kanata.as_mut() {
+            log.record(r);
+        }
+        observer.on_cycle(&r.registers);
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::test_suites;
+    use super::{Debugger, RunStats, StEmu, StopReason, WatchKind};
+    use crate::{
+        elf::function_symbols,
+        test_suites,
+        test_utils::{assembled, loaded, TestConfig},
+    };
+    use brisc_hw::{
+        cache::{Cache, CacheConfig, CacheHierarchy, CacheStats},
+        errors::PipelineError,
+        fusion::{FusionDetector, FusionRule},
+        memory::PAGE_ADDRESS_SIZE,
+        predictor::{BranchPredictor, BranchStats, Btb, Btfn, ReturnStack, StaticNotTaken},
+        timing::{Latencies, RegionStats, TimingModel},
+        REG_A0,
+    };
+    use brisc_isa::{asm::assemble, DecodeErrorKind};
+    use rstest::rstest;
 
     test_suites!(
         base_dir = "../../rv-tests/bin",
@@ -89,7 +256,451 @@ mod test {
         rv64um ~ glob = "rv64um-p-*" ~ must_have = ["64-bit", "m"],
         rv64ua ~ glob = "rv64ua-p-*" ~ must_have = ["64-bit", "a"],
         rv64uc ~ glob = "rv64uc-p-*" ~ must_have = ["64-bit", "c"],
-        rs_program_32 ~ glob = "rs-32bit-*" ~ must_have = ["m", "a", "c"] ~ must_not_have = ["64-bit"],
+        rs_program_32 ~ glob = "rs-32bit-*" ~ must_have = ["m", "a", "c"],
         rs_program_64 ~ glob = "rs-64bit-*" ~ must_have = ["64-bit", "m", "a", "c"]
     );
+
+    #[test]
+    fn test_assembled_program() {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(
+            r#"
+            .data
+            values: .word 3, 5, 7, 11
+            count = (. - values) / 4
+
+            .text
+            .globl _start
+            _start:
+                la a1, values
+                li a2, count
+                li a0, 0
+            loop:
+                lw t0, 0(a1)
+                add a0, a0, t0
+                addi a1, a1, 4
+                addi a2, a2, -1
+                bnez a2, loop
+
+                # Exit with the sum as the exit code.
+                li a7, 93
+                ecall
+            "#,
+        )
+        .build();
+        let register = hart.run().unwrap();
+
+        assert_eq!(register.exit_code, 26);
+        assert_eq!(register.registers[REG_A0 as usize], 26);
+    }
+
+    #[rstest]
+    #[case::static_not_taken(Box::new(StaticNotTaken), 3, 7)]
+    #[case::btfn(Box::new(Btfn), 1, 5)]
+    #[case::btb(Box::new(Btb::new(Btfn, 4)), 1, 2)]
+    #[case::return_stack(Box::new(ReturnStack::new(Btfn, 4)), 1, 1)]
+    fn test_branch_prediction(
+        #[case] predictor: Box<dyn BranchPredictor>,
+        #[case] loop_mispredictions: u64,
+        #[case] total_mispredictions: u64,
+    ) {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(
+            r#"
+            .text
+            .globl _start
+            _start:
+                li a2, 4
+            loop:
+                jal f
+                addi a2, a2, -1
+                bnez a2, loop
+
+                li a0, 0
+                li a7, 93
+                ecall
+            f:
+                ret
+            "#,
+        )
+        .with_predictor(predictor)
+        .build();
+        let entry = hart.register.pc;
+        hart.run().unwrap();
+
+        // The call, the return and the loop branch are each predicted four times.
+        let unit = hart.branch_unit.unwrap();
+        assert_eq!(unit.branches.len(), 3);
+        assert_eq!(unit.branches[&(entry + 12)].mispredictions, loop_mispredictions);
+        assert_eq!(
+            unit.total(),
+            BranchStats { predictions: 12, mispredictions: total_mispredictions }
+        );
+    }
+
+    #[test]
+    fn test_cache_hierarchy() {
+        let elf = assemble(
+            r#"
+            .data
+            .align 6
+            array: .zero 256
+
+            .text
+            .globl _start
+            _start:
+                la a1, array
+                li a2, 64
+            loop:
+                sw a2, 0(a1)
+                lw t0, 0(a1)
+                add a0, a0, t0
+                addi a1, a1, 4
+                addi a2, a2, -1
+                bnez a2, loop
+
+                li a7, 93
+                ecall
+            "#,
+        )
+        .unwrap()
+        .to_elf();
+        let build = || loaded::<StEmu<_>, TestConfig>(&elf);
+
+        // A 256-byte L1D of 16-byte lines, which the array fills exactly.
+        let l1 = CacheConfig::new(256, 4, 16);
+        let caches = CacheHierarchy::new(
+            Cache::new(l1).unwrap(),
+            Cache::new(l1).unwrap().with_miss_attribution(),
+        )
+        .with_l2(Cache::new(CacheConfig::new(4096, 8, 64)).unwrap());
+        let mut hart = build().with_caches(caches).build();
+        let entry = hart.register.pc;
+        let register = hart.run().unwrap();
+
+        // The caches do not change the result of the program.
+        assert_eq!(register.exit_code, 2080);
+        assert_eq!(register, build().build().run().unwrap());
+
+        // Every store misses on the first word of a line, and the load after it hits.
+        let caches = hart.caches.unwrap();
+        assert_eq!(
+            caches.l1d.stats,
+            CacheStats { accesses: 128, hits: 112, misses: 16, ..Default::default() }
+        );
+        assert_eq!(
+            caches.l1d.misses_by_pc.unwrap().into_iter().collect::<Vec<_>>(),
+            [(entry + 12, 16)]
+        );
+        // The L2 misses on the four 64-byte lines of the array, and the one of the code.
+        assert_eq!(caches.l2.unwrap().stats.misses, 5);
+    }
+
+    #[test]
+    fn test_timing_model() {
+        let elf = assemble(
+            r#"
+            .text
+            .globl _start
+            _start:
+                li a0, 0
+                li a2, 3
+            loop:
+                jal add2
+                addi a2, a2, -1
+                bnez a2, loop
+
+                li a7, 93
+                ecall
+            add2:
+                addi a0, a0, 2
+                ret
+            "#,
+        )
+        .unwrap()
+        .to_elf();
+
+        let timing =
+            TimingModel::new(Latencies::CV32E40P).with_symbols(function_symbols(&elf).unwrap());
+        let mut hart = loaded::<StEmu<_>, TestConfig>(&elf).with_timing(timing).build();
+        let entry = hart.register.pc;
+        assert_eq!(hart.run().unwrap().exit_code, 6);
+
+        // Taken branches take 3 cycles, jumps 2 and `ecall` 4.
+        let timing = hart.timing.unwrap();
+        assert_eq!((timing.instructions, timing.cycles), (19, 32));
+        let region = |entries, instructions, cycles| RegionStats { entries, instructions, cycles };
+        assert_eq!(
+            timing.blocks.into_iter().collect::<Vec<_>>(),
+            [
+                (entry, region(1, 3, 4)),
+                (entry + 8, region(2, 2, 4)),
+                (entry + 12, region(3, 6, 10)),
+                (entry + 20, region(1, 2, 5)),
+                (entry + 28, region(3, 6, 9)),
+            ]
+        );
+        assert_eq!(
+            timing.functions.into_iter().collect::<Vec<_>>(),
+            [
+                (entry, region(1, 3, 4)),
+                (entry + 8, region(2, 10, 19)),
+                (entry + 28, region(3, 6, 9))
+            ]
+        );
+        assert_eq!(timing.symbols[&(entry + 28)], "add2");
+    }
+
+    #[test]
+    fn test_fusion() {
+        let timing = TimingModel::new(Latencies::SINGLE_CYCLE).with_fusion(FusionDetector::new());
+        let mut hart = assembled::<StEmu<_>, TestConfig>(
+            r#"
+            .text
+            .globl _start
+            _start:
+                li a0, 0x12345678
+                slli a1, a0, 16
+                srli a1, a1, 16
+                slli a2, a1, 2
+                add a2, a2, a0
+                auipc t1, 0
+                jalr zero, 12(t1)
+                addi a0, a0, 1
+                li a7, 93
+                ecall
+            "#,
+        )
+        .with_timing(timing)
+        .build();
+        hart.run().unwrap();
+
+        // Every pair takes a single slot, so the second instruction of each is free.
+        let timing = hart.timing.unwrap();
+        assert_eq!((timing.instructions, timing.cycles), (10, 6));
+        let fusion = timing.fusion.unwrap();
+        assert_eq!(
+            fusion.pairs.into_iter().collect::<Vec<_>>(),
+            [
+                (FusionRule::LoadImmediate, 1),
+                (FusionRule::FarJump, 1),
+                (FusionRule::ZeroExtend, 1),
+                (FusionRule::ShiftAdd, 1),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "128-bit", feature = "m"))]
+    fn test_rv128_program() {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(
+            r#"
+            .data
+            .align 4
+            buffer: .word 0, 0, 0, 0
+
+            .text
+            .globl _start
+            _start:
+                # a0 = 2^64, which only fits in a 128-bit register.
+                li a0, -1
+                srli a0, a0, 64
+                addi a0, a0, 1
+
+                # Round trip the quad word through memory, and read back its upper half.
+                la a1, buffer
+                sq a0, 0(a1)
+                lq a2, 0(a1)
+                ld a3, 8(a1)
+
+                # The double word operations see the lower half of a0, which is zero.
+                addid a4, a0, -5
+                mulhu a5, a0, a0
+                mulh a6, a4, a0
+
+                # Exit with 1 + 1 + (2^64 >> 60).
+                srli a2, a2, 60
+                add a0, a3, a5
+                add a0, a0, a2
+                li a7, 93
+                ecall
+            "#,
+        )
+        .build();
+        let register = hart.run().unwrap();
+
+        assert_eq!(register.exit_code, 18);
+        assert_eq!(register.registers[REG_A0 as usize + 4], -5i128 as u128);
+        assert_eq!(register.registers[REG_A0 as usize + 6], -1i128 as u128);
+    }
+
+    #[test]
+    fn test_decode_error_carries_pc() {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(
+            r#"
+            .text
+            .globl _start
+            _start:
+                nop
+                .word 0x0000000b
+            "#,
+        )
+        .build();
+        let entry = hart.register.pc;
+
+        match hart.run() {
+            Err(PipelineError::InstructionDecodeError { pc, source }) => {
+                assert_eq!(pc, entry + 4);
+                assert_eq!(source.raw, 0x0000000b);
+                assert_eq!(source.kind, DecodeErrorKind::InvalidOpcode(0b000_1011));
+            }
+            res => panic!("expected a decode error, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_bounded_runs() {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(
+            r#"
+            .data
+            value: .word 0
+
+            .text
+            .globl _start
+            _start:
+                la a1, value
+                li a0, 3
+            loop:
+                sw a0, 0(a1)
+                addi a0, a0, -1
+                bnez a0, loop
+                li a7, 93
+                ecall
+            "#,
+        )
+        .build();
+
+        let summary = hart.run_for(3);
+        assert!(matches!(summary.reason, StopReason::BudgetExhausted));
+        assert_eq!((summary.stats.instructions, summary.stats.syscalls), (3, 0));
+        let (head, value) = (hart.register.pc, hart.register.registers[REG_A0 as usize + 1]);
+
+        // The run stops before the instruction at the target, even if it is the first.
+        let summary = hart.run_until_pc(head);
+        assert!(matches!(summary.reason, StopReason::Breakpoint(pc) if pc == head));
+        assert_eq!(summary.stats.instructions, 0);
+
+        let summary = hart.run_for(1);
+        assert!(summary.stats.pages.contains(&(value >> PAGE_ADDRESS_SIZE)));
+        let summary = hart.run_until_pc(head);
+        assert!(matches!(summary.reason, StopReason::Breakpoint(pc) if pc == head));
+        assert_eq!((summary.stats.instructions, hart.register.registers[REG_A0 as usize]), (2, 2));
+
+        // The predicate holds once `a0` is decremented to zero, before `bnez`.
+        let summary = hart.run_until(|hart| hart.register.registers[REG_A0 as usize] == 0);
+        assert!(matches!(summary.reason, StopReason::Predicate));
+        assert_eq!(summary.stats.instructions, 5);
+
+        let summary = hart.run_for(100);
+        assert!(matches!(summary.reason, StopReason::Exited(0)));
+        assert_eq!((summary.stats.instructions, summary.stats.syscalls), (3, 1));
+        assert_eq!(summary.stats.pages_touched(), 1);
+
+        let summary = hart.run_for(100);
+        assert!(matches!(summary.reason, StopReason::Exited(0)));
+        assert_eq!(summary.stats, RunStats::default());
+    }
+
+    #[test]
+    fn test_bounded_run_traps() {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(
+            r#"
+            .text
+            .globl _start
+            _start:
+                j _start
+                .word 0x0000000b
+            "#,
+        )
+        .build();
+        let entry = hart.register.pc;
+
+        // The guest spins forever, so only the budget stops it.
+        let summary = hart.run_for(1_000);
+        assert!(matches!(summary.reason, StopReason::BudgetExhausted));
+        assert_eq!(summary.stats.instructions, 1_000);
+
+        hart.register.pc = entry + 4;
+        let summary = hart.run_for(1_000);
+        assert!(matches!(
+            summary.reason,
+            StopReason::Trap(PipelineError::InstructionDecodeError { pc, .. }) if pc == entry + 4
+        ));
+        assert_eq!(summary.stats.instructions, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "m")]
+    fn test_disabled_extension_is_illegal() {
+        use brisc_isa::{Extension, IsaConfig};
+
+        let elf = assemble(
+            r#"
+            .text
+            .globl _start
+            _start:
+                li a0, 6
+                li a1, 7
+                mul a0, a0, a1
+                li a7, 93
+                ecall
+            "#,
+        )
+        .unwrap()
+        .to_elf();
+        let build = |isa: IsaConfig| loaded::<StEmu<_>, TestConfig>(&elf).with_isa(isa).build();
+
+        assert_eq!(build(IsaConfig::default()).run().unwrap().exit_code, 42);
+        match build(IsaConfig::default().without_extension(Extension::M)).run() {
+            Err(PipelineError::InstructionDecodeError { source, .. }) => {
+                assert_eq!(source.kind, DecodeErrorKind::ExtensionDisabled(Extension::M));
+            }
+            res => panic!("expected a decode error, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_accesses_recorded_on_demand() {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(
+            r#"
+            .data
+            value: .word 0
+
+            .text
+            .globl _start
+            _start:
+                la a1, value
+                sw zero, 0(a1)
+                lw a0, 0(a1)
+                li a2, 4
+                li a7, 64
+                ecall
+            "#,
+        )
+        .build();
+
+        // The `()` observer has no use for the access of the store.
+        for _ in 0..3 {
+            hart.cycle().unwrap();
+        }
+        assert!(hart.accesses.is_empty());
+
+        // A watchpoint needs that of the load, even if it is not triggered.
+        hart.debugger.set_watchpoint(0..1, WatchKind::Write);
+        hart.cycle().unwrap();
+        assert_eq!(hart.accesses.len(), 1);
+
+        // A bounded run needs those of the kernel, to count the pages that it touched.
+        hart.debugger = Debugger::default();
+        let summary = hart.run_for(3);
+        assert_eq!((summary.stats.instructions, hart.accesses.len()), (3, 1));
+    }
 }
@@ -0,0 +1,790 @@
+//! Snapshots of the complete state of a [StEmu], in a versioned and checksummed binary format.
+//!
+//! A snapshot starts with a header holding the magic bytes `BRISCSNP`, the format version and the
+//! XLEN of the hart, as little-endian `u16`s. It is followed by tagged sections, each a `u16` tag
+//! and the `u64` length of its payload, and ends with the CRC-32 of every byte before it.
+//!
+//! | Tag | Section    | Payload                                                              |
+//! |-----|------------|----------------------------------------------------------------------|
+//! | 1   | ISA        | The enabled extensions, as the low 26 bits of `misa`                 |
+//! | 2   | Register   | Every field of the [PipelineRegister]                                |
+//! | 3   | Memory     | The page size, then every allocated page by index, zero pages elided |
+//! | 4   | Kernel     | The state saved by [Kernel::save_state]                              |
+//!
+//! Register-sized values take `XLEN / 8` little-endian bytes, and optional values are prefixed with
+//! a byte that is 1 if they are set. A reader loads every version up to [SNAPSHOT_VERSION]. Later
+//! versions only add sections or append fields to the end of a section, so that the state missing
+//! from earlier versions keeps its default, and sections with unknown tags are skipped. The
+//! optional fields of the register section, from the predicted program counter on, default to
+//! unset when the section ends before them.
+//!
+//! [Kernel::save_state]: brisc_hw::kernel::Kernel::save_state
+
+use super::StEmu;
+use crate::cfg::EmuConfig;
+use alloc::{string::String, vec::Vec};
+use brisc_hw::{
+    kernel::Kernel,
+    memory::{Memory, MemoryError, PageIndex, SnapshotMemory, PAGE_ADDRESS_SIZE, PAGE_SIZE},
+    pipeline::PipelineRegister,
+    DecodedInstruction, Extension, IsaConfig, XWord, Xlen,
+};
+use core::marker::PhantomData;
+use thiserror::Error;
+
+/// The magic bytes that start a snapshot.
+const MAGIC: &[u8; 8] = b"BRISCSNP";
+
+/// The version of the snapshot format written by [StEmu::snapshot].
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+/// The length of the header: the magic bytes, the version and the XLEN.
+const HEADER_LEN: usize = MAGIC.len() + 4;
+
+/// The length of the trailing CRC-32.
+const CHECKSUM_LEN: usize = 4;
+
+/// The tag of the ISA section.
+const TAG_ISA: u16 = 1;
+
+/// The tag of the register section.
+const TAG_REGISTER: u16 = 2;
+
+/// The tag of the memory section.
+const TAG_MEMORY: u16 = 3;
+
+/// The tag of the kernel section.
+const TAG_KERNEL: u16 = 4;
+
+/// The extensions that can be enabled beside the base integer instruction set.
+const EXTENSIONS: [Extension; 3] = [Extension::M, Extension::A, Extension::C];
+
+/// An error that occurs while restoring a snapshot.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The data does not start with the magic bytes of a snapshot.
+    #[error("Not a snapshot")]
+    BadMagic,
+    /// The snapshot was written by a later version of the format.
+    #[error("Unsupported snapshot version {0}, expected at most {SNAPSHOT_VERSION}")]
+    UnsupportedVersion(u16),
+    /// The checksum of the snapshot does not match its contents.
+    #[error("Snapshot checksum mismatch: expected {expected:#010x}, found {found:#010x}")]
+    Checksum {
+        /// The checksum stored in the snapshot.
+        expected: u32,
+        /// The checksum of the contents of the snapshot.
+        found: u32,
+    },
+    /// The snapshot was taken on a hart of another register width.
+    #[error("Snapshot of an RV{snapshot} hart cannot be restored on an RV{hart} hart")]
+    XlenMismatch {
+        /// The XLEN of the snapshot.
+        snapshot: u16,
+        /// The XLEN of the hart.
+        hart: u16,
+    },
+    /// The snapshot enables an extension that is not compiled into this build.
+    #[error("Snapshot enables the `{0}` extension, which is not compiled in")]
+    MissingExtension(char),
+    /// The snapshot ended in the middle of a value.
+    #[error("Snapshot is truncated")]
+    Truncated,
+    /// The snapshot is structurally invalid.
+    #[error("Malformed snapshot: {0}")]
+    Malformed(&'static str),
+    /// The kernel rejected its saved state.
+    #[error("Failed to restore the kernel state: {0}")]
+    Kernel(String),
+    /// The memory could not allocate a page of the snapshot.
+    #[error("Failed to restore memory: {0}")]
+    Memory(MemoryError),
+}
+
+impl<Config> StEmu<Config>
+where
+    Config: EmuConfig,
+{
+    /// Serializes the architectural state of the hart: the enabled extensions, the
+    /// [PipelineRegister] including the load reservation, every allocated page of memory and the
+    /// state of the kernel. Instrumentation such as the branch predictor, caches, timing model,
+    /// logs, observer and debugger is not part of the snapshot.
+    ///
+    /// Pages are written in order of their index, so equal states produce equal snapshots.
+    pub fn snapshot(&self) -> Vec<u8>
+    where
+        Config::Memory: SnapshotMemory,
+    {
+        let mut writer = Writer::<Config::Xlen>::new();
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.u16(SNAPSHOT_VERSION);
+        writer.u16(Config::Xlen::X_LEN as u16);
+
+        writer.section(TAG_ISA, |w| {
+            let extensions = EXTENSIONS.into_iter().filter(|&extension| self.isa.has(extension));
+            w.u32(
+                extensions
+                    .fold(misa_bit(Extension::I), |bits, extension| bits | misa_bit(extension)),
+            );
+        });
+        writer.section(TAG_REGISTER, |w| w.register(&self.register));
+        writer.section(TAG_MEMORY, |w| {
+            let mut indices = self.memory.page_indices();
+            indices.sort_unstable();
+            w.u8(PAGE_ADDRESS_SIZE as u8);
+            w.u64(indices.len() as u64);
+            for index in indices {
+                w.word(index);
+                match self.memory.page(index).filter(|page| page.iter().any(|&byte| byte != 0)) {
+                    Some(page) => {
+                        w.u8(1);
+                        w.bytes.extend_from_slice(page);
+                    }
+                    None => w.u8(0),
+                }
+            }
+        });
+        writer.section(TAG_KERNEL, |w| self.kernel.save_state(&mut w.bytes));
+
+        let checksum = crc32(&writer.bytes);
+        writer.u32(checksum);
+        writer.bytes
+    }
+
+    /// Restores the state serialized by [Self::snapshot], replacing the memory of the hart, and
+    /// resuming bounded runs from the restored program counter. Running the restored hart
+    /// produces the same state as running the hart that was snapshotted.
+    ///
+    /// The snapshot is validated in full before anything but the kernel is modified, so the hart
+    /// is left untouched if it is rejected.
+    pub fn restore(&mut self, snapshot: &[u8]) -> Result<(), SnapshotError>
+    where
+        Config::Memory: Default,
+    {
+        let mut reader = Reader::<Config::Xlen>::open(snapshot)?;
+        let (mut isa, mut register, mut pages, mut kernel) = (None, None, None, &[][..]);
+        while !reader.bytes.is_empty() {
+            let tag = reader.u16()?;
+            let length = reader.u64()?;
+            let mut section = Reader::<Config::Xlen>::new(reader.take(length)?);
+            match tag {
+                TAG_ISA => isa = Some(section.isa()?),
+                TAG_REGISTER => register = Some(section.register()?),
+                TAG_MEMORY => pages = Some(section.pages()?),
+                TAG_KERNEL => kernel = section.bytes,
+                // Sections added by later versions of the format.
+                _ => {}
+            }
+        }
+        let register = register.ok_or(SnapshotError::Malformed("missing register section"))?;
+        let pages = pages.ok_or(SnapshotError::Malformed("missing memory section"))?;
+
+        let mut memory = Config::Memory::default();
+        for (index, data) in pages {
+            let page = memory.alloc(index).map_err(SnapshotError::Memory)?;
+            if let Some(data) = data {
+                page.copy_from_slice(data);
+            }
+        }
+        self.kernel.restore_state(kernel).map_err(SnapshotError::Kernel)?;
+
+        self.memory = memory;
+        self.register = register;
+        if let Some(isa) = isa {
+            self.isa = isa;
+        }
+        self.debugger.stopped_at = None;
+        self.debugger.hit = None;
+        Ok(())
+    }
+}
+
+/// The index of a page saved in a snapshot, along with its contents, or [None] if it is zeroed.
+type SavedPage<'a> = (PageIndex, Option<&'a [u8]>);
+
+/// Serializes the values of a snapshot, with register-sized values of the width of `X`.
+#[derive(Debug)]
+struct Writer<X> {
+    /// The bytes written so far.
+    bytes: Vec<u8>,
+    /// The register width of the hart.
+    xlen: PhantomData<X>,
+}
+
+impl<X: Xlen> Writer<X> {
+    /// Creates a new, empty [Writer].
+    const fn new() -> Self {
+        Self { bytes: Vec::new(), xlen: PhantomData }
+    }
+
+    /// Writes a section with the payload written by `payload`.
+    fn section(&mut self, tag: u16, payload: impl FnOnce(&mut Self)) {
+        self.u16(tag);
+        let length_at = self.bytes.len();
+        self.u64(0);
+        payload(self);
+        let length = (self.bytes.len() - length_at - 8) as u64;
+        self.bytes[length_at..length_at + 8].copy_from_slice(&length.to_le_bytes());
+    }
+
+    /// Writes every field of `register`.
+    fn register(&mut self, register: &PipelineRegister<X>) {
+        self.u8(register.exit as u8);
+        self.word(register.exit_code);
+        self.word(register.pc);
+        for value in register.registers {
+            self.word(value);
+        }
+        self.word(register.next_pc);
+        self.optional_word(register.predicted_pc);
+        self.optional(register.instruction.map(|decoded| decoded.raw), Self::u32);
+        self.optional_word(register.rs1_value);
+        self.optional_word(register.rs2_value);
+        self.optional_word(register.immediate);
+        self.optional(register.rd, Self::u8);
+        self.optional_word(register.alu_result);
+        self.optional_word(register.memory);
+        #[cfg(feature = "a")]
+        self.optional_word(register.reservation);
+        #[cfg(not(feature = "a"))]
+        self.optional_word(None);
+    }
+
+    /// Writes a byte.
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    /// Writes a little-endian `u16`.
+    fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Writes a little-endian `u32`.
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Writes a little-endian `u64`.
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Writes a register-sized value, as its `X::X_LEN / 8` little-endian bytes.
+    fn word(&mut self, value: XWord) {
+        self.bytes.extend_from_slice(&value.to_le_bytes()[..X::X_LEN / 8]);
+    }
+
+    /// Writes an optional value with `write`, prefixed with whether it is set.
+    fn optional<T>(&mut self, value: Option<T>, write: impl FnOnce(&mut Self, T)) {
+        self.u8(value.is_some() as u8);
+        if let Some(value) = value {
+            write(self, value);
+        }
+    }
+
+    /// Writes an optional register-sized value.
+    fn optional_word(&mut self, value: Option<XWord>) {
+        self.optional(value, Self::word);
+    }
+}
+
+/// Deserializes the values of a snapshot, with register-sized values of the width of `X`.
+#[derive(Debug)]
+struct Reader<'a, X> {
+    /// The bytes left to read.
+    bytes: &'a [u8],
+    /// The register width of the hart.
+    xlen: PhantomData<X>,
+}
+
+impl<'a, X: Xlen> Reader<'a, X> {
+    /// Creates a new [Reader] over `bytes`.
+    const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, xlen: PhantomData }
+    }
+
+    /// Checks the header and checksum of `snapshot`, and returns a [Reader] over its sections.
+    fn open(snapshot: &'a [u8]) -> Result<Self, SnapshotError> {
+        if !snapshot.starts_with(MAGIC) {
+            return Err(SnapshotError::BadMagic);
+        }
+        if snapshot.len() < HEADER_LEN + CHECKSUM_LEN {
+            return Err(SnapshotError::Truncated);
+        }
+        let (contents, checksum) = snapshot.split_at(snapshot.len() - CHECKSUM_LEN);
+        let mut header = Self::new(&contents[MAGIC.len()..]);
+        let (version, xlen) = (header.u16()?, header.u16()?);
+        if version > SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let expected = u32::from_le_bytes(checksum.try_into().expect("Checksum is 4 bytes"));
+        let found = crc32(contents);
+        if expected != found {
+            return Err(SnapshotError::Checksum { expected, found });
+        }
+        if xlen as usize != X::X_LEN {
+            return Err(SnapshotError::XlenMismatch { snapshot: xlen, hart: X::X_LEN as u16 });
+        }
+        Ok(header)
+    }
+
+    /// Reads the ISA section.
+    fn isa(&mut self) -> Result<IsaConfig<X>, SnapshotError> {
+        let extensions = self.u32()?;
+        let mut isa = IsaConfig::new();
+        for extension in EXTENSIONS {
+            if extensions & misa_bit(extension) != 0 {
+                isa = isa.with_extension(extension);
+                if !isa.has(extension) {
+                    return Err(SnapshotError::MissingExtension(extension.letter()));
+                }
+            }
+        }
+        Ok(isa)
+    }
+
+    /// Reads the register section.
+    fn register(&mut self) -> Result<PipelineRegister<X>, SnapshotError> {
+        let exit = match self.u8()? {
+            0 => false,
+            1 => true,
+            _ => return Err(SnapshotError::Malformed("invalid exit flag")),
+        };
+        let (exit_code, pc) = (self.word()?, self.word()?);
+        let mut registers = [0; 32];
+        for value in &mut registers {
+            *value = self.word()?;
+        }
+        let next_pc = self.word()?;
+
+        // The optional fields may be cut from the end of the section.
+        let predicted_pc = self.trailing(Self::optional_word)?;
+        let instruction = self
+            .trailing(|reader| reader.optional(Self::u32))?
+            .map(|raw| DecodedInstruction::decode_for::<X>(raw))
+            .transpose()
+            .map_err(|_| SnapshotError::Malformed("invalid instruction"))?;
+        let (rs1_value, rs2_value, immediate) = (
+            self.trailing(Self::optional_word)?,
+            self.trailing(Self::optional_word)?,
+            self.trailing(Self::optional_word)?,
+        );
+        let rd = self.trailing(|reader| reader.optional(Self::u8))?;
+        let (alu_result, memory) =
+            (self.trailing(Self::optional_word)?, self.trailing(Self::optional_word)?);
+        #[cfg_attr(not(feature = "a"), allow(unused_variables))]
+        let reservation = self.trailing(Self::optional_word)?;
+
+        Ok(PipelineRegister {
+            exit,
+            exit_code,
+            pc,
+            registers,
+            next_pc,
+            predicted_pc,
+            instruction,
+            rs1_value,
+            rs2_value,
+            immediate,
+            rd,
+            alu_result,
+            memory,
+            #[cfg(feature = "a")]
+            reservation,
+            xlen: PhantomData,
+        })
+    }
+
+    /// Reads the memory section.
+    fn pages(&mut self) -> Result<Vec<SavedPage<'a>>, SnapshotError> {
+        if self.u8()? as usize != PAGE_ADDRESS_SIZE {
+            return Err(SnapshotError::Malformed("page size mismatch"));
+        }
+        let count = self.u64()?;
+        // Every page takes at least a byte, which bounds the allocation.
+        let mut pages = Vec::with_capacity(count.min(self.bytes.len() as u64) as usize);
+        for _ in 0..count {
+            let index = self.word()?;
+            let data = match self.u8()? {
+                0 => None,
+                1 => Some(self.take(PAGE_SIZE as u64)?),
+                _ => return Err(SnapshotError::Malformed("invalid page kind")),
+            };
+            pages.push((index, data));
+        }
+        Ok(pages)
+    }
+
+    /// Reads `length` bytes.
+    const fn take(&mut self, length: u64) -> Result<&'a [u8], SnapshotError> {
+        if length > self.bytes.len() as u64 {
+            return Err(SnapshotError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(length as usize);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// Reads `N` bytes.
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        Ok(self.take(N as u64)?.try_into().expect("Took N bytes"))
+    }
+
+    /// Reads a byte.
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    /// Reads a little-endian `u16`.
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    /// Reads a little-endian `u32`.
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    /// Reads a little-endian `u64`.
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    /// Reads a register-sized value.
+    fn word(&mut self) -> Result<XWord, SnapshotError> {
+        let mut word = [0; size_of::<XWord>()];
+        word[..X::X_LEN / 8].copy_from_slice(self.take((X::X_LEN / 8) as u64)?);
+        Ok(XWord::from_le_bytes(word))
+    }
+
+    /// Reads an optional value with `read`.
+    fn optional<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, SnapshotError>,
+    ) -> Result<Option<T>, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => read(self).map(Some),
+            _ => Err(SnapshotError::Malformed("invalid optional flag")),
+        }
+    }
+
+    /// Reads a field with `read`, or returns its default if the section ends before it, as the
+    /// fields appended to a section by later versions are absent from earlier ones.
+    fn trailing<T: Default>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, SnapshotError>,
+    ) -> Result<T, SnapshotError> {
+        if self.bytes.is_empty() {
+            Ok(T::default())
+        } else {
+            read(self)
+        }
+    }
+
+    /// Reads an optional register-sized value.
+    fn optional_word(&mut self) -> Result<Option<XWord>, SnapshotError> {
+        self.optional(Self::word)
+    }
+}
+
+/// Returns the bit of an extension in `misa`.
+const fn misa_bit(extension: Extension) -> u32 {
+    1 << (extension.letter() as u32 - 'a' as u32)
+}
+
+/// The lookup table of [crc32], for the reflected polynomial `0xEDB88320`.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { crc >> 1 ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Computes the CRC-32 (ISO-HDLC) of `data`.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ crc >> 8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc32, SnapshotError, CHECKSUM_LEN, HEADER_LEN, SNAPSHOT_VERSION, TAG_REGISTER};
+    use crate::{
+        st::{StEmu, StopReason},
+        test_utils::{assembled, loaded, RiscvTestKernel, TestConfig},
+    };
+    use alloc::{string::String, vec::Vec};
+    use brisc_hw::{
+        errors::PipelineResult, kernel::Kernel, memory::Memory, pipeline::PipelineRegister,
+        NativeXlen, XWord, Xlen, REG_A0,
+    };
+    use brisc_isa::asm::assemble;
+    use rstest::rstest;
+
+    const PROGRAM: &str = r#"
+        .data
+        table: .zero 64
+
+        .text
+        .globl _start
+        _start:
+            la a1, table
+            li a0, 16
+            li a2, 0
+        loop:
+            add a2, a2, a0
+            sw a2, 0(a1)
+            addi a1, a1, 4
+            addi a0, a0, -1
+            bnez a0, loop
+            andi a0, a2, 0xFF
+            li a7, 93
+            ecall
+        "#;
+
+    /// Replaces the checksum of `snapshot` with the checksum of its contents.
+    fn reseal(snapshot: &mut [u8]) {
+        let contents = snapshot.len() - CHECKSUM_LEN;
+        let checksum = crc32(&snapshot[..contents]);
+        snapshot[contents..].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[rstest]
+    #[case::start(0)]
+    #[case::setup(3)]
+    #[case::loop_body(20)]
+    #[case::before_exit(85)]
+    fn test_roundtrip_is_deterministic(#[case] split: u64) {
+        roundtrip::<brisc_hw::Rv32>(split);
+        roundtrip::<NativeXlen>(split);
+    }
+
+    /// Snapshots a hart with the register width `X` after `split` instructions, and checks that
+    /// the restored hart runs to the same state.
+    fn roundtrip<X: Xlen>(split: u64) {
+        let mut original = assembled::<StEmu<_>, TestConfig<X>>(PROGRAM).build();
+        original.run_for(split);
+        let snapshot = original.snapshot();
+
+        let mut restored = assembled::<StEmu<_>, TestConfig<X>>(".text\n_start: nop").build();
+        restored.restore(&snapshot).unwrap();
+        assert_eq!(restored.snapshot(), snapshot);
+
+        for hart in [&mut original, &mut restored] {
+            assert!(matches!(hart.run_for(1_000).reason, StopReason::Exited(0x88)));
+        }
+        assert_eq!(original.register, restored.register);
+        assert_eq!(original.snapshot(), restored.snapshot());
+    }
+
+    #[rstest]
+    #[case::magic(0, SnapshotError::BadMagic)]
+    #[case::contents(40, SnapshotError::Checksum { expected: 0, found: 0 })]
+    fn test_corruption_is_detected(#[case] offset: usize, #[case] expected: SnapshotError) {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(PROGRAM).build();
+        let mut snapshot = hart.snapshot();
+        assert!(matches!(hart.restore(&snapshot[..6]), Err(SnapshotError::BadMagic)));
+        assert!(matches!(hart.restore(&snapshot[..10]), Err(SnapshotError::Truncated)));
+
+        snapshot[offset] ^= 0x10;
+        let error = hart.restore(&snapshot).unwrap_err();
+        assert_eq!(core::mem::discriminant(&error), core::mem::discriminant(&expected));
+    }
+
+    #[test]
+    fn test_version_compatibility() {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(PROGRAM).build();
+        hart.run_for(10);
+        let mut snapshot = hart.snapshot();
+
+        // Sections from later versions are skipped.
+        let mut extended = snapshot[..snapshot.len() - CHECKSUM_LEN].to_vec();
+        extended.extend_from_slice(&0xBEEF_u16.to_le_bytes());
+        extended.extend_from_slice(&3_u64.to_le_bytes());
+        extended.extend_from_slice(&[1, 2, 3, 0, 0, 0, 0]);
+        reseal(&mut extended);
+        let mut restored = assembled::<StEmu<_>, TestConfig>(PROGRAM).build();
+        restored.restore(&extended).unwrap();
+        assert_eq!(restored.snapshot(), snapshot);
+
+        // Later versions are rejected outright.
+        snapshot[8..10].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        reseal(&mut snapshot);
+        assert_eq!(
+            restored.restore(&snapshot),
+            Err(SnapshotError::UnsupportedVersion(SNAPSHOT_VERSION + 1))
+        );
+    }
+
+    /// Returns `snapshot` with its register section cut to `length` bytes.
+    fn cut_register_section(snapshot: &[u8], length: usize) -> Vec<u8> {
+        let mut cut = snapshot[..HEADER_LEN].to_vec();
+        let mut sections = &snapshot[HEADER_LEN..snapshot.len() - CHECKSUM_LEN];
+        while !sections.is_empty() {
+            let (header, rest) = sections.split_at(10);
+            let section_length = u64::from_le_bytes(header[2..].try_into().unwrap()) as usize;
+            let (mut payload, rest) = rest.split_at(section_length);
+            if u16::from_le_bytes([header[0], header[1]]) == TAG_REGISTER {
+                payload = &payload[..length];
+            }
+            cut.extend_from_slice(&header[..2]);
+            cut.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+            cut.extend_from_slice(payload);
+            sections = rest;
+        }
+        cut.extend_from_slice(&[0; CHECKSUM_LEN]);
+        reseal(&mut cut);
+        cut
+    }
+
+    #[test]
+    fn test_short_register_section() {
+        let mut hart = assembled::<StEmu<_>, TestConfig>(PROGRAM).build();
+        hart.run_for(10);
+        let snapshot = hart.snapshot();
+
+        // A version 1 register section without the fields from the predicted program counter on,
+        // which take their defaults.
+        let required = 1 + 35 * (NativeXlen::X_LEN / 8);
+        let mut restored = assembled::<StEmu<_>, TestConfig>(PROGRAM).build();
+        restored.restore(&cut_register_section(&snapshot, required)).unwrap();
+        let (original, register) = (&hart.register, &restored.register);
+        assert_eq!(
+            (register.pc, register.registers, register.next_pc),
+            (original.pc, original.registers, original.next_pc)
+        );
+        assert_eq!((register.instruction, register.rd, register.memory), (None, None, None));
+        assert!(matches!(restored.run_for(1_000).reason, StopReason::Exited(0x88)));
+
+        // A field cut in the middle is still an error.
+        assert_eq!(
+            restored.restore(&cut_register_section(&snapshot, required - 1)),
+            Err(SnapshotError::Truncated)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "64-bit")]
+    fn test_xlen_mismatch() {
+        let snapshot = StEmu::<TestConfig<brisc_hw::Rv32>>::default().snapshot();
+        assert_eq!(
+            assembled::<StEmu<_>, TestConfig>(PROGRAM).build().restore(&snapshot),
+            Err(SnapshotError::XlenMismatch { snapshot: 32, hart: NativeXlen::X_LEN as u16 })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "a")]
+    fn test_reservation_is_preserved() {
+        let program = assemble(
+            r#"
+            .data
+            value: .word 41
+
+            .text
+            .globl _start
+            _start:
+                la a1, value
+                lr.w a0, (a1)
+            reserved:
+                addi a0, a0, 1
+                sc.w a2, a0, (a1)
+                lw a0, 0(a1)
+                add a0, a0, a2
+                li a7, 93
+                ecall
+            "#,
+        )
+        .unwrap();
+        let elf = program.to_elf();
+        let mut original = loaded::<StEmu<_>, TestConfig>(&elf).build();
+        original.run_until_pc(program.symbol("reserved").unwrap().address);
+        assert!(original.register.reservation.is_some());
+
+        let mut restored = loaded::<StEmu<_>, TestConfig>(&elf).build();
+        restored.restore(&original.snapshot()).unwrap();
+        assert_eq!(restored.register.reservation, original.register.reservation);
+        assert!(matches!(restored.run_for(100).reason, StopReason::Exited(42)));
+    }
+
+    /// The [TestConfig] of a hart running on the [CountingKernel].
+    type CountingConfig = TestConfig<NativeXlen, (), CountingKernel>;
+
+    /// A [Kernel] counting the calls to syscall 172, which returns the count in `a0`.
+    #[derive(Debug, Default)]
+    struct CountingKernel(u64);
+
+    impl Kernel for CountingKernel {
+        fn syscall<M: Memory, X: Xlen>(
+            &mut self,
+            sysno: XWord,
+            memory: &mut M,
+            p_reg: &mut PipelineRegister<X>,
+        ) -> PipelineResult<XWord> {
+            if sysno != 172 {
+                return RiscvTestKernel.syscall(sysno, memory, p_reg);
+            }
+            self.0 += 1;
+            p_reg.registers[REG_A0 as usize] = self.0 as XWord;
+            Ok(0)
+        }
+
+        fn save_state(&self, state: &mut Vec<u8>) {
+            state.extend_from_slice(&self.0.to_le_bytes());
+        }
+
+        fn restore_state(&mut self, state: &[u8]) -> Result<(), String> {
+            let count = state.try_into().map_err(|_| String::from("Expected a u64 count"))?;
+            self.0 = u64::from_le_bytes(count);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_kernel_state() {
+        let program = assemble(
+            r#"
+            .text
+            .globl _start
+            _start:
+                li a7, 172
+                ecall
+                ecall
+            counted:
+                ecall
+                li a7, 93
+                ecall
+            "#,
+        )
+        .unwrap();
+        let elf = program.to_elf();
+        let mut original = loaded::<StEmu<_>, CountingConfig>(&elf).build();
+        original.run_until_pc(program.symbol("counted").unwrap().address);
+        let snapshot = original.snapshot();
+
+        let mut restored = loaded::<StEmu<_>, CountingConfig>(&elf).build();
+        restored.restore(&snapshot).unwrap();
+        assert_eq!(restored.kernel.0, 2);
+        assert!(matches!(restored.run_for(100).reason, StopReason::Exited(3)));
+
+        // A stateless kernel rejects the saved count.
+        let mut stateless = loaded::<StEmu<_>, TestConfig>(&elf).build();
+        assert!(matches!(stateless.restore(&snapshot), Err(SnapshotError::Kernel(_))));
+    }
+}
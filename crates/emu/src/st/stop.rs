@@ -0,0 +1,91 @@
+//! The outcome of a bounded run of a [StEmu].
+//!
+//! [StEmu]: super::StEmu
+
+use super::WatchpointHit;
+use crate::observer::MemoryAccess;
+use alloc::collections::BTreeSet;
+use brisc_hw::{
+    errors::PipelineError,
+    memory::{PageIndex, PAGE_ADDRESS_SIZE},
+    pipeline::PipelineRegister,
+    XWord, Xlen,
+};
+
+/// The reason that a bounded run stopped.
+#[derive(Debug)]
+pub enum StopReason {
+    /// The program exited with the given code.
+    Exited(XWord),
+    /// The run retired as many instructions as it was allowed to.
+    BudgetExhausted,
+    /// The program counter reached the given address, at a breakpoint or the target of the run.
+    /// The instruction at it has not executed.
+    Breakpoint(XWord),
+    /// The program counter reached a guest `ebreak` at the given address, which has not executed.
+    Ebreak(XWord),
+    /// A watchpoint was triggered by the last instruction retired.
+    Watchpoint(WatchpointHit),
+    /// The instruction at the program counter raised an error, and has not retired.
+    Trap(PipelineError),
+    /// The predicate of the run was satisfied.
+    Predicate,
+}
+
+/// The activity of a bounded run.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RunStats {
+    /// The number of instructions retired.
+    pub instructions: u64,
+    /// The number of system calls made.
+    pub syscalls: u64,
+    /// The memory pages that instructions were fetched from, read or written, by index.
+    pub pages: BTreeSet<PageIndex>,
+}
+
+impl RunStats {
+    /// Returns the number of memory pages touched.
+    pub fn pages_touched(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Accounts for the instruction in `p_reg`, once it has retired, and for the memory `accesses`
+    /// that it made, or that the kernel made during its system call.
+    pub(crate) fn record<X: Xlen>(
+        &mut self,
+        p_reg: &PipelineRegister<X>,
+        accesses: &[MemoryAccess],
+    ) {
+        let Some(decoded) = p_reg.instruction else {
+            return;
+        };
+        self.instructions += 1;
+        self.syscalls += decoded.instruction.is_system_call() as u64;
+
+        self.touch::<X>(p_reg.pc, decoded.size());
+        for access in accesses {
+            self.touch::<X>(access.address, access.bytes);
+        }
+    }
+
+    /// Marks the pages holding the `bytes` bytes at `address` as touched.
+    fn touch<X: Xlen>(&mut self, address: XWord, bytes: usize) {
+        let last = X::truncate(address.wrapping_add(bytes.max(1) as XWord - 1));
+        let (first, last) = (address >> PAGE_ADDRESS_SIZE, last >> PAGE_ADDRESS_SIZE);
+        if first <= last {
+            self.pages.extend(first..=last);
+        } else {
+            // The access wraps around the address space.
+            self.pages.extend([first, last]);
+        }
+    }
+}
+
+/// The outcome of a bounded run.
+#[derive(Debug)]
+pub struct RunSummary {
+    /// The reason that the run stopped.
+    pub reason: StopReason,
+    /// The activity of the run.
+    pub stats: RunStats,
+}
@@ -1,14 +1,30 @@
 //! Test utilities for the emulator crate.
 
-use crate::{cfg::EmuConfig, st::StEmu};
+use crate::{
+    builder::BuilderCore,
+    cfg::EmuConfig,
+    elf::elf_xlen,
+    observer::Observer,
+    ooo::{OooEmu, OooEmuBuilder},
+    pipelined::{PipelinedEmu, PipelinedEmuBuilder},
+    st::{StEmu, StEmuBuilder},
+};
 use brisc_hw::{
     errors::PipelineResult,
     kernel::Kernel,
     memory::{Memory, SimpleMemory},
     pipeline::PipelineRegister,
-    XWord, REG_A0, REG_A1, REG_A2,
+    predictor::{Btb, ReturnStack, TageLite},
+    NativeXlen, Rv32, XWord, Xlen, REG_A0, REG_A1, REG_A2,
+};
+use brisc_isa::asm::assemble_for;
+use std::{
+    cell::RefCell,
+    fmt, fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    rc::Rc,
 };
-use std::{fs, path::PathBuf};
 
 use rstest as _;
 use tracing_subscriber as _;
@@ -30,10 +46,11 @@ macro_rules! test_suites {
                 #[exclude("\\.dump$")]
                 path: std::path::PathBuf,
             ) {
-                // Init global tracing subscriber
-                tracing_subscriber::fmt()
+                // Init global tracing subscriber. Tests may share a process, so only the first
+                // initialization succeeds.
+                let _ = tracing_subscriber::fmt()
                     .with_max_level(tracing::Level::DEBUG)
-                    .init();
+                    .try_init();
                 tracing::info!(target: "test-runner", "Running test: {:?}", path.file_name().unwrap());
 
                 $crate::test_utils::run_riscv_test(&path);
@@ -42,15 +59,23 @@ macro_rules! test_suites {
     }
 }
 
-/// Helper function to run a single test case
+/// Helper function to run a single test case, on a hart with the register width of the ELF file.
+///
+/// The test runs on both the [StEmu] and the [PipelinedEmu], which must agree on the final
+/// architectural state and on the number of instructions executed.
 pub fn run_riscv_test(test_path: &PathBuf) -> f64 {
-    // Load the program
     let elf_bytes = fs::read(test_path).unwrap();
-    let mut hart = StEmu::<TestStEmuConfig>::builder()
-        .with_kernel(RiscvTestKernel)
-        .with_elf(&elf_bytes)
-        .unwrap()
-        .build();
+    match elf_xlen(&elf_bytes).unwrap() {
+        32 => run_riscv_test_with::<Rv32>(test_path, &elf_bytes),
+        #[cfg(feature = "64-bit")]
+        64 => run_riscv_test_with::<brisc_hw::Rv64>(test_path, &elf_bytes),
+        xlen => panic!("Unsupported XLEN {xlen}: {:?}", test_path.file_name().unwrap()),
+    }
+}
+
+fn run_riscv_test_with<X: Xlen>(test_path: &Path, elf_bytes: &[u8]) -> f64 {
+    // Load the program
+    let mut hart = loaded::<StEmu<_>, TestConfig<X>>(elf_bytes).build();
 
     // Run the program until it exits
     let mut clock = 0;
@@ -72,27 +97,126 @@ pub fn run_riscv_test(test_path: &PathBuf) -> f64 {
         hart.register.exit_code >> 1,
     );
 
+    // Run the program again on the pipelined hart, which must retire the same instructions
+    // whatever path the branch predictor steers fetch down.
+    let mut pipelined = loaded::<PipelinedEmu<_>, TestConfig<X>>(elf_bytes)
+        .with_predictor(ReturnStack::new(Btb::new(TageLite::new(), 8), 16))
+        .build();
+    let register = pipelined.run().unwrap();
+    let stats = pipelined.stats;
+    let branches = pipelined.branch_unit.as_ref().unwrap().total();
+    tracing::info!(
+        target: "test-runner",
+        "pipelined: {stats:?} | cpi: {} | misprediction rate: {}",
+        stats.cpi(),
+        branches.misprediction_rate(),
+    );
+
+    assert_eq!(
+        (register.exit_code, register.registers),
+        (hart.register.exit_code, hart.register.registers),
+        "Pipelined hart diverged: {:?}",
+        test_path.file_name().unwrap(),
+    );
+    assert_eq!(stats.retired, clock);
+
     ips
 }
 
-#[derive(Default)]
-struct TestStEmuConfig;
+/// A hart that test programs are loaded into, see [assembled].
+pub trait TestHart<C: EmuConfig> {
+    /// The builder of the hart.
+    type Builder: Default;
+
+    /// Returns the configuration shared by the builders of every hart, of `builder`.
+    fn core(builder: &mut Self::Builder) -> &mut BuilderCore<C>;
+}
+
+impl<C: EmuConfig> TestHart<C> for StEmu<C> {
+    type Builder = StEmuBuilder<C>;
+
+    fn core(builder: &mut Self::Builder) -> &mut BuilderCore<C> {
+        &mut builder.hart
+    }
+}
+
+impl<C: EmuConfig> TestHart<C> for PipelinedEmu<C> {
+    type Builder = PipelinedEmuBuilder<C>;
 
-impl EmuConfig for TestStEmuConfig {
+    fn core(builder: &mut Self::Builder) -> &mut BuilderCore<C> {
+        &mut builder.hart
+    }
+}
+
+impl<C: EmuConfig> TestHart<C> for OooEmu<C> {
+    type Builder = OooEmuBuilder<C>;
+
+    fn core(builder: &mut Self::Builder) -> &mut BuilderCore<C> {
+        &mut builder.hart
+    }
+}
+
+/// Returns a builder of the hart `E` with the configuration `C`, loaded with the program
+/// assembled from `source` for the register width of `C` and running on the default kernel of
+/// `C`.
+///
+/// ## Panics
+///
+/// Panics if `source` cannot be assembled.
+pub fn assembled<E, C>(source: &str) -> E::Builder
+where
+    E: TestHart<C>,
+    C: EmuConfig,
+    C::Memory: Default,
+    C::Kernel: Default,
+{
+    loaded::<E, C>(&assemble_for::<C::Xlen>(source).unwrap().to_elf())
+}
+
+/// Returns a builder of the hart `E` with the configuration `C`, loaded with the raw ELF file
+/// `elf` and running on the default kernel of `C`.
+///
+/// ## Panics
+///
+/// Panics if the ELF file cannot be loaded into the hart.
+pub fn loaded<E, C>(elf: &[u8]) -> E::Builder
+where
+    E: TestHart<C>,
+    C: EmuConfig,
+    C::Memory: Default,
+    C::Kernel: Default,
+{
+    let mut builder = E::Builder::default();
+    let core = E::core(&mut builder);
+    core.kernel = Some(C::Kernel::default());
+    core.load_elf(elf).unwrap();
+    builder
+}
+
+/// The [EmuConfig] used for running test programs, observed by `O` and running on the kernel `K`.
+#[derive(Debug, Default)]
+pub(crate) struct TestConfig<X = NativeXlen, O = (), K = RiscvTestKernel>(PhantomData<(X, O, K)>);
+
+impl<X: Xlen, O: Observer<X> + Default, K: Kernel> EmuConfig for TestConfig<X, O, K> {
     type Memory = SimpleMemory;
 
-    type Kernel = RiscvTestKernel;
+    type Kernel = K;
+
+    type Xlen = X;
+
+    type Observer = O;
 }
 
-#[derive(Default)]
-struct RiscvTestKernel;
+/// A [Kernel] supporting the `exit` and `write` syscalls used by test programs.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct RiscvTestKernel;
 
 impl Kernel for RiscvTestKernel {
-    fn syscall<M: Memory>(
+    fn syscall<M: Memory, X: Xlen>(
         &mut self,
         sysno: XWord,
         mem: &mut M,
-        p_reg: &mut PipelineRegister,
+        p_reg: &mut PipelineRegister<X>,
     ) -> PipelineResult<XWord> {
         match sysno {
             0x5D => {
@@ -120,3 +244,21 @@ impl Kernel for RiscvTestKernel {
         Ok(0)
     }
 }
+
+/// A text sink shared between a writer handed to a hart and the test that inspects its output.
+#[derive(Debug, Default, Clone)]
+pub struct SharedString(Rc<RefCell<String>>);
+
+impl SharedString {
+    /// Returns the text written so far.
+    pub fn text(&self) -> String {
+        self.0.borrow().clone()
+    }
+}
+
+impl fmt::Write for SharedString {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.borrow_mut().push_str(s);
+        Ok(())
+    }
+}
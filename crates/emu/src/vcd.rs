@@ -0,0 +1,342 @@
+//! Export of the stages of a run as a Value Change Dump, for waveform viewers such as GTKWave.
+//!
+//! The [VcdWriter] is an [Observer] of the hart. The pipeline register of the instruction that
+//! completed each stage on a cycle is dumped in the scope of the stage, `if`, `id`, `ex`, `mem` or
+//! `wb`, and the architectural register file in the `regs` scope. The dump advances by one time
+//! unit per cycle, only records the signals that changed, and is streamed to a [Write] sink as the
+//! hart runs.
+
+use crate::observer::{Observer, Stage};
+use alloc::vec::Vec;
+use brisc_hw::{pipeline::PipelineRegister, XWord, Xlen};
+use core::fmt::{self, Write};
+
+/// The scope of every [Stage], in pipeline order.
+const SCOPES: [&str; 5] = ["if", "id", "ex", "mem", "wb"];
+
+/// The signals dumped for every stage, in declaration order. `valid` is low when no instruction
+/// completed the stage on the cycle.
+const SIGNALS: [&str; 10] = [
+    "valid",
+    "pc",
+    "next_pc",
+    "instruction_raw",
+    "rs1_value",
+    "rs2_value",
+    "rd",
+    "alu_result",
+    "memory",
+    "reservation",
+];
+
+/// The values of the [SIGNALS] of a stage on which no instruction completed.
+const EMPTY: [Option<XWord>; 10] = [Some(0), None, None, None, None, None, None, None, None, None];
+
+/// An [Observer] writing a Value Change Dump of the stages of a hart to `W`, sampled at the end of
+/// every cycle.
+///
+/// Write errors are not reported to the hart, which keeps running: once a write fails, nothing
+/// more is written to the sink, and [Self::failed] returns `true`.
+#[derive(Debug, Clone, Default)]
+pub struct VcdWriter<W> {
+    /// The sink of the dump.
+    sink: W,
+    /// Whether a write to [Self::sink] failed.
+    failed: bool,
+    /// The time of the next sample.
+    time: u64,
+    /// The signals of the instruction that completed every stage on the current cycle.
+    stages: [Option<[Option<XWord>; 10]>; 5],
+    /// The last value dumped for every signal, or [None] if it was unknown. Empty until the
+    /// signals are declared by the first sample.
+    values: Vec<Option<XWord>>,
+}
+
+impl<W: Write> VcdWriter<W> {
+    /// Creates a new [VcdWriter], writing the dump to `sink`.
+    pub const fn new(sink: W) -> Self {
+        Self { sink, failed: false, time: 0, stages: [None; 5], values: Vec::new() }
+    }
+
+    /// Returns the sink of the dump.
+    pub const fn sink(&self) -> &W {
+        &self.sink
+    }
+
+    /// Consumes the writer, returning the sink of the dump.
+    pub fn into_sink(self) -> W {
+        self.sink
+    }
+
+    /// Returns whether a write to the sink failed, truncating the dump.
+    pub const fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Samples the stages completed on the cycle, and the register file `registers`, at its end.
+    fn sample<X: Xlen>(&mut self, registers: &[XWord; 32]) {
+        let widths = widths::<X>();
+        let first = self.values.is_empty();
+        let stages = core::mem::take(&mut self.stages);
+        let values = stages
+            .iter()
+            .flat_map(|values| values.unwrap_or(EMPTY))
+            .chain(registers.iter().map(|&value| Some(X::truncate(value))));
+        let widths = core::iter::repeat_n(widths, SCOPES.len())
+            .flatten()
+            .chain(core::iter::repeat_n(X::X_LEN as u32, registers.len()));
+        let changes = values
+            .zip(widths)
+            .enumerate()
+            .filter(|&(index, (value, _))| first || self.values[index] != value)
+            .collect::<Vec<_>>();
+
+        self.failed = self.write_changes::<X>(first, &changes).is_err();
+        self.time += 1;
+    }
+
+    /// Writes the `changes` of a sample, as `(index, (value, width))`, declaring the signals
+    /// first if it is the `first` sample.
+    fn write_changes<X: Xlen>(
+        &mut self,
+        first: bool,
+        changes: &[(usize, (Option<XWord>, u32))],
+    ) -> fmt::Result {
+        if first {
+            self.declare(&widths::<X>())?;
+            write!(self.sink, "#{}\n$dumpvars\n", self.time)?;
+        } else if !changes.is_empty() {
+            writeln!(self.sink, "#{}", self.time)?;
+        }
+        for &(index, (value, width)) in changes {
+            self.values[index] = value;
+            write_value(&mut self.sink, index, width, value)?;
+        }
+        if first {
+            self.sink.write_str("$end\n")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the header of the dump, declaring the signals of every stage and of the register
+    /// file.
+    fn declare(&mut self, widths: &[u32; 10]) -> fmt::Result {
+        let sink = &mut self.sink;
+        let mut index = 0;
+        let mut var = |sink: &mut W, width: u32, name: &dyn fmt::Display| {
+            index += 1;
+            writeln!(sink, "$var wire {width} {} {name} $end", Identifier(index - 1))
+        };
+
+        sink.write_str("$version brisc $end\n$timescale 1ns $end\n$scope module brisc $end\n")?;
+        for scope in SCOPES {
+            writeln!(sink, "$scope module {scope} $end")?;
+            for (name, &width) in SIGNALS.iter().zip(widths) {
+                var(sink, width, name)?;
+            }
+            sink.write_str("$upscope $end\n")?;
+        }
+        sink.write_str("$scope module regs $end\n")?;
+        let xlen = widths[1];
+        for register in 0..32 {
+            var(sink, xlen, &format_args!("x{register}"))?;
+        }
+        sink.write_str("$upscope $end\n$upscope $end\n$enddefinitions $end\n")?;
+
+        self.values = alloc::vec![None; index];
+        Ok(())
+    }
+}
+
+impl<X: Xlen, W: Write> Observer<X> for VcdWriter<W> {
+    fn on_stage(&mut self, stage: Stage, p_reg: &PipelineRegister<X>) {
+        self.stages[stage as usize] = Some(values(p_reg));
+    }
+
+    /// System calls complete in ID, where they are handled by the kernel.
+    fn on_syscall(&mut self, _: XWord, p_reg: &PipelineRegister<X>) {
+        self.stages[Stage::Decode as usize] = Some(values(p_reg));
+    }
+
+    fn on_cycle(&mut self, registers: &[XWord; 32]) {
+        if !self.failed {
+            self.sample::<X>(registers);
+        }
+    }
+}
+
+/// Returns the widths of the [SIGNALS] of a stage, in bits.
+const fn widths<X: Xlen>() -> [u32; 10] {
+    let xlen = X::X_LEN as u32;
+    [1, xlen, xlen, 32, xlen, xlen, 5, xlen, xlen, xlen]
+}
+
+/// Returns the values of the [SIGNALS] of `p_reg`.
+fn values<X: Xlen>(p_reg: &PipelineRegister<X>) -> [Option<XWord>; 10] {
+    let truncate = |value: Option<XWord>| value.map(X::truncate);
+
+    #[cfg(feature = "a")]
+    let reservation = p_reg.reservation;
+    #[cfg(not(feature = "a"))]
+    let reservation = None;
+    [
+        Some(1),
+        Some(X::truncate(p_reg.pc)),
+        Some(X::truncate(p_reg.next_pc)),
+        p_reg.instruction.map(|decoded| decoded.raw as XWord),
+        truncate(p_reg.rs1_value),
+        truncate(p_reg.rs2_value),
+        p_reg.rd.map(XWord::from),
+        truncate(p_reg.alu_result),
+        truncate(p_reg.memory),
+        truncate(reservation),
+    ]
+}
+
+/// Writes the change of the signal `index`, `width` bits wide, to `value` to `sink`.
+fn write_value(
+    sink: &mut impl Write,
+    index: usize,
+    width: u32,
+    value: Option<XWord>,
+) -> fmt::Result {
+    let id = Identifier(index);
+    match (width, value) {
+        (1, Some(value)) => writeln!(sink, "{}{id}", value & 1),
+        (1, None) => writeln!(sink, "x{id}"),
+        (_, Some(value)) => writeln!(sink, "b{value:b} {id}"),
+        (_, None) => writeln!(sink, "bx {id}"),
+    }
+}
+
+/// The short identifier of a signal in the dump, made of printable ASCII characters.
+#[derive(Debug, Clone, Copy)]
+struct Identifier(usize);
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut index = self.0;
+        loop {
+            f.write_char((b'!' + (index % 94) as u8) as char)?;
+            index /= 94;
+            if index == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Identifier, VcdWriter};
+    use crate::{
+        pipelined::PipelinedEmu,
+        st::StEmu,
+        test_utils::{assembled, SharedString, TestConfig},
+    };
+    use brisc_hw::{NativeXlen, XWord};
+    use std::collections::BTreeMap;
+
+    const PROGRAM: &str = r#"
+        .text
+        .globl _start
+        _start:
+            li a0, 5
+            addi a0, a0, 1
+            li a7, 93
+            ecall
+    "#;
+
+    /// The [TestConfig] of a hart dumping its signals to a [SharedString].
+    type VcdConfig = TestConfig<NativeXlen, VcdWriter<SharedString>>;
+
+    /// Returns the changes of every signal of `dump`, by hierarchical name, as `(time, value)`.
+    fn changes(dump: &str) -> BTreeMap<String, Vec<(u64, String)>> {
+        let (mut scopes, mut names, mut time) = (Vec::new(), BTreeMap::new(), 0);
+        let mut changes = BTreeMap::<String, Vec<_>>::new();
+        for line in dump.lines() {
+            let fields = line.split(' ').collect::<Vec<_>>();
+            match fields[..] {
+                ["$scope", "module", scope, "$end"] => scopes.push(scope),
+                ["$upscope", "$end"] => _ = scopes.pop(),
+                ["$var", "wire", _, id, name, "$end"] => {
+                    names.insert(id.to_string(), format!("{}.{name}", scopes.join(".")));
+                }
+                [value, id] if value.starts_with('b') => {
+                    changes.entry(names[id].clone()).or_default().push((time, value[1..].into()));
+                }
+                [change] if change.starts_with('#') => time = change[1..].parse().unwrap(),
+                [change] if !change.starts_with('$') => {
+                    let (value, id) = change.split_at(1);
+                    changes.entry(names[id].clone()).or_default().push((time, value.into()));
+                }
+                _ => {}
+            }
+        }
+        changes
+    }
+
+    #[test]
+    fn test_identifiers_are_unique() {
+        let identifiers =
+            (0..94 * 94 + 1).map(|index| Identifier(index).to_string()).collect::<Vec<_>>();
+        assert_eq!(identifiers[..2], ["!", "\""]);
+        assert_eq!(identifiers[94], "!\"");
+        let mut unique = identifiers.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), identifiers.len());
+    }
+
+    #[test]
+    fn test_single_cycle_dump() {
+        let sink = SharedString::default();
+        let mut hart = assembled::<StEmu<_>, VcdConfig>(PROGRAM)
+            .with_observer(VcdWriter::new(sink.clone()))
+            .build();
+        let entry = hart.register.pc;
+        hart.run().unwrap();
+        let changes = changes(&sink.text());
+
+        // Every instruction passes through every stage in one cycle, so each stage is sampled
+        // once per instruction. `ecall` completes in ID.
+        let pcs = changes["brisc.if.pc"].iter().map(|(time, pc)| (*time, pc.clone()));
+        assert_eq!(
+            pcs.collect::<Vec<_>>(),
+            (0..4).map(|i| (i, format!("{:b}", entry + 4 * i as XWord))).collect::<Vec<_>>()
+        );
+        assert_eq!(changes["brisc.id.pc"], changes["brisc.if.pc"]);
+        assert_eq!(changes["brisc.regs.x10"], [(0, "101".into()), (1, "110".into())]);
+        assert_eq!(changes["brisc.id.valid"], [(0, "1".into())]);
+        assert_eq!(changes["brisc.wb.valid"], [(0, "1".into()), (3, "0".into())]);
+    }
+
+    #[test]
+    fn test_pipelined_dump() {
+        let sink = SharedString::default();
+        let mut hart = assembled::<PipelinedEmu<_>, VcdConfig>(PROGRAM)
+            .with_observer(VcdWriter::new(sink.clone()))
+            .build();
+        hart.run().unwrap();
+        let changes = changes(&sink.text());
+
+        // `addi` reads `a0` forwarded from EX/MEM, and completes EX a cycle after `li`. The
+        // register file is written as each completes WB.
+        assert_eq!(
+            changes["brisc.ex.alu_result"][..3],
+            [(0, "x".into()), (2, "101".into()), (3, "110".into())]
+        );
+        assert_eq!(changes["brisc.mem.rd"][..2], [(0, "x".into()), (3, "1010".into())]);
+        assert_eq!(
+            changes["brisc.regs.x10"],
+            [(0, "0".into()), (4, "101".into()), (5, "110".into())]
+        );
+        // `ecall` is stalled in ID, with fetch, until the older instructions have retired, and
+        // runs on the last cycle.
+        assert_eq!(changes["brisc.if.valid"], [(0, "1".into()), (4, "0".into())]);
+        assert_eq!(
+            changes["brisc.id.valid"],
+            [(0, "0".into()), (1, "1".into()), (4, "0".into()), (6, "1".into())]
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Configuration of a cache level.
+
+use thiserror::Error;
+
+/// The policy that selects the line to evict from a full set.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Replacement {
+    /// Evicts the least recently used line.
+    #[default]
+    Lru,
+    /// Evicts a line that is not recently used, tracked with a binary tree of bits per set. The
+    /// associativity must be a power of two.
+    Plru,
+    /// Evicts a pseudo-random line. The sequence is seeded identically for every cache, so that
+    /// runs are reproducible.
+    Random,
+}
+
+/// The policy for stores that hit or miss in the cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WritePolicy {
+    /// Stores allocate the line and mark it dirty. Dirty lines are written to the next level
+    /// when they are evicted.
+    #[default]
+    WriteBack,
+    /// Stores are forwarded to the next level immediately, and do not allocate the line on a miss.
+    WriteThrough,
+}
+
+/// A hardware prefetcher, filling lines ahead of demand accesses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefetcher {
+    /// Prefetches the next line on a miss, and on the first hit to a prefetched line.
+    NextLine,
+    /// Tracks the stride between the accesses of each instruction in a direct-mapped table of
+    /// `entries` entries, and prefetches one stride ahead once the same stride repeats.
+    Stride {
+        /// The number of entries of the table. Must be a power of two.
+        entries: usize,
+    },
+}
+
+/// The configuration of a cache level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// The capacity of the cache in bytes.
+    pub size: usize,
+    /// The number of lines in each set.
+    pub ways: usize,
+    /// The size of a line in bytes.
+    pub line_size: usize,
+    /// The replacement policy.
+    pub replacement: Replacement,
+    /// The write policy.
+    pub write_policy: WritePolicy,
+    /// The prefetcher, if any.
+    pub prefetcher: Option<Prefetcher>,
+}
+
+impl CacheConfig {
+    /// Creates a new write-back [CacheConfig] with LRU replacement and no prefetcher.
+    pub const fn new(size: usize, ways: usize, line_size: usize) -> Self {
+        Self {
+            size,
+            ways,
+            line_size,
+            replacement: Replacement::Lru,
+            write_policy: WritePolicy::WriteBack,
+            prefetcher: None,
+        }
+    }
+
+    /// Assigns the replacement policy.
+    pub const fn with_replacement(mut self, replacement: Replacement) -> Self {
+        self.replacement = replacement;
+        self
+    }
+
+    /// Assigns the write policy.
+    pub const fn with_write_policy(mut self, write_policy: WritePolicy) -> Self {
+        self.write_policy = write_policy;
+        self
+    }
+
+    /// Assigns the prefetcher.
+    pub const fn with_prefetcher(mut self, prefetcher: Prefetcher) -> Self {
+        self.prefetcher = Some(prefetcher);
+        self
+    }
+
+    /// Returns the number of sets of the cache.
+    pub const fn sets(&self) -> usize {
+        self.size / (self.ways * self.line_size)
+    }
+
+    /// Checks that the geometry of the cache can be indexed by address bits.
+    pub fn validate(&self) -> Result<(), CacheConfigError> {
+        if self.ways == 0 || self.line_size == 0 || self.size < self.ways * self.line_size {
+            return Err(CacheConfigError::TooSmall {
+                size: self.size,
+                ways: self.ways,
+                line_size: self.line_size,
+            });
+        }
+        if self.ways > 64 {
+            return Err(CacheConfigError::TooManyWays(self.ways));
+        }
+
+        let mut powers = [("line size", self.line_size), ("number of sets", self.sets())]
+            .into_iter()
+            .chain((self.replacement == Replacement::Plru).then_some(("associativity", self.ways)))
+            .chain(match self.prefetcher {
+                Some(Prefetcher::Stride { entries }) => Some(("number of stride entries", entries)),
+                _ => None,
+            });
+        if let Some((name, value)) = powers.find(|(_, value)| !value.is_power_of_two()) {
+            return Err(CacheConfigError::NotPowerOfTwo(name, value));
+        }
+        if self.size != self.sets() * self.ways * self.line_size {
+            return Err(CacheConfigError::UnevenSize {
+                size: self.size,
+                ways: self.ways,
+                line_size: self.line_size,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// An error in a [CacheConfig].
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheConfigError {
+    /// A dimension of the cache is not a power of two.
+    #[error("The {0} must be a power of two, got {1}")]
+    NotPowerOfTwo(&'static str, usize),
+    /// The cache cannot hold a single set.
+    #[error("A cache of {size} bytes cannot hold a set of {ways} lines of {line_size} bytes")]
+    TooSmall {
+        /// The capacity of the cache in bytes.
+        size: usize,
+        /// The number of lines in each set.
+        ways: usize,
+        /// The size of a line in bytes.
+        line_size: usize,
+    },
+    /// The capacity of the cache is not a whole number of sets.
+    #[error("A cache of {size} bytes is not a whole number of sets of {ways} lines of {line_size} bytes")]
+    UnevenSize {
+        /// The capacity of the cache in bytes.
+        size: usize,
+        /// The number of lines in each set.
+        ways: usize,
+        /// The size of a line in bytes.
+        line_size: usize,
+    },
+    /// The associativity exceeds the 64 ways that the replacement state can track.
+    #[error("The associativity must be at most 64 ways, got {0}")]
+    TooManyWays(usize),
+}
@@ -0,0 +1,306 @@
+//! A single set-associative cache level.
+
+use super::{CacheConfig, CacheConfigError, Prefetcher, Replacement, WritePolicy};
+use crate::memory::Address;
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use brisc_isa::XWord;
+
+/// The access counters of a cache level.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of demand accesses, one per line touched.
+    pub accesses: u64,
+    /// The number of demand accesses that found their line in the cache.
+    pub hits: u64,
+    /// The number of demand accesses that did not find their line in the cache.
+    pub misses: u64,
+    /// The number of valid lines replaced, by demand misses or prefetches.
+    pub evictions: u64,
+    /// The number of evicted lines that were dirty, and written to the next level.
+    pub writebacks: u64,
+    /// The number of lines filled by the prefetcher.
+    pub prefetches: u64,
+    /// The number of prefetched lines that were hit by a demand access before being evicted.
+    pub useful_prefetches: u64,
+}
+
+impl CacheStats {
+    /// Returns the fraction of the demand accesses that missed, or 0 if there were none.
+    pub fn miss_rate(&self) -> f64 {
+        if self.accesses == 0 {
+            return 0.0;
+        }
+        self.misses as f64 / self.accesses as f64
+    }
+}
+
+/// The outcome of an access to a cache level, along with the requests that it makes to the next
+/// level. Lines are identified by their base address.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AccessOutcome {
+    /// Whether the line was in the cache.
+    pub hit: bool,
+    /// The line to read from the next level, as it was allocated on a miss.
+    pub fill: Option<Address>,
+    /// The dirty line that was evicted, to write to the next level.
+    pub writeback: Option<Address>,
+    /// The line that a store wrote through to the next level.
+    pub write_through: Option<Address>,
+    /// The line that the prefetcher requests into this level.
+    pub prefetch: Option<Address>,
+}
+
+/// A cache line.
+#[derive(Debug, Default, Clone, Copy)]
+struct Line {
+    /// The line number, i.e. the address shifted right by the line offset bits.
+    number: Address,
+    /// Whether the line holds data.
+    valid: bool,
+    /// Whether the line was written since it was filled.
+    dirty: bool,
+    /// Whether the line was filled by the prefetcher, and not yet hit by a demand access.
+    prefetched: bool,
+    /// The time of the last access to the line, for LRU replacement.
+    last_used: u64,
+}
+
+/// An entry of the stride prefetcher table.
+#[derive(Debug, Default, Clone, Copy)]
+struct StrideEntry {
+    /// The program counter of the instruction that owns the entry.
+    pc: XWord,
+    /// The last address accessed by the instruction.
+    last: Address,
+    /// The last stride between the accesses of the instruction.
+    stride: Address,
+}
+
+/// A set-associative cache level. The cache only tracks which lines it holds, and never the data
+/// in them, so it cannot change the functional behavior of the memory it sits in front of.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    /// The configuration of the cache.
+    config: CacheConfig,
+    /// The number of bits of the offset within a line.
+    offset_bits: u32,
+    /// The lines, set by set.
+    lines: Vec<Line>,
+    /// The tree-PLRU bits of every set.
+    plru: Vec<u64>,
+    /// The number of accesses so far, used to order the accesses for LRU replacement.
+    clock: u64,
+    /// The state of the xorshift generator for random replacement.
+    seed: u64,
+    /// The stride prefetcher table.
+    strides: Vec<StrideEntry>,
+    /// The access counters of the cache.
+    pub stats: CacheStats,
+    /// The number of demand misses of every instruction, by program counter, if attribution is
+    /// enabled with [Self::with_miss_attribution].
+    pub misses_by_pc: Option<BTreeMap<XWord, u64>>,
+}
+
+impl Cache {
+    /// Creates a new, empty [Cache], after validating `config`.
+    pub fn new(config: CacheConfig) -> Result<Self, CacheConfigError> {
+        config.validate()?;
+
+        let entries = match config.prefetcher {
+            Some(Prefetcher::Stride { entries }) => entries,
+            _ => 0,
+        };
+        Ok(Self {
+            config,
+            offset_bits: config.line_size.trailing_zeros(),
+            lines: vec![Line::default(); config.sets() * config.ways],
+            plru: vec![0; config.sets()],
+            clock: 0,
+            seed: 0x2545_F491_4F6C_DD1D,
+            strides: vec![StrideEntry::default(); entries],
+            stats: CacheStats::default(),
+            misses_by_pc: None,
+        })
+    }
+
+    /// Enables the attribution of demand misses to the instructions that caused them.
+    pub fn with_miss_attribution(mut self) -> Self {
+        self.misses_by_pc = Some(BTreeMap::new());
+        self
+    }
+
+    /// Returns the configuration of the cache.
+    pub const fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Returns the base address of the line holding `address`.
+    pub const fn line_base(&self, address: Address) -> Address {
+        (address >> self.offset_bits) << self.offset_bits
+    }
+
+    /// Returns `true` if the line holding `address` is in the cache.
+    pub fn contains(&self, address: Address) -> bool {
+        self.find(address >> self.offset_bits).is_some()
+    }
+
+    /// Performs a demand access by the instruction at `pc` to the line holding `address`.
+    pub fn access(&mut self, pc: XWord, address: Address, write: bool) -> AccessOutcome {
+        let number = address >> self.offset_bits;
+        let base = number << self.offset_bits;
+        let write_through = write && self.config.write_policy == WritePolicy::WriteThrough;
+        let mut outcome = AccessOutcome {
+            write_through: write_through.then_some(base),
+            prefetch: self.train_stride(pc, address),
+            ..Default::default()
+        };
+        self.stats.accesses += 1;
+
+        if let Some(index) = self.find(number) {
+            self.stats.hits += 1;
+            self.touch(index);
+
+            let line = &mut self.lines[index];
+            line.dirty |= write && !write_through;
+            if core::mem::take(&mut line.prefetched) {
+                self.stats.useful_prefetches += 1;
+                outcome.prefetch = outcome.prefetch.or(self.next_line(base));
+            }
+            return AccessOutcome { hit: true, ..outcome };
+        }
+
+        self.stats.misses += 1;
+        if let Some(misses) = self.misses_by_pc.as_mut() {
+            *misses.entry(pc).or_default() += 1;
+        }
+
+        // Stores that write through do not allocate the line.
+        if !write_through {
+            outcome.writeback = self.allocate(number, write, false);
+            outcome.fill = Some(base);
+        }
+        outcome.prefetch = outcome.prefetch.or(self.next_line(base));
+        outcome
+    }
+
+    /// Fills the line holding `address` on behalf of the prefetcher, if it is not in the cache
+    /// already. Prefetches are not demand accesses, and are only counted in
+    /// [CacheStats::prefetches].
+    pub fn prefetch(&mut self, address: Address) -> AccessOutcome {
+        let number = address >> self.offset_bits;
+        if self.find(number).is_some() {
+            return AccessOutcome { hit: true, ..Default::default() };
+        }
+
+        self.stats.prefetches += 1;
+        let writeback = self.allocate(number, false, true);
+        AccessOutcome { fill: Some(number << self.offset_bits), writeback, ..Default::default() }
+    }
+
+    /// Returns the index of the line `number` in [Self::lines], if it is in the cache.
+    fn find(&self, number: Address) -> Option<usize> {
+        let set = self.set(number);
+        (set..set + self.config.ways)
+            .find(|&index| self.lines[index].valid && self.lines[index].number == number)
+    }
+
+    /// Returns the index of the first line of the set that holds the line `number`.
+    const fn set(&self, number: Address) -> usize {
+        (number as usize & (self.config.sets() - 1)) * self.config.ways
+    }
+
+    /// Replaces a line of the set of the line `number` with it. Returns the base address of the
+    /// replaced line if it was dirty.
+    fn allocate(&mut self, number: Address, dirty: bool, prefetched: bool) -> Option<Address> {
+        let index = self.victim(self.set(number));
+        let replaced = self.lines[index];
+        self.lines[index] =
+            Line { number, valid: true, dirty, prefetched, last_used: self.lines[index].last_used };
+        self.touch(index);
+
+        if !replaced.valid {
+            return None;
+        }
+        self.stats.evictions += 1;
+        replaced.dirty.then(|| {
+            self.stats.writebacks += 1;
+            replaced.number << self.offset_bits
+        })
+    }
+
+    /// Returns the index of the line to replace in the set starting at `set`, preferring invalid
+    /// lines.
+    fn victim(&mut self, set: usize) -> usize {
+        let ways = self.config.ways;
+        if let Some(index) = (set..set + ways).find(|&index| !self.lines[index].valid) {
+            return index;
+        }
+
+        match self.config.replacement {
+            Replacement::Lru => {
+                (set..set + ways).min_by_key(|&index| self.lines[index].last_used).unwrap_or(set)
+            }
+            Replacement::Plru => {
+                let bits = self.plru[set / ways];
+                let mut node = 1;
+                for _ in 0..ways.trailing_zeros() {
+                    node = 2 * node + ((bits >> node) & 1) as usize;
+                }
+                set + node - ways
+            }
+            Replacement::Random => {
+                self.seed ^= self.seed << 13;
+                self.seed ^= self.seed >> 7;
+                self.seed ^= self.seed << 17;
+                set + (self.seed % ways as u64) as usize
+            }
+        }
+    }
+
+    /// Marks the line at `index` as the most recently used of its set.
+    fn touch(&mut self, index: usize) {
+        self.clock += 1;
+        self.lines[index].last_used = self.clock;
+
+        if self.config.replacement == Replacement::Plru {
+            // Point every node on the path to the line away from it.
+            let ways = self.config.ways;
+            let (set, way) = (index / ways, index % ways);
+            let mut node = 1;
+            for level in (0..ways.trailing_zeros()).rev() {
+                let bit = (way >> level) & 1;
+                self.plru[set] = (self.plru[set] & !(1 << node)) | (((bit ^ 1) as u64) << node);
+                node = 2 * node + bit;
+            }
+        }
+    }
+
+    /// Returns the line after `base` if the cache has a next-line prefetcher.
+    fn next_line(&self, base: Address) -> Option<Address> {
+        (self.config.prefetcher == Some(Prefetcher::NextLine))
+            .then(|| base.wrapping_add(self.config.line_size as Address))
+    }
+
+    /// Records the access to `address` by the instruction at `pc` in the stride prefetcher table,
+    /// and returns the address to prefetch once the instruction repeats the same stride.
+    fn train_stride(&mut self, pc: XWord, address: Address) -> Option<Address> {
+        if self.strides.is_empty() {
+            return None;
+        }
+
+        let index = (pc >> 1) as usize & (self.strides.len() - 1);
+        let entry = &mut self.strides[index];
+        if entry.pc != pc {
+            *entry = StrideEntry { pc, last: address, stride: 0 };
+            return None;
+        }
+
+        let stride = address.wrapping_sub(entry.last);
+        let confirmed = stride != 0 && stride == entry.stride;
+        entry.last = address;
+        entry.stride = stride;
+
+        let target = address.wrapping_add(stride);
+        (confirmed && target >> self.offset_bits != address >> self.offset_bits).then_some(target)
+    }
+}
@@ -0,0 +1,270 @@
+//! A timing-free cache hierarchy model, fed with the addresses of fetches and memory accesses.
+//!
+//! The caches sit alongside the [Memory] of a hart. They only track which lines each level
+//! holds, to count hits, misses and evictions, and never hold data, so they cannot change the
+//! result of a program.
+//!
+//! [Memory]: crate::memory::Memory
+
+use crate::{memory::Address, pipeline::PipelineRegister};
+use brisc_isa::{InstructionClass, XWord, Xlen};
+
+#[cfg(feature = "a")]
+use brisc_isa::{AmoFunction, Instruction};
+
+mod config;
+pub use config::{CacheConfig, CacheConfigError, Prefetcher, Replacement, WritePolicy};
+
+mod level;
+pub use level::{AccessOutcome, Cache, CacheStats};
+
+/// Split level 1 instruction and data caches, backed by an optional unified level 2 cache.
+#[derive(Debug, Clone)]
+pub struct CacheHierarchy {
+    /// The level 1 instruction cache, accessed by instruction fetch.
+    pub l1i: Cache,
+    /// The level 1 data cache, accessed by loads, stores and atomic memory operations.
+    pub l1d: Cache,
+    /// The unified level 2 cache, if any, serving the misses and writes of both level 1 caches.
+    pub l2: Option<Cache>,
+}
+
+impl CacheHierarchy {
+    /// Creates a new [CacheHierarchy] with the level 1 caches `l1i` and `l1d`, backed directly by
+    /// memory.
+    pub const fn new(l1i: Cache, l1d: Cache) -> Self {
+        Self { l1i, l1d, l2: None }
+    }
+
+    /// Adds a unified level 2 cache behind the level 1 caches.
+    pub fn with_l2(mut self, l2: Cache) -> Self {
+        self.l2 = Some(l2);
+        self
+    }
+
+    /// Records the fetch of the `bytes` bytes long instruction at `pc`.
+    pub fn fetch(&mut self, pc: XWord, bytes: usize) {
+        access(&mut self.l1i, self.l2.as_mut(), pc, pc, bytes, false);
+    }
+
+    /// Records a load of `bytes` bytes at `address` by the instruction at `pc`.
+    pub fn load(&mut self, pc: XWord, address: Address, bytes: usize) {
+        access(&mut self.l1d, self.l2.as_mut(), pc, address, bytes, false);
+    }
+
+    /// Records a store of `bytes` bytes at `address` by the instruction at `pc`.
+    pub fn store(&mut self, pc: XWord, address: Address, bytes: usize) {
+        access(&mut self.l1d, self.l2.as_mut(), pc, address, bytes, true);
+    }
+
+    /// Records the fetch of the instruction in `p_reg`, once it has passed [instruction_fetch].
+    ///
+    /// [instruction_fetch]: crate::pipeline::instruction_fetch
+    pub fn record_fetch<X: Xlen>(&mut self, p_reg: &PipelineRegister<X>) {
+        if let Some(instruction) = p_reg.instruction {
+            self.fetch(p_reg.pc, instruction.size());
+        }
+    }
+
+    /// Records the data access of the instruction in `p_reg`, if any, once it has passed
+    /// [mem_access]. Atomic memory operations other than `lr` are recorded as stores, as they
+    /// need the line for writing.
+    ///
+    /// [mem_access]: crate::pipeline::mem_access
+    pub fn record_mem_access<X: Xlen>(&mut self, p_reg: &PipelineRegister<X>) {
+        let Some(instruction) = p_reg.instruction.map(|decoded| decoded.instruction) else {
+            return;
+        };
+        let Some(width) = instruction.memory_width() else {
+            return;
+        };
+
+        let (address, write) = match instruction.class() {
+            InstructionClass::Load => (p_reg.alu_result, false),
+            InstructionClass::Store => (p_reg.alu_result, true),
+            #[cfg(feature = "a")]
+            InstructionClass::Amo => {
+                (p_reg.rs1_value, !matches!(instruction, Instruction::Amo(_, AmoFunction::Lr)))
+            }
+            _ => return,
+        };
+        if let Some(address) = address {
+            let l1d = &mut self.l1d;
+            access(l1d, self.l2.as_mut(), p_reg.pc, X::truncate(address), width.bytes(), write);
+        }
+    }
+}
+
+/// Performs the access of `bytes` bytes at `address` by the instruction at `pc` in `l1`, line by
+/// line, and forwards its requests to `l2`.
+fn access(
+    l1: &mut Cache,
+    mut l2: Option<&mut Cache>,
+    pc: XWord,
+    address: Address,
+    bytes: usize,
+    write: bool,
+) {
+    let line_size = l1.config().line_size as Address;
+    let first = l1.line_base(address);
+    let last = l1.line_base(address.wrapping_add(bytes.max(1) as Address - 1));
+
+    let mut base = first;
+    loop {
+        let outcome = l1.access(pc, base, write);
+        forward(l1, l2.as_deref_mut(), pc, outcome);
+        if base == last {
+            break;
+        }
+        base = base.wrapping_add(line_size);
+    }
+}
+
+/// Forwards the requests of an access to `l1` to `l2`, and fills the line requested by the
+/// prefetcher of `l1`. Without a level 2 cache, the requests go to memory, which always hits.
+fn forward(l1: &mut Cache, mut l2: Option<&mut Cache>, pc: XWord, outcome: AccessOutcome) {
+    if let Some(l2) = l2.as_deref_mut() {
+        let requests =
+            [(outcome.writeback, true), (outcome.write_through, true), (outcome.fill, false)];
+        for (address, write) in requests {
+            if let Some(address) = address {
+                if let Some(prefetch) = l2.access(pc, address, write).prefetch {
+                    l2.prefetch(prefetch);
+                }
+            }
+        }
+    }
+
+    if let Some(prefetch) = outcome.prefetch {
+        let outcome = l1.prefetch(prefetch);
+        forward(l1, l2, pc, AccessOutcome { prefetch: None, ..outcome });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    /// A 4-way cache of 4 sets of 16-byte lines.
+    fn cache(config: impl FnOnce(CacheConfig) -> CacheConfig) -> Cache {
+        Cache::new(config(CacheConfig::new(256, 4, 16))).unwrap()
+    }
+
+    #[test]
+    fn test_miss_rate() {
+        assert_eq!(CacheStats::default().miss_rate(), 0.0);
+        let stats = CacheStats { accesses: 8, misses: 2, ..Default::default() };
+        assert_eq!(stats.miss_rate(), 0.25);
+    }
+
+    #[rstest]
+    #[case::not_power_of_two(
+        CacheConfig::new(256, 4, 12),
+        CacheConfigError::NotPowerOfTwo("line size", 12)
+    )]
+    #[case::too_small(CacheConfig::new(32, 4, 16), CacheConfigError::TooSmall { size: 32, ways: 4, line_size: 16 })]
+    #[case::uneven(
+        CacheConfig::new(192, 4, 16),
+        CacheConfigError::NotPowerOfTwo("number of sets", 3)
+    )]
+    #[case::plru(
+        CacheConfig::new(192, 3, 16).with_replacement(Replacement::Plru),
+        CacheConfigError::NotPowerOfTwo("associativity", 3)
+    )]
+    fn test_invalid_config(#[case] config: CacheConfig, #[case] expected: CacheConfigError) {
+        assert_eq!(Cache::new(config).unwrap_err(), expected);
+    }
+
+    #[rstest]
+    #[case::lru(Replacement::Lru, 6, 2)]
+    #[case::plru(Replacement::Plru, 5, 1)]
+    fn test_replacement(
+        #[case] replacement: Replacement,
+        #[case] misses: u64,
+        #[case] evictions: u64,
+    ) {
+        let mut cache = cache(|config| config.with_replacement(replacement));
+
+        // Five lines mapping to set 0. LRU evicts the second line for the fifth, which the tree
+        // of PLRU approximates by evicting the third.
+        for line in [0, 1, 2, 3, 0, 4, 0, 1] {
+            cache.access(0, line * 64, false);
+        }
+        assert_eq!(cache.stats.misses, misses);
+        assert_eq!(cache.stats.evictions, evictions);
+        assert!(cache.contains(0) && cache.contains(4 * 64) && cache.contains(64));
+    }
+
+    #[test]
+    fn test_random_replacement_is_reproducible() {
+        let run = || {
+            let mut cache = cache(|config| config.with_replacement(Replacement::Random));
+            (0..64).for_each(|i| _ = cache.access(0, (i * 7 % 9) * 64, false));
+            cache.stats
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[rstest]
+    #[case::write_back(WritePolicy::WriteBack, 6, 1)]
+    #[case::write_through(WritePolicy::WriteThrough, 7, 0)]
+    fn test_write_policy(
+        #[case] write_policy: WritePolicy,
+        #[case] misses: u64,
+        #[case] writebacks: u64,
+    ) {
+        let l1d = cache(|config| config.with_write_policy(write_policy));
+        let l2 = Cache::new(CacheConfig::new(4096, 8, 16)).unwrap();
+        let mut caches = CacheHierarchy::new(cache(|config| config), l1d).with_l2(l2);
+
+        // Two stores to a line, four loads that fill its set, and a load of the line again.
+        caches.store(0, 0x100, 4);
+        caches.store(0, 0x104, 4);
+        for line in 1..=4 {
+            caches.load(0, 0x100 + line * 64, 4);
+        }
+        caches.load(0, 0x100, 4);
+
+        // Write-through stores miss without allocating the line, which is never dirty.
+        assert_eq!(caches.l1d.stats.misses, misses);
+        assert_eq!(caches.l1d.stats.writebacks, writebacks);
+        assert_eq!(caches.l2.unwrap().stats.accesses, 7);
+    }
+
+    #[rstest]
+    #[case::next_line(Prefetcher::NextLine, 1)]
+    #[case::stride(Prefetcher::Stride { entries: 16 }, 3)]
+    fn test_prefetcher(#[case] prefetcher: Prefetcher, #[case] misses: u64) {
+        let l1 = cache(|config| config.with_prefetcher(prefetcher));
+        let mut caches = CacheHierarchy::new(l1.clone(), l1);
+
+        // A load walking through 16 lines. The stride prefetcher needs to see the stride twice.
+        for line in 0..16 {
+            caches.load(0x80, line * 16, 4);
+        }
+        assert_eq!(caches.l1d.stats.misses, misses);
+        assert_eq!(caches.l1d.stats.useful_prefetches, 16 - misses);
+    }
+
+    #[test]
+    fn test_unaligned_access_and_attribution() {
+        let l1d = cache(|config| config).with_miss_attribution();
+        let l2 = Cache::new(CacheConfig::new(1024, 4, 32)).unwrap().with_miss_attribution();
+        let mut caches = CacheHierarchy::new(cache(|config| config), l1d).with_l2(l2);
+
+        // The load straddles two lines of the level 1 cache, but a single level 2 line, which
+        // the fetch then hits.
+        caches.load(0x40, 0x2C, 8);
+        caches.fetch(0x24, 4);
+
+        assert_eq!(caches.l1d.stats.misses, 2);
+        assert_eq!(caches.l1d.misses_by_pc.unwrap()[&0x40], 2);
+        let l2 = caches.l2.unwrap();
+        assert_eq!((l2.stats.hits, l2.stats.misses), (2, 1));
+        assert_eq!(
+            l2.misses_by_pc.unwrap().into_iter().collect::<alloc::vec::Vec<_>>(),
+            [(0x40, 1)]
+        );
+    }
+}
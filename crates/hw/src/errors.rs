@@ -11,8 +11,14 @@ pub enum PipelineError {
     #[error("Missing Pipeline State: {0}")]
     MissingState(&'static str),
     /// An error occurred while decoding an instruction.
-    #[error(transparent)]
-    InstructionDecodeError(#[from] InstructionDecodeError),
+    #[error("{source} at pc {pc:#x}")]
+    InstructionDecodeError {
+        /// The program counter of the instruction.
+        pc: XWord,
+        /// The underlying decode error, which carries the raw instruction bits.
+        #[source]
+        source: InstructionDecodeError,
+    },
     /// An error occurred in the memory bus.
     #[error("{0}")]
     MemoryError(MemoryError),
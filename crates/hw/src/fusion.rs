@@ -0,0 +1,211 @@
+//! Detection of macro-op fusion opportunities in the dynamic instruction stream.
+//!
+//! A core that fuses macro-ops decodes two adjacent instructions into a single internal operation,
+//! which takes one slot through the rest of the pipeline. The pairs detected here follow the
+//! fusion idioms proposed by Celio et al. in "The Renewed Case for the Reduced Instruction Set
+//! Computer", and recommended by the unprivileged specification for `mulh` and `mul`. In every
+//! pair, the second instruction consumes the result of the first, and overwrites it unless noted.
+
+use crate::pipeline::PipelineRegister;
+use alloc::collections::BTreeMap;
+use brisc_isa::{
+    ImmediateArithmeticFunction, Instruction, RegisterArithmeticFunction, XWord, Xlen,
+};
+
+/// A rule under which two adjacent instructions fuse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FusionRule {
+    /// `lui rd, imm` followed by `addi rd, rd, imm` (or `addiw`), loading a 32-bit immediate.
+    LoadImmediate,
+    /// `auipc rd, imm` followed by `jalr rd', imm(rd)`, a call or jump to a PC-relative target.
+    /// `rd` is kept live for tail calls through a temporary.
+    FarJump,
+    /// `slli rd, rs1, n` followed by `srli rd, rd, n`, clearing the upper `n` bits.
+    ZeroExtend,
+    /// `slli rd, rs1, 1..=3` followed by `add rd, rd, rs2`, indexing an array for a load.
+    ShiftAdd,
+    /// `mulh[[s]u] rdh, rs1, rs2` followed by `mul rdl, rs1, rs2`, the full product of two
+    /// registers, where `rdh` is neither `rs1` nor `rs2`. `rdh` is kept live.
+    #[cfg(feature = "m")]
+    MulHighLow,
+}
+
+/// Returns the rule under which `first` fuses with `second`, immediately after it, if any.
+pub fn fusion_rule(first: &Instruction, second: &Instruction) -> Option<FusionRule> {
+    use ImmediateArithmeticFunction::{Addi, Slli, Srli};
+    use Instruction::{Auipc, ImmediateArithmetic, Jalr, Lui, RegisterArithmetic};
+
+    let rule = match (first, second) {
+        (Lui(u), ImmediateArithmetic(i, Addi)) if i.rs1 == u.rd && i.rd == u.rd => {
+            FusionRule::LoadImmediate
+        }
+        #[cfg(feature = "64-bit")]
+        (
+            Lui(u),
+            Instruction::ImmediateArithmeticWord(
+                i,
+                brisc_isa::ImmediateArithmeticWordFunction::Addiw,
+            ),
+        ) if i.rs1 == u.rd && i.rd == u.rd => FusionRule::LoadImmediate,
+        (Auipc(u), Jalr(i)) if i.rs1 == u.rd => FusionRule::FarJump,
+        (ImmediateArithmetic(shl, Slli), ImmediateArithmetic(shr, Srli))
+            if shr.rs1 == shl.rd && shr.rd == shl.rd && shr.imm == shl.imm =>
+        {
+            FusionRule::ZeroExtend
+        }
+        (
+            ImmediateArithmetic(shl, Slli),
+            RegisterArithmetic(add, RegisterArithmeticFunction::Add),
+        ) if (1..=3).contains(&shl.imm) &&
+            add.rd == shl.rd &&
+            (add.rs1 == shl.rd) != (add.rs2 == shl.rd) =>
+        {
+            FusionRule::ShiftAdd
+        }
+        #[cfg(feature = "m")]
+        (
+            RegisterArithmetic(high, function),
+            RegisterArithmetic(low, RegisterArithmeticFunction::Mul),
+        ) if matches!(
+            function,
+            RegisterArithmeticFunction::Mulh |
+                RegisterArithmeticFunction::Mulhsu |
+                RegisterArithmeticFunction::Mulhu
+        ) && (high.rs1, high.rs2) == (low.rs1, low.rs2) &&
+            high.rd != high.rs1 &&
+            high.rd != high.rs2 =>
+        {
+            FusionRule::MulHighLow
+        }
+        _ => return None,
+    };
+
+    // Fusing into the zero register would discard the result of the pair.
+    (first.rd() != Some(0)).then_some(rule)
+}
+
+/// Counts the fusible pairs in the stream of instructions retired by a hart.
+///
+/// Each instruction fuses at most once, with the instruction retired right before it, if that
+/// instruction was not itself fused and is adjacent to it in memory.
+#[derive(Debug, Default, Clone)]
+pub struct FusionDetector {
+    /// The number of instructions observed.
+    pub instructions: u64,
+    /// The number of fused pairs, by rule.
+    pub pairs: BTreeMap<FusionRule, u64>,
+    /// The last instruction observed, and the address following it, if it was not fused.
+    previous: Option<(Instruction, XWord)>,
+}
+
+impl FusionDetector {
+    /// Creates a new [FusionDetector].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Observes the instruction in `p_reg`, once it has retired. Returns the rule under which it
+    /// fuses with the previous instruction, if any.
+    pub fn observe<X: Xlen>(&mut self, p_reg: &PipelineRegister<X>) -> Option<FusionRule> {
+        let Some(decoded) = p_reg.instruction else {
+            self.previous = None;
+            return None;
+        };
+        self.instructions += 1;
+
+        let rule = self
+            .previous
+            .take()
+            .filter(|&(_, next_pc)| next_pc == p_reg.pc)
+            .and_then(|(previous, _)| fusion_rule(&previous, &decoded.instruction));
+        match rule {
+            Some(rule) => *self.pairs.entry(rule).or_default() += 1,
+            None => {
+                let next_pc = X::truncate(p_reg.pc + decoded.size() as XWord);
+                self.previous = Some((decoded.instruction, next_pc));
+            }
+        }
+        rule
+    }
+
+    /// Returns the total number of fused pairs, each of which saves a slot.
+    pub fn fused(&self) -> u64 {
+        self.pairs.values().sum()
+    }
+
+    /// Returns the fraction of the instructions observed that would take a slot on a core that
+    /// fuses every pair, or 0 if none were observed.
+    pub fn slot_ratio(&self) -> f64 {
+        if self.instructions == 0 {
+            return 0.0;
+        }
+        (self.instructions - self.fused()) as f64 / self.instructions as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use brisc_isa::{DecodedInstruction, NativeXlen};
+    use rstest::rstest;
+
+    fn decode(raw: u32) -> Instruction {
+        Instruction::try_from(raw).unwrap()
+    }
+
+    #[rstest]
+    // lui a0, 0x12345; addi a0, a0, 0x678
+    #[case::load_immediate(0x12345537, 0x67850513, Some(FusionRule::LoadImmediate))]
+    // lui a0, 0x12345; addi a1, a0, 0x678
+    #[case::other_rd(0x12345537, 0x67850593, None)]
+    // auipc ra, 0; jalr ra, 16(ra)
+    #[case::far_jump(0x00000097, 0x010080e7, Some(FusionRule::FarJump))]
+    // auipc t1, 0; jr 16(t1)
+    #[case::tail(0x00000317, 0x01030067, Some(FusionRule::FarJump))]
+    // slli a0, a1, 16; srli a0, a0, 16
+    #[case::zero_extend(0x01059513, 0x01055513, Some(FusionRule::ZeroExtend))]
+    // slli a0, a1, 16; srli a0, a0, 8
+    #[case::other_shift(0x01059513, 0x00855513, None)]
+    // slli a0, a1, 2; add a0, a0, a2
+    #[case::shift_add(0x00259513, 0x00c50533, Some(FusionRule::ShiftAdd))]
+    // slli a0, a1, 4; add a0, a0, a2
+    #[case::shift_too_far(0x00459513, 0x00c50533, None)]
+    // lui zero, 1; addi zero, zero, 1
+    #[case::zero_register(0x00001037, 0x00100013, None)]
+    fn test_fusion_rule(
+        #[case] first: u32,
+        #[case] second: u32,
+        #[case] expected: Option<FusionRule>,
+    ) {
+        assert_eq!(fusion_rule(&decode(first), &decode(second)), expected);
+    }
+
+    #[rstest]
+    #[cfg(feature = "m")]
+    // mulhu a2, a0, a1; mul a3, a0, a1
+    #[case::mul_high_low(0x02b53633, 0x02b506b3, Some(FusionRule::MulHighLow))]
+    // mulhu a0, a0, a1; mul a3, a0, a1
+    #[case::clobbered_source(0x02b53533, 0x02b506b3, None)]
+    fn test_mul_high_low(
+        #[case] first: u32,
+        #[case] second: u32,
+        #[case] expected: Option<FusionRule>,
+    ) {
+        assert_eq!(fusion_rule(&decode(first), &decode(second)), expected);
+    }
+
+    #[test]
+    fn test_slot_ratio() {
+        let mut detector = FusionDetector::new();
+        assert_eq!(detector.slot_ratio(), 0.0);
+
+        // lui a0, 0x12345; addi a0, a0, 0x678; addi a1, a1, 1
+        let mut p_reg = PipelineRegister::<NativeXlen>::new(0);
+        for raw in [0x12345537, 0x67850513, 0x00158593] {
+            p_reg.instruction = Some(DecodedInstruction::decode(raw).unwrap());
+            detector.observe(&p_reg);
+            p_reg.pc += 4;
+        }
+        assert_eq!((detector.fused(), detector.slot_ratio()), (1, 2.0 / 3.0));
+    }
+}
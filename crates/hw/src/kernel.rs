@@ -1,25 +1,42 @@
 //! Linux kernel interface.
 
 use crate::{errors::PipelineResult, memory::Memory, pipeline::PipelineRegister};
-use brisc_isa::XWord;
+use alloc::{format, string::String, vec::Vec};
+use brisc_isa::{XWord, Xlen};
 
 /// The [`Kernel`] trait defines the interface for performing system calls.
 pub trait Kernel {
     /// Perform a system call with the given arguments.
-    fn syscall<M: Memory>(
+    fn syscall<M: Memory, X: Xlen>(
         &mut self,
         syscall_no: XWord,
         memory: &mut M,
-        p_reg: &mut PipelineRegister,
+        p_reg: &mut PipelineRegister<X>,
     ) -> PipelineResult<XWord>;
+
+    /// Appends the state of the kernel to `state`, when the emulator is snapshotted. Stateless
+    /// kernels save nothing, which is the default.
+    fn save_state(&self, state: &mut Vec<u8>) {
+        _ = state;
+    }
+
+    /// Restores the state appended by [Self::save_state], when the emulator is restored from a
+    /// snapshot. By default, only the empty state of a stateless kernel is accepted.
+    fn restore_state(&mut self, state: &[u8]) -> Result<(), String> {
+        if state.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Unexpected kernel state of {} bytes", state.len()))
+        }
+    }
 }
 
 impl Kernel for () {
-    fn syscall<M: Memory>(
+    fn syscall<M: Memory, X: Xlen>(
         &mut self,
         _: XWord,
         _: &mut M,
-        _: &mut PipelineRegister,
+        _: &mut PipelineRegister<X>,
     ) -> PipelineResult<XWord> {
         unimplemented!()
     }
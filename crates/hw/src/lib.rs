@@ -7,10 +7,15 @@
 
 extern crate alloc;
 
+pub mod cache;
 pub mod errors;
+pub mod fusion;
 pub mod kernel;
 pub mod memory;
+pub mod ooo;
 pub mod pipeline;
+pub mod predictor;
+pub mod timing;
 
 // Re-export the ISA types.
 pub use brisc_isa::*;
@@ -6,6 +6,9 @@ use crate::memory::{
 use alloc::{format, string::String, vec::Vec};
 use brisc_isa::{Byte, DoubleWord, HalfWord, Word, XWord};
 
+#[cfg(feature = "128-bit")]
+use brisc_isa::QuadWord;
+
 /// Length (in bytes) of a [HalfWord].
 const HW_LEN: usize = HalfWord::BITS as usize >> 3;
 
@@ -15,6 +18,10 @@ const W_LEN: usize = Word::BITS as usize >> 3;
 /// Length (in bytes) of a [DoubleWord].
 const DW_LEN: usize = DoubleWord::BITS as usize >> 3;
 
+/// Length (in bytes) of a [QuadWord].
+#[cfg(feature = "128-bit")]
+const QW_LEN: usize = QuadWord::BITS as usize >> 3;
+
 /// Type alias for a memory address.
 pub type Address = XWord;
 
@@ -231,6 +238,65 @@ pub trait Memory {
         Ok(())
     }
 
+    /// Get a 128-bit [QuadWord] from memory at a given address, which need not be aligned.
+    #[cfg(feature = "128-bit")]
+    fn get_quadword(&self, address: Address) -> MemoryResult<QuadWord> {
+        // Compute the page index and the memory address within it.
+        let page_index = address >> PAGE_ADDRESS_SIZE;
+        let page_address = address as usize & PAGE_ADDRESS_MASK;
+
+        // Create a temporary buffer to store the quadword.
+        let mut dat = [0u8; QW_LEN];
+        let mut count = 0;
+
+        // Attempt to read the quadword from a single page.
+        if let Some(page) = self.page(page_index) {
+            let dat_len = QW_LEN.min(PAGE_SIZE - page_address);
+            dat[..dat_len].copy_from_slice(&page[page_address..page_address + dat_len]);
+            count += dat_len;
+        }
+
+        // If the quadword read will cross a page boundary, read the rest from the next page.
+        if count < QW_LEN {
+            if let Some(page) = self.page(page_index + 1) {
+                let dat_len = QW_LEN - count;
+                dat[count..].copy_from_slice(&page[..dat_len]);
+            }
+        }
+
+        Ok(QuadWord::from_le_bytes(dat))
+    }
+
+    /// Set a 128-bit [QuadWord] in memory at a given address, which need not be aligned.
+    #[cfg(feature = "128-bit")]
+    fn set_quadword(&mut self, address: Address, value: QuadWord) -> MemoryResult<()> {
+        // Compute the page index and the memory address within it.
+        let page_index = address >> PAGE_ADDRESS_SIZE;
+        let page_address = address as usize & PAGE_ADDRESS_MASK;
+        let dat = value.to_le_bytes();
+
+        // Attempt to lookup the page in memory, and allocate it if it does not exist.
+        let page_one =
+            if let Some(page) = self.page_mut(page_index) { page } else { self.alloc(page_index)? };
+
+        // Write as much of the quadword to the first page as possible.
+        let dat_len = QW_LEN.min(PAGE_SIZE - page_address);
+        page_one[page_address..page_address + dat_len].copy_from_slice(&dat[..dat_len]);
+
+        // If the quadword write will cross a page boundary, write the rest to the next page.
+        if dat_len < QW_LEN {
+            // Attempt to lookup the page in memory, and allocate it if it does not exist.
+            let page_two = if let Some(page) = self.page_mut(page_index + 1) {
+                page
+            } else {
+                self.alloc(page_index + 1)?
+            };
+            page_two[..QW_LEN - dat_len].copy_from_slice(&dat[dat_len..]);
+        }
+
+        Ok(())
+    }
+
     /// Set a range of memory at a given [Address].
     ///
     /// ## Takes
@@ -313,3 +379,9 @@ pub trait Memory {
         format!("{:.1} {}iB", (total as f64) / (div as f64), ['K', 'M', 'G', 'T', 'P', 'E'][exp])
     }
 }
+
+/// A [Memory] that can list its allocated pages, as needed to snapshot it.
+pub trait SnapshotMemory: Memory {
+    /// Returns the indices of the pages allocated within the [Memory], in no particular order.
+    fn page_indices(&self) -> Vec<PageIndex>;
+}
@@ -1,9 +1,10 @@
 //! Simple memory implementation for the `brisc-hw` crate.
 
+use alloc::vec::Vec;
 use hashbrown::HashMap;
 
 mod interface;
-pub use interface::{Address, Memory};
+pub use interface::{Address, Memory, SnapshotMemory};
 
 mod errors;
 pub use errors::{MemoryError, MemoryResult};
@@ -49,3 +50,9 @@ impl Memory for SimpleMemory {
         self.inner_mut().get_mut(&page_index)
     }
 }
+
+impl SnapshotMemory for SimpleMemory {
+    fn page_indices(&self) -> Vec<PageIndex> {
+        self.inner().keys().copied().collect()
+    }
+}
@@ -0,0 +1,136 @@
+//! Configuration of the out-of-order core model.
+
+use crate::timing::Latencies;
+use thiserror::Error;
+
+/// A class of functional units, each with its own reservation stations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FunctionalUnit {
+    /// Integer arithmetic and logic, along with the system instructions and fences.
+    Alu,
+    /// Multiplication and division. Multipliers are pipelined, while a division occupies its
+    /// unit until it completes.
+    MulDiv,
+    /// Branches and jumps.
+    Branch,
+    /// Address generation for loads, stores and atomic memory operations.
+    Memory,
+}
+
+impl FunctionalUnit {
+    /// Every class of functional unit.
+    pub const ALL: [Self; 4] = [Self::Alu, Self::MulDiv, Self::Branch, Self::Memory];
+
+    /// Returns the index of the class in [Self::ALL].
+    pub const fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// The resources of a class of functional units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnitConfig {
+    /// The number of reservation station entries, holding the dispatched instructions of the
+    /// class until they issue.
+    pub stations: usize,
+    /// The number of units of the class, each of which can start an instruction per cycle.
+    pub units: usize,
+}
+
+impl UnitConfig {
+    /// Creates a new [UnitConfig].
+    pub const fn new(stations: usize, units: usize) -> Self {
+        Self { stations, units }
+    }
+}
+
+/// The configuration of an [OooCore].
+///
+/// [OooCore]: super::OooCore
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OooConfig {
+    /// The number of instructions dispatched, issued and retired per cycle.
+    pub width: usize,
+    /// The number of entries of the reorder buffer.
+    pub rob_size: usize,
+    /// The number of entries of the load/store queue, held by memory instructions from dispatch
+    /// until they retire.
+    pub lsq_size: usize,
+    /// The resources of each class of functional units, indexed by [FunctionalUnit::index].
+    pub units: [UnitConfig; 4],
+    /// The latencies of the instruction classes, from issue to completion.
+    pub latencies: Latencies,
+    /// The number of cycles after a mispredicted branch completes before the instructions on the
+    /// correct path can be dispatched.
+    pub mispredict_penalty: u32,
+}
+
+impl OooConfig {
+    /// Creates a new [OooConfig] of the given width, with two ALUs, and a single unit of every
+    /// other class.
+    pub const fn new(width: usize, rob_size: usize, lsq_size: usize) -> Self {
+        Self {
+            width,
+            rob_size,
+            lsq_size,
+            units: [
+                UnitConfig::new(16, 2),
+                UnitConfig::new(8, 1),
+                UnitConfig::new(8, 1),
+                UnitConfig::new(16, 1),
+            ],
+            latencies: Latencies::SINGLE_CYCLE,
+            mispredict_penalty: 3,
+        }
+    }
+
+    /// Assigns the resources of the functional units of class `unit`.
+    pub const fn with_unit(mut self, unit: FunctionalUnit, config: UnitConfig) -> Self {
+        self.units[unit.index()] = config;
+        self
+    }
+
+    /// Assigns the latencies of the instruction classes.
+    pub const fn with_latencies(mut self, latencies: Latencies) -> Self {
+        self.latencies = latencies;
+        self
+    }
+
+    /// Assigns the misprediction penalty.
+    pub const fn with_mispredict_penalty(mut self, penalty: u32) -> Self {
+        self.mispredict_penalty = penalty;
+        self
+    }
+
+    /// Returns the resources of the functional units of class `unit`.
+    pub const fn unit(&self, unit: FunctionalUnit) -> UnitConfig {
+        self.units[unit.index()]
+    }
+
+    /// Checks that every structure of the core can hold at least one instruction.
+    pub fn validate(&self) -> Result<(), OooConfigError> {
+        let sizes =
+            [("width", self.width), ("ROB size", self.rob_size), ("LSQ size", self.lsq_size)]
+                .into_iter()
+                .chain(self.units.iter().flat_map(|unit| {
+                    [
+                        ("number of reservation stations", unit.stations),
+                        ("number of units", unit.units),
+                    ]
+                }));
+        for (name, size) in sizes {
+            if size == 0 {
+                return Err(OooConfigError::Empty(name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An error in an [OooConfig].
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OooConfigError {
+    /// A structure of the core cannot hold a single instruction.
+    #[error("The {0} must be at least 1")]
+    Empty(&'static str),
+}
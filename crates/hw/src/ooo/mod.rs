@@ -0,0 +1,430 @@
+//! A Tomasulo-style out-of-order core model, estimating the cycles of a run from its stream of
+//! executed instructions.
+//!
+//! The model only tracks timing. Instructions are executed in program order by the stages of
+//! [pipeline], on the correct path, and handed to the [OooCore] once executed. The core then
+//! schedules them through its structures:
+//!
+//! - Dispatch renames the source registers of up to [OooConfig::width] instructions per cycle to
+//!   the reorder buffer entries of their producers, and places them in the reservation stations of
+//!   their [FunctionalUnit] and, for memory instructions, the load/store queue.
+//! - Issue starts the oldest instructions whose operands are ready on a free unit. A load issues
+//!   once every older store to its bytes has, and takes its data from the youngest of them if it
+//!   covers the load.
+//! - Retirement removes completed instructions from the head of the reorder buffer in program
+//!   order, so that the architectural state is precise.
+//!
+//! Mispredicted branches stop dispatch until they complete, plus [OooConfig::mispredict_penalty]
+//! cycles. System instructions and fences are serializing, and atomic memory operations only
+//! issue from the head of the reorder buffer.
+//!
+//! [pipeline]: crate::pipeline
+
+use crate::{memory::Address, pipeline::PipelineRegister};
+use alloc::{collections::VecDeque, vec, vec::Vec};
+use brisc_isa::{InstructionClass, XWord, Xlen};
+
+#[cfg(feature = "m")]
+use crate::timing::{mul_div, MulDiv};
+
+mod config;
+pub use config::{FunctionalUnit, OooConfig, OooConfigError, UnitConfig};
+
+mod stats;
+pub use stats::{OooStats, StallReason};
+
+/// The kind of a memory access in the load/store queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AccessKind {
+    /// A load.
+    Load,
+    /// A store.
+    Store,
+    /// An atomic memory operation, which both reads and writes.
+    Amo,
+}
+
+/// A memory access in the load/store queue.
+#[derive(Debug, Clone, Copy)]
+struct Access {
+    /// The kind of the access.
+    kind: AccessKind,
+    /// The address of the first byte accessed.
+    address: Address,
+    /// The number of bytes accessed.
+    bytes: usize,
+}
+
+impl Access {
+    /// Returns the address past the last byte accessed.
+    const fn end(&self) -> Address {
+        self.address.wrapping_add(self.bytes as Address)
+    }
+
+    /// Returns `true` if the access touches any byte of `other`.
+    const fn overlaps(&self, other: &Self) -> bool {
+        self.address < other.end() && other.address < self.end()
+    }
+
+    /// Returns `true` if the access touches every byte of `other`.
+    const fn covers(&self, other: &Self) -> bool {
+        self.address <= other.address && other.end() <= self.end()
+    }
+}
+
+/// Where a load takes its data from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LoadSource {
+    /// The load reads from memory.
+    Memory,
+    /// The load takes the data of an older store that covers it, available at the given cycle.
+    Forwarded(u64),
+    /// The load overlaps an older store or atomic memory operation that it cannot take its data
+    /// from, and has to wait.
+    Blocked,
+}
+
+/// An instruction in the reorder buffer.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    /// The sequence number of the instruction, in program order.
+    seq: u64,
+    /// The class of functional unit that executes the instruction.
+    unit: FunctionalUnit,
+    /// The sequence numbers of the producers of the source operands that were in flight at
+    /// dispatch.
+    sources: [Option<u64>; 2],
+    /// The destination register, if any.
+    rd: Option<u8>,
+    /// The number of cycles from issue to completion.
+    latency: u32,
+    /// The number of cycles that the instruction occupies its unit.
+    occupancy: u32,
+    /// The memory access of the instruction, if any.
+    access: Option<Access>,
+    /// Whether the instruction may only issue from the head of the reorder buffer.
+    at_head: bool,
+    /// The cycle at which the result of the instruction is available, once issued.
+    done_at: Option<u64>,
+}
+
+/// The state of the front end, which supplies dispatch with instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Front {
+    /// Instructions are supplied on the correct path.
+    Open,
+    /// The front end is waiting for the mispredicted branch with the given sequence number to
+    /// complete.
+    Mispredicted(u64),
+    /// The front end is refilling until the given cycle.
+    Refilling(u64),
+    /// The serializing instruction with the given sequence number has not retired.
+    Serialized(u64),
+}
+
+/// A Tomasulo-style out-of-order core model. See the [module documentation](self) for the
+/// structure of the core.
+///
+/// Every cycle, the owner of the core calls [Self::begin_cycle], which retires and issues
+/// instructions, then [Self::dispatch] with the next executed instructions while
+/// [Self::can_dispatch], and finally [Self::end_cycle].
+#[derive(Debug, Clone)]
+pub struct OooCore {
+    /// The configuration of the core.
+    config: OooConfig,
+    /// The current cycle.
+    now: u64,
+    /// The reorder buffer, oldest first.
+    rob: VecDeque<Entry>,
+    /// The register alias table: the sequence number of the last in-flight producer of every
+    /// register.
+    rat: [Option<u64>; 32],
+    /// The number of instructions waiting to issue in the reservation stations of every class.
+    waiting: [usize; 4],
+    /// The cycle until which every unit is busy, by class.
+    busy: [Vec<u64>; 4],
+    /// The number of memory instructions in the load/store queue.
+    lsq: usize,
+    /// The sequence number of the next instruction dispatched.
+    next_seq: u64,
+    /// The number of instructions dispatched on the current cycle.
+    dispatched: usize,
+    /// The reason that dispatch stopped on the current cycle, if it did.
+    stall: Option<StallReason>,
+    /// The state of the front end.
+    front: Front,
+    /// The performance counters of the core.
+    pub stats: OooStats,
+}
+
+impl OooCore {
+    /// Creates a new, empty [OooCore], after validating `config`.
+    pub fn new(config: OooConfig) -> Result<Self, OooConfigError> {
+        config.validate()?;
+
+        Ok(Self {
+            config,
+            now: 0,
+            rob: VecDeque::with_capacity(config.rob_size),
+            rat: [None; 32],
+            waiting: [0; 4],
+            busy: config.units.map(|unit| vec![0; unit.units]),
+            lsq: 0,
+            next_seq: 0,
+            dispatched: 0,
+            stall: None,
+            front: Front::Open,
+            stats: OooStats { rob_occupancy: vec![0; config.rob_size + 1], ..Default::default() },
+        })
+    }
+
+    /// Returns the configuration of the core.
+    pub const fn config(&self) -> &OooConfig {
+        &self.config
+    }
+
+    /// Returns `true` if no instruction is in flight.
+    pub fn is_empty(&self) -> bool {
+        self.rob.is_empty()
+    }
+
+    /// Starts a cycle, retiring the oldest completed instructions and issuing the oldest ready
+    /// ones.
+    pub fn begin_cycle(&mut self) {
+        self.dispatched = 0;
+        self.stall = None;
+        self.retire();
+        self.issue();
+    }
+
+    /// Ends the cycle, recording the occupancy of the reorder buffer and the stall reason.
+    pub fn end_cycle(&mut self) {
+        self.stats.cycles += 1;
+        self.stats.rob_occupancy[self.rob.len()] += 1;
+        if let Some(reason) = self.stall {
+            *self.stats.stalls.entry(reason).or_default() += 1;
+        }
+        self.now += 1;
+    }
+
+    /// Returns `true` if the width of dispatch is not exhausted on the current cycle, and it has
+    /// not stalled.
+    pub const fn can_dispatch(&self) -> bool {
+        self.dispatched < self.config.width && self.stall.is_none()
+    }
+
+    /// Dispatches the executed instruction in `p_reg`, or returns the reason that it cannot be
+    /// dispatched on the current cycle. A stalled instruction must be dispatched again on a later
+    /// cycle, before any younger one.
+    pub fn dispatch<X: Xlen>(&mut self, p_reg: &PipelineRegister<X>) -> Result<(), StallReason> {
+        let Some(decoded) = p_reg.instruction else {
+            return Ok(());
+        };
+        let instruction = decoded.instruction;
+        let class = instruction.class();
+        let serializing = matches!(class, InstructionClass::System | InstructionClass::Fence);
+        let unit = match class {
+            InstructionClass::Branch | InstructionClass::Jump => FunctionalUnit::Branch,
+            InstructionClass::Load | InstructionClass::Store | InstructionClass::Amo => {
+                FunctionalUnit::Memory
+            }
+            #[cfg(feature = "m")]
+            InstructionClass::Alu if mul_div::<X>(&instruction).is_some() => FunctionalUnit::MulDiv,
+            _ => FunctionalUnit::Alu,
+        };
+        let access = access_of(p_reg);
+
+        let stall = self.front_stall().or_else(|| {
+            if serializing && !self.rob.is_empty() {
+                Some(StallReason::Serialize)
+            } else if self.rob.len() == self.config.rob_size {
+                Some(StallReason::RobFull)
+            } else if self.waiting[unit.index()] == self.config.unit(unit).stations {
+                Some(StallReason::StationsFull(unit))
+            } else if access.is_some() && self.lsq == self.config.lsq_size {
+                Some(StallReason::LsqFull)
+            } else {
+                None
+            }
+        });
+        if let Some(reason) = stall {
+            self.stall = Some(reason);
+            return Err(reason);
+        }
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.dispatched += 1;
+
+        // Rename the sources to their in-flight producers, and the destination to the instruction.
+        let source = |register: Option<u8>| {
+            register
+                .filter(|&register| register != 0)
+                .and_then(|register| self.rat[register as usize])
+        };
+        let sources = [source(instruction.rs1()), source(instruction.rs2())];
+        let rd = instruction.rd().filter(|&rd| rd != 0);
+        if let Some(rd) = rd {
+            self.rat[rd as usize] = Some(seq);
+        }
+
+        let latency = self.config.latencies.of(p_reg);
+        #[cfg(feature = "m")]
+        let occupancy = match mul_div::<X>(&instruction) {
+            Some(MulDiv::Div { .. }) => latency,
+            _ => 1,
+        };
+        #[cfg(not(feature = "m"))]
+        let occupancy = 1;
+
+        self.waiting[unit.index()] += 1;
+        self.lsq += access.is_some() as usize;
+        self.rob.push_back(Entry {
+            seq,
+            unit,
+            sources,
+            rd,
+            latency,
+            occupancy,
+            access,
+            at_head: serializing || class == InstructionClass::Amo,
+            done_at: None,
+        });
+
+        if serializing {
+            self.front = Front::Serialized(seq);
+        } else if instruction.is_control_flow() {
+            let fallthrough = X::truncate(p_reg.pc + decoded.size() as XWord);
+            if X::truncate(p_reg.next_pc) != p_reg.predicted_pc.unwrap_or(fallthrough) {
+                self.stats.mispredictions += 1;
+                self.front = Front::Mispredicted(seq);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the reason that the front end cannot supply an instruction on the current cycle,
+    /// if any, after updating its state.
+    fn front_stall(&mut self) -> Option<StallReason> {
+        if let Front::Mispredicted(seq) = self.front {
+            if let Some(done_at) = self.entry(seq).and_then(|entry| entry.done_at) {
+                self.front = Front::Refilling(done_at + self.config.mispredict_penalty as u64);
+            }
+        }
+
+        match self.front {
+            Front::Open => None,
+            Front::Mispredicted(_) => Some(StallReason::Mispredict),
+            Front::Refilling(until) if self.now < until => Some(StallReason::Mispredict),
+            Front::Serialized(seq) if self.entry(seq).is_some() => Some(StallReason::Serialize),
+            Front::Refilling(_) | Front::Serialized(_) => {
+                self.front = Front::Open;
+                None
+            }
+        }
+    }
+
+    /// Returns the in-flight instruction with the sequence number `seq`, if it has not retired.
+    fn entry(&self, seq: u64) -> Option<&Entry> {
+        let head = self.rob.front()?.seq;
+        seq.checked_sub(head).and_then(|index| self.rob.get(index as usize))
+    }
+
+    /// Returns `true` if the result of the instruction `seq` is available on the current cycle.
+    fn is_ready(&self, seq: u64) -> bool {
+        self.entry(seq).is_none_or(|entry| entry.done_at.is_some_and(|done| done <= self.now))
+    }
+
+    /// Retires up to [OooConfig::width] completed instructions from the head of the reorder
+    /// buffer.
+    fn retire(&mut self) {
+        for _ in 0..self.config.width {
+            let Some(&head) = self.rob.front() else {
+                return;
+            };
+            if !self.is_ready(head.seq) {
+                return;
+            }
+
+            self.rob.pop_front();
+            if let Some(rd) = head.rd {
+                if self.rat[rd as usize] == Some(head.seq) {
+                    self.rat[rd as usize] = None;
+                }
+            }
+            self.lsq -= head.access.is_some() as usize;
+            self.stats.retired += 1;
+        }
+    }
+
+    /// Issues up to [OooConfig::width] of the oldest instructions whose operands are ready to a
+    /// free unit of their class.
+    fn issue(&mut self) {
+        let mut issued = 0;
+        for index in 0..self.rob.len() {
+            if issued == self.config.width {
+                return;
+            }
+            let entry = self.rob[index];
+            let ready = entry.sources.iter().flatten().all(|&source| self.is_ready(source));
+            if entry.done_at.is_some() || !ready || (entry.at_head && index != 0) {
+                continue;
+            }
+
+            let now = self.now;
+            let mut done_at = now + entry.latency as u64;
+            let source = match entry.access {
+                Some(access) if access.kind == AccessKind::Load => self.load_source(index, &access),
+                _ => LoadSource::Memory,
+            };
+            match source {
+                LoadSource::Memory => {}
+                LoadSource::Forwarded(data_at) => done_at = data_at.max(now + 1),
+                LoadSource::Blocked => continue,
+            }
+
+            let Some(busy) = self.busy[entry.unit.index()].iter_mut().find(|busy| **busy <= now)
+            else {
+                continue;
+            };
+            *busy = now + entry.occupancy as u64;
+            self.rob[index].done_at = Some(done_at);
+            self.stats.forwarded_loads += matches!(source, LoadSource::Forwarded(_)) as u64;
+            self.waiting[entry.unit.index()] -= 1;
+            issued += 1;
+        }
+    }
+
+    /// Checks the load `access` at `index` in the reorder buffer against the youngest older
+    /// store or atomic memory operation to any of its bytes, which has not retired.
+    fn load_source(&self, index: usize, access: &Access) -> LoadSource {
+        let older = self.rob.range(..index).rev();
+        let Some((entry, store)) = older
+            .filter_map(|entry| entry.access.map(|store| (entry, store)))
+            .find(|(_, store)| store.kind != AccessKind::Load && store.overlaps(access))
+        else {
+            return LoadSource::Memory;
+        };
+
+        match entry.done_at {
+            Some(data_at) if store.kind == AccessKind::Store && store.covers(access) => {
+                LoadSource::Forwarded(data_at)
+            }
+            _ => LoadSource::Blocked,
+        }
+    }
+}
+
+/// Returns the memory access of the executed instruction in `p_reg`, if any.
+fn access_of<X: Xlen>(p_reg: &PipelineRegister<X>) -> Option<Access> {
+    let instruction = p_reg.instruction?.instruction;
+    let bytes = instruction.memory_width()?.bytes();
+
+    let (kind, address) = match instruction.class() {
+        InstructionClass::Load => (AccessKind::Load, p_reg.alu_result?),
+        InstructionClass::Store => (AccessKind::Store, p_reg.alu_result?),
+        InstructionClass::Amo => (AccessKind::Amo, p_reg.rs1_value?),
+        _ => return None,
+    };
+    Some(Access { kind, address: X::truncate(address), bytes })
+}
@@ -0,0 +1,80 @@
+//! Performance counters of the out-of-order core model.
+
+use super::FunctionalUnit;
+use alloc::{collections::BTreeMap, vec::Vec};
+
+/// The reason that dispatch stopped before filling its width on a cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StallReason {
+    /// The reorder buffer was full.
+    RobFull,
+    /// The reservation stations of a class of functional units were full.
+    StationsFull(FunctionalUnit),
+    /// The load/store queue was full.
+    LsqFull,
+    /// A mispredicted branch had not completed, or the front end was refilling after it.
+    Mispredict,
+    /// A system instruction or a fence was waiting for the older instructions to retire, or for
+    /// itself to retire.
+    Serialize,
+}
+
+/// Performance counters of an [OooCore].
+///
+/// [OooCore]: super::OooCore
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OooStats {
+    /// The number of cycles executed.
+    pub cycles: u64,
+    /// The number of instructions retired.
+    pub retired: u64,
+    /// The number of cycles that the reorder buffer held each number of instructions at the end
+    /// of a cycle, indexed by occupancy.
+    pub rob_occupancy: Vec<u64>,
+    /// The number of cycles that dispatch was stopped by each reason.
+    pub stalls: BTreeMap<StallReason, u64>,
+    /// The number of loads whose data was forwarded from an older store in the load/store queue.
+    pub forwarded_loads: u64,
+    /// The number of mispredicted branches and jumps.
+    pub mispredictions: u64,
+}
+
+impl OooStats {
+    /// Returns the average number of instructions retired per cycle, or 0 before the first cycle.
+    pub fn ipc(&self) -> f64 {
+        if self.cycles == 0 {
+            return 0.0;
+        }
+        self.retired as f64 / self.cycles as f64
+    }
+
+    /// Returns the average occupancy of the reorder buffer, or 0 before the first cycle.
+    pub fn mean_rob_occupancy(&self) -> f64 {
+        if self.cycles == 0 {
+            return 0.0;
+        }
+        let total = self
+            .rob_occupancy
+            .iter()
+            .enumerate()
+            .map(|(occupancy, &cycles)| occupancy as u64 * cycles)
+            .sum::<u64>();
+        total as f64 / self.cycles as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OooStats;
+    use alloc::vec;
+
+    #[test]
+    fn test_averages() {
+        let empty = OooStats::default();
+        assert_eq!((empty.ipc(), empty.mean_rob_occupancy()), (0.0, 0.0));
+
+        let stats =
+            OooStats { cycles: 4, retired: 6, rob_occupancy: vec![1, 2, 1], ..Default::default() };
+        assert_eq!((stats.ipc(), stats.mean_rob_occupancy()), (1.5, 1.0));
+    }
+}
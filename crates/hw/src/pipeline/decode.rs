@@ -2,17 +2,15 @@
 
 use super::register::PipelineRegister;
 use crate::errors::{PipelineError, PipelineResult};
-use brisc_isa::{Instruction, REG_A7};
+use brisc_isa::{Xlen, REG_A7};
 
-/// Executes the instruction fetch stage.
+/// Executes the instruction decode stage.
 ///
-/// In this stage, the raw instruction is decoded, the register values are read from the
-/// register file, and then finally copied into the [PipelineRegister].
-pub fn decode_instruction(register: &mut PipelineRegister) -> PipelineResult<()> {
-    // Decode the raw instruction.
-    let instruction_raw =
-        register.instruction_raw.ok_or(PipelineError::MissingState("instruction_raw"))?;
-    let instruction = Instruction::try_from(instruction_raw)?;
+/// In this stage, the register values are read from the register file for the instruction that
+/// was decoded during fetch, and then finally copied into the [PipelineRegister].
+pub fn decode_instruction<X: Xlen>(register: &mut PipelineRegister<X>) -> PipelineResult<()> {
+    let instruction =
+        register.instruction.ok_or(PipelineError::MissingState("instruction"))?.instruction;
 
     // Read register values and update the stage state.
     register.rs1_value = instruction.rs1().map(|rs1| register.registers[rs1 as usize]);
@@ -20,9 +18,6 @@ pub fn decode_instruction(register: &mut PipelineRegister) -> PipelineResult<()>
     register.rd = instruction.rd();
     register.immediate = instruction.immediate();
 
-    // Set the decoded instruction in the pipeline register.
-    register.instruction = Some(instruction);
-
     // Throw an interrupt if the instruction is a system call.
     if instruction.is_system_call() {
         return Err(PipelineError::SyscallException(register.registers[REG_A7 as usize]));
@@ -4,7 +4,7 @@ use super::register::PipelineRegister;
 use crate::errors::{PipelineError, PipelineResult};
 use brisc_isa::{
     BType, BranchFunction, EnvironmentFunction, IType, ImmediateArithmeticFunction, Instruction,
-    RegisterArithmeticFunction, SXWord, XWord, SHIFT_MASK,
+    RegisterArithmeticFunction, XWord, Xlen,
 };
 
 #[cfg(feature = "64-bit")]
@@ -12,12 +12,20 @@ use brisc_isa::{
     sign_extend, ImmediateArithmeticWordFunction, RegisterArithmeticWordFunction, Word,
 };
 
-#[cfg(feature = "m")]
-use brisc_isa::{DoubleXWord, X_LEN};
+#[cfg(feature = "128-bit")]
+use brisc_isa::{DoubleWord, ImmediateArithmeticDoubleFunction, RegisterArithmeticDoubleFunction};
+
+#[cfg(all(feature = "m", not(feature = "128-bit")))]
+use brisc_isa::DoubleXWord;
 
 /// Execute the ALU stage of the pipeline.
-pub fn execute(p_reg: &mut PipelineRegister) -> PipelineResult<()> {
-    let instruction = p_reg.instruction.ok_or(PipelineError::MissingState("instruction"))?;
+///
+/// Results are computed over [XWord], and truncated to the register width of the hart when they
+/// are written back. Operations that depend on the width, such as signed comparisons and shifts,
+/// interpret their operands with [Xlen::signed] and [Xlen::SHIFT_MASK].
+pub fn execute<X: Xlen>(p_reg: &mut PipelineRegister<X>) -> PipelineResult<()> {
+    let instruction =
+        p_reg.instruction.ok_or(PipelineError::MissingState("instruction"))?.instruction;
 
     let result = match instruction {
         Instruction::MemoryLoad(_, _) | Instruction::MemoryStore(_, _) => execute_mem(p_reg)?,
@@ -31,16 +39,16 @@ pub fn execute(p_reg: &mut PipelineRegister) -> PipelineResult<()> {
         }
         Instruction::RegisterArithmetic(_, funct) => execute_reg_arithmetic(p_reg, funct)?,
         Instruction::Lui(u_type) => u_type.imm,
-        Instruction::Auipc(u_type) => p_reg.pc + u_type.imm,
+        Instruction::Auipc(u_type) => p_reg.pc.wrapping_add(u_type.imm),
         Instruction::Jal(j_type) => {
             let result = p_reg.next_pc;
-            p_reg.next_pc = p_reg.pc + j_type.imm;
+            p_reg.next_pc = p_reg.pc.wrapping_add(j_type.imm);
             result
         }
         Instruction::Jalr(i_type) => {
             let result = p_reg.next_pc;
             let rs1 = p_reg.rs1_value.ok_or(PipelineError::MissingState("rs1_value"))?;
-            p_reg.next_pc = (rs1 + i_type.imm) & !1;
+            p_reg.next_pc = rs1.wrapping_add(i_type.imm) & !1;
             result
         }
         Instruction::Fence => {
@@ -61,6 +69,14 @@ pub fn execute(p_reg: &mut PipelineRegister) -> PipelineResult<()> {
         }
         #[cfg(feature = "64-bit")]
         Instruction::RegisterArithmeticWord(_, funct) => execute_reg_arithmetic_word(p_reg, funct)?,
+        #[cfg(feature = "128-bit")]
+        Instruction::ImmediateArithmeticDouble(i_type, funct) => {
+            execute_imm_arithmetic_double(p_reg, i_type, funct)?
+        }
+        #[cfg(feature = "128-bit")]
+        Instruction::RegisterArithmeticDouble(_, funct) => {
+            execute_reg_arithmetic_double(p_reg, funct)?
+        }
         #[cfg(feature = "a")]
         Instruction::Amo(_, _) => 0,
     };
@@ -74,20 +90,20 @@ pub fn execute(p_reg: &mut PipelineRegister) -> PipelineResult<()> {
 /// [MemoryLoad]: brisc_isa::Instruction::MemoryLoad
 /// [MemoryStore]:brisc_isa::Instruction::MemoryStore
 #[inline(always)]
-fn execute_mem(p_reg: &PipelineRegister) -> PipelineResult<XWord> {
+fn execute_mem<X: Xlen>(p_reg: &PipelineRegister<X>) -> PipelineResult<XWord> {
     p_reg.effective_address().ok_or(PipelineError::MissingState("effective_address"))
 }
 
 /// Executes a [BranchFunction] instruction, returning the target address.
 #[inline(always)]
-fn execute_branch(
-    p_reg: &PipelineRegister,
+fn execute_branch<X: Xlen>(
+    p_reg: &PipelineRegister<X>,
     b_type: BType,
     funct: BranchFunction,
 ) -> PipelineResult<XWord> {
     let rs1 = p_reg.rs1_value.ok_or(PipelineError::MissingState("rs1_value"))?;
     let rs2 = p_reg.rs2_value.ok_or(PipelineError::MissingState("rs2_value"))?;
-    let target = p_reg.pc + b_type.imm;
+    let target = p_reg.pc.wrapping_add(b_type.imm);
 
     match funct {
         BranchFunction::Beq => {
@@ -101,12 +117,12 @@ fn execute_branch(
             }
         }
         BranchFunction::Blt => {
-            if (rs1 as SXWord) < (rs2 as SXWord) {
+            if X::signed(rs1) < X::signed(rs2) {
                 return Ok(target);
             }
         }
         BranchFunction::Bge => {
-            if (rs1 as SXWord) >= (rs2 as SXWord) {
+            if X::signed(rs1) >= X::signed(rs2) {
                 return Ok(target);
             }
         }
@@ -127,8 +143,8 @@ fn execute_branch(
 
 /// Executes an [ImmediateArithmeticFunction] instruction.
 #[inline(always)]
-fn execute_imm_arithmetic(
-    p_reg: &PipelineRegister,
+fn execute_imm_arithmetic<X: Xlen>(
+    p_reg: &PipelineRegister<X>,
     i_type: IType,
     funct: ImmediateArithmeticFunction,
 ) -> PipelineResult<XWord> {
@@ -139,11 +155,13 @@ fn execute_imm_arithmetic(
         ImmediateArithmeticFunction::Xori => i_type.imm ^ rs1,
         ImmediateArithmeticFunction::Ori => i_type.imm | rs1,
         ImmediateArithmeticFunction::Andi => i_type.imm & rs1,
-        ImmediateArithmeticFunction::Slli => rs1 << (i_type.imm & SHIFT_MASK),
-        ImmediateArithmeticFunction::Srli => rs1 >> (i_type.imm & SHIFT_MASK),
-        ImmediateArithmeticFunction::Srai => (rs1 as SXWord >> (i_type.imm & SHIFT_MASK)) as XWord,
-        ImmediateArithmeticFunction::Slti => ((rs1 as SXWord) < (i_type.imm as SXWord)) as XWord,
-        ImmediateArithmeticFunction::Sltiu => (rs1 < i_type.imm) as XWord,
+        ImmediateArithmeticFunction::Slli => rs1 << (i_type.imm & X::SHIFT_MASK),
+        ImmediateArithmeticFunction::Srli => rs1 >> (i_type.imm & X::SHIFT_MASK),
+        ImmediateArithmeticFunction::Srai => {
+            (X::signed(rs1) >> (i_type.imm & X::SHIFT_MASK)) as XWord
+        }
+        ImmediateArithmeticFunction::Slti => (X::signed(rs1) < X::signed(i_type.imm)) as XWord,
+        ImmediateArithmeticFunction::Sltiu => (rs1 < X::truncate(i_type.imm)) as XWord,
     };
 
     Ok(res)
@@ -151,8 +169,8 @@ fn execute_imm_arithmetic(
 
 /// Executes a [RegisterArithmeticFunction] instruction.
 #[inline(always)]
-fn execute_reg_arithmetic(
-    p_reg: &PipelineRegister,
+fn execute_reg_arithmetic<X: Xlen>(
+    p_reg: &PipelineRegister<X>,
     funct: RegisterArithmeticFunction,
 ) -> PipelineResult<XWord> {
     let rs1 = p_reg.rs1_value.ok_or(PipelineError::MissingState("rs1_value"))?;
@@ -164,11 +182,11 @@ fn execute_reg_arithmetic(
         RegisterArithmeticFunction::Xor => rs1 ^ rs2,
         RegisterArithmeticFunction::Or => rs1 | rs2,
         RegisterArithmeticFunction::And => rs1 & rs2,
-        RegisterArithmeticFunction::Sll => rs1 << (rs2 & SHIFT_MASK),
-        RegisterArithmeticFunction::Srl => rs1 >> (rs2 & SHIFT_MASK),
-        RegisterArithmeticFunction::Sra => (rs1 as SXWord >> (rs2 & SHIFT_MASK)) as XWord,
+        RegisterArithmeticFunction::Sll => rs1 << (rs2 & X::SHIFT_MASK),
+        RegisterArithmeticFunction::Srl => rs1 >> (rs2 & X::SHIFT_MASK),
+        RegisterArithmeticFunction::Sra => (X::signed(rs1) >> (rs2 & X::SHIFT_MASK)) as XWord,
         RegisterArithmeticFunction::Slt => {
-            if (rs1 as SXWord) < (rs2 as SXWord) {
+            if X::signed(rs1) < X::signed(rs2) {
                 1
             } else {
                 0
@@ -182,56 +200,46 @@ fn execute_reg_arithmetic(
             }
         }
         #[cfg(feature = "m")]
-        RegisterArithmeticFunction::Mul => rs1 * rs2,
+        RegisterArithmeticFunction::Mul => rs1.wrapping_mul(rs2),
         #[cfg(feature = "m")]
         RegisterArithmeticFunction::Mulh => {
-            let rs1 = crate::sign_extend(rs1 as DoubleXWord, (X_LEN - 1) as DoubleXWord);
-            let rs2 = crate::sign_extend(rs2 as DoubleXWord, (X_LEN - 1) as DoubleXWord);
-            #[cfg(not(feature = "64-bit"))]
-            let result = (rs1 as i64).wrapping_mul(rs2 as i64);
-            #[cfg(feature = "64-bit")]
-            let result = (rs1 as i128).wrapping_mul(rs2 as i128);
-            (result >> X_LEN) as XWord
+            // Correct the unsigned product for the two's complement weight of negative operands.
+            let mut result = mulhu::<X>(rs1, rs2);
+            if X::signed(rs1) < 0 {
+                result = result.wrapping_sub(rs2);
+            }
+            if X::signed(rs2) < 0 {
+                result = result.wrapping_sub(rs1);
+            }
+            result
         }
         #[cfg(feature = "m")]
         RegisterArithmeticFunction::Mulhsu => {
-            let rs1 = crate::sign_extend(rs1 as DoubleXWord, (X_LEN - 1) as DoubleXWord);
-            #[cfg(not(feature = "64-bit"))]
-            let result = (rs1 as i64).wrapping_mul(rs2 as u64 as i64);
-            #[cfg(feature = "64-bit")]
-            let result = (rs1 as i128).wrapping_mul(rs2 as i128);
-            (result >> X_LEN) as XWord
+            let result = mulhu::<X>(rs1, rs2);
+            if X::signed(rs1) < 0 {
+                result.wrapping_sub(rs2)
+            } else {
+                result
+            }
         }
         #[cfg(feature = "m")]
-        RegisterArithmeticFunction::Mulhu => {
-            #[cfg(not(feature = "64-bit"))]
-            let result = (rs1 as u64) * (rs2 as u64);
-            #[cfg(feature = "64-bit")]
-            let result = (rs1 as u128) * (rs2 as u128);
-            (result >> X_LEN) as XWord
-        }
+        RegisterArithmeticFunction::Mulhu => mulhu::<X>(rs1, rs2),
         #[cfg(feature = "m")]
         RegisterArithmeticFunction::Div => {
             if rs2 == 0 {
                 XWord::MAX
             } else {
-                ((rs1 as SXWord).wrapping_div(rs2 as SXWord)) as XWord
+                X::signed(rs1).wrapping_div(X::signed(rs2)) as XWord
             }
         }
         #[cfg(feature = "m")]
-        RegisterArithmeticFunction::Divu => {
-            if rs2 == 0 {
-                XWord::MAX
-            } else {
-                rs1 / rs2
-            }
-        }
+        RegisterArithmeticFunction::Divu => rs1.checked_div(rs2).unwrap_or(XWord::MAX),
         #[cfg(feature = "m")]
         RegisterArithmeticFunction::Rem => {
             if rs2 == 0 {
                 rs1
             } else {
-                ((rs1 as SXWord).wrapping_rem(rs2 as SXWord)) as XWord
+                X::signed(rs1).wrapping_rem(X::signed(rs2)) as XWord
             }
         }
         #[cfg(feature = "m")]
@@ -250,8 +258,8 @@ fn execute_reg_arithmetic(
 /// Executes an [ImmediateArithmeticWordFunction] instruction.
 #[cfg(feature = "64-bit")]
 #[inline(always)]
-fn execute_imm_arithmetic_word(
-    p_reg: &PipelineRegister,
+fn execute_imm_arithmetic_word<X: Xlen>(
+    p_reg: &PipelineRegister<X>,
     i_type: IType,
     funct: ImmediateArithmeticWordFunction,
 ) -> PipelineResult<XWord> {
@@ -270,8 +278,8 @@ fn execute_imm_arithmetic_word(
 /// Executes a [RegisterArithmeticWordFunction] instruction.
 #[cfg(feature = "64-bit")]
 #[inline(always)]
-fn execute_reg_arithmetic_word(
-    p_reg: &PipelineRegister,
+fn execute_reg_arithmetic_word<X: Xlen>(
+    p_reg: &PipelineRegister<X>,
     funct: RegisterArithmeticWordFunction,
 ) -> PipelineResult<XWord> {
     let rs1 = p_reg.rs1_value.ok_or(PipelineError::MissingState("rs1_value"))? as Word;
@@ -280,11 +288,9 @@ fn execute_reg_arithmetic_word(
     let result = match funct {
         RegisterArithmeticWordFunction::Addw => rs1.wrapping_add(rs2),
         RegisterArithmeticWordFunction::Subw => rs1.wrapping_sub(rs2),
-        RegisterArithmeticWordFunction::Sllw => rs1 << (rs2 & SHIFT_MASK as Word),
-        RegisterArithmeticWordFunction::Srlw => rs1 >> (rs2 & SHIFT_MASK as Word),
-        RegisterArithmeticWordFunction::Sraw => {
-            ((rs1 as i32) >> (rs2 & SHIFT_MASK as Word)) as Word
-        }
+        RegisterArithmeticWordFunction::Sllw => rs1 << (rs2 & 0x1F),
+        RegisterArithmeticWordFunction::Srlw => rs1 >> (rs2 & 0x1F),
+        RegisterArithmeticWordFunction::Sraw => ((rs1 as i32) >> (rs2 & 0x1F)) as Word,
         #[cfg(feature = "m")]
         RegisterArithmeticWordFunction::Mulw => {
             let result = (rs1 as i32 as i64) * (rs2 as i32 as i64);
@@ -299,13 +305,7 @@ fn execute_reg_arithmetic_word(
             }
         }
         #[cfg(feature = "m")]
-        RegisterArithmeticWordFunction::Divuw => {
-            if rs2 == 0 {
-                Word::MAX
-            } else {
-                rs1 / rs2
-            }
-        }
+        RegisterArithmeticWordFunction::Divuw => rs1.checked_div(rs2).unwrap_or(Word::MAX),
         #[cfg(feature = "m")]
         RegisterArithmeticWordFunction::Remw => {
             if rs2 == 0 {
@@ -326,3 +326,100 @@ fn execute_reg_arithmetic_word(
 
     Ok(sign_extend(result as XWord, 31))
 }
+
+/// Executes an [ImmediateArithmeticDoubleFunction] instruction.
+#[cfg(feature = "128-bit")]
+#[inline(always)]
+fn execute_imm_arithmetic_double<X: Xlen>(
+    p_reg: &PipelineRegister<X>,
+    i_type: IType,
+    funct: ImmediateArithmeticDoubleFunction,
+) -> PipelineResult<XWord> {
+    let rs1 = p_reg.rs1_value.ok_or(PipelineError::MissingState("rs1_value"))? as DoubleWord;
+
+    let result = match funct {
+        ImmediateArithmeticDoubleFunction::Addid => (i_type.imm as DoubleWord).wrapping_add(rs1),
+        ImmediateArithmeticDoubleFunction::Sllid => rs1 << (i_type.imm & 0x3F),
+        ImmediateArithmeticDoubleFunction::Srlid => rs1 >> (i_type.imm & 0x3F),
+        ImmediateArithmeticDoubleFunction::Sraid => {
+            ((rs1 as i64) >> (i_type.imm & 0x3F)) as DoubleWord
+        }
+    };
+
+    Ok(sign_extend(result as XWord, 63))
+}
+
+/// Executes a [RegisterArithmeticDoubleFunction] instruction.
+#[cfg(feature = "128-bit")]
+#[inline(always)]
+fn execute_reg_arithmetic_double<X: Xlen>(
+    p_reg: &PipelineRegister<X>,
+    funct: RegisterArithmeticDoubleFunction,
+) -> PipelineResult<XWord> {
+    let rs1 = p_reg.rs1_value.ok_or(PipelineError::MissingState("rs1_value"))? as DoubleWord;
+    let rs2 = p_reg.rs2_value.ok_or(PipelineError::MissingState("rs2_value"))? as DoubleWord;
+
+    let result = match funct {
+        RegisterArithmeticDoubleFunction::Addd => rs1.wrapping_add(rs2),
+        RegisterArithmeticDoubleFunction::Subd => rs1.wrapping_sub(rs2),
+        RegisterArithmeticDoubleFunction::Slld => rs1 << (rs2 & 0x3F),
+        RegisterArithmeticDoubleFunction::Srld => rs1 >> (rs2 & 0x3F),
+        RegisterArithmeticDoubleFunction::Srad => ((rs1 as i64) >> (rs2 & 0x3F)) as DoubleWord,
+        #[cfg(feature = "m")]
+        RegisterArithmeticDoubleFunction::Muld => rs1.wrapping_mul(rs2),
+        #[cfg(feature = "m")]
+        RegisterArithmeticDoubleFunction::Divd => {
+            if rs2 == 0 {
+                DoubleWord::MAX
+            } else {
+                ((rs1 as i64).wrapping_div(rs2 as i64)) as DoubleWord
+            }
+        }
+        #[cfg(feature = "m")]
+        RegisterArithmeticDoubleFunction::Divud => rs1.checked_div(rs2).unwrap_or(DoubleWord::MAX),
+        #[cfg(feature = "m")]
+        RegisterArithmeticDoubleFunction::Remd => {
+            if rs2 == 0 {
+                rs1
+            } else {
+                ((rs1 as i64).wrapping_rem(rs2 as i64)) as DoubleWord
+            }
+        }
+        #[cfg(feature = "m")]
+        RegisterArithmeticDoubleFunction::Remud => {
+            if rs2 == 0 {
+                rs1
+            } else {
+                rs1 % rs2
+            }
+        }
+    };
+
+    Ok(sign_extend(result as XWord, 63))
+}
+
+/// Returns the upper [Xlen::X_LEN] bits of the unsigned product of two register values.
+#[cfg(all(feature = "m", not(feature = "128-bit")))]
+#[inline(always)]
+const fn mulhu<X: Xlen>(rs1: XWord, rs2: XWord) -> XWord {
+    ((rs1 as DoubleXWord * rs2 as DoubleXWord) >> X::X_LEN) as XWord
+}
+
+/// Returns the upper [Xlen::X_LEN] bits of the unsigned product of two register values.
+///
+/// There is no integer type twice as wide as a 128-bit register, so the full product of RV128
+/// operands is assembled from the products of their 64-bit halves.
+#[cfg(all(feature = "m", feature = "128-bit"))]
+#[inline(always)]
+const fn mulhu<X: Xlen>(rs1: XWord, rs2: XWord) -> XWord {
+    if X::X_LEN <= 64 {
+        return (rs1 * rs2) >> X::X_LEN;
+    }
+
+    const LOW: XWord = DoubleWord::MAX as XWord;
+    let (a_hi, a_lo) = (rs1 >> 64, rs1 & LOW);
+    let (b_hi, b_lo) = (rs2 >> 64, rs2 & LOW);
+    let (lo, mid_a, mid_b) = (a_lo * b_lo, a_hi * b_lo, a_lo * b_hi);
+    let carry = ((lo >> 64) + (mid_a & LOW) + (mid_b & LOW)) >> 64;
+    a_hi * b_hi + (mid_a >> 64) + (mid_b >> 64) + carry
+}
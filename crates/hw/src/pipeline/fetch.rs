@@ -5,27 +5,28 @@ use crate::{
     errors::{PipelineError, PipelineResult},
     memory::Memory,
 };
-use cfg_if::cfg_if;
+use brisc_isa::{IsaConfig, XWord, Xlen};
 
 /// Execute the Instruction Fetch pipeline stage.
-pub fn instruction_fetch<M: Memory>(
-    p_reg: &mut PipelineRegister,
+///
+/// The fetched word is decoded immediately, as the length of the instruction is needed to
+/// compute the next program counter. Instructions from extensions that `isa` disables are
+/// rejected as illegal.
+pub fn instruction_fetch<M: Memory, X: Xlen>(
+    p_reg: &mut PipelineRegister<X>,
     memory: &M,
+    isa: &IsaConfig<X>,
 ) -> PipelineResult<()> {
     // Fetch the instruction from memory at the current program counter.
     let instr_raw = memory.get_word(p_reg.pc).map_err(PipelineError::MemoryError)?;
-    p_reg.instruction_raw = Some(instr_raw);
+    let instruction = isa
+        .decode(instr_raw)
+        .map_err(|source| PipelineError::InstructionDecodeError { pc: p_reg.pc, source })?;
+    p_reg.instruction = Some(instruction);
 
     // Increment the program counter eagerly. If a branch is taken, the program counter
     // will be updated in the `execute` stage.
-    cfg_if! {
-        if #[cfg(feature = "c")] {
-            let inc = if brisc_isa::is_compressed(instr_raw) { 2 } else { 4 };
-        } else {
-            let inc = 4;
-        }
-    };
-    p_reg.next_pc = p_reg.pc + inc;
+    p_reg.next_pc = X::truncate(p_reg.pc + instruction.size() as XWord);
 
     Ok(())
 }
@@ -6,18 +6,25 @@ use crate::{
     memory::Memory,
 };
 use brisc_isa::{
-    sign_extend, Byte, HalfWord, Instruction, LoadFunction, StoreFunction, Word, XWord,
+    sign_extend, Byte, HalfWord, Instruction, LoadFunction, StoreFunction, Word, XWord, Xlen,
 };
 
 #[cfg(feature = "64-bit")]
 use brisc_isa::DoubleWord;
 
+#[cfg(feature = "128-bit")]
+use brisc_isa::QuadWord;
+
 #[cfg(feature = "a")]
-use brisc_isa::{AmoFunction, SXWord};
+use brisc_isa::AmoFunction;
 
 /// Execute the Memory pipeline stage.
-pub fn mem_access<M: Memory>(p_reg: &mut PipelineRegister, memory: &mut M) -> PipelineResult<()> {
-    let instruction = p_reg.instruction.ok_or(PipelineError::MissingState("instruction"))?;
+pub fn mem_access<M: Memory, X: Xlen>(
+    p_reg: &mut PipelineRegister<X>,
+    memory: &mut M,
+) -> PipelineResult<()> {
+    let instruction =
+        p_reg.instruction.ok_or(PipelineError::MissingState("instruction"))?.instruction;
     let effective_address = p_reg.alu_result.ok_or(PipelineError::MissingState("alu_result"))?;
 
     match instruction {
@@ -50,10 +57,25 @@ pub fn mem_access<M: Memory>(p_reg: &mut PipelineRegister, memory: &mut M) -> Pi
                 LoadFunction::Lwu => {
                     memory.get_word(effective_address).map_err(PipelineError::MemoryError)? as XWord
                 }
-                #[cfg(feature = "64-bit")]
+                #[cfg(all(feature = "64-bit", not(feature = "128-bit")))]
                 LoadFunction::Ld => {
                     memory.get_doubleword(effective_address).map_err(PipelineError::MemoryError)?
                 }
+                #[cfg(feature = "128-bit")]
+                LoadFunction::Ld => sign_extend(
+                    memory.get_doubleword(effective_address).map_err(PipelineError::MemoryError)?
+                        as XWord,
+                    63,
+                ),
+                #[cfg(feature = "128-bit")]
+                LoadFunction::Ldu => {
+                    memory.get_doubleword(effective_address).map_err(PipelineError::MemoryError)?
+                        as XWord
+                }
+                #[cfg(feature = "128-bit")]
+                LoadFunction::Lq => {
+                    memory.get_quadword(effective_address).map_err(PipelineError::MemoryError)?
+                }
             };
             p_reg.memory = Some(value);
         }
@@ -82,6 +104,12 @@ pub fn mem_access<M: Memory>(p_reg: &mut PipelineRegister, memory: &mut M) -> Pi
                         .set_doubleword(effective_address, value as DoubleWord)
                         .map_err(PipelineError::MemoryError)?;
                 }
+                #[cfg(feature = "128-bit")]
+                StoreFunction::Sq => {
+                    memory
+                        .set_quadword(effective_address, value as QuadWord)
+                        .map_err(PipelineError::MemoryError)?;
+                }
             }
         }
         #[cfg(feature = "a")]
@@ -92,8 +120,9 @@ pub fn mem_access<M: Memory>(p_reg: &mut PipelineRegister, memory: &mut M) -> Pi
                 return Err(PipelineError::BadAmoSize(size));
             }
 
-            let addr = p_reg.rs1_value.ok_or(PipelineError::MissingState("rs1_value"))?;
-            if size == 8 && addr & 7 != 0 || size == 4 && addr & 3 != 0 {
+            let addr =
+                X::truncate(p_reg.rs1_value.ok_or(PipelineError::MissingState("rs1_value"))?);
+            if addr & (size as XWord - 1) != 0 {
                 return Err(PipelineError::UnalignedAmo);
             }
 
@@ -101,8 +130,14 @@ pub fn mem_access<M: Memory>(p_reg: &mut PipelineRegister, memory: &mut M) -> Pi
                 AmoFunction::Lr => {
                     let value = match size {
                         4 => memory.get_word(addr).map_err(PipelineError::MemoryError)? as XWord,
-                        #[cfg(feature = "64-bit")]
+                        #[cfg(all(feature = "64-bit", not(feature = "128-bit")))]
                         8 => memory.get_doubleword(addr).map_err(PipelineError::MemoryError)?,
+                        #[cfg(feature = "128-bit")]
+                        8 => sign_extend(
+                            memory.get_doubleword(addr).map_err(PipelineError::MemoryError)?
+                                as XWord,
+                            63,
+                        ),
                         _ => return Err(PipelineError::BadAmoSize(size)),
                     };
                     p_reg.memory = Some(value);
@@ -121,7 +156,7 @@ pub fn mem_access<M: Memory>(p_reg: &mut PipelineRegister, memory: &mut M) -> Pi
                                     .map_err(PipelineError::MemoryError)?,
                                 #[cfg(feature = "64-bit")]
                                 8 => memory
-                                    .set_doubleword(addr, rs2)
+                                    .set_doubleword(addr, rs2 as DoubleWord)
                                     .map_err(PipelineError::MemoryError)?,
                                 _ => return Err(PipelineError::BadAmoSize(size)),
                             }
@@ -139,7 +174,8 @@ pub fn mem_access<M: Memory>(p_reg: &mut PipelineRegister, memory: &mut M) -> Pi
                     let mut mem = match size {
                         4 => memory.get_word(addr).map_err(PipelineError::MemoryError)? as XWord,
                         #[cfg(feature = "64-bit")]
-                        8 => memory.get_doubleword(addr).map_err(PipelineError::MemoryError)?,
+                        8 => memory.get_doubleword(addr).map_err(PipelineError::MemoryError)?
+                            as XWord,
                         _ => return Err(PipelineError::BadAmoSize(size)),
                     };
 
@@ -149,6 +185,12 @@ pub fn mem_access<M: Memory>(p_reg: &mut PipelineRegister, memory: &mut M) -> Pi
                         mem = sign_extend(mem & 0xFFFF_FFFF, 31);
                     }
 
+                    #[cfg(feature = "128-bit")]
+                    if size == 8 {
+                        rs2 = sign_extend(rs2 & DoubleWord::MAX as XWord, 63);
+                        mem = sign_extend(mem & DoubleWord::MAX as XWord, 63);
+                    }
+
                     p_reg.memory = Some(mem as XWord);
 
                     mem = match instr {
@@ -157,8 +199,8 @@ pub fn mem_access<M: Memory>(p_reg: &mut PipelineRegister, memory: &mut M) -> Pi
                         AmoFunction::Amoxor => mem ^ rs2,
                         AmoFunction::Amoand => mem & rs2,
                         AmoFunction::Amoor => mem | rs2,
-                        AmoFunction::Amomin => (rs2 as SXWord).min(mem as SXWord) as XWord,
-                        AmoFunction::Amomax => (rs2 as SXWord).max(mem as SXWord) as XWord,
+                        AmoFunction::Amomin => X::signed(rs2).min(X::signed(mem)) as XWord,
+                        AmoFunction::Amomax => X::signed(rs2).max(X::signed(mem)) as XWord,
                         AmoFunction::Amominu => rs2.min(mem),
                         AmoFunction::Amomaxu => rs2.max(mem),
                         _ => unreachable!(),
@@ -179,3 +221,37 @@ pub fn mem_access<M: Memory>(p_reg: &mut PipelineRegister, memory: &mut M) -> Pi
 
     Ok(())
 }
+
+#[cfg(test)]
+#[cfg(all(feature = "64-bit", feature = "a"))]
+mod test {
+    use super::mem_access;
+    use crate::{
+        errors::PipelineError,
+        memory::{Memory, SimpleMemory},
+        pipeline::PipelineRegister,
+    };
+    use brisc_isa::{DecodedInstruction, Rv32, XWord};
+
+    /// Performs `amoadd.w a0, a2, (a1)` on an RV32 hart, with `a1` holding `address`.
+    fn amoadd_w(memory: &mut SimpleMemory, address: XWord) -> Result<Option<XWord>, PipelineError> {
+        let mut p_reg = PipelineRegister::<Rv32>::new(0);
+        p_reg.instruction = Some(DecodedInstruction::decode_for::<Rv32>(0x00c5a52f).unwrap());
+        p_reg.rs1_value = Some(address);
+        p_reg.rs2_value = Some(5);
+        p_reg.alu_result = Some(address);
+        mem_access(&mut p_reg, memory).map(|()| p_reg.memory)
+    }
+
+    #[test]
+    fn test_amo_address() {
+        let mut memory = SimpleMemory::default();
+        memory.set_word(0x1000, 2).unwrap();
+
+        // The upper bits of the address are dropped on RV32.
+        assert_eq!(amoadd_w(&mut memory, 0x1_0000_1000).unwrap(), Some(2));
+        assert_eq!(memory.get_word(0x1000).unwrap(), 7);
+
+        assert!(matches!(amoadd_w(&mut memory, 0x1002), Err(PipelineError::UnalignedAmo)));
+    }
+}
@@ -1,14 +1,18 @@
 //! Pipeline registers and control signals.
 
 use crate::memory::Address;
-use brisc_isa::{Instruction, Word, XWord};
+use brisc_isa::{DecodedInstruction, NativeXlen, XWord, Xlen};
+use core::marker::PhantomData;
 
 /// The [PipelineRegister] represents an intermediate state of an instruction's execution within
 /// the CPU pipeline. As the [PipelineRegister] passes through each stage, the type is saturated.
 /// Ultimately, it is discarded after it has made its way through the register write-back stage
 /// via [PipelineRegister::advance].
+///
+/// Register values and addresses are stored zero-extended to [XWord], whatever the register
+/// width `X` of the hart.
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
-pub struct PipelineRegister {
+pub struct PipelineRegister<X = NativeXlen> {
     /// If the last instruction exited the program, this will be `true`.
     pub exit: bool,
     /// The exit code of the program, if it exited.
@@ -19,10 +23,11 @@ pub struct PipelineRegister {
     pub registers: [XWord; 32],
     /// The next program counter.
     pub next_pc: XWord,
-    /// The current instruction (raw).
-    pub instruction_raw: Option<Word>,
-    /// The current instruction (decoded).
-    pub instruction: Option<Instruction>,
+    /// The program counter predicted at fetch for the next instruction, if the instruction is a
+    /// branch and a branch predictor is in use.
+    pub predicted_pc: Option<XWord>,
+    /// The current instruction, along with its raw encoding and length.
+    pub instruction: Option<DecodedInstruction>,
     /// The cached value of the `rs1` register.
     pub rs1_value: Option<XWord>,
     /// The cached value of the `rs2` register.
@@ -38,9 +43,11 @@ pub struct PipelineRegister {
     /// The load reservation address, if any.
     #[cfg(feature = "a")]
     pub reservation: Option<Address>,
+    /// The register width of the hart.
+    pub xlen: PhantomData<X>,
 }
 
-impl PipelineRegister {
+impl<X: Xlen> PipelineRegister<X> {
     /// Creates a new [PipelineRegister] with the given program counter.
     pub fn new(pc: XWord) -> Self {
         Self { pc, ..Default::default() }
@@ -49,7 +56,7 @@ impl PipelineRegister {
     /// Clear the [PipelineRegister] and set the program counter to the next program counter.
     pub fn advance(&mut self) {
         *self = Self {
-            pc: self.next_pc,
+            pc: X::truncate(self.next_pc),
             registers: self.registers,
             #[cfg(feature = "a")]
             reservation: self.reservation,
@@ -60,6 +67,6 @@ impl PipelineRegister {
     /// Computes the effective address of the memory operation if [Self::rs1_value] and
     /// [Self::immediate] are [Some].
     pub fn effective_address(&self) -> Option<Address> {
-        self.rs1_value.and_then(|rs1| self.immediate.map(|imm| rs1.wrapping_add(imm)))
+        self.rs1_value.and_then(|rs1| self.immediate.map(|imm| X::truncate(rs1.wrapping_add(imm))))
     }
 }
@@ -2,9 +2,10 @@
 
 use super::register::PipelineRegister;
 use crate::errors::PipelineResult;
+use brisc_isa::Xlen;
 
-/// Execute the WriteBack pipeline stage.
-pub const fn writeback(p_reg: &mut PipelineRegister) -> PipelineResult<()> {
+/// Execute the WriteBack pipeline stage. Results are truncated to the register width of the hart.
+pub fn writeback<X: Xlen>(p_reg: &mut PipelineRegister<X>) -> PipelineResult<()> {
     if let Some(rd) = p_reg.rd {
         // No-op illegal writes to the zero register.
         if rd == 0 {
@@ -13,9 +14,9 @@ pub const fn writeback(p_reg: &mut PipelineRegister) -> PipelineResult<()> {
 
         // Store the result in the destination register.
         if let Some(mem) = p_reg.memory {
-            p_reg.registers[rd as usize] = mem;
+            p_reg.registers[rd as usize] = X::truncate(mem);
         } else if let Some(alu_result) = p_reg.alu_result {
-            p_reg.registers[rd as usize] = alu_result;
+            p_reg.registers[rd as usize] = X::truncate(alu_result);
         }
     }
 
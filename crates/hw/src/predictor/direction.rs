@@ -0,0 +1,100 @@
+//! Static and counter-based direction predictors.
+
+use super::{pc_index, saturate, Branch, BranchKind, BranchPredictor};
+use alloc::{vec, vec::Vec};
+use brisc_isa::XWord;
+
+/// Predicts that conditional branches are never taken.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StaticNotTaken;
+
+impl BranchPredictor for StaticNotTaken {
+    fn predict(&mut self, branch: &Branch) -> XWord {
+        branch.direction(false)
+    }
+
+    fn update(&mut self, _: &Branch, _: XWord) {}
+}
+
+/// Predicts that backward conditional branches are taken, and forward ones are not, as backward
+/// branches usually close loops.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Btfn;
+
+impl BranchPredictor for Btfn {
+    fn predict(&mut self, branch: &Branch) -> XWord {
+        branch.direction(branch.target().is_some_and(|target| target < branch.pc))
+    }
+
+    fn update(&mut self, _: &Branch, _: XWord) {}
+}
+
+/// Predicts the direction of conditional branches with a table of 2-bit saturating counters,
+/// indexed by the address of the branch.
+#[derive(Debug, Clone)]
+pub struct Bimodal {
+    /// The number of bits of the index into [Self::counters].
+    bits: u32,
+    /// The 2-bit counters. A branch is predicted taken if its counter is 2 or 3.
+    counters: Vec<u8>,
+}
+
+impl Bimodal {
+    /// Creates a new [Bimodal] predictor with `1 << bits` counters, initialized weakly not taken.
+    pub fn new(bits: u32) -> Self {
+        Self { bits, counters: vec![1; 1 << bits] }
+    }
+}
+
+impl BranchPredictor for Bimodal {
+    fn predict(&mut self, branch: &Branch) -> XWord {
+        branch.direction(self.counters[pc_index(branch.pc, self.bits)] >= 2)
+    }
+
+    fn update(&mut self, branch: &Branch, next_pc: XWord) {
+        if let BranchKind::Conditional { .. } = branch.kind {
+            let counter = &mut self.counters[pc_index(branch.pc, self.bits)];
+            *counter = saturate(*counter, next_pc != branch.fallthrough, 2);
+        }
+    }
+}
+
+/// Predicts the direction of conditional branches with a table of 2-bit saturating counters,
+/// indexed by the address of the branch hashed with the global history of branch outcomes.
+#[derive(Debug, Clone)]
+pub struct Gshare {
+    /// The number of bits of the index into [Self::counters], and of the global history.
+    bits: u32,
+    /// The outcomes of the most recent conditional branches, the latest in the lowest bit.
+    history: usize,
+    /// The 2-bit counters. A branch is predicted taken if its counter is 2 or 3.
+    counters: Vec<u8>,
+}
+
+impl Gshare {
+    /// Creates a new [Gshare] predictor with `1 << bits` counters, initialized weakly not taken,
+    /// and `bits` bits of global history.
+    pub fn new(bits: u32) -> Self {
+        Self { bits, history: 0, counters: vec![1; 1 << bits] }
+    }
+
+    /// Returns the index of the counter of `branch` under the current global history.
+    const fn index(&self, branch: &Branch) -> usize {
+        pc_index(branch.pc, self.bits) ^ (self.history & ((1 << self.bits) - 1))
+    }
+}
+
+impl BranchPredictor for Gshare {
+    fn predict(&mut self, branch: &Branch) -> XWord {
+        branch.direction(self.counters[self.index(branch)] >= 2)
+    }
+
+    fn update(&mut self, branch: &Branch, next_pc: XWord) {
+        if let BranchKind::Conditional { .. } = branch.kind {
+            let taken = next_pc != branch.fallthrough;
+            let index = self.index(branch);
+            self.counters[index] = saturate(self.counters[index], taken, 2);
+            self.history = (self.history << 1) | taken as usize;
+        }
+    }
+}
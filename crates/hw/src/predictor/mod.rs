@@ -0,0 +1,292 @@
+//! Branch predictors, consulted at fetch.
+//!
+//! A [BranchPredictor] predicts the address of the instruction that follows a control-flow
+//! instruction. Fetch decodes instructions immediately, so the targets of conditional branches and
+//! `jal` are known when they are predicted, and only the targets of `jalr` must be remembered.
+//!
+//! The direction predictors ([StaticNotTaken], [Btfn], [Bimodal], [Gshare] and [TageLite]) predict
+//! `jalr` as not taken. The [Btb] and [ReturnStack] wrap another predictor to predict their
+//! targets, e.g. `ReturnStack::new(Btb::new(Gshare::new(12), 8), 16)`.
+
+use crate::pipeline::PipelineRegister;
+use alloc::{boxed::Box, collections::BTreeMap};
+use brisc_isa::{Instruction, XWord, Xlen, REG_RA, REG_ZERO};
+use core::fmt::Debug;
+
+mod direction;
+pub use direction::{Bimodal, Btfn, Gshare, StaticNotTaken};
+
+mod tage;
+pub use tage::TageLite;
+
+mod target;
+pub use target::{Btb, ReturnStack};
+
+/// A branch predictor, consulted at fetch for every control-flow instruction.
+pub trait BranchPredictor: Debug {
+    /// Predicts the address of the instruction that follows `branch`.
+    fn predict(&mut self, branch: &Branch) -> XWord;
+
+    /// Trains the predictor with the address of the instruction that followed `branch`, once it
+    /// has been resolved in the execute stage.
+    fn update(&mut self, branch: &Branch, next_pc: XWord);
+}
+
+impl<P: BranchPredictor + ?Sized> BranchPredictor for Box<P> {
+    fn predict(&mut self, branch: &Branch) -> XWord {
+        (**self).predict(branch)
+    }
+
+    fn update(&mut self, branch: &Branch, next_pc: XWord) {
+        (**self).update(branch, next_pc)
+    }
+}
+
+/// A control-flow instruction, as seen by a [BranchPredictor].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Branch {
+    /// The address of the instruction.
+    pub pc: XWord,
+    /// The address of the instruction that sequentially follows it.
+    pub fallthrough: XWord,
+    /// The kind of the control-flow instruction.
+    pub kind: BranchKind,
+}
+
+/// The kind of a control-flow instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchKind {
+    /// A conditional branch to `target`.
+    Conditional {
+        /// The address that the branch jumps to if it is taken.
+        target: XWord,
+    },
+    /// A `jal` to `target`.
+    Jump {
+        /// The address that the jump lands at.
+        target: XWord,
+        /// Whether the jump is a call, linking the return address in `ra`.
+        call: bool,
+    },
+    /// A `jalr`, whose target is only known once it is executed.
+    Indirect {
+        /// Whether the jump is a call, linking the return address in `ra`.
+        call: bool,
+        /// Whether the jump is a return to the address in `ra`.
+        ret: bool,
+    },
+}
+
+impl Branch {
+    /// Returns the [Branch] for the instruction fetched into `p_reg`, or [None] if it is not a
+    /// control-flow instruction.
+    pub fn of<X: Xlen>(p_reg: &PipelineRegister<X>) -> Option<Self> {
+        let decoded = p_reg.instruction?;
+        let pc = p_reg.pc;
+        let target = |imm: XWord| X::truncate(pc.wrapping_add(imm));
+        let kind = match decoded.instruction {
+            Instruction::Branch(b_type, _) => {
+                BranchKind::Conditional { target: target(b_type.imm) }
+            }
+            Instruction::Jal(j_type) => {
+                BranchKind::Jump { target: target(j_type.imm), call: j_type.rd == REG_RA as u8 }
+            }
+            Instruction::Jalr(i_type) => BranchKind::Indirect {
+                call: i_type.rd == REG_RA as u8,
+                ret: i_type.rs1 == REG_RA as u8 && i_type.rd == REG_ZERO as u8,
+            },
+            _ => return None,
+        };
+
+        Some(Self { pc, fallthrough: X::truncate(pc + decoded.size() as XWord), kind })
+    }
+
+    /// Returns the target of the branch if it is taken, if it is known at fetch.
+    pub const fn target(&self) -> Option<XWord> {
+        match self.kind {
+            BranchKind::Conditional { target } | BranchKind::Jump { target, .. } => Some(target),
+            BranchKind::Indirect { .. } => None,
+        }
+    }
+
+    /// Returns the address that a direction predictor predicts for the branch: `target` if
+    /// `taken` for conditional branches, the target of `jal`, and the fallthrough of `jalr`.
+    pub const fn direction(&self, taken: bool) -> XWord {
+        match self.kind {
+            BranchKind::Conditional { target } if taken => target,
+            BranchKind::Jump { target, .. } => target,
+            _ => self.fallthrough,
+        }
+    }
+}
+
+/// The prediction statistics of a static branch.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BranchStats {
+    /// The number of times the branch was predicted.
+    pub predictions: u64,
+    /// The number of times the prediction was wrong.
+    pub mispredictions: u64,
+}
+
+impl BranchStats {
+    /// Returns the fraction of the predictions of the branch that were wrong, or 0 if the branch
+    /// was never predicted.
+    pub fn misprediction_rate(&self) -> f64 {
+        if self.predictions == 0 {
+            return 0.0;
+        }
+        self.mispredictions as f64 / self.predictions as f64
+    }
+}
+
+/// A [BranchPredictor], along with the outcome of its predictions for every static branch.
+#[derive(Debug)]
+pub struct BranchUnit {
+    /// The branch predictor.
+    pub predictor: Box<dyn BranchPredictor>,
+    /// The prediction statistics of every static branch, by address.
+    pub branches: BTreeMap<XWord, BranchStats>,
+}
+
+impl BranchUnit {
+    /// Creates a new [BranchUnit] around `predictor`.
+    pub fn new(predictor: impl BranchPredictor + 'static) -> Self {
+        Self { predictor: Box::new(predictor), branches: BTreeMap::new() }
+    }
+
+    /// Predicts the address of the instruction following the one fetched into `p_reg`, and
+    /// stores it in [PipelineRegister::predicted_pc] if it is a control-flow instruction.
+    pub fn predict<X: Xlen>(&mut self, p_reg: &mut PipelineRegister<X>) {
+        p_reg.predicted_pc =
+            Branch::of(p_reg).map(|branch| X::truncate(self.predictor.predict(&branch)));
+    }
+
+    /// Compares the prediction for the executed instruction in `p_reg` with its resolved
+    /// [PipelineRegister::next_pc], and trains the predictor. Returns `true` if the instruction was
+    /// mispredicted.
+    pub fn resolve<X: Xlen>(&mut self, p_reg: &PipelineRegister<X>) -> bool {
+        let (Some(predicted_pc), Some(branch)) = (p_reg.predicted_pc, Branch::of(p_reg)) else {
+            return false;
+        };
+        let next_pc = X::truncate(p_reg.next_pc);
+        self.predictor.update(&branch, next_pc);
+
+        let mispredicted = predicted_pc != next_pc;
+        let stats = self.branches.entry(branch.pc).or_default();
+        stats.predictions += 1;
+        stats.mispredictions += mispredicted as u64;
+        mispredicted
+    }
+
+    /// Returns the prediction statistics of all static branches combined.
+    pub fn total(&self) -> BranchStats {
+        self.branches.values().fold(BranchStats::default(), |total, stats| BranchStats {
+            predictions: total.predictions + stats.predictions,
+            mispredictions: total.mispredictions + stats.mispredictions,
+        })
+    }
+}
+
+/// Increments a saturating counter of `bits` bits if `up`, and decrements it otherwise.
+const fn saturate(counter: u8, up: bool, bits: u32) -> u8 {
+    if up {
+        if counter < (1 << bits) - 1 {
+            counter + 1
+        } else {
+            counter
+        }
+    } else {
+        counter.saturating_sub(1)
+    }
+}
+
+/// Returns the index of `pc` in a table of `1 << bits` entries. Instructions are at least two
+/// bytes long, so the lowest bit of `pc` is discarded.
+const fn pc_index(pc: XWord, bits: u32) -> usize {
+    ((pc >> 1) as usize) & ((1 << bits) - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    /// A conditional branch at `0x100` jumping backwards to `0x80`.
+    const LOOP: Branch =
+        Branch { pc: 0x100, fallthrough: 0x104, kind: BranchKind::Conditional { target: 0x80 } };
+
+    /// Returns the number of mispredictions of `predictor` over `outcomes` of [LOOP].
+    fn mispredictions(predictor: &mut dyn BranchPredictor, outcomes: &[bool]) -> usize {
+        outcomes
+            .iter()
+            .filter(|&&taken| {
+                let next_pc = LOOP.direction(taken);
+                let predicted = predictor.predict(&LOOP);
+                predictor.update(&LOOP, next_pc);
+                predicted != next_pc
+            })
+            .count()
+    }
+
+    /// A loop of 4 iterations, run 64 times.
+    fn loop_pattern() -> alloc::vec::Vec<bool> {
+        (0..256).map(|i| i % 4 != 3).collect()
+    }
+
+    #[test]
+    fn test_misprediction_rate() {
+        assert_eq!(BranchStats::default().misprediction_rate(), 0.0);
+        let stats = BranchStats { predictions: 4, mispredictions: 1 };
+        assert_eq!(stats.misprediction_rate(), 0.25);
+    }
+
+    #[rstest]
+    #[case::static_not_taken(Box::new(StaticNotTaken), 192)]
+    #[case::btfn(Box::new(Btfn), 64)]
+    #[case::bimodal(Box::new(Bimodal::new(10)), 65)]
+    fn test_loop_mispredictions(
+        #[case] mut predictor: Box<dyn BranchPredictor>,
+        #[case] expected: usize,
+    ) {
+        assert_eq!(mispredictions(predictor.as_mut(), &loop_pattern()), expected);
+    }
+
+    #[rstest]
+    #[case::gshare(Box::new(Gshare::new(10)))]
+    #[case::tage_lite(Box::new(TageLite::new()))]
+    fn test_history_learns_loop(#[case] mut predictor: Box<dyn BranchPredictor>) {
+        // With the global history, the exit of the loop becomes predictable once trained.
+        let outcomes = loop_pattern();
+        mispredictions(predictor.as_mut(), &outcomes);
+        assert_eq!(mispredictions(predictor.as_mut(), &outcomes), 0);
+    }
+
+    #[test]
+    fn test_target_predictors() {
+        let call = Branch {
+            pc: 0x200,
+            fallthrough: 0x204,
+            kind: BranchKind::Jump { target: 0x400, call: true },
+        };
+        let ret = Branch {
+            pc: 0x410,
+            fallthrough: 0x412,
+            kind: BranchKind::Indirect { call: false, ret: true },
+        };
+        let indirect = Branch {
+            pc: 0x300,
+            fallthrough: 0x304,
+            kind: BranchKind::Indirect { call: true, ret: false },
+        };
+        let mut predictor = ReturnStack::new(Btb::new(StaticNotTaken, 4), 4);
+
+        // The return address is pushed by the call, and the target of `jalr` is learned.
+        assert_eq!(predictor.predict(&call), 0x400);
+        assert_eq!(predictor.predict(&ret), 0x204);
+        assert_eq!(predictor.predict(&indirect), 0x304);
+        predictor.update(&indirect, 0x800);
+        assert_eq!(predictor.predict(&indirect), 0x800);
+        assert_eq!(predictor.predict(&ret), 0x304);
+    }
+}
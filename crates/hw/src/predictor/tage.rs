@@ -0,0 +1,154 @@
+//! A reduced TAGE (TAgged GEometric history length) direction predictor.
+
+use super::{pc_index, saturate, Bimodal, Branch, BranchKind, BranchPredictor};
+use alloc::{vec, vec::Vec};
+use brisc_isa::XWord;
+
+/// The lengths of the global history used by the tagged tables, shortest first.
+const HISTORY_LENGTHS: [u32; 4] = [4, 8, 16, 32];
+
+/// The number of bits of the index into each tagged table.
+const INDEX_BITS: u32 = 10;
+
+/// The number of bits of the tags of the tagged tables.
+const TAG_BITS: u32 = 9;
+
+/// An entry of a tagged table.
+#[derive(Debug, Default, Clone, Copy)]
+struct TageEntry {
+    /// The partial tag of the branch and history that allocated the entry.
+    tag: u16,
+    /// A 3-bit counter. The branch is predicted taken if it is 4 or more.
+    counter: u8,
+    /// A 2-bit counter of how useful the entry has been. Entries that are not useful may be
+    /// replaced.
+    useful: u8,
+}
+
+/// Predicts the direction of conditional branches with a [Bimodal] base predictor and four tagged
+/// tables, indexed by the address of the branch hashed with geometrically increasing lengths of
+/// global history.
+///
+/// The prediction comes from the tagged table with the longest history whose entry matches the
+/// branch, or from the base predictor if none does. On a misprediction, an entry is allocated in a
+/// table with a longer history than the one that provided the prediction.
+#[derive(Debug, Clone)]
+pub struct TageLite {
+    /// The base predictor.
+    base: Bimodal,
+    /// The tagged tables, by increasing history length.
+    tables: [Vec<TageEntry>; 4],
+    /// The outcomes of the most recent conditional branches, the latest in the lowest bit.
+    history: u64,
+}
+
+impl Default for TageLite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TageLite {
+    /// Creates a new [TageLite] predictor.
+    pub fn new() -> Self {
+        Self {
+            base: Bimodal::new(12),
+            tables: core::array::from_fn(|_| vec![TageEntry::default(); 1 << INDEX_BITS]),
+            history: 0,
+        }
+    }
+
+    /// Returns the index and tag of `branch` in the tagged table `table`.
+    const fn index_tag(&self, branch: &Branch, table: usize) -> (usize, u16) {
+        let length = HISTORY_LENGTHS[table];
+        let index = pc_index(branch.pc, INDEX_BITS) ^ fold(self.history, length, INDEX_BITS);
+        let tag = (pc_index(branch.pc, TAG_BITS) ^
+            fold(self.history, length, TAG_BITS - 1) ^
+            (table << 1)) &
+            ((1 << TAG_BITS) - 1);
+        // The top bit marks the entry as allocated, as empty entries are zeroed.
+        (index, tag as u16 | 1 << TAG_BITS)
+    }
+
+    /// Returns the longest tagged table whose entry matches `branch`, along with the index of the
+    /// entry.
+    fn provider(&self, branch: &Branch) -> Option<(usize, usize)> {
+        (0..self.tables.len()).rev().find_map(|table| {
+            let (index, tag) = self.index_tag(branch, table);
+            (self.tables[table][index].tag == tag).then_some((table, index))
+        })
+    }
+
+    /// Returns whether `branch` is predicted taken, from the provider if any, or the base
+    /// predictor.
+    fn taken(&mut self, branch: &Branch) -> bool {
+        match self.provider(branch) {
+            Some((table, index)) => self.tables[table][index].counter >= 4,
+            None => self.base.predict(branch) != branch.fallthrough,
+        }
+    }
+}
+
+impl BranchPredictor for TageLite {
+    fn predict(&mut self, branch: &Branch) -> XWord {
+        let taken = self.taken(branch);
+        branch.direction(taken)
+    }
+
+    fn update(&mut self, branch: &Branch, next_pc: XWord) {
+        let BranchKind::Conditional { .. } = branch.kind else {
+            return;
+        };
+        let taken = next_pc != branch.fallthrough;
+        let predicted = self.taken(branch);
+
+        let provider = self.provider(branch);
+        match provider {
+            Some((table, index)) => {
+                let alternate = self.base.predict(branch) != branch.fallthrough;
+                let entry = &mut self.tables[table][index];
+                if predicted != alternate {
+                    entry.useful = saturate(entry.useful, predicted == taken, 2);
+                }
+                entry.counter = saturate(entry.counter, taken, 3);
+            }
+            None => self.base.update(branch, next_pc),
+        }
+
+        // Allocate an entry with a longer history, or age the candidates if none is free.
+        if predicted != taken {
+            let longer = provider.map_or(0, |(table, _)| table + 1)..self.tables.len();
+            let free = longer.clone().find(|&table| {
+                let (index, _) = self.index_tag(branch, table);
+                self.tables[table][index].useful == 0
+            });
+            match free {
+                Some(table) => {
+                    let (index, tag) = self.index_tag(branch, table);
+                    self.tables[table][index] =
+                        TageEntry { tag, counter: if taken { 4 } else { 3 }, useful: 0 };
+                }
+                None => {
+                    for table in longer {
+                        let (index, _) = self.index_tag(branch, table);
+                        let entry = &mut self.tables[table][index];
+                        entry.useful = entry.useful.saturating_sub(1);
+                    }
+                }
+            }
+        }
+
+        self.history = (self.history << 1) | taken as u64;
+    }
+}
+
+/// Folds the latest `length` bits of `history` into `bits` bits by XOR.
+const fn fold(history: u64, length: u32, bits: u32) -> usize {
+    let mut history = history & ((1 << length) - 1);
+    let mut folded = 0;
+    while history != 0 {
+        folded ^= history & ((1 << bits) - 1);
+        history >>= bits;
+    }
+    folded as usize
+}
@@ -0,0 +1,86 @@
+//! Target predictors for `jalr`, wrapping a direction predictor.
+
+use super::{pc_index, Branch, BranchKind, BranchPredictor};
+use alloc::{collections::VecDeque, vec, vec::Vec};
+use brisc_isa::XWord;
+
+/// A direct-mapped branch target buffer, predicting the target of `jalr` from its last target.
+/// Every other prediction is left to the wrapped predictor.
+#[derive(Debug, Clone)]
+pub struct Btb<P> {
+    /// The wrapped predictor.
+    inner: P,
+    /// The number of bits of the index into [Self::entries].
+    bits: u32,
+    /// The address and the last target of the `jalr` that last used each entry.
+    entries: Vec<Option<(XWord, XWord)>>,
+}
+
+impl<P: BranchPredictor> Btb<P> {
+    /// Creates a new [Btb] with `1 << bits` entries around `inner`.
+    pub fn new(inner: P, bits: u32) -> Self {
+        Self { inner, bits, entries: vec![None; 1 << bits] }
+    }
+}
+
+impl<P: BranchPredictor> BranchPredictor for Btb<P> {
+    fn predict(&mut self, branch: &Branch) -> XWord {
+        let predicted = self.inner.predict(branch);
+        match (branch.kind, self.entries[pc_index(branch.pc, self.bits)]) {
+            (BranchKind::Indirect { .. }, Some((pc, target))) if pc == branch.pc => target,
+            _ => predicted,
+        }
+    }
+
+    fn update(&mut self, branch: &Branch, next_pc: XWord) {
+        if let BranchKind::Indirect { .. } = branch.kind {
+            self.entries[pc_index(branch.pc, self.bits)] = Some((branch.pc, next_pc));
+        }
+        self.inner.update(branch, next_pc);
+    }
+}
+
+/// A return-address stack, pushing the return address of calls and predicting that returns jump
+/// to the latest one. Calls and returns are recognized by their use of `ra`, and every other
+/// prediction is left to the wrapped predictor.
+///
+/// The stack is updated as branches are predicted, so calls and returns fetched down a
+/// mispredicted path may leave it out of sync. When it is full, the oldest return address is
+/// dropped.
+#[derive(Debug, Clone)]
+pub struct ReturnStack<P> {
+    /// The wrapped predictor.
+    inner: P,
+    /// The maximum number of return addresses.
+    depth: usize,
+    /// The return addresses, the latest at the back.
+    stack: VecDeque<XWord>,
+}
+
+impl<P: BranchPredictor> ReturnStack<P> {
+    /// Creates a new [ReturnStack] of `depth` return addresses around `inner`.
+    pub fn new(inner: P, depth: usize) -> Self {
+        Self { inner, depth, stack: VecDeque::with_capacity(depth) }
+    }
+}
+
+impl<P: BranchPredictor> BranchPredictor for ReturnStack<P> {
+    fn predict(&mut self, branch: &Branch) -> XWord {
+        let predicted = self.inner.predict(branch);
+        match branch.kind {
+            BranchKind::Jump { call: true, .. } | BranchKind::Indirect { call: true, .. } => {
+                self.stack.push_back(branch.fallthrough);
+                if self.stack.len() > self.depth {
+                    self.stack.pop_front();
+                }
+                predicted
+            }
+            BranchKind::Indirect { ret: true, .. } => self.stack.pop_back().unwrap_or(predicted),
+            _ => predicted,
+        }
+    }
+
+    fn update(&mut self, branch: &Branch, next_pc: XWord) {
+        self.inner.update(branch, next_pc);
+    }
+}
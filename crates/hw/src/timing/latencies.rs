@@ -0,0 +1,233 @@
+//! Per-instruction-class latencies, and presets approximating in-order cores.
+
+use crate::pipeline::PipelineRegister;
+use brisc_isa::{InstructionClass, XWord, Xlen};
+
+#[cfg(feature = "m")]
+use brisc_isa::{Extension, Instruction, X_LEN};
+
+/// The number of cycles that an instruction of each class takes to retire.
+///
+/// Division is iterative on most cores. With [Self::div_early_out], a division only takes as many
+/// of the [Self::div] cycles as its dividend has significant bits, and a division by zero
+/// completes in a single cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Latencies {
+    /// Integer arithmetic, logic and upper immediate instructions.
+    pub alu: u32,
+    /// Multiplications.
+    pub mul: u32,
+    /// Divisions and remainders with a full-width dividend.
+    pub div: u32,
+    /// Whether divisions finish early on small dividends and zero divisors.
+    pub div_early_out: bool,
+    /// Memory loads.
+    pub load: u32,
+    /// Memory stores.
+    pub store: u32,
+    /// Atomic memory operations, including `lr` and `sc`.
+    pub amo: u32,
+    /// Conditional branches that are not taken.
+    pub branch: u32,
+    /// Conditional branches that are taken.
+    pub taken_branch: u32,
+    /// Unconditional jumps (`jal` and `jalr`).
+    pub jump: u32,
+    /// Environment calls and breakpoints.
+    pub system: u32,
+    /// Memory ordering fences.
+    pub fence: u32,
+}
+
+impl Default for Latencies {
+    fn default() -> Self {
+        Self::SINGLE_CYCLE
+    }
+}
+
+impl Latencies {
+    /// Every instruction takes a single cycle, as on the single-cycle hart.
+    pub const SINGLE_CYCLE: Self = Self {
+        alu: 1,
+        mul: 1,
+        div: 1,
+        div_early_out: false,
+        load: 1,
+        store: 1,
+        amo: 1,
+        branch: 1,
+        taken_branch: 1,
+        jump: 1,
+        system: 1,
+        fence: 1,
+    };
+
+    /// Approximates PicoRV32, a size-optimized multi-cycle core, with the PCPI multiplier and
+    /// divider. `jal` takes 3 cycles and `jalr` 6, which are both modelled as 3.
+    pub const PICORV32: Self = Self {
+        alu: 3,
+        mul: 40,
+        div: 40,
+        div_early_out: false,
+        load: 5,
+        store: 5,
+        amo: 5,
+        branch: 3,
+        taken_branch: 5,
+        jump: 3,
+        system: 4,
+        fence: 3,
+    };
+
+    /// Approximates the CV32E40P, a 4-stage in-order core, with a single-cycle multiplier and a
+    /// 3 to 35 cycle divider.
+    pub const CV32E40P: Self = Self {
+        alu: 1,
+        mul: 1,
+        div: 35,
+        div_early_out: true,
+        load: 1,
+        store: 1,
+        amo: 2,
+        branch: 1,
+        taken_branch: 3,
+        jump: 2,
+        system: 4,
+        fence: 2,
+    };
+
+    /// Approximates Rocket, a 5-stage in-order core, with its default iterative multiplier and
+    /// divider. Jumps are assumed to be predicted by its branch target buffer.
+    pub const ROCKET: Self = Self {
+        alu: 1,
+        mul: 8,
+        div: 64,
+        div_early_out: true,
+        load: 2,
+        store: 1,
+        amo: 4,
+        branch: 1,
+        taken_branch: 3,
+        jump: 1,
+        system: 5,
+        fence: 3,
+    };
+
+    /// Returns the latency of the executed instruction in `p_reg`.
+    pub fn of<X: Xlen>(&self, p_reg: &PipelineRegister<X>) -> u32 {
+        let Some(decoded) = p_reg.instruction else {
+            return 0;
+        };
+
+        match decoded.instruction.class() {
+            InstructionClass::Alu => {
+                #[cfg(feature = "m")]
+                match mul_div::<X>(&decoded.instruction) {
+                    Some(MulDiv::Mul) => return self.mul,
+                    Some(MulDiv::Div { width, signed }) => {
+                        let dividend = p_reg.rs1_value.unwrap_or_default();
+                        let divisor = p_reg.rs2_value.unwrap_or_default();
+                        return self.div_latency(dividend, divisor, width, signed);
+                    }
+                    None => {}
+                }
+                self.alu
+            }
+            InstructionClass::Branch => {
+                let fallthrough = X::truncate(p_reg.pc + decoded.size() as XWord);
+                if X::truncate(p_reg.next_pc) == fallthrough {
+                    self.branch
+                } else {
+                    self.taken_branch
+                }
+            }
+            InstructionClass::Jump => self.jump,
+            InstructionClass::Load => self.load,
+            InstructionClass::Store => self.store,
+            InstructionClass::Amo => self.amo,
+            InstructionClass::Fence => self.fence,
+            InstructionClass::System => self.system,
+        }
+    }
+
+    /// Returns the latency of a `width` bits wide division of `dividend` by `divisor`.
+    #[cfg(feature = "m")]
+    fn div_latency(&self, dividend: XWord, divisor: XWord, width: u32, signed: bool) -> u32 {
+        if !self.div_early_out {
+            return self.div;
+        }
+
+        let mask = XWord::MAX >> (X_LEN as u32 - width);
+        if divisor & mask == 0 {
+            return 1;
+        }
+        let mut magnitude = dividend & mask;
+        if signed && (magnitude >> (width - 1)) & 1 == 1 {
+            magnitude = magnitude.wrapping_neg() & mask;
+        }
+
+        let bits = (XWord::BITS - magnitude.leading_zeros()) as u64;
+        (self.div as u64 * bits).div_ceil(width as u64).max(1) as u32
+    }
+}
+
+/// An operation of the M extension.
+#[cfg(feature = "m")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MulDiv {
+    /// A multiplication.
+    Mul,
+    /// A division or remainder on `width` bits.
+    Div {
+        /// The width of the operands, in bits.
+        width: u32,
+        /// Whether the operands are signed.
+        signed: bool,
+    },
+}
+
+/// Returns the M extension operation performed by `instruction`, if any.
+#[cfg(feature = "m")]
+pub(crate) fn mul_div<X: Xlen>(instruction: &Instruction) -> Option<MulDiv> {
+    let (r_type, width) = match instruction {
+        Instruction::RegisterArithmetic(r_type, _) => (r_type, X::X_LEN as u32),
+        #[cfg(feature = "64-bit")]
+        Instruction::RegisterArithmeticWord(r_type, _) => (r_type, 32),
+        #[cfg(feature = "128-bit")]
+        Instruction::RegisterArithmeticDouble(r_type, _) => (r_type, 64),
+        _ => return None,
+    };
+    if instruction.extension() != Extension::M {
+        return None;
+    }
+
+    // The multiplications have the lower `funct3` values, and the signed divisions and remainders
+    // the even upper ones.
+    if r_type.funct3 < 4 {
+        return Some(MulDiv::Mul);
+    }
+    Some(MulDiv::Div { width, signed: r_type.funct3 & 1 == 0 })
+}
+
+#[cfg(all(test, feature = "m"))]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::zero_divisor(100, 0, false, 1)]
+    #[case::small(100, 7, false, 8)]
+    #[case::full(0xFFFF_FFFF, 7, false, 35)]
+    #[case::negative(0xFFFF_FFFF, 7, true, 2)]
+    #[case::upper_bits_ignored(0xFFFF_FFFF_0000_0003, 7, false, 3)]
+    fn test_div_early_out(
+        #[case] dividend: u64,
+        #[case] divisor: XWord,
+        #[case] signed: bool,
+        #[case] expected: u32,
+    ) {
+        // A 32-bit division on the CV32E40P, which takes up to 35 cycles.
+        let latencies = Latencies::CV32E40P;
+        assert_eq!(latencies.div_latency(dividend as XWord, divisor, 32, signed), expected);
+    }
+}
@@ -0,0 +1,128 @@
+//! An additive timing model, estimating the cycles of a run from per-instruction-class latencies.
+//!
+//! Every retired instruction costs the latency of its class in the [Latencies] of the model,
+//! without overlap between instructions. The cycles are accumulated for the whole run, for every
+//! basic block, and for every function if the symbols of the program are known. With a
+//! [FusionDetector], the second instruction of a fused pair only costs the cycles by which it
+//! outlasts the first, so that the pair takes a single slot.
+
+use crate::{fusion::FusionDetector, pipeline::PipelineRegister};
+use alloc::{collections::BTreeMap, string::String};
+use brisc_isa::{InstructionClass, XWord, Xlen};
+
+mod latencies;
+pub use latencies::Latencies;
+
+#[cfg(feature = "m")]
+pub(crate) use latencies::{mul_div, MulDiv};
+
+/// The instructions and estimated cycles spent in a region of code.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RegionStats {
+    /// The number of times that execution entered the region at its first instruction.
+    pub entries: u64,
+    /// The number of instructions retired in the region.
+    pub instructions: u64,
+    /// The estimated number of cycles spent in the region.
+    pub cycles: u64,
+}
+
+impl RegionStats {
+    /// Adds the instructions and cycles of `other` to the region.
+    const fn add(&mut self, other: &Self) {
+        self.entries += other.entries;
+        self.instructions += other.instructions;
+        self.cycles += other.cycles;
+    }
+}
+
+/// Estimates the cycles taken by the instructions retired by a hart.
+///
+/// Basic blocks are delimited dynamically: a block starts at the first instruction retired after
+/// a branch, a jump or a system instruction, and is keyed by the address of that instruction.
+/// Functions are the regions between consecutive symbols given to [Self::with_symbols], and are
+/// keyed by the address of their symbol.
+#[derive(Debug, Clone, Default)]
+pub struct TimingModel {
+    /// The latencies of the instruction classes.
+    pub latencies: Latencies,
+    /// The estimated number of cycles of the run.
+    pub cycles: u64,
+    /// The number of instructions retired.
+    pub instructions: u64,
+    /// The statistics of every basic block, by the address of its first instruction.
+    pub blocks: BTreeMap<XWord, RegionStats>,
+    /// The statistics of every function, by the address of its symbol.
+    pub functions: BTreeMap<XWord, RegionStats>,
+    /// The function symbols of the program, by address.
+    pub symbols: BTreeMap<XWord, String>,
+    /// The detector of fused pairs, if fusion is modeled.
+    pub fusion: Option<FusionDetector>,
+    /// The latency of the last instruction retired.
+    last_latency: u32,
+    /// The basic block being executed, and its statistics so far.
+    block: Option<(XWord, RegionStats)>,
+}
+
+impl TimingModel {
+    /// Creates a new [TimingModel] with the given [Latencies].
+    pub fn new(latencies: Latencies) -> Self {
+        Self { latencies, ..Default::default() }
+    }
+
+    /// Assigns the function symbols of the program, to accumulate cycles per function.
+    pub fn with_symbols(mut self, symbols: BTreeMap<XWord, String>) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    /// Models macro-op fusion, counting the fused pairs with `fusion`.
+    pub fn with_fusion(mut self, fusion: FusionDetector) -> Self {
+        self.fusion = Some(fusion);
+        self
+    }
+
+    /// Returns the name of the function containing `pc`, if any.
+    pub fn function_name(&self, pc: XWord) -> Option<&str> {
+        self.symbols.range(..=pc).next_back().map(|(_, name)| name.as_str())
+    }
+
+    /// Accounts for the instruction in `p_reg`, once it has been executed. Returns the cycles it
+    /// was charged, which are fewer than its latency if it fused with the previous instruction.
+    pub fn retire<X: Xlen>(&mut self, p_reg: &PipelineRegister<X>) -> u32 {
+        let Some(decoded) = p_reg.instruction else {
+            return 0;
+        };
+        let full_latency = self.latencies.of(p_reg);
+        let fused = self.fusion.as_mut().and_then(|fusion| fusion.observe(p_reg)).is_some();
+        let latency =
+            if fused { full_latency.saturating_sub(self.last_latency) } else { full_latency };
+        self.last_latency = full_latency;
+        self.cycles += latency as u64;
+        self.instructions += 1;
+
+        let (_, block) =
+            self.block.get_or_insert((p_reg.pc, RegionStats { entries: 1, ..Default::default() }));
+        block.instructions += 1;
+        block.cycles += latency as u64;
+
+        let class = decoded.instruction.class();
+        if decoded.instruction.is_control_flow() || class == InstructionClass::System {
+            self.flush();
+        }
+        latency
+    }
+
+    /// Ends the current basic block, adding its statistics to its block and function.
+    pub fn flush(&mut self) {
+        let Some((start, block)) = self.block.take() else {
+            return;
+        };
+        self.blocks.entry(start).or_default().add(&block);
+
+        if let Some((&function, _)) = self.symbols.range(..=start).next_back() {
+            let entries = if function == start { block.entries } else { 0 };
+            self.functions.entry(function).or_default().add(&RegionStats { entries, ..block });
+        }
+    }
+}
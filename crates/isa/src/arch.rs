@@ -33,7 +33,19 @@ pub type DoubleWord = u64;
 pub type QuadWord = u128;
 
 cfg_if! {
-    if #[cfg(feature = "64-bit")] {
+    if #[cfg(feature = "128-bit")] {
+        /// X-LEN describes the width of the architecture's word.
+        pub const X_LEN: usize = 128;
+
+        /// A mask for the shift amount.
+        pub const SHIFT_MASK: XWord = 0x7F;
+
+        /// A type alias for a value that is the width of the architecture's word (128-bit).
+        pub type XWord = QuadWord;
+
+        /// A type alias for a signed value that is the width of the architecture's word (128-bit).
+        pub type SXWord = i128;
+    } else if #[cfg(feature = "64-bit")] {
         /// X-LEN describes the width of the architecture's word.
         pub const X_LEN: usize = 64;
 
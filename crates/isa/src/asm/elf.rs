@@ -0,0 +1,333 @@
+//! A minimal ELF writer for assembled [Program]s.
+
+use super::{Program, Section};
+use crate::{DoubleWord, XWord, Xlen};
+use alloc::vec::Vec;
+
+/// The `e_machine` value for RISC-V.
+const EM_RISCV: u16 = 243;
+/// The `e_type` value for executable files.
+const ET_EXEC: u16 = 2;
+/// The `p_type` value for loadable segments.
+const PT_LOAD: u32 = 1;
+/// The alignment of loadable segments, both in the file and in memory.
+const SEGMENT_ALIGN: u64 = 0x1000;
+
+/// Section header types.
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+
+/// Section header flags.
+const SHF_WRITE: u64 = 0x1;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+
+/// Symbol bindings and types.
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+/// The section header indices of the sections written by [write].
+const SHN_TEXT: u16 = 1;
+const SHN_DATA: u16 = 2;
+const SHN_STRTAB: u32 = 4;
+
+/// The section header string table.
+const SHSTRTAB: &[u8] = b"\0.text\0.data\0.symtab\0.strtab\0.shstrtab\0";
+
+/// A little-endian byte buffer that writes addresses and offsets at the width of the ELF class.
+struct Writer {
+    /// The output bytes.
+    bytes: Vec<u8>,
+    /// Whether the file is `ELFCLASS64`.
+    wide: bool,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Writes an address, offset or size field.
+    fn word(&mut self, value: impl Into<u64>) {
+        let value = value.into();
+        if self.wide {
+            self.bytes.extend_from_slice(&value.to_le_bytes());
+        } else {
+            self.u32(value as u32);
+        }
+    }
+
+    /// Writes an address field. RV128 programs are written as ELF64 files, as there is no ELF
+    /// class for 128-bit addresses.
+    fn address(&mut self, value: XWord) {
+        self.word(value as DoubleWord);
+    }
+
+    fn pad_to(&mut self, offset: u64) {
+        self.bytes.resize(offset as usize, 0);
+    }
+
+    const fn offset(&self) -> u64 {
+        self.bytes.len() as u64
+    }
+}
+
+/// A section or segment of the output file.
+struct Chunk<'a> {
+    /// The load address.
+    address: XWord,
+    /// The contents.
+    data: &'a [u8],
+    /// The offset of the contents within the file.
+    offset: u64,
+}
+
+/// Writes `program` as a statically linked ELF executable, of the class of `X`.
+pub(crate) fn write<X: Xlen>(program: &Program<X>) -> Vec<u8> {
+    let wide = X::X_LEN >= 64;
+    let (ehsize, phentsize, shentsize, symsize) =
+        if wide { (64u16, 56u16, 64u16, 24u64) } else { (52, 32, 40, 16) };
+
+    let mut chunks = [
+        Chunk { address: program.text_base, data: &program.text, offset: 0 },
+        Chunk { address: program.data_base, data: &program.data, offset: 0 },
+    ];
+    let phnum = chunks.iter().filter(|chunk| !chunk.data.is_empty()).count() as u16;
+
+    // Lay out the segments at page-aligned file offsets following the headers.
+    let mut offset = ehsize as u64 + phnum as u64 * phentsize as u64;
+    for chunk in chunks.iter_mut() {
+        offset = offset.next_multiple_of(SEGMENT_ALIGN);
+        chunk.offset = offset;
+        offset += chunk.data.len() as u64;
+    }
+
+    // Build the symbol and string tables, with all local symbols preceding the global ones.
+    let mut strtab = alloc::vec![0u8];
+    let mut symbols = program.symbols.iter().collect::<Vec<_>>();
+    symbols.sort_by_key(|symbol| symbol.global);
+    let first_global = 1 + symbols.iter().take_while(|symbol| !symbol.global).count() as u32;
+
+    let mut w = Writer { bytes: Vec::new(), wide };
+
+    // ELF header.
+    w.bytes.extend_from_slice(b"\x7fELF");
+    w.u8(if wide { 2 } else { 1 });
+    w.u8(1);
+    w.u8(1);
+    w.pad_to(16);
+    w.u16(ET_EXEC);
+    w.u16(EM_RISCV);
+    w.u32(1);
+    w.address(program.entry);
+    let phoff = if phnum > 0 { ehsize as u64 } else { 0 };
+    w.word(phoff);
+    let shoff_at = w.offset();
+    w.word(0u64);
+    w.u32(0);
+    w.u16(ehsize);
+    w.u16(phentsize);
+    w.u16(phnum);
+    w.u16(shentsize);
+    w.u16(6);
+    w.u16(5);
+
+    // Program headers.
+    for (chunk, flags) in chunks.iter().zip([0b101u32, 0b110]) {
+        if chunk.data.is_empty() {
+            continue;
+        }
+        let size = chunk.data.len() as u64;
+        w.u32(PT_LOAD);
+        if wide {
+            w.u32(flags);
+        }
+        w.word(chunk.offset);
+        w.address(chunk.address);
+        w.address(chunk.address);
+        w.word(size);
+        w.word(size);
+        if !wide {
+            w.u32(flags);
+        }
+        w.word(SEGMENT_ALIGN);
+    }
+
+    // Segment contents.
+    for chunk in &chunks {
+        w.pad_to(chunk.offset);
+        w.bytes.extend_from_slice(chunk.data);
+    }
+
+    // Symbol table, starting with the null symbol.
+    let symtab_offset = w.offset().next_multiple_of(8);
+    w.pad_to(symtab_offset + symsize);
+    for symbol in &symbols {
+        let name = strtab.len() as u32;
+        strtab.extend_from_slice(symbol.name.as_bytes());
+        strtab.push(0);
+
+        let bind = if symbol.global { STB_GLOBAL } else { STB_LOCAL };
+        let (kind, shndx) = match symbol.section {
+            Section::Text => (STT_FUNC, SHN_TEXT),
+            Section::Data => (STT_OBJECT, SHN_DATA),
+        };
+        w.u32(name);
+        if wide {
+            w.u8((bind << 4) | kind);
+            w.u8(0);
+            w.u16(shndx);
+            w.address(symbol.address);
+            w.word(0u64);
+        } else {
+            w.address(symbol.address);
+            w.word(0u64);
+            w.u8((bind << 4) | kind);
+            w.u8(0);
+            w.u16(shndx);
+        }
+    }
+    let symtab_size = w.offset() - symtab_offset;
+
+    // String tables.
+    let strtab_offset = w.offset();
+    w.bytes.extend_from_slice(&strtab);
+    let shstrtab_offset = w.offset();
+    w.bytes.extend_from_slice(SHSTRTAB);
+
+    // Section headers.
+    let shoff = w.offset().next_multiple_of(8);
+    w.pad_to(shoff);
+    let section = |w: &mut Writer, header: SectionHeader| {
+        w.u32(header.name);
+        w.u32(header.kind);
+        w.word(header.flags);
+        w.address(header.address);
+        w.word(header.offset);
+        w.word(header.size);
+        w.u32(header.link);
+        w.u32(header.info);
+        w.word(header.align);
+        w.word(header.entsize);
+    };
+    section(&mut w, SectionHeader::default());
+    for (chunk, (name, flags)) in
+        chunks.iter().zip([(1, SHF_ALLOC | SHF_EXECINSTR), (7, SHF_ALLOC | SHF_WRITE)])
+    {
+        section(
+            &mut w,
+            SectionHeader {
+                name,
+                kind: SHT_PROGBITS,
+                flags,
+                address: chunk.address,
+                offset: chunk.offset,
+                size: chunk.data.len() as u64,
+                align: 4,
+                ..Default::default()
+            },
+        );
+    }
+    section(
+        &mut w,
+        SectionHeader {
+            name: 13,
+            kind: SHT_SYMTAB,
+            offset: symtab_offset,
+            size: symtab_size,
+            link: SHN_STRTAB,
+            info: first_global,
+            align: 8,
+            entsize: symsize,
+            ..Default::default()
+        },
+    );
+    section(
+        &mut w,
+        SectionHeader {
+            name: 21,
+            kind: SHT_STRTAB,
+            offset: strtab_offset,
+            size: strtab.len() as u64,
+            align: 1,
+            ..Default::default()
+        },
+    );
+    section(
+        &mut w,
+        SectionHeader {
+            name: 29,
+            kind: SHT_STRTAB,
+            offset: shstrtab_offset,
+            size: SHSTRTAB.len() as u64,
+            align: 1,
+            ..Default::default()
+        },
+    );
+
+    // Patch the section header offset into the ELF header.
+    let shoff_at = shoff_at as usize;
+    let shoff_bytes = shoff.to_le_bytes();
+    let width = if wide { 8 } else { 4 };
+    w.bytes[shoff_at..shoff_at + width].copy_from_slice(&shoff_bytes[..width]);
+
+    w.bytes
+}
+
+/// The fields of a section header.
+#[derive(Default)]
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u64,
+    address: XWord,
+    offset: u64,
+    size: u64,
+    link: u32,
+    info: u32,
+    align: u64,
+    entsize: u64,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        asm::{assemble, assemble_for},
+        Rv32, X_LEN,
+    };
+
+    #[test]
+    fn test_write_header() {
+        let program = assemble("_start: nop\n.data\n.word 1").unwrap();
+        let elf = write(&program);
+
+        assert_eq!(&elf[..4], b"\x7fELF");
+        assert_eq!(elf[4], if X_LEN >= 64 { 2 } else { 1 });
+        assert_eq!(u16::from_le_bytes([elf[18], elf[19]]), EM_RISCV);
+
+        // The text segment is loaded from the first page-aligned offset.
+        assert_eq!(&elf[0x1000..0x1004], &0x13u32.to_le_bytes());
+        assert_eq!(&elf[0x2000..0x2004], &1u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_write_rv32() {
+        // RV32 programs are written as ELF32 files, whatever the widest XLEN of the build.
+        let program = assemble_for::<Rv32>("_start: nop").unwrap();
+        let elf = write(&program);
+        assert_eq!(elf[4], 1);
+        assert_eq!(u32::from_le_bytes(elf[24..28].try_into().unwrap()), program.entry as u32);
+    }
+}
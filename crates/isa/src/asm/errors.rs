@@ -0,0 +1,73 @@
+//! Errors for the assembler.
+
+use alloc::string::String;
+use thiserror::Error;
+
+/// An error that occurs while assembling a program, along with the line it occurred on.
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+#[error("line {line}: {kind}")]
+pub struct AsmError {
+    /// The 1-indexed source line that the error occurred on.
+    pub line: usize,
+    /// The kind of error.
+    pub kind: AsmErrorKind,
+}
+
+/// The kinds of [AsmError]s.
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    /// The mnemonic is not a supported instruction or pseudo-instruction.
+    #[error("Unknown mnemonic: {0}")]
+    UnknownMnemonic(String),
+    /// The directive is not supported.
+    #[error("Unknown directive: {0}")]
+    UnknownDirective(String),
+    /// The operand is not a register.
+    #[error("Invalid register: {0}")]
+    InvalidRegister(String),
+    /// The operand could not be parsed.
+    #[error("Invalid operand: {0}")]
+    InvalidOperand(String),
+    /// The instruction was given the wrong number of operands.
+    #[error("Expected {expected} operands, found {found}")]
+    OperandCount {
+        /// The number of operands the instruction accepts.
+        expected: usize,
+        /// The number of operands that were given.
+        found: usize,
+    },
+    /// A symbol was referenced but never defined.
+    #[error("Undefined symbol: {0}")]
+    UndefinedSymbol(String),
+    /// A symbol was defined more than once.
+    #[error("Duplicate symbol: {0}")]
+    DuplicateSymbol(String),
+    /// An expression requires an absolute value, but refers to a section-relative address.
+    #[error("Expression is not absolute: {0}")]
+    NotAbsolute(String),
+    /// A value does not fit in the field it is encoded into.
+    #[error("Value {value} does not fit in {field}")]
+    OutOfRange {
+        /// The value being encoded.
+        value: i64,
+        /// A description of the field.
+        field: &'static str,
+    },
+    /// A PC-relative target is not aligned to the instruction alignment.
+    #[error("Misaligned target offset: {0}")]
+    MisalignedTarget(i64),
+    /// A string literal is malformed.
+    #[error("Invalid string literal: {0}")]
+    InvalidString(String),
+    /// The instruction is only available on harts with a wider register width.
+    #[error("`{mnemonic}` is not available on RV{xlen}")]
+    XlenTooNarrow {
+        /// The mnemonic of the instruction.
+        mnemonic: String,
+        /// The register width of the hart.
+        xlen: usize,
+    },
+    /// The encoded size of a statement changed between passes.
+    #[error("Statement size changed between passes")]
+    SizeMismatch,
+}
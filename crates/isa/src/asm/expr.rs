@@ -0,0 +1,359 @@
+//! Expression evaluation for instruction operands and data directives.
+
+use super::{AsmErrorKind, Section};
+use alloc::string::{String, ToString};
+
+/// The value of an expression: either an absolute number, or an offset into a [Section] whose
+/// base address may not be known yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Value {
+    /// The section that the value is relative to, if any.
+    pub(crate) section: Option<Section>,
+    /// The absolute value, or the offset from the start of [Self::section].
+    pub(crate) offset: i64,
+}
+
+impl Value {
+    /// Creates a new absolute [Value].
+    pub(crate) const fn absolute(value: i64) -> Self {
+        Self { section: None, offset: value }
+    }
+}
+
+/// The symbols and layout that an expression is evaluated against.
+pub(crate) trait Scope {
+    /// Looks up the value of a symbol.
+    fn symbol(&self, name: &str) -> Option<Value>;
+
+    /// Returns the current location counter (`.`).
+    fn location(&self) -> Value;
+
+    /// Returns the base address of a section, if the layout is known.
+    fn base(&self, section: Section) -> Option<i64>;
+
+    /// Resolves a [Value] to an absolute address, if possible.
+    fn resolve(&self, value: Value) -> Option<i64> {
+        value.section.map_or(Some(value.offset), |section| {
+            self.base(section).map(|base| base.wrapping_add(value.offset))
+        })
+    }
+}
+
+/// Evaluates an expression to a [Value].
+pub(crate) fn evaluate<S: Scope>(expr: &str, scope: &S) -> Result<Value, AsmErrorKind> {
+    let mut parser = Parser { src: expr.as_bytes(), pos: 0, scope };
+    let value = parser.expr()?;
+    parser.skip_whitespace();
+    if parser.pos != parser.src.len() {
+        return Err(AsmErrorKind::InvalidOperand(expr.to_string()));
+    }
+    Ok(value)
+}
+
+/// Evaluates an expression that must resolve to an absolute value.
+pub(crate) fn evaluate_absolute<S: Scope>(expr: &str, scope: &S) -> Result<i64, AsmErrorKind> {
+    let value = evaluate(expr, scope)?;
+    scope.resolve(value).ok_or_else(|| AsmErrorKind::NotAbsolute(expr.to_string()))
+}
+
+/// Returns the sign-extended low 12 bits of `value`, as used by `%lo`.
+pub(crate) const fn low12(value: i64) -> i64 {
+    (value << 52) >> 52
+}
+
+/// Returns the upper 20 bits of `value`, rounded to compensate for the sign of [low12].
+pub(crate) const fn high20(value: i64) -> i64 {
+    (value.wrapping_add(0x800) >> 12) & 0xF_FFFF
+}
+
+/// Returns `true` if `c` may start a symbol name.
+pub(crate) const fn is_symbol_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, b'_' | b'.' | b'$')
+}
+
+/// Returns `true` if `c` may continue a symbol name.
+pub(crate) const fn is_symbol_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'_' | b'.' | b'$')
+}
+
+/// A recursive descent parser and evaluator for expressions.
+///
+/// Precedence, from lowest to highest: shifts (`<<`, `>>`), additive (`+`, `-`), multiplicative
+/// (`*`, `/`), unary (`-`, `~`, `+`), and primaries (numbers, characters, symbols, `.`,
+/// parentheses, `%hi(..)` and `%lo(..)`).
+struct Parser<'a, S> {
+    /// The expression source.
+    src: &'a [u8],
+    /// The current position within [Self::src].
+    pos: usize,
+    /// The scope that symbols are resolved in.
+    scope: &'a S,
+}
+
+impl<'a, S: Scope> Parser<'a, S> {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.src.len() && self.src[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.src.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.src[self.pos..].starts_with(token.as_bytes()) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self) -> AsmErrorKind {
+        AsmErrorKind::InvalidOperand(String::from_utf8_lossy(self.src).into_owned())
+    }
+
+    fn absolute(&self, value: Value) -> Result<i64, AsmErrorKind> {
+        self.scope.resolve(value).ok_or_else(|| {
+            AsmErrorKind::NotAbsolute(String::from_utf8_lossy(self.src).into_owned())
+        })
+    }
+
+    fn expr(&mut self) -> Result<Value, AsmErrorKind> {
+        let mut lhs = self.additive()?;
+        loop {
+            if self.eat("<<") {
+                let rhs = self.additive()?;
+                let (l, r) = (self.absolute(lhs)?, self.absolute(rhs)?);
+                lhs = Value::absolute(l.wrapping_shl(r as u32));
+            } else if self.eat(">>") {
+                let rhs = self.additive()?;
+                let (l, r) = (self.absolute(lhs)?, self.absolute(rhs)?);
+                lhs = Value::absolute(l.wrapping_shr(r as u32));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn additive(&mut self) -> Result<Value, AsmErrorKind> {
+        let mut lhs = self.multiplicative()?;
+        loop {
+            if self.eat("+") {
+                let rhs = self.multiplicative()?;
+                lhs = match (lhs.section, rhs.section) {
+                    (None, section) | (section, None) => {
+                        Value { section, offset: lhs.offset.wrapping_add(rhs.offset) }
+                    }
+                    _ => Value::absolute(self.absolute(lhs)?.wrapping_add(self.absolute(rhs)?)),
+                };
+            } else if self.eat("-") {
+                let rhs = self.multiplicative()?;
+                lhs = match (lhs.section, rhs.section) {
+                    (section, None) => {
+                        Value { section, offset: lhs.offset.wrapping_sub(rhs.offset) }
+                    }
+                    (Some(a), Some(b)) if a == b => {
+                        Value::absolute(lhs.offset.wrapping_sub(rhs.offset))
+                    }
+                    _ => Value::absolute(self.absolute(lhs)?.wrapping_sub(self.absolute(rhs)?)),
+                };
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Value, AsmErrorKind> {
+        let mut lhs = self.unary()?;
+        loop {
+            if self.eat("*") {
+                let rhs = self.unary()?;
+                let (l, r) = (self.absolute(lhs)?, self.absolute(rhs)?);
+                lhs = Value::absolute(l.wrapping_mul(r));
+            } else if self.eat("/") {
+                let rhs = self.unary()?;
+                let (l, r) = (self.absolute(lhs)?, self.absolute(rhs)?);
+                lhs = Value::absolute(l.checked_div(r).ok_or_else(|| self.error())?);
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Value, AsmErrorKind> {
+        if self.eat("-") {
+            let value = self.unary()?;
+            Ok(Value::absolute(self.absolute(value)?.wrapping_neg()))
+        } else if self.eat("~") {
+            let value = self.unary()?;
+            Ok(Value::absolute(!self.absolute(value)?))
+        } else if self.eat("+") {
+            self.unary()
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Value, AsmErrorKind> {
+        match self.peek().ok_or_else(|| self.error())? {
+            b'(' => {
+                self.pos += 1;
+                let value = self.expr()?;
+                if !self.eat(")") {
+                    return Err(self.error());
+                }
+                Ok(value)
+            }
+            b'%' => {
+                self.pos += 1;
+                let modifier = self.symbol_name();
+                if !self.eat("(") {
+                    return Err(self.error());
+                }
+                let value = self.expr()?;
+                if !self.eat(")") {
+                    return Err(self.error());
+                }
+                let value = self.absolute(value)?;
+                match modifier {
+                    "hi" => Ok(Value::absolute(high20(value))),
+                    "lo" => Ok(Value::absolute(low12(value))),
+                    _ => Err(self.error()),
+                }
+            }
+            b'\'' => self.character(),
+            c if c.is_ascii_digit() => self.number(),
+            c if is_symbol_start(c) => {
+                let name = self.symbol_name();
+                if name == "." {
+                    return Ok(self.scope.location());
+                }
+                self.scope
+                    .symbol(name)
+                    .ok_or_else(|| AsmErrorKind::UndefinedSymbol(name.to_string()))
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn symbol_name(&mut self) -> &'a str {
+        let start = self.pos;
+        while self.pos < self.src.len() && is_symbol_char(self.src[self.pos]) {
+            self.pos += 1;
+        }
+        // The slice only contains ASCII symbol characters.
+        core::str::from_utf8(&self.src[start..self.pos]).unwrap_or_default()
+    }
+
+    fn number(&mut self) -> Result<Value, AsmErrorKind> {
+        let start = self.pos;
+        while self.pos < self.src.len() && self.src[self.pos].is_ascii_alphanumeric() {
+            self.pos += 1;
+        }
+        let literal = core::str::from_utf8(&self.src[start..self.pos]).unwrap_or_default();
+        let lower = literal.to_ascii_lowercase();
+        let (digits, radix) = [("0x", 16), ("0b", 2), ("0o", 8)]
+            .into_iter()
+            .find_map(|(prefix, radix)| Some((lower.strip_prefix(prefix)?, radix)))
+            .unwrap_or((lower.as_str(), 10));
+        u64::from_str_radix(digits, radix)
+            .map(|value| Value::absolute(value as i64))
+            .map_err(|_| AsmErrorKind::InvalidOperand(literal.to_string()))
+    }
+
+    fn character(&mut self) -> Result<Value, AsmErrorKind> {
+        // Skip the opening quote.
+        self.pos += 1;
+        let c = match self.src.get(self.pos).copied().ok_or_else(|| self.error())? {
+            b'\\' => {
+                self.pos += 1;
+                unescape(self.src.get(self.pos).copied().ok_or_else(|| self.error())?)
+                    .ok_or_else(|| self.error())?
+            }
+            c => c,
+        };
+        self.pos += 1;
+        if self.src.get(self.pos) != Some(&b'\'') {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        Ok(Value::absolute(c as i64))
+    }
+}
+
+/// Maps the character following a `\` in a string or character literal to the byte it denotes.
+pub(crate) const fn unescape(c: u8) -> Option<u8> {
+    match c {
+        b'n' => Some(b'\n'),
+        b't' => Some(b'\t'),
+        b'r' => Some(b'\r'),
+        b'0' => Some(0),
+        b'\\' => Some(b'\\'),
+        b'\'' => Some(b'\''),
+        b'"' => Some(b'"'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    struct TestScope;
+
+    impl Scope for TestScope {
+        fn symbol(&self, name: &str) -> Option<Value> {
+            match name {
+                "msg" => Some(Value { section: Some(Section::Data), offset: 8 }),
+                "end" => Some(Value { section: Some(Section::Data), offset: 22 }),
+                "len" => Some(Value::absolute(14)),
+                _ => None,
+            }
+        }
+
+        fn location(&self) -> Value {
+            Value { section: Some(Section::Text), offset: 0x10 }
+        }
+
+        fn base(&self, section: Section) -> Option<i64> {
+            match section {
+                Section::Text => Some(0x1000),
+                Section::Data => Some(0x2000),
+            }
+        }
+    }
+
+    #[rstest]
+    #[case("42", 42)]
+    #[case("-1", -1)]
+    #[case("0x10 + 0b11", 19)]
+    #[case("2 + 3 * 4", 14)]
+    #[case("(2 + 3) * 4", 20)]
+    #[case("1 << 4 + 1", 32)]
+    #[case("'a'", 97)]
+    #[case("'\\n'", 10)]
+    #[case("len", 14)]
+    #[case("end - msg", 14)]
+    #[case("msg + 4", 0x200C)]
+    #[case(".", 0x1010)]
+    #[case("%hi(0x12345FFF)", 0x12346)]
+    #[case("%lo(0x12345FFF)", -1)]
+    fn test_evaluate(#[case] expr: &str, #[case] expected: i64) {
+        assert_eq!(evaluate_absolute(expr, &TestScope), Ok(expected));
+    }
+
+    #[test]
+    fn test_evaluate_errors() {
+        assert_eq!(
+            evaluate("missing", &TestScope),
+            Err(AsmErrorKind::UndefinedSymbol("missing".into()))
+        );
+        assert!(matches!(evaluate("1 +", &TestScope), Err(AsmErrorKind::InvalidOperand(_))));
+        assert!(matches!(evaluate("1 2", &TestScope), Err(AsmErrorKind::InvalidOperand(_))));
+    }
+}
@@ -0,0 +1,753 @@
+//! Encoding of instruction and pseudo-instruction mnemonics.
+
+use super::{
+    expr::{evaluate, evaluate_absolute, high20, low12, Scope},
+    parse::parse_register,
+    AsmErrorKind,
+};
+use crate::{
+    feature::check_xlen, BType, BranchFunction as B, EnvironmentFunction, IType,
+    ImmediateArithmeticFunction as I, Instruction, JType, LoadFunction as L, RType,
+    RegisterArithmeticFunction as R, SType, StoreFunction as St, UType, Word, XWord, Xlen, REG_RA,
+    REG_T1, REG_ZERO,
+};
+use alloc::{string::ToString, vec, vec::Vec};
+
+/// The encoding of `nop` (`addi x0, x0, 0`), used for padding.
+pub(crate) const NOP: Word = 0x0000_0013;
+
+/// Returns the size in bytes of the encoding of an instruction, as planned during the first pass.
+///
+/// Every statement is a single 4-byte instruction, except for `li` with an absolute immediate
+/// (whose sequence length depends on the value), `li` with a section-relative value (initially
+/// `lui` + `addi`, see [resolved_size]), and the `auipc`-based pseudo-instructions (always 8
+/// bytes).
+pub(crate) fn planned_size<X: Xlen, S: Scope>(
+    mnemonic: &str,
+    operands: &[&str],
+    scope: &S,
+) -> usize {
+    match mnemonic {
+        "li" => match operands.get(1).map(|op| evaluate(op, scope)) {
+            Some(Ok(value)) if value.section.is_none() => {
+                load_immediate::<X>(0, value.offset).map_or(4, |seq| seq.len() * 4)
+            }
+            _ => 8,
+        },
+        "la" | "lla" | "call" | "tail" => 8,
+        _ => 4,
+    }
+}
+
+/// Returns the size in bytes that an instruction needs once its section-relative operands are
+/// resolved, if it exceeds the `lui` + `addi` pair planned for them.
+///
+/// On RV64, the pair sign-extends its 32-bit result, so a `li` of an address at or above
+/// `0x8000_0000` needs a longer sequence, which is only known once the section bases are fixed.
+pub(crate) fn resolved_size<X: Xlen, S: Scope>(
+    mnemonic: &str,
+    operands: &[&str],
+    scope: &S,
+) -> Option<usize> {
+    match mnemonic {
+        "li" => {
+            let value = evaluate_absolute(operands.get(1)?, scope).ok()?;
+            let size = load_immediate::<X>(0, value).ok()?.len() * 4;
+            (!fits_signed(value, 32) && size > 8).then_some(size)
+        }
+        _ => None,
+    }
+}
+
+/// Assembles a single instruction or pseudo-instruction located at `pc` into one or more
+/// [Word]s, occupying exactly `planned` bytes, for a hart with the register width `X`.
+pub(crate) fn assemble<X: Xlen, S: Scope>(
+    mnemonic: &str,
+    operands: &[&str],
+    scope: &S,
+    pc: i64,
+    planned: usize,
+) -> Result<Vec<Word>, AsmErrorKind> {
+    let words = encode::<X, S>(mnemonic, operands, scope, pc, planned)?;
+    if words.iter().any(|&word| check_xlen::<X>(word).is_err()) {
+        return Err(AsmErrorKind::XlenTooNarrow { mnemonic: mnemonic.to_string(), xlen: X::X_LEN });
+    }
+    Ok(words)
+}
+
+/// Encodes a single instruction or pseudo-instruction like [assemble], including instructions
+/// that are only available on harts wider than `X`.
+fn encode<X: Xlen, S: Scope>(
+    mnemonic: &str,
+    operands: &[&str],
+    scope: &S,
+    pc: i64,
+    planned: usize,
+) -> Result<Vec<Word>, AsmErrorKind> {
+    let ops = Operands { operands, scope, pc };
+
+    let instructions = match mnemonic {
+        // Register-register arithmetic.
+        "add" => ops.r_type(R::Add)?,
+        "sub" => ops.r_type(R::Sub)?,
+        "xor" => ops.r_type(R::Xor)?,
+        "or" => ops.r_type(R::Or)?,
+        "and" => ops.r_type(R::And)?,
+        "sll" => ops.r_type(R::Sll)?,
+        "srl" => ops.r_type(R::Srl)?,
+        "sra" => ops.r_type(R::Sra)?,
+        "slt" => ops.r_type(R::Slt)?,
+        "sltu" => ops.r_type(R::Sltu)?,
+        #[cfg(feature = "m")]
+        "mul" => ops.r_type(R::Mul)?,
+        #[cfg(feature = "m")]
+        "mulh" => ops.r_type(R::Mulh)?,
+        #[cfg(feature = "m")]
+        "mulhsu" => ops.r_type(R::Mulhsu)?,
+        #[cfg(feature = "m")]
+        "mulhu" => ops.r_type(R::Mulhu)?,
+        #[cfg(feature = "m")]
+        "div" => ops.r_type(R::Div)?,
+        #[cfg(feature = "m")]
+        "divu" => ops.r_type(R::Divu)?,
+        #[cfg(feature = "m")]
+        "rem" => ops.r_type(R::Rem)?,
+        #[cfg(feature = "m")]
+        "remu" => ops.r_type(R::Remu)?,
+
+        // Register-immediate arithmetic.
+        "addi" => ops.i_type(I::Addi)?,
+        "xori" => ops.i_type(I::Xori)?,
+        "ori" => ops.i_type(I::Ori)?,
+        "andi" => ops.i_type(I::Andi)?,
+        "slti" => ops.i_type(I::Slti)?,
+        "sltiu" => ops.i_type(I::Sltiu)?,
+        "slli" => ops.shift::<X>(I::Slli)?,
+        "srli" => ops.shift::<X>(I::Srli)?,
+        "srai" => ops.shift::<X>(I::Srai)?,
+
+        // Loads and stores.
+        "lb" => ops.load(L::Lb)?,
+        "lh" => ops.load(L::Lh)?,
+        "lw" => ops.load(L::Lw)?,
+        "lbu" => ops.load(L::Lbu)?,
+        "lhu" => ops.load(L::Lhu)?,
+        #[cfg(feature = "64-bit")]
+        "lwu" => ops.load(L::Lwu)?,
+        #[cfg(feature = "64-bit")]
+        "ld" => ops.load(L::Ld)?,
+        #[cfg(feature = "128-bit")]
+        "ldu" => ops.load(L::Ldu)?,
+        #[cfg(feature = "128-bit")]
+        "lq" => ops.load(L::Lq)?,
+        "sb" => ops.store(St::Sb)?,
+        "sh" => ops.store(St::Sh)?,
+        "sw" => ops.store(St::Sw)?,
+        #[cfg(feature = "64-bit")]
+        "sd" => ops.store(St::Sd)?,
+        #[cfg(feature = "128-bit")]
+        "sq" => ops.store(St::Sq)?,
+
+        // Branches.
+        "beq" => ops.branch(B::Beq, false)?,
+        "bne" => ops.branch(B::Bne, false)?,
+        "blt" => ops.branch(B::Blt, false)?,
+        "bge" => ops.branch(B::Bge, false)?,
+        "bltu" => ops.branch(B::Bltu, false)?,
+        "bgeu" => ops.branch(B::Bgeu, false)?,
+        "bgt" => ops.branch(B::Blt, true)?,
+        "ble" => ops.branch(B::Bge, true)?,
+        "bgtu" => ops.branch(B::Bltu, true)?,
+        "bleu" => ops.branch(B::Bgeu, true)?,
+        "beqz" => ops.branch_zero(B::Beq, false)?,
+        "bnez" => ops.branch_zero(B::Bne, false)?,
+        "bltz" => ops.branch_zero(B::Blt, false)?,
+        "bgez" => ops.branch_zero(B::Bge, false)?,
+        "bgtz" => ops.branch_zero(B::Blt, true)?,
+        "blez" => ops.branch_zero(B::Bge, true)?,
+
+        // Upper immediates.
+        "lui" => {
+            ops.expect(2)?;
+            let imm = ops.upper(1)?;
+            vec![Instruction::Lui(UType { rd: ops.reg(0)?, imm })]
+        }
+        "auipc" => {
+            ops.expect(2)?;
+            let imm = ops.upper(1)?;
+            vec![Instruction::Auipc(UType { rd: ops.reg(0)?, imm })]
+        }
+
+        // Jumps.
+        "jal" => match operands.len() {
+            1 => vec![ops.jal(REG_RA as u8, 0)?],
+            _ => {
+                ops.expect(2)?;
+                vec![ops.jal(ops.reg(0)?, 1)?]
+            }
+        },
+        "j" => {
+            ops.expect(1)?;
+            vec![ops.jal(REG_ZERO as u8, 0)?]
+        }
+        "jalr" => match operands.len() {
+            1 => vec![jalr(REG_RA as u8, ops.reg(0)?, 0)],
+            2 => {
+                let (offset, rs1) =
+                    ops.reg(1).map_or_else(|_| ops.memory(1), |rs1| Ok((0, rs1)))?;
+                vec![jalr(ops.reg(0)?, rs1, offset)]
+            }
+            _ => {
+                ops.expect(3)?;
+                vec![jalr(ops.reg(0)?, ops.reg(1)?, ops.signed(2, 12, "a 12-bit immediate")?)]
+            }
+        },
+        "jr" => {
+            ops.expect(1)?;
+            vec![jalr(REG_ZERO as u8, ops.reg(0)?, 0)]
+        }
+        "ret" => {
+            ops.expect(0)?;
+            vec![jalr(REG_ZERO as u8, REG_RA as u8, 0)]
+        }
+        "call" => {
+            ops.expect(1)?;
+            ops.pc_relative_pair(REG_RA as u8, |lo| jalr(REG_RA as u8, REG_RA as u8, lo))?
+        }
+        "tail" => {
+            ops.expect(1)?;
+            ops.pc_relative_pair(REG_T1 as u8, |lo| jalr(REG_ZERO as u8, REG_T1 as u8, lo))?
+        }
+
+        // System.
+        "ecall" => {
+            ops.expect(0)?;
+            vec![Instruction::Environment(IType::default(), EnvironmentFunction::Ecall)]
+        }
+        "ebreak" => {
+            ops.expect(0)?;
+            vec![Instruction::Environment(IType::default(), EnvironmentFunction::Ebreak)]
+        }
+        "fence" | "fence.i" | "fence.tso" => return ops.fence(mnemonic).map(|word| vec![word]),
+
+        // Pseudo-instructions.
+        "nop" => {
+            ops.expect(0)?;
+            vec![addi(0, 0, 0)]
+        }
+        "li" => {
+            ops.expect(2)?;
+            let rd = ops.reg(0)?;
+            let value = ops.absolute(1)?;
+            if planned == 8 && fits_signed(value, 32) {
+                // Section-relative values are planned as a fixed `lui` + `addi` pair.
+                load_upper_pair::<X>(rd, value)
+            } else {
+                load_immediate::<X>(rd, value)?
+            }
+        }
+        "la" | "lla" => {
+            ops.expect(2)?;
+            let rd = ops.reg(0)?;
+            let op = Operands { operands: &operands[1..], scope, pc };
+            op.pc_relative_pair(rd, |lo| addi(rd, rd, lo))?
+        }
+        "mv" => {
+            ops.expect(2)?;
+            vec![addi(ops.reg(0)?, ops.reg(1)?, 0)]
+        }
+        "not" => {
+            ops.expect(2)?;
+            vec![Instruction::ImmediateArithmetic(itype(ops.reg(0)?, ops.reg(1)?, -1), I::Xori)]
+        }
+        "neg" => {
+            ops.expect(2)?;
+            vec![rtype(R::Sub, ops.reg(0)?, REG_ZERO as u8, ops.reg(1)?)]
+        }
+        "seqz" => {
+            ops.expect(2)?;
+            vec![Instruction::ImmediateArithmetic(itype(ops.reg(0)?, ops.reg(1)?, 1), I::Sltiu)]
+        }
+        "snez" => {
+            ops.expect(2)?;
+            vec![rtype(R::Sltu, ops.reg(0)?, REG_ZERO as u8, ops.reg(1)?)]
+        }
+        "sltz" => {
+            ops.expect(2)?;
+            vec![rtype(R::Slt, ops.reg(0)?, ops.reg(1)?, REG_ZERO as u8)]
+        }
+        "sgtz" => {
+            ops.expect(2)?;
+            vec![rtype(R::Slt, ops.reg(0)?, REG_ZERO as u8, ops.reg(1)?)]
+        }
+        "zext.b" => {
+            ops.expect(2)?;
+            vec![Instruction::ImmediateArithmetic(itype(ops.reg(0)?, ops.reg(1)?, 0xFF), I::Andi)]
+        }
+
+        _ => return assemble_extension(&ops, mnemonic),
+    };
+
+    let mut words: Vec<Word> = instructions.iter().map(Instruction::encode).collect();
+    if words.len() * 4 > planned {
+        return Err(AsmErrorKind::SizeMismatch);
+    }
+    words.resize(planned / 4, NOP);
+    Ok(words)
+}
+
+/// Assembles the mnemonics of the `64-bit`, `128-bit` and `a` extensions.
+#[allow(unused_variables)]
+fn assemble_extension<S: Scope>(
+    ops: &Operands<'_, S>,
+    mnemonic: &str,
+) -> Result<Vec<Word>, AsmErrorKind> {
+    #[cfg(feature = "64-bit")]
+    {
+        use crate::{ImmediateArithmeticWordFunction as IW, RegisterArithmeticWordFunction as RW};
+
+        let word_r = |funct: RW| -> Result<Vec<Word>, AsmErrorKind> {
+            ops.expect(3)?;
+            let r_type =
+                RType { rd: ops.reg(0)?, funct3: 0, rs1: ops.reg(1)?, rs2: ops.reg(2)?, funct7: 0 };
+            Ok(vec![Instruction::RegisterArithmeticWord(r_type, funct).encode()])
+        };
+        let word_i = |funct: IW, rd: u8, rs1: u8, imm: i64| -> Vec<Word> {
+            vec![Instruction::ImmediateArithmeticWord(itype(rd, rs1, imm), funct).encode()]
+        };
+
+        match mnemonic {
+            "addw" => return word_r(RW::Addw),
+            "subw" => return word_r(RW::Subw),
+            "sllw" => return word_r(RW::Sllw),
+            "srlw" => return word_r(RW::Srlw),
+            "sraw" => return word_r(RW::Sraw),
+            #[cfg(feature = "m")]
+            "mulw" => return word_r(RW::Mulw),
+            #[cfg(feature = "m")]
+            "divw" => return word_r(RW::Divw),
+            #[cfg(feature = "m")]
+            "divuw" => return word_r(RW::Divuw),
+            #[cfg(feature = "m")]
+            "remw" => return word_r(RW::Remw),
+            #[cfg(feature = "m")]
+            "remuw" => return word_r(RW::Remuw),
+            "addiw" => {
+                ops.expect(3)?;
+                let imm = ops.signed(2, 12, "a 12-bit immediate")?;
+                return Ok(word_i(IW::Addiw, ops.reg(0)?, ops.reg(1)?, imm));
+            }
+            "slliw" | "srliw" | "sraiw" => {
+                ops.expect(3)?;
+                let shamt = ops.absolute(2)?;
+                if !(0..32).contains(&shamt) {
+                    return Err(AsmErrorKind::OutOfRange { value: shamt, field: "a word shift" });
+                }
+                let funct = match mnemonic {
+                    "slliw" => IW::Slliw,
+                    "srliw" => IW::Srliw,
+                    _ => IW::Sraiw,
+                };
+                return Ok(word_i(funct, ops.reg(0)?, ops.reg(1)?, shamt));
+            }
+            "sext.w" => {
+                ops.expect(2)?;
+                return Ok(word_i(IW::Addiw, ops.reg(0)?, ops.reg(1)?, 0));
+            }
+            "negw" => {
+                ops.expect(2)?;
+                let r_type =
+                    RType { rd: ops.reg(0)?, funct3: 0, rs1: 0, rs2: ops.reg(1)?, funct7: 0 };
+                return Ok(vec![Instruction::RegisterArithmeticWord(r_type, RW::Subw).encode()]);
+            }
+            _ => {}
+        }
+    }
+
+    #[cfg(feature = "128-bit")]
+    {
+        use crate::{
+            ImmediateArithmeticDoubleFunction as ID, RegisterArithmeticDoubleFunction as RD,
+        };
+
+        let double_r = |funct: RD| -> Result<Vec<Word>, AsmErrorKind> {
+            ops.expect(3)?;
+            let r_type =
+                RType { rd: ops.reg(0)?, funct3: 0, rs1: ops.reg(1)?, rs2: ops.reg(2)?, funct7: 0 };
+            Ok(vec![Instruction::RegisterArithmeticDouble(r_type, funct).encode()])
+        };
+        let double_i = |funct: ID, rd: u8, rs1: u8, imm: i64| -> Vec<Word> {
+            vec![Instruction::ImmediateArithmeticDouble(itype(rd, rs1, imm), funct).encode()]
+        };
+
+        match mnemonic {
+            "addd" => return double_r(RD::Addd),
+            "subd" => return double_r(RD::Subd),
+            "slld" => return double_r(RD::Slld),
+            "srld" => return double_r(RD::Srld),
+            "srad" => return double_r(RD::Srad),
+            #[cfg(feature = "m")]
+            "muld" => return double_r(RD::Muld),
+            #[cfg(feature = "m")]
+            "divd" => return double_r(RD::Divd),
+            #[cfg(feature = "m")]
+            "divud" => return double_r(RD::Divud),
+            #[cfg(feature = "m")]
+            "remd" => return double_r(RD::Remd),
+            #[cfg(feature = "m")]
+            "remud" => return double_r(RD::Remud),
+            "addid" => {
+                ops.expect(3)?;
+                let imm = ops.signed(2, 12, "a 12-bit immediate")?;
+                return Ok(double_i(ID::Addid, ops.reg(0)?, ops.reg(1)?, imm));
+            }
+            "sllid" | "srlid" | "sraid" => {
+                ops.expect(3)?;
+                let shamt = ops.absolute(2)?;
+                if !(0..64).contains(&shamt) {
+                    return Err(AsmErrorKind::OutOfRange {
+                        value: shamt,
+                        field: "a double word shift",
+                    });
+                }
+                let funct = match mnemonic {
+                    "sllid" => ID::Sllid,
+                    "srlid" => ID::Srlid,
+                    _ => ID::Sraid,
+                };
+                return Ok(double_i(funct, ops.reg(0)?, ops.reg(1)?, shamt));
+            }
+            _ => {}
+        }
+    }
+
+    #[cfg(feature = "a")]
+    if let Some(word) = ops.amo(mnemonic)? {
+        return Ok(vec![word]);
+    }
+
+    Err(AsmErrorKind::UnknownMnemonic(mnemonic.to_string()))
+}
+
+/// The operands of an instruction, along with the scope and address they are evaluated at.
+struct Operands<'a, S> {
+    /// The raw operand strings.
+    operands: &'a [&'a str],
+    /// The scope that expressions are evaluated in.
+    scope: &'a S,
+    /// The address of the instruction.
+    pc: i64,
+}
+
+impl<S: Scope> Operands<'_, S> {
+    const fn expect(&self, count: usize) -> Result<(), AsmErrorKind> {
+        if self.operands.len() == count {
+            Ok(())
+        } else {
+            Err(AsmErrorKind::OperandCount { expected: count, found: self.operands.len() })
+        }
+    }
+
+    fn operand(&self, index: usize) -> Result<&str, AsmErrorKind> {
+        self.operands
+            .get(index)
+            .copied()
+            .ok_or(AsmErrorKind::OperandCount { expected: index + 1, found: self.operands.len() })
+    }
+
+    fn reg(&self, index: usize) -> Result<u8, AsmErrorKind> {
+        parse_register(self.operand(index)?)
+    }
+
+    fn absolute(&self, index: usize) -> Result<i64, AsmErrorKind> {
+        evaluate_absolute(self.operand(index)?, self.scope)
+    }
+
+    fn signed(&self, index: usize, bits: u32, field: &'static str) -> Result<i64, AsmErrorKind> {
+        let value = self.absolute(index)?;
+        if fits_signed(value, bits) {
+            Ok(value)
+        } else {
+            Err(AsmErrorKind::OutOfRange { value, field })
+        }
+    }
+
+    /// Parses a 20-bit upper immediate, returning it shifted into place.
+    fn upper(&self, index: usize) -> Result<XWord, AsmErrorKind> {
+        let value = self.absolute(index)?;
+        if !(-0x8_0000..=0xF_FFFF).contains(&value) {
+            return Err(AsmErrorKind::OutOfRange { value, field: "a 20-bit upper immediate" });
+        }
+        Ok(((value & 0xF_FFFF) << 12) as XWord)
+    }
+
+    /// Parses a memory operand of the form `offset(reg)` or `(reg)`.
+    fn memory(&self, index: usize) -> Result<(i64, u8), AsmErrorKind> {
+        let operand = self.operand(index)?;
+        let invalid = || AsmErrorKind::InvalidOperand(operand.to_string());
+        let inner = operand.strip_suffix(')').ok_or_else(invalid)?;
+        let open = inner.rfind('(').ok_or_else(invalid)?;
+        let rs1 = parse_register(&inner[open + 1..])?;
+        let offset = inner[..open].trim();
+        let offset = if offset.is_empty() { 0 } else { evaluate_absolute(offset, self.scope)? };
+        if !fits_signed(offset, 12) {
+            return Err(AsmErrorKind::OutOfRange { value: offset, field: "a 12-bit offset" });
+        }
+        Ok((offset, rs1))
+    }
+
+    /// Parses a PC-relative target, returning its offset from the instruction.
+    fn target(&self, index: usize, bits: u32, field: &'static str) -> Result<i64, AsmErrorKind> {
+        let offset = self.absolute(index)?.wrapping_sub(self.pc);
+        if offset & 1 != 0 {
+            return Err(AsmErrorKind::MisalignedTarget(offset));
+        }
+        if !fits_signed(offset, bits) {
+            return Err(AsmErrorKind::OutOfRange { value: offset, field });
+        }
+        Ok(offset)
+    }
+
+    fn r_type(&self, funct: R) -> Result<Vec<Instruction>, AsmErrorKind> {
+        self.expect(3)?;
+        Ok(vec![rtype(funct, self.reg(0)?, self.reg(1)?, self.reg(2)?)])
+    }
+
+    fn i_type(&self, funct: I) -> Result<Vec<Instruction>, AsmErrorKind> {
+        self.expect(3)?;
+        let imm = self.signed(2, 12, "a 12-bit immediate")?;
+        Ok(vec![Instruction::ImmediateArithmetic(itype(self.reg(0)?, self.reg(1)?, imm), funct)])
+    }
+
+    fn shift<X: Xlen>(&self, funct: I) -> Result<Vec<Instruction>, AsmErrorKind> {
+        self.expect(3)?;
+        let shamt = self.absolute(2)?;
+        if !(0..X::X_LEN as i64).contains(&shamt) {
+            return Err(AsmErrorKind::OutOfRange { value: shamt, field: "a shift amount" });
+        }
+        Ok(vec![Instruction::ImmediateArithmetic(itype(self.reg(0)?, self.reg(1)?, shamt), funct)])
+    }
+
+    fn load(&self, funct: L) -> Result<Vec<Instruction>, AsmErrorKind> {
+        self.expect(2)?;
+        let (offset, rs1) = self.memory(1)?;
+        Ok(vec![Instruction::MemoryLoad(itype(self.reg(0)?, rs1, offset), funct)])
+    }
+
+    fn store(&self, funct: St) -> Result<Vec<Instruction>, AsmErrorKind> {
+        self.expect(2)?;
+        let (offset, rs1) = self.memory(1)?;
+        let s_type = SType { funct3: 0, rs1, rs2: self.reg(0)?, imm: offset as XWord };
+        Ok(vec![Instruction::MemoryStore(s_type, funct)])
+    }
+
+    fn branch(&self, funct: B, swap: bool) -> Result<Vec<Instruction>, AsmErrorKind> {
+        self.expect(3)?;
+        let (mut rs1, mut rs2) = (self.reg(0)?, self.reg(1)?);
+        if swap {
+            core::mem::swap(&mut rs1, &mut rs2);
+        }
+        let imm = self.target(2, 13, "a branch offset")? as XWord;
+        Ok(vec![Instruction::Branch(BType { funct3: 0, rs1, rs2, imm }, funct)])
+    }
+
+    fn branch_zero(&self, funct: B, swap: bool) -> Result<Vec<Instruction>, AsmErrorKind> {
+        self.expect(2)?;
+        let (mut rs1, mut rs2) = (self.reg(0)?, REG_ZERO as u8);
+        if swap {
+            core::mem::swap(&mut rs1, &mut rs2);
+        }
+        let imm = self.target(1, 13, "a branch offset")? as XWord;
+        Ok(vec![Instruction::Branch(BType { funct3: 0, rs1, rs2, imm }, funct)])
+    }
+
+    fn jal(&self, rd: u8, index: usize) -> Result<Instruction, AsmErrorKind> {
+        let imm = self.target(index, 21, "a jump offset")? as XWord;
+        Ok(Instruction::Jal(JType { rd, imm }))
+    }
+
+    /// Assembles an `auipc rd, %pcrel_hi(target)` followed by an instruction consuming the low
+    /// 12 bits of the offset.
+    fn pc_relative_pair(
+        &self,
+        rd: u8,
+        low: impl FnOnce(i64) -> Instruction,
+    ) -> Result<Vec<Instruction>, AsmErrorKind> {
+        let offset = self.absolute(0)?.wrapping_sub(self.pc);
+        if !fits_signed(offset, 32) {
+            return Err(AsmErrorKind::OutOfRange { value: offset, field: "a PC-relative offset" });
+        }
+        let hi = high20(offset);
+        let auipc = Instruction::Auipc(UType { rd, imm: (hi << 12) as XWord });
+        Ok(vec![auipc, low(low12(offset))])
+    }
+
+    fn fence(&self, mnemonic: &str) -> Result<Word, AsmErrorKind> {
+        match mnemonic {
+            "fence.i" => {
+                self.expect(0)?;
+                Ok(0x0000_100F)
+            }
+            "fence.tso" => {
+                self.expect(0)?;
+                Ok(0x8330_000F)
+            }
+            _ if self.operands.is_empty() => Ok(Instruction::Fence.encode()),
+            _ => {
+                self.expect(2)?;
+                let set = |index: usize| -> Result<Word, AsmErrorKind> {
+                    let operand = self.operand(index)?;
+                    operand.chars().try_fold(0, |acc, c| match c {
+                        'i' => Ok(acc | 0b1000),
+                        'o' => Ok(acc | 0b0100),
+                        'r' => Ok(acc | 0b0010),
+                        'w' => Ok(acc | 0b0001),
+                        _ => Err(AsmErrorKind::InvalidOperand(operand.to_string())),
+                    })
+                };
+                Ok((set(0)? << 24) | (set(1)? << 20) | 0x0F)
+            }
+        }
+    }
+
+    /// Assembles an `lr`, `sc` or `amo*` mnemonic, returning [None] if the mnemonic is not an
+    /// atomic memory operation.
+    #[cfg(feature = "a")]
+    fn amo(&self, mnemonic: &str) -> Result<Option<Word>, AsmErrorKind> {
+        use crate::AmoFunction as A;
+
+        let mut parts = mnemonic.split('.');
+        let funct = match parts.next().unwrap_or_default() {
+            "lr" => A::Lr,
+            "sc" => A::Sc,
+            "amoswap" => A::Amoswap,
+            "amoadd" => A::Amoadd,
+            "amoxor" => A::Amoxor,
+            "amoand" => A::Amoand,
+            "amoor" => A::Amoor,
+            "amomin" => A::Amomin,
+            "amomax" => A::Amomax,
+            "amominu" => A::Amominu,
+            "amomaxu" => A::Amomaxu,
+            _ => return Ok(None),
+        };
+        let funct3 = match parts.next() {
+            Some("w") => 0b010,
+            #[cfg(feature = "64-bit")]
+            Some("d") => 0b011,
+            _ => return Err(AsmErrorKind::UnknownMnemonic(mnemonic.to_string())),
+        };
+        let ordering = match parts.next() {
+            None => 0b00,
+            Some("rl") => 0b01,
+            Some("aq") => 0b10,
+            Some("aqrl") => 0b11,
+            Some(_) => return Err(AsmErrorKind::UnknownMnemonic(mnemonic.to_string())),
+        };
+        if parts.next().is_some() {
+            return Err(AsmErrorKind::UnknownMnemonic(mnemonic.to_string()));
+        }
+
+        // `lr` has no `rs2` operand, and every form takes its address as `(rs1)`.
+        let (rs2, address) = if matches!(funct, A::Lr) {
+            self.expect(2)?;
+            (0, 1)
+        } else {
+            self.expect(3)?;
+            (self.reg(1)?, 2)
+        };
+        let (offset, rs1) = self.memory(address)?;
+        if offset != 0 {
+            return Err(AsmErrorKind::InvalidOperand(self.operand(address)?.to_string()));
+        }
+
+        let r_type = RType { rd: self.reg(0)?, funct3, rs1, rs2, funct7: ordering };
+        Ok(Some(Instruction::Amo(r_type, funct).encode()))
+    }
+}
+
+/// Returns the instruction sequence that loads the absolute `value` into `rd`.
+///
+/// Values that fit in 32 bits use `lui` and/or `addi` (`addiw` on RV64). Wider values on RV64
+/// recursively load the upper bits, shift them into place, and add the low 12 bits.
+pub(crate) fn load_immediate<X: Xlen>(
+    rd: u8,
+    value: i64,
+) -> Result<Vec<Instruction>, AsmErrorKind> {
+    if X::X_LEN == 32 {
+        if !(i32::MIN as i64..=u32::MAX as i64).contains(&value) {
+            return Err(AsmErrorKind::OutOfRange { value, field: "a 32-bit register" });
+        }
+        let value = value as i32 as i64;
+        let (hi, lo) = (high20(value), low12(value));
+        return Ok(match (hi, lo) {
+            (0, lo) => vec![addi(rd, 0, lo)],
+            (hi, 0) => vec![lui(rd, hi)],
+            (hi, lo) => vec![lui(rd, hi), addi(rd, rd, lo)],
+        });
+    }
+
+    if fits_signed(value, 32) {
+        let (hi, lo) = (high20(value), low12(value));
+        return Ok(match (hi, lo) {
+            (0, lo) => vec![addi(rd, 0, lo)],
+            (hi, 0) => vec![lui(rd, hi)],
+            (hi, lo) => vec![lui(rd, hi), addi_word::<X>(rd, lo)],
+        });
+    }
+
+    let lo = low12(value);
+    let hi = value.wrapping_sub(lo) >> 12;
+    let zeros = hi.trailing_zeros();
+    let mut sequence = load_immediate::<X>(rd, hi >> zeros)?;
+    sequence.push(Instruction::ImmediateArithmetic(itype(rd, rd, 12 + zeros as i64), I::Slli));
+    if lo != 0 {
+        sequence.push(addi(rd, rd, lo));
+    }
+    Ok(sequence)
+}
+
+/// Returns the fixed `lui` + `addi` pair that loads a 32-bit `value` into `rd`.
+fn load_upper_pair<X: Xlen>(rd: u8, value: i64) -> Vec<Instruction> {
+    vec![lui(rd, high20(value)), addi_word::<X>(rd, low12(value))]
+}
+
+/// Returns `true` if `value` fits in a `bits`-bit two's complement field.
+const fn fits_signed(value: i64, bits: u32) -> bool {
+    let min = -(1i64 << (bits - 1));
+    let max = (1i64 << (bits - 1)) - 1;
+    value >= min && value <= max
+}
+
+const fn itype(rd: u8, rs1: u8, imm: i64) -> IType {
+    IType { rd, funct3: 0, rs1, imm: imm as XWord }
+}
+
+const fn rtype(funct: R, rd: u8, rs1: u8, rs2: u8) -> Instruction {
+    Instruction::RegisterArithmetic(RType { rd, funct3: 0, rs1, rs2, funct7: 0 }, funct)
+}
+
+const fn addi(rd: u8, rs1: u8, imm: i64) -> Instruction {
+    Instruction::ImmediateArithmetic(itype(rd, rs1, imm), I::Addi)
+}
+
+/// Returns `addiw rd, rd, imm` on RV64 and wider, and `addi rd, rd, imm` on RV32.
+#[cfg_attr(not(feature = "64-bit"), allow(clippy::extra_unused_type_parameters))]
+const fn addi_word<X: Xlen>(rd: u8, imm: i64) -> Instruction {
+    #[cfg(feature = "64-bit")]
+    if X::X_LEN >= 64 {
+        return Instruction::ImmediateArithmeticWord(
+            itype(rd, rd, imm),
+            crate::ImmediateArithmeticWordFunction::Addiw,
+        );
+    }
+    addi(rd, rd, imm)
+}
+
+const fn lui(rd: u8, hi: i64) -> Instruction {
+    Instruction::Lui(UType { rd, imm: (hi << 12) as XWord })
+}
+
+const fn jalr(rd: u8, rs1: u8, imm: i64) -> Instruction {
+    Instruction::Jalr(itype(rd, rs1, imm))
+}
@@ -0,0 +1,760 @@
+//! A small two-pass assembler for RISC-V assembly source.
+//!
+//! The assembler accepts the GNU `as` syntax for the instructions supported by this crate, along
+//! with labels, the common pseudo-instructions (`li`, `la`, `mv`, `j`, `call`, `ret`, ...) and a
+//! subset of directives (`.text`, `.data`, `.word`, `.ascii`, `.align`, ...). All symbols are
+//! resolved at assembly time, so the output is a fully linked [Program] that requires no
+//! relocations. It can be used as raw bytes, or packaged into a minimal ELF file with
+//! [Program::to_elf]. Programs are assembled for harts of the widest XLEN of the build by
+//! [assemble], and for harts of any supported XLEN by [assemble_for].
+//!
+//! ```
+//! use brisc_isa::asm::assemble;
+//!
+//! let program = assemble(
+//!     r#"
+//!     .text
+//!     _start:
+//!         li a0, 42
+//!         li a7, 93
+//!         ecall
+//!     "#,
+//! )
+//! .unwrap();
+//! assert_eq!(program.entry, program.text_base);
+//! assert_eq!(program.text.len(), 12);
+//! ```
+
+use crate::{NativeXlen, XWord, Xlen};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+mod errors;
+pub use errors::{AsmError, AsmErrorKind};
+
+mod elf;
+mod expr;
+mod mnemonics;
+mod parse;
+
+use expr::{evaluate, evaluate_absolute, Scope, Value};
+use parse::{parse_line, parse_string, Line, Statement};
+
+/// The default base address of the `.text` section.
+pub const DEFAULT_TEXT_BASE: XWord = 0x1_0000;
+
+/// The alignment of the `.data` section when it is placed after `.text`.
+const DATA_ALIGN: i64 = 0x1000;
+
+/// Assembles `source` with the default [Assembler] configuration.
+pub fn assemble(source: &str) -> Result<Program, AsmError> {
+    assemble_for::<NativeXlen>(source)
+}
+
+/// Assembles `source` like [assemble], for a hart with the register width `X`.
+pub fn assemble_for<X: Xlen>(source: &str) -> Result<Program<X>, AsmError> {
+    Assembler::<X>::new().assemble(source)
+}
+
+/// A section of an assembled [Program].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Section {
+    /// Executable code (`.text`).
+    Text,
+    /// Initialized and zero-initialized data (`.data`, `.rodata`, `.bss`).
+    Data,
+}
+
+/// A label defined in an assembled [Program].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// The name of the symbol.
+    pub name: String,
+    /// The address of the symbol.
+    pub address: XWord,
+    /// The section the symbol is defined in.
+    pub section: Section,
+    /// Whether the symbol was declared with `.globl`.
+    pub global: bool,
+}
+
+/// A fully linked, assembled program for a hart with the register width `X`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program<X = NativeXlen> {
+    /// The load address of [Self::text].
+    pub text_base: XWord,
+    /// The contents of the `.text` section.
+    pub text: Vec<u8>,
+    /// The load address of [Self::data].
+    pub data_base: XWord,
+    /// The contents of the `.data` section.
+    pub data: Vec<u8>,
+    /// The entry point: the address of `_start` if defined, otherwise [Self::text_base].
+    pub entry: XWord,
+    /// The labels defined in the program, sorted by address.
+    pub symbols: Vec<Symbol>,
+    /// The register width the program was assembled for.
+    pub xlen: PhantomData<X>,
+}
+
+impl<X: Xlen> Program<X> {
+    /// Looks up a [Symbol] by name.
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    /// Packages the program into a minimal, statically linked ELF executable, with one `PT_LOAD`
+    /// segment per non-empty section and a symbol table. RV32 programs are written as ELF32 files,
+    /// and wider programs as ELF64 files.
+    pub fn to_elf(&self) -> Vec<u8> {
+        elf::write(self)
+    }
+}
+
+/// A two-pass assembler.
+///
+/// The first pass lays out every statement and assigns section-relative addresses to labels. The
+/// second pass fixes the section base addresses and encodes each statement, resolving all symbol
+/// references. If a resolved value needs a longer instruction sequence than was planned for it,
+/// both passes are repeated with more room for that statement.
+///
+/// Instructions are encoded for a hart with the register width `X`: instructions that are only
+/// available on wider harts are rejected, and pseudo-instructions expand to sequences that are
+/// valid on `X`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Assembler<X = NativeXlen> {
+    /// The base address of the `.text` section.
+    text_base: XWord,
+    /// The base address of the `.data` section. If [None], the data section is placed at the
+    /// next 4KiB boundary after the end of `.text`.
+    data_base: Option<XWord>,
+    /// The register width to assemble for.
+    xlen: PhantomData<X>,
+}
+
+impl<X: Xlen> Default for Assembler<X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X: Xlen> Assembler<X> {
+    /// Creates a new [Assembler], placing `.text` at [DEFAULT_TEXT_BASE].
+    pub const fn new() -> Self {
+        Self { text_base: DEFAULT_TEXT_BASE, data_base: None, xlen: PhantomData }
+    }
+
+    /// Assigns the base address of the `.text` section.
+    pub const fn with_text_base(mut self, base: XWord) -> Self {
+        self.text_base = base;
+        self
+    }
+
+    /// Assigns the base address of the `.data` section.
+    pub const fn with_data_base(mut self, base: XWord) -> Self {
+        self.data_base = Some(base);
+        self
+    }
+
+    /// Assembles `source` into a [Program].
+    pub fn assemble(&self, source: &str) -> Result<Program<X>, AsmError> {
+        let lines = source.lines().map(parse_line).collect::<Vec<_>>();
+
+        // The minimum size of each statement, raised when a resolved value needs more room than
+        // was planned. Sizes only grow, so the layout settles after a few rounds.
+        let mut minimums = vec![0; lines.len()];
+        loop {
+            // Pass 1: lay out all statements without section base addresses.
+            let mut pass = Pass::new(None);
+            let mut plans = Vec::with_capacity(lines.len());
+            for (i, (line, &minimum)) in lines.iter().zip(&minimums).enumerate() {
+                let plan = pass
+                    .layout::<X>(line, minimum)
+                    .map_err(|kind| AsmError { line: i + 1, kind })?;
+                plans.push(plan);
+            }
+
+            let text_base = self.text_base as i64;
+            let data_base = self.data_base.map_or_else(
+                || align_up(text_base.wrapping_add(pass.text.len() as i64), DATA_ALIGN),
+                |base| base as i64,
+            );
+
+            // Pass 2: encode all statements with the final layout, unless some outgrew it.
+            let mut pass =
+                Pass { bases: Some([text_base, data_base]), ..Pass::new(Some(pass.symbols)) };
+            let mut grown = false;
+            for (i, (line, plan)) in lines.iter().zip(plans).enumerate() {
+                match pass.resolved_size::<X>(line) {
+                    Some(size) if size > plan => {
+                        minimums[i] = size;
+                        grown = true;
+                    }
+                    _ if grown => {}
+                    _ => {
+                        pass.emit::<X>(line, plan).map_err(|kind| AsmError { line: i + 1, kind })?
+                    }
+                }
+            }
+
+            if !grown {
+                return Ok(pass.finish(text_base, data_base));
+            }
+        }
+    }
+}
+
+/// The state of a single assembler pass.
+#[derive(Debug)]
+struct Pass {
+    /// The symbols defined so far.
+    symbols: BTreeMap<String, Value>,
+    /// The symbols declared with `.globl`.
+    globals: BTreeSet<String>,
+    /// The labels, in the order they were defined.
+    labels: Vec<String>,
+    /// Whether this is the first pass, where symbols may not be defined yet.
+    first: bool,
+    /// The section that statements are currently emitted into.
+    section: Section,
+    /// The base addresses of the text and data sections, if known.
+    bases: Option<[i64; 2]>,
+    /// The contents of the `.text` section.
+    text: Vec<u8>,
+    /// The contents of the `.data` section.
+    data: Vec<u8>,
+}
+
+impl Scope for Pass {
+    fn symbol(&self, name: &str) -> Option<Value> {
+        self.symbols.get(name).copied()
+    }
+
+    fn location(&self) -> Value {
+        Value { section: Some(self.section), offset: self.buffer().len() as i64 }
+    }
+
+    fn base(&self, section: Section) -> Option<i64> {
+        self.bases.map(|bases| bases[section as usize])
+    }
+}
+
+impl Pass {
+    /// Creates a new pass. The second pass starts with the symbols found in the first.
+    fn new(symbols: Option<BTreeMap<String, Value>>) -> Self {
+        Self {
+            first: symbols.is_none(),
+            symbols: symbols.unwrap_or_default(),
+            globals: BTreeSet::new(),
+            labels: Vec::new(),
+            section: Section::Text,
+            bases: None,
+            text: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    const fn buffer(&self) -> &Vec<u8> {
+        match self.section {
+            Section::Text => &self.text,
+            Section::Data => &self.data,
+        }
+    }
+
+    const fn buffer_mut(&mut self) -> &mut Vec<u8> {
+        match self.section {
+            Section::Text => &mut self.text,
+            Section::Data => &mut self.data,
+        }
+    }
+
+    /// Lays out a line during the first pass, returning the planned size of its instruction, of at
+    /// least `minimum` bytes.
+    fn layout<X: Xlen>(&mut self, line: &Line<'_>, minimum: usize) -> Result<usize, AsmErrorKind> {
+        let plan = match &line.statement {
+            Some(Statement::Instruction { mnemonic, operands }) => {
+                mnemonics::planned_size::<X, _>(mnemonic, operands, self).max(minimum)
+            }
+            _ => 0,
+        };
+        self.emit::<X>(line, plan)?;
+        Ok(plan)
+    }
+
+    /// Returns the size that the instruction of a line needs with the final layout, if it can
+    /// exceed its planned size.
+    fn resolved_size<X: Xlen>(&self, line: &Line<'_>) -> Option<usize> {
+        match &line.statement {
+            Some(Statement::Instruction { mnemonic, operands }) => {
+                mnemonics::resolved_size::<X, _>(mnemonic, operands, self)
+            }
+            _ => None,
+        }
+    }
+
+    /// Processes a line, emitting its contents into the current section.
+    fn emit<X: Xlen>(&mut self, line: &Line<'_>, plan: usize) -> Result<(), AsmErrorKind> {
+        for &label in &line.labels {
+            let value = self.location();
+            if self.first && self.symbols.insert(label.to_string(), value).is_some() {
+                return Err(AsmErrorKind::DuplicateSymbol(label.to_string()));
+            }
+            self.labels.push(label.to_string());
+        }
+
+        match &line.statement {
+            None => Ok(()),
+            Some(Statement::Assignment { name, expr }) => self.assign(name, expr),
+            Some(Statement::Directive { name, args }) => self.directive(name, args),
+            Some(Statement::Instruction { mnemonic, operands }) => {
+                if self.first {
+                    let len = self.buffer().len() + plan;
+                    self.buffer_mut().resize(len, 0);
+                    return Ok(());
+                }
+                let pc = self.resolve(self.location()).unwrap_or_default();
+                let words = mnemonics::assemble::<X, _>(mnemonic, operands, self, pc, plan)?;
+                let buffer = self.buffer_mut();
+                words.iter().for_each(|word| buffer.extend_from_slice(&word.to_le_bytes()));
+                Ok(())
+            }
+        }
+    }
+
+    /// Assigns the value of `expr` to the symbol `name`. During the first pass, assignments that
+    /// refer to symbols which are not defined yet are deferred to the second pass.
+    fn assign(&mut self, name: &str, expr: &str) -> Result<(), AsmErrorKind> {
+        match evaluate(expr, self) {
+            Ok(value) => {
+                self.symbols.insert(name.to_string(), value);
+                Ok(())
+            }
+            Err(AsmErrorKind::UndefinedSymbol(_) | AsmErrorKind::NotAbsolute(_)) if self.first => {
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Evaluates an absolute expression. During the first pass, unresolved values evaluate to
+    /// zero, as they only affect the contents of the output and not its layout.
+    fn value(&self, expr: &str) -> Result<i64, AsmErrorKind> {
+        match evaluate_absolute(expr, self) {
+            Err(AsmErrorKind::UndefinedSymbol(_) | AsmErrorKind::NotAbsolute(_)) if self.first => {
+                Ok(0)
+            }
+            result => result,
+        }
+    }
+
+    fn directive(&mut self, name: &str, args: &[&str]) -> Result<(), AsmErrorKind> {
+        let expect = |count: usize| {
+            if args.len() == count {
+                Ok(())
+            } else {
+                Err(AsmErrorKind::OperandCount { expected: count, found: args.len() })
+            }
+        };
+
+        match name {
+            ".text" => self.section = Section::Text,
+            ".data" | ".rodata" | ".bss" | ".sdata" | ".sbss" => self.section = Section::Data,
+            ".section" => {
+                let section = args.first().copied().unwrap_or_default();
+                self.section =
+                    if section.starts_with(".text") { Section::Text } else { Section::Data };
+            }
+            ".globl" | ".global" => {
+                self.globals.extend(args.iter().map(|arg| arg.to_string()));
+            }
+            ".local" | ".type" | ".size" | ".file" | ".option" | ".attribute" | ".ident" => {}
+            ".byte" => self.data_values(args, 1)?,
+            ".half" | ".short" | ".2byte" => self.data_values(args, 2)?,
+            ".word" | ".long" | ".4byte" => self.data_values(args, 4)?,
+            ".dword" | ".quad" | ".8byte" => self.data_values(args, 8)?,
+            ".ascii" | ".asciz" | ".string" => {
+                for arg in args {
+                    let bytes = parse_string(arg)?;
+                    let buffer = self.buffer_mut();
+                    buffer.extend_from_slice(&bytes);
+                    if name != ".ascii" {
+                        buffer.push(0);
+                    }
+                }
+            }
+            ".align" | ".p2align" | ".balign" => {
+                let amount = self.layout_value(args.first().copied().unwrap_or_default())?;
+                let alignment = if name == ".balign" {
+                    amount
+                } else if (0..32).contains(&amount) {
+                    1 << amount
+                } else {
+                    return Err(AsmErrorKind::OutOfRange { value: amount, field: "an alignment" });
+                };
+                if alignment <= 0 || alignment & (alignment - 1) != 0 {
+                    return Err(AsmErrorKind::OutOfRange {
+                        value: alignment,
+                        field: "an alignment",
+                    });
+                }
+                self.align(alignment as usize);
+            }
+            ".zero" | ".space" | ".skip" => {
+                let size = self.layout_value(args.first().copied().unwrap_or_default())?;
+                let fill = args.get(1).map(|fill| self.value(fill)).transpose()?.unwrap_or(0);
+                let size = usize::try_from(size)
+                    .map_err(|_| AsmErrorKind::OutOfRange { value: size, field: "a size" })?;
+                let buffer = self.buffer_mut();
+                buffer.resize(buffer.len() + size, fill as u8);
+            }
+            ".equ" | ".set" => {
+                expect(2)?;
+                self.assign(args[0], args[1])?;
+            }
+            _ => return Err(AsmErrorKind::UnknownDirective(name.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Evaluates an expression that determines the layout, which must be resolvable during the
+    /// first pass.
+    fn layout_value(&self, expr: &str) -> Result<i64, AsmErrorKind> {
+        if expr.is_empty() {
+            return Err(AsmErrorKind::OperandCount { expected: 1, found: 0 });
+        }
+        evaluate_absolute(expr, self)
+    }
+
+    /// Emits each argument as a little-endian value of `size` bytes.
+    fn data_values(&mut self, args: &[&str], size: usize) -> Result<(), AsmErrorKind> {
+        for arg in args {
+            let value = self.value(arg)?;
+            let bits = size as u32 * 8;
+            if bits < 64 && !(-(1i64 << (bits - 1))..(1i64 << bits)).contains(&value) {
+                return Err(AsmErrorKind::OutOfRange { value, field: "a data directive" });
+            }
+            self.buffer_mut().extend_from_slice(&value.to_le_bytes()[..size]);
+        }
+        Ok(())
+    }
+
+    /// Pads the current section to a multiple of `alignment`. Code is padded with `nop`s.
+    fn align(&mut self, alignment: usize) {
+        let section = self.section;
+        let buffer = self.buffer_mut();
+        let target = buffer.len().next_multiple_of(alignment);
+        if section == Section::Text {
+            buffer.resize(buffer.len().next_multiple_of(4).min(target), 0);
+            while buffer.len() < target {
+                buffer.extend_from_slice(&mnemonics::NOP.to_le_bytes());
+            }
+        }
+        buffer.resize(target, 0);
+    }
+
+    /// Consumes the final pass, producing the [Program].
+    fn finish<X: Xlen>(self, text_base: i64, data_base: i64) -> Program<X> {
+        let mut symbols = self
+            .labels
+            .iter()
+            .filter_map(|name| {
+                let value = self.symbols.get(name)?;
+                Some(Symbol {
+                    name: name.clone(),
+                    address: self.resolve(*value)? as XWord,
+                    section: value.section?,
+                    global: self.globals.contains(name),
+                })
+            })
+            .collect::<Vec<_>>();
+        symbols.sort_by_key(|symbol| symbol.address);
+
+        let entry = symbols
+            .iter()
+            .find(|symbol| symbol.name == "_start")
+            .map_or(text_base as XWord, |symbol| symbol.address);
+
+        Program {
+            text_base: text_base as XWord,
+            text: self.text,
+            data_base: data_base as XWord,
+            data: self.data,
+            entry,
+            symbols,
+            xlen: PhantomData,
+        }
+    }
+}
+
+/// Rounds `value` up to the next multiple of `alignment`, which must be a power of two.
+const fn align_up(value: i64, alignment: i64) -> i64 {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Instruction, Rv32, Word};
+    use alloc::{format, vec::Vec};
+
+    fn words(bytes: &[u8]) -> Vec<Word> {
+        bytes.chunks(4).map(|chunk| Word::from_le_bytes(chunk.try_into().unwrap())).collect()
+    }
+
+    fn disassemble(bytes: &[u8]) -> Vec<String> {
+        words(bytes)
+            .into_iter()
+            .map(|word| format!("{}", Instruction::try_from(word).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_assemble_instructions() {
+        let program = assemble(
+            "
+            addi a0, zero, -1
+            sw a1, 8(sp)
+            lw t0, -4(s0)
+            xor x5, x6, x7
+            srai a0, a0, 3
+            lui a0, 0x12345
+            ecall
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            disassemble(&program.text),
+            [
+                "li a0, -1",
+                "sw a1, 8(sp)",
+                "lw t0, -4(s0)",
+                "xor t0, t1, t2",
+                "srai a0, a0, 3",
+                "lui a0, 0x12345",
+                "ecall"
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "128-bit")]
+    fn test_assemble_rv128() {
+        let program = assemble(
+            "
+            lq a0, 16(sp)
+            sq a0, 32(sp)
+            slli a0, a0, 100
+            addid a1, a0, -1
+            sraid a1, a1, 63
+            subd a2, a1, a0
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            disassemble(&program.text),
+            [
+                "lq a0, 16(sp)",
+                "sq a0, 32(sp)",
+                "slli a0, a0, 100",
+                "addid a1, a0, -1",
+                "sraid a1, a1, 63",
+                "subd a2, a1, a0"
+            ]
+        );
+        assert_eq!(
+            assemble("sllid a0, a0, 64").unwrap_err().kind,
+            AsmErrorKind::OutOfRange { value: 64, field: "a double word shift" }
+        );
+    }
+
+    #[test]
+    fn test_assemble_labels() {
+        let program = assemble(
+            "
+            _start:
+                li a0, 3
+            loop:
+                addi a0, a0, -1
+                bnez a0, loop
+                j done
+                nop
+            done:
+                ret
+            ",
+        )
+        .unwrap();
+        assert_eq!(program.entry, DEFAULT_TEXT_BASE);
+        assert_eq!(program.symbol("done").unwrap().address, DEFAULT_TEXT_BASE + 20);
+        assert_eq!(
+            disassemble(&program.text),
+            ["li a0, 3", "addi a0, a0, -1", "bnez a0, .-4", "j .+8", "nop", "ret"]
+        );
+    }
+
+    #[test]
+    fn test_assemble_data() {
+        let program = Assembler::<NativeXlen>::new()
+            .with_text_base(0x1000)
+            .with_data_base(0x4000)
+            .assemble(
+                r#"
+                .data
+                msg: .ascii "hi\n"
+                len = . - msg
+                .align 2
+                value: .word 0xdeadbeef, len
+                .byte 1, -1
+                .half 0x1234
+                .text
+                .globl _start
+                _start:
+                    la a1, value
+                    li a2, len
+                "#,
+            )
+            .unwrap();
+
+        assert_eq!(program.data_base, 0x4000);
+        assert_eq!(
+            program.data,
+            [b'h', b'i', b'\n', 0, 0xEF, 0xBE, 0xAD, 0xDE, 3, 0, 0, 0, 1, 0xFF, 0x34, 0x12]
+        );
+        assert_eq!(program.symbol("value").unwrap().address, 0x4004);
+        assert!(program.symbol("_start").unwrap().global);
+        assert_eq!(program.entry, 0x1000);
+
+        // `la` expands to `auipc` + `addi`; `li` with a small absolute value is a single `addi`.
+        assert_eq!(disassemble(&program.text), ["auipc a1, 0x3", "addi a1, a1, 4", "li a2, 3"]);
+    }
+
+    #[test]
+    fn test_assemble_forward_reference() {
+        // `li` with a label resolved in the second pass keeps its planned `lui` + `addi` size.
+        let program = assemble(
+            "
+            li a0, end
+            end:
+            ",
+        )
+        .unwrap();
+        assert_eq!(program.text.len(), 8);
+        assert_eq!(program.symbol("end").unwrap().address, DEFAULT_TEXT_BASE + 8);
+    }
+
+    #[test]
+    #[cfg(feature = "64-bit")]
+    fn test_assemble_high_text_base() {
+        // On RV64, `lui` + `addi` sign-extends, so labels at or above 0x8000_0000 need more room.
+        let program = Assembler::<NativeXlen>::new()
+            .with_text_base(0x8000_0000)
+            .assemble(
+                "
+                li a0, end
+                li a1, 0x7ff
+                end:
+                ",
+            )
+            .unwrap();
+        let end = program.symbol("end").unwrap().address;
+        assert_eq!(end, 0x8000_0000 + program.text.len() as XWord);
+        assert_eq!(
+            disassemble(&program.text),
+            ["li a0, 1", "slli a0, a0, 31", "addi a0, a0, 16", "li a1, 2047"]
+        );
+    }
+
+    #[test]
+    fn test_assemble_align_text() {
+        let program = assemble(".byte 1\n.align 3\nnop").unwrap();
+        assert_eq!(program.text.len(), 12);
+        assert_eq!(words(&program.text[4..]), [0x13, 0x13]);
+    }
+
+    fn load_immediate<X: Xlen>(value: i64) -> Result<Vec<String>, AsmErrorKind> {
+        let sequence = mnemonics::load_immediate::<X>(10, value)?;
+        Ok(sequence.iter().map(|i| format!("{i}")).collect())
+    }
+
+    #[test]
+    fn test_load_immediate() {
+        assert_eq!(load_immediate::<Rv32>(0).unwrap(), ["li a0, 0"]);
+        assert_eq!(load_immediate::<Rv32>(0x1000).unwrap(), ["lui a0, 0x1"]);
+        assert_eq!(
+            load_immediate::<Rv32>(0x12345678).unwrap(),
+            ["lui a0, 0x12345", "addi a0, a0, 1656"]
+        );
+        assert!(load_immediate::<Rv32>(0x1_0000_0001).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "64-bit")]
+    fn test_load_immediate_rv64() {
+        use crate::Rv64;
+
+        assert_eq!(load_immediate::<Rv64>(0x1000).unwrap(), ["lui a0, 0x1"]);
+        assert_eq!(
+            load_immediate::<Rv64>(0x12345678).unwrap(),
+            ["lui a0, 0x12345", "addiw a0, a0, 1656"]
+        );
+        assert_eq!(
+            load_immediate::<Rv64>(0x1_0000_0001).unwrap(),
+            ["li a0, 1", "slli a0, a0, 32", "addi a0, a0, 1"]
+        );
+    }
+
+    #[test]
+    fn test_assemble_rv32() {
+        let program = assemble_for::<Rv32>("li a0, 0x12345678\nslli a0, a0, 31").unwrap();
+        assert_eq!(
+            disassemble(&program.text),
+            ["lui a0, 0x12345", "addi a0, a0, 1656", "slli a0, a0, 31"]
+        );
+
+        assert_eq!(
+            assemble_for::<Rv32>("slli a0, a0, 32").unwrap_err().kind,
+            AsmErrorKind::OutOfRange { value: 32, field: "a shift amount" }
+        );
+        #[cfg(feature = "64-bit")]
+        assert_eq!(
+            assemble_for::<Rv32>("nop\nld a0, 0(sp)").unwrap_err(),
+            AsmError {
+                line: 2,
+                kind: AsmErrorKind::XlenTooNarrow { mnemonic: "ld".into(), xlen: 32 }
+            }
+        );
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        assert_eq!(
+            assemble("nop\nfoo a0").unwrap_err(),
+            AsmError { line: 2, kind: AsmErrorKind::UnknownMnemonic("foo".into()) }
+        );
+        assert_eq!(
+            assemble("a:\na:").unwrap_err(),
+            AsmError { line: 2, kind: AsmErrorKind::DuplicateSymbol("a".into()) }
+        );
+        assert_eq!(
+            assemble("j nowhere").unwrap_err().kind,
+            AsmErrorKind::UndefinedSymbol("nowhere".into())
+        );
+        assert_eq!(
+            assemble("addi a0, a0, 2048").unwrap_err().kind,
+            AsmErrorKind::OutOfRange { value: 2048, field: "a 12-bit immediate" }
+        );
+        assert_eq!(
+            assemble("add a0, a1").unwrap_err().kind,
+            AsmErrorKind::OperandCount { expected: 3, found: 2 }
+        );
+        assert_eq!(
+            assemble(".frobnicate").unwrap_err().kind,
+            AsmErrorKind::UnknownDirective(".frobnicate".into())
+        );
+        assert!(["mv a0, x32", "lw a0, a1"].iter().all(|src| assemble(src).is_err()));
+    }
+}
@@ -0,0 +1,245 @@
+//! Line-level parsing of assembly source.
+
+use super::{
+    expr::{is_symbol_char, is_symbol_start, unescape},
+    AsmErrorKind,
+};
+use alloc::{string::ToString, vec::Vec};
+
+/// A single line of assembly source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Line<'a> {
+    /// The labels defined at the start of the line.
+    pub(crate) labels: Vec<&'a str>,
+    /// The statement following the labels, if any.
+    pub(crate) statement: Option<Statement<'a>>,
+}
+
+/// A statement within a [Line].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Statement<'a> {
+    /// An assembler directive, such as `.word 1, 2`.
+    Directive {
+        /// The directive name, including the leading `.`.
+        name: &'a str,
+        /// The comma-separated arguments.
+        args: Vec<&'a str>,
+    },
+    /// An instruction or pseudo-instruction, such as `addi a0, a0, 1`.
+    Instruction {
+        /// The mnemonic.
+        mnemonic: &'a str,
+        /// The comma-separated operands.
+        operands: Vec<&'a str>,
+    },
+    /// A symbol assignment, such as `len = . - msg`.
+    Assignment {
+        /// The symbol being assigned.
+        name: &'a str,
+        /// The expression assigned to the symbol.
+        expr: &'a str,
+    },
+}
+
+/// Parses a single line of assembly source.
+pub(crate) fn parse_line(line: &str) -> Line<'_> {
+    let mut rest = strip_comment(line).trim();
+    let mut labels = Vec::new();
+
+    // Consume any leading `label:` definitions.
+    loop {
+        let name_len = symbol_len(rest);
+        if name_len == 0 {
+            break;
+        }
+        let after = rest[name_len..].trim_start();
+        if let Some(after) = after.strip_prefix(':') {
+            labels.push(&rest[..name_len]);
+            rest = after.trim_start();
+        } else {
+            break;
+        }
+    }
+
+    if rest.is_empty() {
+        return Line { labels, statement: None };
+    }
+
+    let (head, tail) = rest.split_once(|c: char| c.is_ascii_whitespace()).unwrap_or((rest, ""));
+    let tail = tail.trim();
+
+    // Symbol assignments, e.g. `len = . - msg`.
+    let name_len = symbol_len(rest);
+    if name_len > 0 {
+        let after = rest[name_len..].trim_start();
+        if let Some(expr) = after.strip_prefix('=') {
+            if !expr.starts_with('=') {
+                let statement =
+                    Statement::Assignment { name: &rest[..name_len], expr: expr.trim() };
+                return Line { labels, statement: Some(statement) };
+            }
+        }
+    }
+
+    let statement = if head.starts_with('.') {
+        Statement::Directive { name: head, args: split_operands(tail) }
+    } else {
+        Statement::Instruction { mnemonic: head, operands: split_operands(tail) }
+    };
+    Line { labels, statement: Some(statement) }
+}
+
+/// Removes a trailing `#` comment from a line, ignoring `#` within string or character literals.
+fn strip_comment(line: &str) -> &str {
+    let bytes = line.as_bytes();
+    let mut quote = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match (quote, bytes[i]) {
+            (Some(_), b'\\') => i += 1,
+            (Some(q), c) if c == q => quote = None,
+            (None, c @ (b'"' | b'\'')) => quote = Some(c),
+            (None, b'#') => return &line[..i],
+            _ => {}
+        }
+        i += 1;
+    }
+    line
+}
+
+/// Returns the length of the symbol name at the start of `s`, or `0` if there is none.
+fn symbol_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    if bytes.first().is_none_or(|&c| !is_symbol_start(c)) {
+        return 0;
+    }
+    bytes.iter().take_while(|&&c| is_symbol_char(c)).count()
+}
+
+/// Splits a comma-separated operand list, ignoring commas within string literals and
+/// parentheses.
+pub(crate) fn split_operands(s: &str) -> Vec<&str> {
+    let mut operands = Vec::new();
+    if s.trim().is_empty() {
+        return operands;
+    }
+
+    let bytes = s.as_bytes();
+    let (mut start, mut depth, mut quote) = (0, 0usize, None);
+    let mut i = 0;
+    while i < bytes.len() {
+        match (quote, bytes[i]) {
+            (Some(_), b'\\') => i += 1,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, c @ (b'"' | b'\'')) => quote = Some(c),
+            (None, b'(') => depth += 1,
+            (None, b')') => depth = depth.saturating_sub(1),
+            (None, b',') if depth == 0 => {
+                operands.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    operands.push(s[start..].trim());
+    operands
+}
+
+/// Parses a double-quoted string literal, processing escape sequences.
+pub(crate) fn parse_string(literal: &str) -> Result<Vec<u8>, AsmErrorKind> {
+    let invalid = || AsmErrorKind::InvalidString(literal.to_string());
+    let inner =
+        literal.trim().strip_prefix('"').and_then(|s| s.strip_suffix('"')).ok_or_else(invalid)?;
+
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut iter = inner.bytes();
+    while let Some(c) = iter.next() {
+        if c == b'\\' {
+            bytes.push(iter.next().and_then(unescape).ok_or_else(invalid)?);
+        } else {
+            bytes.push(c);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Parses a register operand, by ABI name (`a0`, `fp`) or by number (`x10`).
+pub(crate) fn parse_register(operand: &str) -> Result<u8, AsmErrorKind> {
+    let name = operand.trim();
+    if let Some(index) = name.strip_prefix('x').and_then(|n| n.parse::<u8>().ok()) {
+        if index < 32 {
+            return Ok(index);
+        }
+    }
+    if name == "fp" {
+        return Ok(crate::REG_S0_FP as u8);
+    }
+    crate::REGISTER_ABI_NAMES
+        .iter()
+        .position(|&abi| abi == name)
+        .map(|index| index as u8)
+        .ok_or_else(|| AsmErrorKind::InvalidRegister(name.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            parse_line("loop: addi a0, a0, -1 # decrement"),
+            Line {
+                labels: vec!["loop"],
+                statement: Some(Statement::Instruction {
+                    mnemonic: "addi",
+                    operands: vec!["a0", "a0", "-1"]
+                })
+            }
+        );
+        assert_eq!(
+            parse_line("msg: .ascii \"a, #b\""),
+            Line {
+                labels: vec!["msg"],
+                statement: Some(Statement::Directive { name: ".ascii", args: vec!["\"a, #b\""] })
+            }
+        );
+        assert_eq!(
+            parse_line("len = . - msg"),
+            Line {
+                labels: vec![],
+                statement: Some(Statement::Assignment { name: "len", expr: ". - msg" })
+            }
+        );
+        assert_eq!(
+            parse_line("a: b: # only labels"),
+            Line { labels: vec!["a", "b"], statement: None }
+        );
+    }
+
+    #[test]
+    fn test_split_operands() {
+        assert_eq!(split_operands("a0, 8(sp)"), vec!["a0", "8(sp)"]);
+        assert_eq!(split_operands("a0, %lo(sym + 4)(a1)"), vec!["a0", "%lo(sym + 4)(a1)"]);
+        assert_eq!(split_operands("','"), vec!["','"]);
+        assert!(split_operands("  ").is_empty());
+    }
+
+    #[test]
+    fn test_parse_string() {
+        assert_eq!(parse_string("\"hi\\n\""), Ok(b"hi\n".to_vec()));
+        assert!(parse_string("hi").is_err());
+    }
+
+    #[test]
+    fn test_parse_register() {
+        assert_eq!(parse_register("zero"), Ok(0));
+        assert_eq!(parse_register("x31"), Ok(31));
+        assert_eq!(parse_register("fp"), Ok(8));
+        assert_eq!(parse_register("a7"), Ok(17));
+        assert!(parse_register("x32").is_err());
+    }
+}
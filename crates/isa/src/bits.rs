@@ -92,7 +92,7 @@ mod test {
     #[case(XWord::MAX, (XWord::BITS - 1) as XWord)]
     #[case(0, (XWord::BITS - 1) as XWord)]
     #[cfg_attr(debug_assertions, should_panic)]
-    #[case(XWord::MAX, 64)]
+    #[case(XWord::MAX, XWord::BITS as XWord)]
     fn test_sign_extension_u64_simple(#[case] data: XWord, #[case] index: XWord) {
         if (data >> index) & 1 == 1 {
             let ones = XWord::MAX << index;
@@ -0,0 +1,354 @@
+//! Runtime selection of the ISA extensions that a hart implements.
+
+use crate::{
+    errors::IsaParseError, DecodeErrorKind, DecodedInstruction, Extension, Feature,
+    InstructionDecodeError, NativeXlen, Word, XWord, Xlen,
+};
+use core::{
+    fmt::{self, Display, Formatter},
+    marker::PhantomData,
+    str::FromStr,
+};
+
+/// The multi-letter extensions that are accepted in ISA strings. `brisc` does not model CSRs or
+/// instruction cache coherence, but toolchains routinely emit these names, so they are accepted
+/// and otherwise ignored.
+const IGNORED_EXTENSIONS: [&str; 2] = ["zicsr", "zifencei"];
+
+/// The ISA extensions enabled on a hart, modeled on the `misa` CSR.
+///
+/// The cargo features of `brisc-isa` determine which extensions are compiled in, and act as an
+/// upper bound on the extensions that an [IsaConfig] can enable. This allows a single build to
+/// emulate both an `rv64imac` and an `rv64i` target, while `no_std` builds that only need the base
+/// instruction set stay small.
+///
+/// An [IsaConfig] can be parsed from an ISA string such as `rv64imac_zicsr` or
+/// `rv64i2p1_m2p0_a2p1_c2p0`. The XLEN of the string must match the register width `X`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IsaConfig<X = NativeXlen> {
+    /// The enabled extensions, with bit `n` set for the extension named by the `n`th letter of the
+    /// alphabet, as in `misa`.
+    extensions: u32,
+    /// The register width of the hart.
+    xlen: PhantomData<X>,
+}
+
+impl<X: Xlen> Default for IsaConfig<X> {
+    /// Enables every extension that is compiled into this build.
+    fn default() -> Self {
+        Self::new()
+            .with_extension(Extension::M)
+            .with_extension(Extension::A)
+            .with_extension(Extension::C)
+    }
+}
+
+impl<X: Xlen> IsaConfig<X> {
+    /// Creates a new [IsaConfig] with only the base integer instruction set enabled.
+    pub const fn new() -> Self {
+        Self { extensions: Self::bit(Extension::I), xlen: PhantomData }
+    }
+
+    /// Enables an extension. Extensions that are not compiled into this build are ignored.
+    pub const fn with_extension(mut self, extension: Extension) -> Self {
+        if Self::is_compiled(extension) {
+            self.extensions |= Self::bit(extension);
+        }
+        self
+    }
+
+    /// Disables an extension. The base integer instruction set cannot be disabled.
+    pub const fn without_extension(mut self, extension: Extension) -> Self {
+        if !matches!(extension, Extension::I) {
+            self.extensions &= !Self::bit(extension);
+        }
+        self
+    }
+
+    /// Returns `true` if the extension is enabled.
+    pub const fn has(&self, extension: Extension) -> bool {
+        self.extensions & Self::bit(extension) != 0
+    }
+
+    /// Returns the value of the `misa` CSR for this configuration, with the `MXL` field in the
+    /// upper two bits and the extensions in the lower 26 bits.
+    pub const fn misa(&self) -> XWord {
+        ((X::MXL as XWord) << (X::X_LEN - 2)) | self.extensions as XWord
+    }
+
+    /// Decodes an instruction like [DecodedInstruction::decode_for], rejecting instructions from
+    /// disabled extensions as illegal.
+    pub fn decode(&self, raw: Word) -> Result<DecodedInstruction, InstructionDecodeError> {
+        let decoded = DecodedInstruction::decode_for::<X>(raw)?;
+        self.check(&decoded)?;
+        Ok(decoded)
+    }
+
+    /// Checks that a decoded instruction belongs to an enabled extension. Compressed instructions
+    /// belong to the C extension, regardless of the instruction that they expand to.
+    pub const fn check(&self, decoded: &DecodedInstruction) -> Result<(), InstructionDecodeError> {
+        let extension =
+            if decoded.is_compressed() { Extension::C } else { decoded.instruction.extension() };
+        if self.has(extension) {
+            Ok(())
+        } else {
+            Err(InstructionDecodeError {
+                raw: decoded.raw,
+                kind: DecodeErrorKind::ExtensionDisabled(extension),
+            })
+        }
+    }
+
+    /// Returns the `misa` bit of an extension.
+    const fn bit(extension: Extension) -> u32 {
+        1 << (extension.letter() as u32 - 'a' as u32)
+    }
+
+    /// Returns `true` if support for the extension is compiled into this build.
+    const fn is_compiled(extension: Extension) -> bool {
+        match extension.feature() {
+            Some(feature) => feature.is_enabled(),
+            None => true,
+        }
+    }
+}
+
+impl<X: Xlen> FromStr for IsaConfig<X> {
+    type Err = IsaParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s.as_bytes();
+        if bytes.len() < 2 || !bytes[..2].eq_ignore_ascii_case(b"rv") {
+            return Err(IsaParseError::MissingPrefix);
+        }
+
+        let mut pos = 2;
+        let xlen = parse_number(bytes, &mut pos).unwrap_or(0);
+        if xlen == 64 && !Feature::Rv64.is_enabled() {
+            return Err(IsaParseError::FeatureDisabled { name: "rv64", feature: Feature::Rv64 });
+        }
+        if xlen == 128 && !Feature::Rv128.is_enabled() {
+            return Err(IsaParseError::FeatureDisabled { name: "rv128", feature: Feature::Rv128 });
+        }
+        if xlen as usize != X::X_LEN {
+            return Err(IsaParseError::UnsupportedXlen(xlen));
+        }
+
+        match bytes.get(pos).map(u8::to_ascii_lowercase) {
+            Some(b'i') => pos += 1,
+            Some(base @ (b'e' | b'g')) => {
+                return Err(IsaParseError::UnsupportedExtension(base as char))
+            }
+            _ => return Err(IsaParseError::MissingBase),
+        }
+        skip_version(bytes, &mut pos);
+
+        let mut config = Self::new();
+        while let Some(&byte) = bytes.get(pos) {
+            let letter = byte.to_ascii_lowercase();
+            match letter {
+                b'_' => pos += 1,
+                b'z' | b's' | b'x' => {
+                    let start = pos;
+                    while bytes.get(pos).is_some_and(u8::is_ascii_alphabetic) {
+                        pos += 1;
+                    }
+                    let name = &s[start..pos];
+                    if !IGNORED_EXTENSIONS.iter().any(|known| known.eq_ignore_ascii_case(name)) {
+                        return Err(IsaParseError::UnknownExtension(start));
+                    }
+                    skip_version(bytes, &mut pos);
+                }
+                _ => {
+                    let (extension, name) = match letter {
+                        b'm' => (Extension::M, "m"),
+                        b'a' => (Extension::A, "a"),
+                        b'c' => (Extension::C, "c"),
+                        _ => return Err(IsaParseError::UnsupportedExtension(letter as char)),
+                    };
+                    if let Some(feature) = extension.feature().filter(|f| !f.is_enabled()) {
+                        return Err(IsaParseError::FeatureDisabled { name, feature });
+                    }
+                    config = config.with_extension(extension);
+                    pos += 1;
+                    skip_version(bytes, &mut pos);
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+impl<X: Xlen> Display for IsaConfig<X> {
+    /// Renders the configuration as a canonical ISA string, such as `rv64imac`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "rv{}", X::X_LEN)?;
+        for extension in [Extension::I, Extension::M, Extension::A, Extension::C] {
+            if self.has(extension) {
+                write!(f, "{}", extension.letter())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a decimal number at `pos`, advancing past it.
+fn parse_number(bytes: &[u8], pos: &mut usize) -> Option<u32> {
+    let start = *pos;
+    let mut value = 0u32;
+    while let Some(digit) = bytes.get(*pos).filter(|b| b.is_ascii_digit()) {
+        value = value.saturating_mul(10).saturating_add((digit - b'0') as u32);
+        *pos += 1;
+    }
+    (*pos > start).then_some(value)
+}
+
+/// Skips an optional extension version at `pos`, such as `2` or `2p1`.
+fn skip_version(bytes: &[u8], pos: &mut usize) {
+    if parse_number(bytes, pos).is_some() &&
+        bytes.get(*pos).is_some_and(|b| b.eq_ignore_ascii_case(&b'p')) &&
+        bytes.get(*pos + 1).is_some_and(u8::is_ascii_digit)
+    {
+        *pos += 1;
+        parse_number(bytes, pos);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Rv32, X_LEN};
+    use rstest::rstest;
+
+    #[test]
+    #[cfg(all(feature = "m", feature = "a", feature = "c"))]
+    fn test_parse_default() {
+        let isa = format!("rv{X_LEN}imac_zicsr");
+        assert_eq!(isa.parse::<IsaConfig>(), Ok(IsaConfig::default()));
+        assert_eq!(IsaConfig::<NativeXlen>::default().to_string(), isa.trim_end_matches("_zicsr"));
+    }
+
+    #[rstest]
+    #[case("rv64i", Ok(IsaConfig::new()))]
+    #[case("RV64I", Ok(IsaConfig::new()))]
+    #[case("rv64i2p1_zicsr2p0_zifencei2p0", Ok(IsaConfig::new()))]
+    #[case("rv64", Err(IsaParseError::MissingBase))]
+    #[case("rvi", Err(IsaParseError::UnsupportedXlen(0)))]
+    #[case("x86_64", Err(IsaParseError::MissingPrefix))]
+    #[case("rv256i", Err(IsaParseError::UnsupportedXlen(256)))]
+    #[case("rv64e", Err(IsaParseError::UnsupportedExtension('e')))]
+    #[case("rv64gc", Err(IsaParseError::UnsupportedExtension('g')))]
+    #[case("rv64if", Err(IsaParseError::UnsupportedExtension('f')))]
+    #[case("rv64i_zba", Err(IsaParseError::UnknownExtension(6)))]
+    fn test_parse(#[case] isa: &str, #[case] expected: Result<IsaConfig, IsaParseError>) {
+        // The cases are written for RV64, and apply equally to RV32 and RV128 builds.
+        let isa = isa.replace("rv64", &format!("rv{X_LEN}")).replace("RV64", &format!("RV{X_LEN}"));
+        let expected = expected.map_err(|err| match err {
+            IsaParseError::UnknownExtension(pos) => {
+                IsaParseError::UnknownExtension(pos + X_LEN.to_string().len() - 2)
+            }
+            err => err,
+        });
+        assert_eq!(isa.parse::<IsaConfig>(), expected);
+    }
+
+    #[test]
+    #[cfg(all(feature = "64-bit", feature = "m", feature = "c"))]
+    fn test_parse_extensions() {
+        let config: IsaConfig = format!("rv{X_LEN}i2p1_m2p0_c2p0").parse().unwrap();
+        assert!(config.has(Extension::M) && config.has(Extension::C));
+        assert!(!config.has(Extension::A));
+        assert_eq!(config.to_string(), format!("rv{X_LEN}imc"));
+        let mxl = (NativeXlen::MXL as XWord) << (X_LEN - 2);
+        assert_eq!(config.misa(), mxl | (1 << 2) | (1 << 8) | (1 << 12));
+    }
+
+    #[test]
+    #[cfg(not(feature = "m"))]
+    fn test_parse_feature_disabled() {
+        let isa = format!("rv{X_LEN}im");
+        assert_eq!(
+            isa.parse::<IsaConfig>(),
+            Err(IsaParseError::FeatureDisabled { name: "m", feature: Feature::M })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "m")]
+    fn test_decode_disabled_extension() {
+        // mul a0, a1, a2
+        let raw = 0x02c58533;
+        assert!(IsaConfig::<NativeXlen>::default().decode(raw).is_ok());
+        assert_eq!(
+            IsaConfig::<NativeXlen>::default().without_extension(Extension::M).decode(raw),
+            Err(InstructionDecodeError {
+                raw,
+                kind: DecodeErrorKind::ExtensionDisabled(Extension::M)
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "c")]
+    fn test_decode_disabled_compressed() {
+        // c.li a0, 1
+        let err = IsaConfig::<NativeXlen>::new().decode(0x4505).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::ExtensionDisabled(Extension::C));
+        assert_eq!(err.raw, 0x4505);
+    }
+
+    #[test]
+    fn test_rv32() {
+        let config: IsaConfig<Rv32> = "rv32i".parse().unwrap();
+        assert_eq!(config.misa(), (1 << 30) | (1 << 8));
+        assert_eq!(config.to_string(), "rv32i");
+        let expected = if cfg!(feature = "64-bit") {
+            IsaParseError::UnsupportedXlen(64)
+        } else {
+            IsaParseError::FeatureDisabled { name: "rv64", feature: Feature::Rv64 }
+        };
+        assert_eq!("rv64i".parse::<IsaConfig<Rv32>>(), Err(expected));
+        let expected = if cfg!(feature = "128-bit") {
+            IsaParseError::UnsupportedXlen(128)
+        } else {
+            IsaParseError::FeatureDisabled { name: "rv128", feature: Feature::Rv128 }
+        };
+        assert_eq!("rv128i".parse::<IsaConfig<Rv32>>(), Err(expected));
+    }
+
+    #[test]
+    #[cfg(feature = "64-bit")]
+    fn test_decode_rv32() {
+        let config = IsaConfig::<Rv32>::default();
+        // ld a0, 0(sp)
+        assert_eq!(
+            config.decode(0x00013503).unwrap_err().kind,
+            DecodeErrorKind::XlenTooNarrow { mnemonic: "ld", xlen: 32 }
+        );
+        // addi a0, a0, 1
+        assert!(config.decode(0x00150513).is_ok());
+    }
+
+    #[test]
+    #[cfg(all(feature = "64-bit", feature = "c"))]
+    fn test_decode_rv32_compressed() {
+        let config = IsaConfig::<Rv32>::default();
+        // c.jal .+20 on RV32, and c.addiw a6, 4 on RV64.
+        let decoded = config.decode(0x2811).unwrap();
+        assert_eq!(decoded.to_string(), "c.jal .+20");
+        assert_eq!(
+            IsaConfig::<NativeXlen>::default().decode(0x2811).unwrap().to_string(),
+            "c.addiw a6, 4"
+        );
+        assert_eq!(
+            IsaConfig::<crate::Rv64>::default().decode(0x2811).unwrap().to_string(),
+            "c.addiw a6, 4"
+        );
+        // c.ldsp ra, 8(sp)
+        assert_eq!(
+            config.decode(0x60a2).unwrap_err().kind,
+            DecodeErrorKind::XlenTooNarrow { mnemonic: "c.ldsp", xlen: 32 }
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Decoded instructions that retain their original encoding.
+
+use crate::{DecodeErrorKind, Instruction, InstructionDecodeError, NativeXlen, Word, Xlen};
+use core::fmt::{self, Display, Formatter};
+
+/// The length of an encoded instruction, determined by the low bits of its first 16-bit parcel.
+///
+/// Only the 16-bit and 32-bit lengths are defined by the ratified ISA. The longer lengths are
+/// recognized so that they can be skipped or reported, but `brisc` does not decode any
+/// instructions of those lengths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum InstructionLength {
+    /// A 16-bit compressed instruction (`xxxxxxxxxxxxxxaa`, `aa != 11`).
+    Compressed,
+    /// A 32-bit instruction (`xxxxxxxxxxxbbb11`, `bbb != 111`).
+    Standard,
+    /// A 48-bit instruction (`xxxxxxxxxx011111`).
+    Bits48,
+    /// A 64-bit instruction (`xxxxxxxxx0111111`).
+    Bits64,
+}
+
+impl InstructionLength {
+    /// Determines the length of an instruction from its first 16-bit parcel. Returns [None] for
+    /// the encodings reserved for instructions of 80 bits or longer.
+    pub const fn of(parcel: Word) -> Option<Self> {
+        if parcel & 0b11 != 0b11 {
+            Some(Self::Compressed)
+        } else if parcel & 0b1_1100 != 0b1_1100 {
+            Some(Self::Standard)
+        } else if parcel & 0b10_0000 == 0 {
+            Some(Self::Bits48)
+        } else if parcel & 0b100_0000 == 0 {
+            Some(Self::Bits64)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the length of the instruction in bytes.
+    pub const fn bytes(self) -> usize {
+        match self {
+            Self::Compressed => 2,
+            Self::Standard => 4,
+            Self::Bits48 => 6,
+            Self::Bits64 => 8,
+        }
+    }
+}
+
+/// An [Instruction] along with the encoding it was decoded from.
+///
+/// Decoding a compressed instruction expands it into its standard-length equivalent, which is
+/// all that execution needs. Traces, disassembly and code density statistics also need to know
+/// the original form, which this type preserves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedInstruction {
+    /// The decoded instruction. Compressed instructions are expanded.
+    pub instruction: Instruction,
+    /// The raw encoding of the instruction. Only the low 16 bits are set for compressed
+    /// instructions.
+    pub raw: Word,
+    /// The length of the encoding.
+    pub length: InstructionLength,
+    /// The compressed form of the instruction, if it was decoded from one.
+    #[cfg(feature = "c")]
+    compressed: Option<crate::CompressedInstruction>,
+}
+
+impl DecodedInstruction {
+    /// Decodes an instruction from the [Word] at the program counter, which may contain a
+    /// compressed instruction in its low 16 bits followed by unrelated bits.
+    pub fn decode(raw: Word) -> Result<Self, InstructionDecodeError> {
+        Self::decode_with::<NativeXlen>(raw, false)
+    }
+
+    /// Decodes an instruction like [Self::decode], rejecting every reserved or illegal encoding
+    /// as [Instruction::decode_strict] does.
+    pub fn decode_strict(raw: Word) -> Result<Self, InstructionDecodeError> {
+        Self::decode_with::<NativeXlen>(raw, true)
+    }
+
+    /// Decodes an instruction like [Self::decode], for a hart with the register width `X`.
+    pub fn decode_for<X: Xlen>(raw: Word) -> Result<Self, InstructionDecodeError> {
+        Self::decode_with::<X>(raw, false)
+    }
+
+    /// Decodes an instruction like [Self::decode_strict], for a hart with the register width `X`.
+    pub fn decode_strict_for<X: Xlen>(raw: Word) -> Result<Self, InstructionDecodeError> {
+        Self::decode_with::<X>(raw, true)
+    }
+
+    /// Determines the length of the instruction in `raw`, and decodes it for a hart with the
+    /// register width `X`.
+    fn decode_with<X: Xlen>(raw: Word, strict: bool) -> Result<Self, InstructionDecodeError> {
+        let length = InstructionLength::of(raw).ok_or_else(|| {
+            InstructionDecodeError::new(raw, DecodeErrorKind::InvalidOpcode((raw & 0x7F) as u8))
+        })?;
+        let raw = match length {
+            InstructionLength::Compressed => raw & 0xFFFF,
+            _ => raw,
+        };
+
+        #[cfg(feature = "c")]
+        if length == InstructionLength::Compressed {
+            let compressed = if strict {
+                crate::CompressedInstruction::decode_strict_for::<X>(raw as crate::HalfWord)?
+            } else {
+                crate::CompressedInstruction::decode_for::<X>(raw as crate::HalfWord)?
+            };
+            return Ok(Self {
+                instruction: compressed.expand(),
+                raw,
+                length,
+                compressed: Some(compressed),
+            });
+        }
+
+        let instruction = if strict {
+            Instruction::decode_strict_for::<X>(raw)?
+        } else {
+            Instruction::decode_for::<X>(raw)?
+        };
+        Ok(Self {
+            instruction,
+            raw,
+            length,
+            #[cfg(feature = "c")]
+            compressed: None,
+        })
+    }
+
+    /// Returns the length of the instruction's encoding in bytes.
+    pub const fn size(&self) -> usize {
+        self.length.bytes()
+    }
+
+    /// Returns `true` if the instruction was decoded from a 16-bit compressed encoding.
+    pub const fn is_compressed(&self) -> bool {
+        matches!(self.length, InstructionLength::Compressed)
+    }
+
+    /// Returns the compressed form of the instruction, if it was decoded from one.
+    #[cfg(feature = "c")]
+    pub const fn compressed(&self) -> Option<crate::CompressedInstruction> {
+        self.compressed
+    }
+}
+
+impl Display for DecodedInstruction {
+    /// Renders the instruction in its original form, so that `c.addi` is not shown as `addi`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        #[cfg(feature = "c")]
+        if let Some(compressed) = self.compressed() {
+            return compressed.fmt(f);
+        }
+        self.instruction.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0x0000_4505, Some(InstructionLength::Compressed))]
+    #[case(0x0000_0013, Some(InstructionLength::Standard))]
+    #[case(0x0000_001F, Some(InstructionLength::Bits48))]
+    #[case(0x0000_003F, Some(InstructionLength::Bits64))]
+    #[case(0x0000_007F, None)]
+    fn test_instruction_length(#[case] parcel: Word, #[case] expected: Option<InstructionLength>) {
+        assert_eq!(InstructionLength::of(parcel), expected);
+    }
+
+    #[test]
+    fn test_decode_standard() {
+        // addi a0, a0, 1
+        let decoded = DecodedInstruction::decode(0x00150513).unwrap();
+        assert_eq!(decoded.size(), 4);
+        assert!(!decoded.is_compressed());
+        assert_eq!(decoded.raw, 0x00150513);
+        assert_eq!(decoded.instruction, Instruction::try_from(0x00150513).unwrap());
+        assert_eq!(decoded.to_string(), "addi a0, a0, 1");
+    }
+
+    #[test]
+    #[cfg(feature = "c")]
+    fn test_decode_compressed() {
+        // c.addi a0, 1, followed by the low half of the next instruction.
+        let decoded = DecodedInstruction::decode(0x0513_0505).unwrap();
+        assert_eq!(decoded.size(), 2);
+        assert!(decoded.is_compressed());
+        assert_eq!(decoded.raw, 0x0505);
+        assert_eq!(decoded.instruction, Instruction::try_from(0x00150513).unwrap());
+        assert_eq!(decoded.compressed().map(|c| c.encode()), Some(0x0505));
+        assert_eq!(decoded.to_string(), "c.addi a0, 1");
+    }
+
+    #[test]
+    fn test_decode_strict() {
+        // csrr t5, mcause
+        assert!(DecodedInstruction::decode(0x34202f73).is_ok());
+        assert!(DecodedInstruction::decode_strict(0x34202f73).is_err());
+    }
+}
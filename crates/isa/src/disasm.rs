@@ -0,0 +1,690 @@
+//! Disassembly of decoded instructions into objdump-style assembly text.
+
+use crate::{
+    BranchFunction, EnvironmentFunction, ImmediateArithmeticFunction, Instruction, LoadFunction,
+    RegisterArithmeticFunction, SXWord, StoreFunction, XWord, SHIFT_MASK,
+};
+use core::fmt::{self, Display, Formatter};
+
+/// The ABI mnemonics of the 32 integer registers, indexed by register number.
+pub const REGISTER_ABI_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// Options controlling how an instruction is rendered by [Disassembly].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayOptions {
+    /// The address of the instruction. If set, PC-relative targets are rendered as absolute
+    /// addresses rather than as offsets from `.`.
+    pub pc: Option<XWord>,
+    /// Render registers by number (`x10`) rather than by ABI name (`a0`).
+    pub numeric_registers: bool,
+    /// Render the canonical instruction rather than recognizing pseudo-instructions.
+    pub no_aliases: bool,
+}
+
+impl DisplayOptions {
+    /// Creates a new set of [DisplayOptions] with the default rendering.
+    pub const fn new() -> Self {
+        Self { pc: None, numeric_registers: false, no_aliases: false }
+    }
+
+    /// Renders PC-relative targets as absolute addresses, relative to `pc`.
+    pub const fn with_pc(mut self, pc: XWord) -> Self {
+        self.pc = Some(pc);
+        self
+    }
+
+    /// Renders registers by number (`xN`) instead of by ABI name.
+    pub const fn with_numeric_registers(mut self) -> Self {
+        self.numeric_registers = true;
+        self
+    }
+
+    /// Disables pseudo-instruction recognition.
+    pub const fn without_aliases(mut self) -> Self {
+        self.no_aliases = true;
+        self
+    }
+
+    /// Returns a displayable register name for register `index`.
+    const fn reg(&self, index: u8) -> Register {
+        Register { index, numeric: self.numeric_registers }
+    }
+
+    /// Returns a displayable PC-relative target for the given `offset`.
+    const fn target(&self, offset: XWord) -> Target {
+        Target { offset, pc: self.pc }
+    }
+}
+
+/// A displayable disassembly of an instruction, created by `disassemble`.
+#[derive(Debug, Clone, Copy)]
+pub struct Disassembly<'a, T> {
+    /// The instruction being rendered.
+    instruction: &'a T,
+    /// The rendering options.
+    options: DisplayOptions,
+}
+
+impl Instruction {
+    /// Returns a [Display]able disassembly of the instruction, rendered with the given
+    /// [DisplayOptions].
+    pub const fn disassemble(&self, options: DisplayOptions) -> Disassembly<'_, Self> {
+        Disassembly { instruction: self, options }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.disassemble(DisplayOptions::new()).fmt(f)
+    }
+}
+
+impl Display for Disassembly<'_, Instruction> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let o = &self.options;
+        let alias = !o.no_aliases;
+
+        match *self.instruction {
+            Instruction::MemoryLoad(i, funct) => {
+                let mnemonic = match funct {
+                    LoadFunction::Lb => "lb",
+                    LoadFunction::Lh => "lh",
+                    LoadFunction::Lw => "lw",
+                    LoadFunction::Lbu => "lbu",
+                    LoadFunction::Lhu => "lhu",
+                    #[cfg(feature = "64-bit")]
+                    LoadFunction::Lwu => "lwu",
+                    #[cfg(feature = "64-bit")]
+                    LoadFunction::Ld => "ld",
+                    #[cfg(feature = "128-bit")]
+                    LoadFunction::Ldu => "ldu",
+                    #[cfg(feature = "128-bit")]
+                    LoadFunction::Lq => "lq",
+                };
+                write!(f, "{mnemonic} {}, {}({})", o.reg(i.rd), i.imm as SXWord, o.reg(i.rs1))
+            }
+            Instruction::MemoryStore(s, funct) => {
+                let mnemonic = match funct {
+                    StoreFunction::Sb => "sb",
+                    StoreFunction::Sh => "sh",
+                    StoreFunction::Sw => "sw",
+                    #[cfg(feature = "64-bit")]
+                    StoreFunction::Sd => "sd",
+                    #[cfg(feature = "128-bit")]
+                    StoreFunction::Sq => "sq",
+                };
+                write!(f, "{mnemonic} {}, {}({})", o.reg(s.rs2), s.imm as SXWord, o.reg(s.rs1))
+            }
+            Instruction::Branch(b, funct) => {
+                let target = o.target(b.imm);
+                match (funct, b.rs1, b.rs2) {
+                    (BranchFunction::Beq, rs1, 0) if alias => {
+                        write!(f, "beqz {}, {target}", o.reg(rs1))
+                    }
+                    (BranchFunction::Bne, rs1, 0) if alias => {
+                        write!(f, "bnez {}, {target}", o.reg(rs1))
+                    }
+                    (BranchFunction::Blt, rs1, 0) if alias => {
+                        write!(f, "bltz {}, {target}", o.reg(rs1))
+                    }
+                    (BranchFunction::Bge, rs1, 0) if alias => {
+                        write!(f, "bgez {}, {target}", o.reg(rs1))
+                    }
+                    (BranchFunction::Blt, 0, rs2) if alias => {
+                        write!(f, "bgtz {}, {target}", o.reg(rs2))
+                    }
+                    (BranchFunction::Bge, 0, rs2) if alias => {
+                        write!(f, "blez {}, {target}", o.reg(rs2))
+                    }
+                    (funct, rs1, rs2) => {
+                        let mnemonic = match funct {
+                            BranchFunction::Beq => "beq",
+                            BranchFunction::Bne => "bne",
+                            BranchFunction::Blt => "blt",
+                            BranchFunction::Bge => "bge",
+                            BranchFunction::Bltu => "bltu",
+                            BranchFunction::Bgeu => "bgeu",
+                        };
+                        write!(f, "{mnemonic} {}, {}, {target}", o.reg(rs1), o.reg(rs2))
+                    }
+                }
+            }
+            Instruction::ImmediateArithmetic(i, funct) => {
+                let imm = i.imm as SXWord;
+                match (funct, i.rd, i.rs1, imm) {
+                    (ImmediateArithmeticFunction::Addi, 0, 0, 0) if alias => f.write_str("nop"),
+                    (ImmediateArithmeticFunction::Addi, rd, 0, imm) if alias => {
+                        write!(f, "li {}, {imm}", o.reg(rd))
+                    }
+                    (ImmediateArithmeticFunction::Addi, rd, rs1, 0) if alias => {
+                        write!(f, "mv {}, {}", o.reg(rd), o.reg(rs1))
+                    }
+                    (ImmediateArithmeticFunction::Xori, rd, rs1, -1) if alias => {
+                        write!(f, "not {}, {}", o.reg(rd), o.reg(rs1))
+                    }
+                    (ImmediateArithmeticFunction::Sltiu, rd, rs1, 1) if alias => {
+                        write!(f, "seqz {}, {}", o.reg(rd), o.reg(rs1))
+                    }
+                    (ImmediateArithmeticFunction::Andi, rd, rs1, 0xFF) if alias => {
+                        write!(f, "zext.b {}, {}", o.reg(rd), o.reg(rs1))
+                    }
+                    (
+                        funct @ (ImmediateArithmeticFunction::Slli |
+                        ImmediateArithmeticFunction::Srli |
+                        ImmediateArithmeticFunction::Srai),
+                        rd,
+                        rs1,
+                        _,
+                    ) => {
+                        let mnemonic = match funct {
+                            ImmediateArithmeticFunction::Slli => "slli",
+                            ImmediateArithmeticFunction::Srli => "srli",
+                            _ => "srai",
+                        };
+                        let shamt = i.imm & SHIFT_MASK;
+                        write!(f, "{mnemonic} {}, {}, {shamt}", o.reg(rd), o.reg(rs1))
+                    }
+                    (funct, rd, rs1, imm) => {
+                        let mnemonic = match funct {
+                            ImmediateArithmeticFunction::Addi => "addi",
+                            ImmediateArithmeticFunction::Xori => "xori",
+                            ImmediateArithmeticFunction::Ori => "ori",
+                            ImmediateArithmeticFunction::Andi => "andi",
+                            ImmediateArithmeticFunction::Slti => "slti",
+                            ImmediateArithmeticFunction::Sltiu => "sltiu",
+                            _ => unreachable!("shifts are rendered above"),
+                        };
+                        write!(f, "{mnemonic} {}, {}, {imm}", o.reg(rd), o.reg(rs1))
+                    }
+                }
+            }
+            Instruction::RegisterArithmetic(r, funct) => match (funct, r.rd, r.rs1, r.rs2) {
+                (RegisterArithmeticFunction::Add, rd, 0, rs2) if alias => {
+                    write!(f, "mv {}, {}", o.reg(rd), o.reg(rs2))
+                }
+                (RegisterArithmeticFunction::Sub, rd, 0, rs2) if alias => {
+                    write!(f, "neg {}, {}", o.reg(rd), o.reg(rs2))
+                }
+                (RegisterArithmeticFunction::Sltu, rd, 0, rs2) if alias => {
+                    write!(f, "snez {}, {}", o.reg(rd), o.reg(rs2))
+                }
+                (RegisterArithmeticFunction::Slt, rd, rs1, 0) if alias => {
+                    write!(f, "sltz {}, {}", o.reg(rd), o.reg(rs1))
+                }
+                (RegisterArithmeticFunction::Slt, rd, 0, rs2) if alias => {
+                    write!(f, "sgtz {}, {}", o.reg(rd), o.reg(rs2))
+                }
+                (funct, rd, rs1, rs2) => {
+                    let mnemonic = match funct {
+                        RegisterArithmeticFunction::Add => "add",
+                        RegisterArithmeticFunction::Sub => "sub",
+                        RegisterArithmeticFunction::Xor => "xor",
+                        RegisterArithmeticFunction::Or => "or",
+                        RegisterArithmeticFunction::And => "and",
+                        RegisterArithmeticFunction::Sll => "sll",
+                        RegisterArithmeticFunction::Srl => "srl",
+                        RegisterArithmeticFunction::Sra => "sra",
+                        RegisterArithmeticFunction::Slt => "slt",
+                        RegisterArithmeticFunction::Sltu => "sltu",
+                        #[cfg(feature = "m")]
+                        RegisterArithmeticFunction::Mul => "mul",
+                        #[cfg(feature = "m")]
+                        RegisterArithmeticFunction::Mulh => "mulh",
+                        #[cfg(feature = "m")]
+                        RegisterArithmeticFunction::Mulhsu => "mulhsu",
+                        #[cfg(feature = "m")]
+                        RegisterArithmeticFunction::Mulhu => "mulhu",
+                        #[cfg(feature = "m")]
+                        RegisterArithmeticFunction::Div => "div",
+                        #[cfg(feature = "m")]
+                        RegisterArithmeticFunction::Divu => "divu",
+                        #[cfg(feature = "m")]
+                        RegisterArithmeticFunction::Rem => "rem",
+                        #[cfg(feature = "m")]
+                        RegisterArithmeticFunction::Remu => "remu",
+                    };
+                    write!(f, "{mnemonic} {}, {}, {}", o.reg(rd), o.reg(rs1), o.reg(rs2))
+                }
+            },
+            Instruction::Lui(u) => write!(f, "lui {}, {:#x}", o.reg(u.rd), upper_immediate(u.imm)),
+            Instruction::Auipc(u) => {
+                write!(f, "auipc {}, {:#x}", o.reg(u.rd), upper_immediate(u.imm))
+            }
+            Instruction::Jal(j) => {
+                let target = o.target(j.imm);
+                match j.rd {
+                    0 if alias => write!(f, "j {target}"),
+                    1 if alias => write!(f, "jal {target}"),
+                    rd => write!(f, "jal {}, {target}", o.reg(rd)),
+                }
+            }
+            Instruction::Jalr(i) => match (i.rd, i.rs1, i.imm) {
+                (0, 1, 0) if alias => f.write_str("ret"),
+                (0, rs1, 0) if alias => write!(f, "jr {}", o.reg(rs1)),
+                (1, rs1, 0) if alias => write!(f, "jalr {}", o.reg(rs1)),
+                (rd, rs1, imm) => {
+                    write!(f, "jalr {}, {}({})", o.reg(rd), imm as SXWord, o.reg(rs1))
+                }
+            },
+            Instruction::Environment(_, EnvironmentFunction::Ecall) => f.write_str("ecall"),
+            Instruction::Environment(_, EnvironmentFunction::Ebreak) => f.write_str("ebreak"),
+            Instruction::Fence => f.write_str("fence"),
+            #[cfg(feature = "64-bit")]
+            Instruction::ImmediateArithmeticWord(i, funct) => {
+                use crate::ImmediateArithmeticWordFunction as F;
+
+                match (funct, i.rd, i.rs1, i.imm) {
+                    (F::Addiw, rd, rs1, 0) if alias => {
+                        write!(f, "sext.w {}, {}", o.reg(rd), o.reg(rs1))
+                    }
+                    (F::Addiw, rd, rs1, imm) => {
+                        write!(f, "addiw {}, {}, {}", o.reg(rd), o.reg(rs1), imm as SXWord)
+                    }
+                    (funct, rd, rs1, imm) => {
+                        let mnemonic = match funct {
+                            F::Slliw => "slliw",
+                            F::Srliw => "srliw",
+                            _ => "sraiw",
+                        };
+                        write!(f, "{mnemonic} {}, {}, {}", o.reg(rd), o.reg(rs1), imm & 0x1F)
+                    }
+                }
+            }
+            #[cfg(feature = "64-bit")]
+            Instruction::RegisterArithmeticWord(r, funct) => {
+                use crate::RegisterArithmeticWordFunction as F;
+
+                match (funct, r.rd, r.rs1, r.rs2) {
+                    (F::Subw, rd, 0, rs2) if alias => {
+                        write!(f, "negw {}, {}", o.reg(rd), o.reg(rs2))
+                    }
+                    (funct, rd, rs1, rs2) => {
+                        let mnemonic = match funct {
+                            F::Addw => "addw",
+                            F::Subw => "subw",
+                            F::Sllw => "sllw",
+                            F::Srlw => "srlw",
+                            F::Sraw => "sraw",
+                            #[cfg(feature = "m")]
+                            F::Mulw => "mulw",
+                            #[cfg(feature = "m")]
+                            F::Divw => "divw",
+                            #[cfg(feature = "m")]
+                            F::Divuw => "divuw",
+                            #[cfg(feature = "m")]
+                            F::Remw => "remw",
+                            #[cfg(feature = "m")]
+                            F::Remuw => "remuw",
+                        };
+                        write!(f, "{mnemonic} {}, {}, {}", o.reg(rd), o.reg(rs1), o.reg(rs2))
+                    }
+                }
+            }
+            #[cfg(feature = "128-bit")]
+            Instruction::ImmediateArithmeticDouble(i, funct) => {
+                use crate::ImmediateArithmeticDoubleFunction as F;
+
+                match funct {
+                    F::Addid => {
+                        write!(f, "addid {}, {}, {}", o.reg(i.rd), o.reg(i.rs1), i.imm as SXWord)
+                    }
+                    funct => {
+                        let mnemonic = match funct {
+                            F::Sllid => "sllid",
+                            F::Srlid => "srlid",
+                            _ => "sraid",
+                        };
+                        write!(f, "{mnemonic} {}, {}, {}", o.reg(i.rd), o.reg(i.rs1), i.imm & 0x3F)
+                    }
+                }
+            }
+            #[cfg(feature = "128-bit")]
+            Instruction::RegisterArithmeticDouble(r, funct) => {
+                use crate::RegisterArithmeticDoubleFunction as F;
+
+                let mnemonic = match funct {
+                    F::Addd => "addd",
+                    F::Subd => "subd",
+                    F::Slld => "slld",
+                    F::Srld => "srld",
+                    F::Srad => "srad",
+                    #[cfg(feature = "m")]
+                    F::Muld => "muld",
+                    #[cfg(feature = "m")]
+                    F::Divd => "divd",
+                    #[cfg(feature = "m")]
+                    F::Divud => "divud",
+                    #[cfg(feature = "m")]
+                    F::Remd => "remd",
+                    #[cfg(feature = "m")]
+                    F::Remud => "remud",
+                };
+                write!(f, "{mnemonic} {}, {}, {}", o.reg(r.rd), o.reg(r.rs1), o.reg(r.rs2))
+            }
+            #[cfg(feature = "a")]
+            Instruction::Amo(r, funct) => {
+                use crate::AmoFunction as F;
+
+                let mnemonic = match funct {
+                    F::Lr => "lr",
+                    F::Sc => "sc",
+                    F::Amoswap => "amoswap",
+                    F::Amoadd => "amoadd",
+                    F::Amoxor => "amoxor",
+                    F::Amoand => "amoand",
+                    F::Amoor => "amoor",
+                    F::Amomin => "amomin",
+                    F::Amomax => "amomax",
+                    F::Amominu => "amominu",
+                    F::Amomaxu => "amomaxu",
+                };
+                let width = if r.funct3 == 0b011 { "d" } else { "w" };
+                let ordering = match r.funct7 & 0b11 {
+                    0b11 => ".aqrl",
+                    0b10 => ".aq",
+                    0b01 => ".rl",
+                    _ => "",
+                };
+                write!(f, "{mnemonic}.{width}{ordering} {}, ", o.reg(r.rd))?;
+                if !matches!(funct, F::Lr) {
+                    write!(f, "{}, ", o.reg(r.rs2))?;
+                }
+                write!(f, "({})", o.reg(r.rs1))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "c")]
+mod compressed {
+    use super::{upper_immediate, Disassembly, DisplayOptions};
+    use crate::{C1SubFunct, C2SubFunct, CompressedInstruction, SXWord, C0, C1, C2};
+    use core::fmt::{self, Display, Formatter};
+
+    impl CompressedInstruction {
+        /// Returns a [Display]able disassembly of the compressed instruction, rendered with the
+        /// given [DisplayOptions].
+        pub const fn disassemble(&self, options: DisplayOptions) -> Disassembly<'_, Self> {
+            Disassembly { instruction: self, options }
+        }
+    }
+
+    impl Display for CompressedInstruction {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            self.disassemble(DisplayOptions::new()).fmt(f)
+        }
+    }
+
+    impl Display for Disassembly<'_, CompressedInstruction> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            let o = &self.options;
+
+            // The operands are rendered from the expanded form, which has already unscrambled
+            // the immediates.
+            let expanded = self.instruction.expand();
+            let imm = expanded.immediate().unwrap_or_default();
+            let rd = expanded.rd().unwrap_or_default();
+            let rs1 = expanded.rs1().unwrap_or_default();
+            let rs2 = expanded.rs2().unwrap_or_default();
+
+            let (mnemonic, form) = match *self.instruction {
+                CompressedInstruction::C0(c0) => match c0 {
+                    C0::CAddi4spn(_) => ("c.addi4spn", Form::RdRs1Imm),
+                    C0::CLw(_) => ("c.lw", Form::Load),
+                    C0::CSw(_) => ("c.sw", Form::Store),
+                    #[cfg(feature = "64-bit")]
+                    C0::CLd(_) => ("c.ld", Form::Load),
+                    #[cfg(feature = "64-bit")]
+                    C0::CSd(_) => ("c.sd", Form::Store),
+                },
+                CompressedInstruction::C1(c1) => match c1 {
+                    C1::CAddi(ci) if ci.rs1_rd == 0 && imm == 0 => ("c.nop", Form::None),
+                    C1::CAddi(_) => ("c.addi", Form::RdImm),
+                    C1::CJal(_) => ("c.jal", Form::Target),
+                    C1::CLi(_) => ("c.li", Form::RdImm),
+                    C1::CAddi16sp(_) => ("c.addi16sp", Form::RdImm),
+                    C1::CLui(_) => ("c.lui", Form::RdUpper),
+                    C1::SubFunct(sub) => match sub {
+                        C1SubFunct::CSrli(_) => ("c.srli", Form::RdShamt),
+                        C1SubFunct::CSrai(_) => ("c.srai", Form::RdShamt),
+                        C1SubFunct::CAndi(_) => ("c.andi", Form::RdImm),
+                        C1SubFunct::CSub(_) => ("c.sub", Form::RdRs2),
+                        C1SubFunct::CXor(_) => ("c.xor", Form::RdRs2),
+                        C1SubFunct::COr(_) => ("c.or", Form::RdRs2),
+                        C1SubFunct::CAnd(_) => ("c.and", Form::RdRs2),
+                        #[cfg(feature = "64-bit")]
+                        C1SubFunct::CSubw(_) => ("c.subw", Form::RdRs2),
+                        #[cfg(feature = "64-bit")]
+                        C1SubFunct::CAddw(_) => ("c.addw", Form::RdRs2),
+                    },
+                    C1::CJ(_) => ("c.j", Form::Target),
+                    C1::CBeqz(_) => ("c.beqz", Form::Rs1Target),
+                    C1::CBnez(_) => ("c.bnez", Form::Rs1Target),
+                    #[cfg(feature = "64-bit")]
+                    C1::CAddiw(_) => ("c.addiw", Form::RdImm),
+                },
+                CompressedInstruction::C2(c2) => match c2 {
+                    C2::CSlli(_) => ("c.slli", Form::RdShamt),
+                    C2::CLwsp(_) => ("c.lwsp", Form::Load),
+                    C2::CSwsp(_) => ("c.swsp", Form::Store),
+                    #[cfg(feature = "64-bit")]
+                    C2::CLdsp(_) => ("c.ldsp", Form::Load),
+                    #[cfg(feature = "64-bit")]
+                    C2::CSdsp(_) => ("c.sdsp", Form::Store),
+                    C2::SubFunct(sub) => match sub {
+                        C2SubFunct::CJr(_) => ("c.jr", Form::Rs1),
+                        C2SubFunct::CMv(_) => ("c.mv", Form::RdRs2),
+                        C2SubFunct::CEBreak => ("c.ebreak", Form::None),
+                        C2SubFunct::CJalr(_) => ("c.jalr", Form::Rs1),
+                        C2SubFunct::CAdd(_) => ("c.add", Form::RdRs2),
+                    },
+                },
+            };
+
+            f.write_str(mnemonic)?;
+            match form {
+                Form::None => Ok(()),
+                Form::RdImm => write!(f, " {}, {}", o.reg(rd), imm as SXWord),
+                Form::RdShamt => write!(f, " {}, {}", o.reg(rd), imm & crate::SHIFT_MASK),
+                Form::RdUpper => write!(f, " {}, {:#x}", o.reg(rd), upper_immediate(imm)),
+                Form::RdRs1Imm => {
+                    write!(f, " {}, {}, {}", o.reg(rd), o.reg(rs1), imm as SXWord)
+                }
+                Form::RdRs2 => write!(f, " {}, {}", o.reg(rd), o.reg(rs2)),
+                Form::Rs1 => write!(f, " {}", o.reg(rs1)),
+                Form::Load => write!(f, " {}, {}({})", o.reg(rd), imm as SXWord, o.reg(rs1)),
+                Form::Store => write!(f, " {}, {}({})", o.reg(rs2), imm as SXWord, o.reg(rs1)),
+                Form::Target => write!(f, " {}", o.target(imm)),
+                Form::Rs1Target => write!(f, " {}, {}", o.reg(rs1), o.target(imm)),
+            }
+        }
+    }
+
+    /// The operand layout of a compressed instruction's assembly syntax.
+    #[derive(Debug, Clone, Copy)]
+    enum Form {
+        /// No operands.
+        None,
+        /// `rd, imm`
+        RdImm,
+        /// `rd, shamt`
+        RdShamt,
+        /// `rd, imm[31:12]`
+        RdUpper,
+        /// `rd, rs1, imm`
+        RdRs1Imm,
+        /// `rd, rs2`
+        RdRs2,
+        /// `rs1`
+        Rs1,
+        /// `rd, imm(rs1)`
+        Load,
+        /// `rs2, imm(rs1)`
+        Store,
+        /// `target`
+        Target,
+        /// `rs1, target`
+        Rs1Target,
+    }
+}
+
+/// Returns the 20-bit upper immediate of a `lui` / `auipc` instruction, as written in assembly.
+const fn upper_immediate(imm: XWord) -> XWord {
+    (imm >> 12) & 0xF_FFFF
+}
+
+/// A register operand, rendered by ABI name or by number.
+#[derive(Debug, Clone, Copy)]
+struct Register {
+    /// The register number.
+    index: u8,
+    /// Whether to render the register by number.
+    numeric: bool,
+}
+
+impl Display for Register {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.numeric {
+            write!(f, "x{}", self.index)
+        } else {
+            f.write_str(REGISTER_ABI_NAMES[self.index as usize & 0x1F])
+        }
+    }
+}
+
+/// A PC-relative target operand.
+#[derive(Debug, Clone, Copy)]
+struct Target {
+    /// The sign-extended offset from the instruction's address.
+    offset: XWord,
+    /// The address of the instruction, if known.
+    pc: Option<XWord>,
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.pc {
+            Some(pc) => write!(f, "{:#x}", pc.wrapping_add(self.offset)),
+            None if (self.offset as SXWord) < 0 => write!(f, ".-{}", (self.offset as SXWord).abs()),
+            None => write!(f, ".+{}", self.offset),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0x01010513, "addi a0, sp, 16")]
+    #[case(0x00000013, "nop")]
+    #[case(0x00500513, "li a0, 5")]
+    #[case(0xfff00513, "li a0, -1")]
+    #[case(0x00058513, "mv a0, a1")]
+    #[case(0xfff5c513, "not a0, a1")]
+    #[case(0x40b00533, "neg a0, a1")]
+    #[case(0x0015b513, "seqz a0, a1")]
+    #[case(0x00b03533, "snez a0, a1")]
+    #[case(0x00008067, "ret")]
+    #[case(0x00050067, "jr a0")]
+    #[case(0x000500e7, "jalr a0")]
+    #[case(0x004500e7, "jalr ra, 4(a0)")]
+    #[case(0x00c5a503, "lw a0, 12(a1)")]
+    #[case(0xfea42c23, "sw a0, -8(s0)")]
+    #[case(0x00b50533, "add a0, a0, a1")]
+    #[case(0x00451513, "slli a0, a0, 4")]
+    #[case(0x40455513, "srai a0, a0, 4")]
+    #[case(0x12345537, "lui a0, 0x12345")]
+    #[case(0xfffff517, "auipc a0, 0xfffff")]
+    #[case(0x00000073, "ecall")]
+    #[case(0x00100073, "ebreak")]
+    #[case(0x0ff0000f, "fence")]
+    fn test_display(#[case] raw: u32, #[case] expected: &str) {
+        let instruction = Instruction::try_from(raw).unwrap();
+        assert_eq!(instruction.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case(0x00050463, ".+8", "0x1008")]
+    #[case(0xfe050ee3, ".-4", "0xffc")]
+    fn test_display_branch_target(
+        #[case] raw: u32,
+        #[case] relative: &str,
+        #[case] absolute: &str,
+    ) {
+        let instruction = Instruction::try_from(raw).unwrap();
+        assert_eq!(instruction.to_string(), format!("beqz a0, {relative}"));
+        assert_eq!(
+            instruction.disassemble(DisplayOptions::new().with_pc(0x1000)).to_string(),
+            format!("beqz a0, {absolute}")
+        );
+    }
+
+    #[test]
+    fn test_display_jal() {
+        let j = Instruction::try_from(0x0100006f).unwrap();
+        assert_eq!(j.to_string(), "j .+16");
+        let call = Instruction::try_from(0x010000ef).unwrap();
+        assert_eq!(call.disassemble(DisplayOptions::new().with_pc(0x100)).to_string(), "jal 0x110");
+    }
+
+    #[test]
+    fn test_display_numeric_and_no_aliases() {
+        let instruction = Instruction::try_from(0x00500513).unwrap();
+        let options = DisplayOptions::new().with_numeric_registers().without_aliases();
+        assert_eq!(instruction.disassemble(options).to_string(), "addi x10, x0, 5");
+    }
+
+    #[test]
+    #[cfg(feature = "64-bit")]
+    fn test_display_rv64() {
+        let sext = Instruction::try_from(0x0005051b).unwrap();
+        assert_eq!(sext.to_string(), "sext.w a0, a0");
+        let ld = Instruction::try_from(0x00813083).unwrap();
+        assert_eq!(ld.to_string(), "ld ra, 8(sp)");
+    }
+
+    #[rstest]
+    #[cfg(feature = "128-bit")]
+    #[case(0x0101250f, "lq a0, 16(sp)")]
+    #[case(0x00a14823, "sq a0, 16(sp)")]
+    #[case(0x00817503, "ldu a0, 8(sp)")]
+    #[case(0x04051513, "slli a0, a0, 64")]
+    #[case(0x0015855b, "addid a0, a1, 1")]
+    #[case(0x4205d55b, "sraid a0, a1, 32")]
+    #[case(0x40c5857b, "subd a0, a1, a2")]
+    fn test_display_rv128(#[case] raw: u32, #[case] expected: &str) {
+        let instruction = Instruction::try_from(raw).unwrap();
+        assert_eq!(instruction.to_string(), expected);
+    }
+
+    #[test]
+    #[cfg(feature = "a")]
+    fn test_display_amo() {
+        let amo = Instruction::try_from(0x06b5252f).unwrap();
+        assert_eq!(amo.to_string(), "amoadd.w.aqrl a0, a1, (a0)");
+        let lr = Instruction::try_from(0x1005252f).unwrap();
+        assert_eq!(lr.to_string(), "lr.w a0, (a0)");
+    }
+
+    #[rstest]
+    #[cfg(feature = "c")]
+    #[case(0x0001, "c.nop")]
+    #[case(0x0505, "c.addi a0, 1")]
+    #[case(0x4515, "c.li a0, 5")]
+    #[case(0x852e, "c.mv a0, a1")]
+    #[case(0x8082, "c.jr ra")]
+    #[case(0x0808, "c.addi4spn a0, sp, 16")]
+    #[case(0x4188, "c.lw a0, 0(a1)")]
+    #[case(0x9002, "c.ebreak")]
+    #[case(0x6505, "c.lui a0, 0x1")]
+    fn test_display_compressed(#[case] raw: u16, #[case] expected: &str) {
+        let instruction = crate::CompressedInstruction::decode(raw).unwrap();
+        assert_eq!(instruction.to_string(), expected);
+    }
+}
@@ -1,21 +1,167 @@
 //! Errors for the `brisc-isa` crate.
 
+use crate::{feature::gated_instruction, Extension, Feature, Word};
+use core::fmt::{self, Display, Formatter};
 use thiserror::Error;
 
 /// An error that occurs when decoding an [Instruction].
 ///
 /// [Instruction]: crate::Instruction
 #[derive(Error, Clone, Copy, Debug, PartialEq, Eq)]
-pub enum InstructionDecodeError {
+#[error("Failed to decode instruction {raw:#010x}: {kind}")]
+pub struct InstructionDecodeError {
+    /// The raw bits of the instruction. Only the low 16 bits are set for compressed instructions.
+    pub raw: Word,
+    /// The reason that the instruction could not be decoded.
+    pub kind: DecodeErrorKind,
+}
+
+impl InstructionDecodeError {
+    /// Creates a new [InstructionDecodeError] for the instruction `raw`.
+    ///
+    /// If `raw` is not [DecodeErrorKind::Reserved], but is a valid instruction from an extension
+    /// that is disabled in this build, the `kind` is replaced with
+    /// [DecodeErrorKind::FeatureDisabled] so that the error says which feature to enable.
+    pub fn new(raw: Word, kind: DecodeErrorKind) -> Self {
+        let kind = match (kind, gated_instruction(raw)) {
+            (DecodeErrorKind::Reserved, _) => kind,
+            (_, Some((mnemonic, features))) if features.iter().any(|f| !f.is_enabled()) => {
+                DecodeErrorKind::FeatureDisabled { mnemonic, features }
+            }
+            _ => kind,
+        };
+        Self { raw, kind }
+    }
+}
+
+/// The reason that an instruction could not be decoded.
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeErrorKind {
     /// Invalid opcode.
     #[error("Invalid opcode: {0:07b}")]
     InvalidOpcode(u8),
     /// Invalid function qualifiers.
-    #[error("Invalid function qualifiers: {q_b:03b} and {q_b:07b}")]
+    #[error("Invalid function qualifiers: {q_a:03b} and {q_b:07b}")]
     InvalidFunction {
         /// First function qualifier.
         q_a: u8,
         /// Second function qualifier.
         q_b: u8,
     },
+    /// An encoding that the specification reserves, rejected by strict decoding.
+    #[error("Reserved encoding")]
+    Reserved,
+    /// A valid instruction from an extension that is disabled in this build.
+    #[error("`{mnemonic}` requires the {}", FeatureList(features))]
+    FeatureDisabled {
+        /// The mnemonic of the instruction.
+        mnemonic: &'static str,
+        /// The crate features that the instruction requires.
+        features: &'static [Feature],
+    },
+    /// A valid instruction that is only available on harts with a wider register width.
+    #[error("`{mnemonic}` is not available on RV{xlen}")]
+    XlenTooNarrow {
+        /// The mnemonic of the instruction.
+        mnemonic: &'static str,
+        /// The register width of the hart.
+        xlen: usize,
+    },
+    /// A valid instruction from an extension that the [IsaConfig] disables.
+    ///
+    /// [IsaConfig]: crate::IsaConfig
+    #[error("Extension `{}` is disabled by the ISA configuration", .0.letter())]
+    ExtensionDisabled(Extension),
+}
+
+/// An error that occurs when parsing an [IsaConfig] from an ISA string.
+///
+/// [IsaConfig]: crate::IsaConfig
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IsaParseError {
+    /// The ISA string does not start with `rv`.
+    #[error("ISA string must start with `rv`")]
+    MissingPrefix,
+    /// The XLEN is missing, or differs from the register width of the hart.
+    #[error("XLEN {0} does not match the register width of the hart")]
+    UnsupportedXlen(u32),
+    /// The XLEN is not followed by the `i` base integer instruction set.
+    #[error("ISA string must name the `i` base integer instruction set after the XLEN")]
+    MissingBase,
+    /// A single-letter extension that `brisc` does not implement.
+    #[error("Unsupported extension `{0}`")]
+    UnsupportedExtension(char),
+    /// A multi-letter extension that is not recognized, starting at the given byte offset.
+    #[error("Unknown extension at byte {0}")]
+    UnknownExtension(usize),
+    /// An extension, or the XLEN, that is implemented but not compiled into this build.
+    #[error("`{name}` requires the `{}` feature", feature.name())]
+    FeatureDisabled {
+        /// The name of the extension, or `rv64` / `rv128`.
+        name: &'static str,
+        /// The crate feature that must be enabled.
+        feature: Feature,
+    },
+}
+
+/// Renders a list of features as "`a` feature", "`a` and `b` features", and so on.
+struct FeatureList(&'static [Feature]);
+
+impl Display for FeatureList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, feature) in self.0.iter().enumerate() {
+            match i {
+                0 => {}
+                i if i + 1 == self.0.len() => f.write_str(" and ")?,
+                _ => f.write_str(", ")?,
+            }
+            write!(f, "`{}`", feature.name())?;
+        }
+        f.write_str(if self.0.len() == 1 { " feature" } else { " features" })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_invalid_function_message() {
+        let err = InstructionDecodeError::new(
+            0x0000_2063,
+            DecodeErrorKind::InvalidFunction { q_a: 0b010, q_b: 0 },
+        );
+        assert_eq!(
+            err.to_string(),
+            "Failed to decode instruction 0x00002063: Invalid function qualifiers: 010 and 0000000"
+        );
+    }
+
+    #[test]
+    fn test_feature_disabled_message() {
+        // mulw a0, a1, a2
+        let err = InstructionDecodeError::new(0x02c5853b, DecodeErrorKind::InvalidOpcode(0x3b));
+        if cfg!(all(feature = "64-bit", feature = "m")) {
+            assert_eq!(err.kind, DecodeErrorKind::InvalidOpcode(0x3b));
+        } else {
+            assert_eq!(
+                err.to_string(),
+                "Failed to decode instruction 0x02c5853b: `mulw` requires the `64-bit` and `m` \
+                 features"
+            );
+        }
+    }
+
+    #[test]
+    fn test_feature_list() {
+        assert_eq!(FeatureList(&[Feature::M]).to_string(), "`m` feature");
+        assert_eq!(
+            FeatureList(&[Feature::Rv64, Feature::M]).to_string(),
+            "`64-bit` and `m` features"
+        );
+        assert_eq!(
+            FeatureList(&[Feature::Rv64, Feature::M, Feature::A]).to_string(),
+            "`64-bit`, `m` and `a` features"
+        );
+    }
 }
@@ -0,0 +1,274 @@
+//! Crate features that gate parts of the ISA, and the instructions that they gate.
+
+use crate::{DecodeErrorKind, InstructionDecodeError, Word, Xlen};
+
+/// A crate feature that gates part of the ISA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// The `64-bit` feature, enabling RV64I.
+    Rv64,
+    /// The `128-bit` feature, enabling the experimental RV128I.
+    Rv128,
+    /// The `m` feature, enabling the M extension.
+    M,
+    /// The `a` feature, enabling the A extension.
+    A,
+    /// The `c` feature, enabling the C extension.
+    C,
+}
+
+impl Feature {
+    /// Returns the name of the crate feature.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Rv64 => "64-bit",
+            Self::Rv128 => "128-bit",
+            Self::M => "m",
+            Self::A => "a",
+            Self::C => "c",
+        }
+    }
+
+    /// Returns `true` if the feature is enabled in the current build.
+    pub const fn is_enabled(self) -> bool {
+        match self {
+            Self::Rv64 => cfg!(feature = "64-bit"),
+            Self::Rv128 => cfg!(feature = "128-bit"),
+            Self::M => cfg!(feature = "m"),
+            Self::A => cfg!(feature = "a"),
+            Self::C => cfg!(feature = "c"),
+        }
+    }
+}
+
+/// Rejects instructions that are only available on harts wider than `X`, such as `ld` or
+/// `c.addiw` on RV32, and `lq` on RV64.
+pub(crate) fn check_xlen<X: Xlen>(raw: Word) -> Result<(), InstructionDecodeError> {
+    match gated_instruction(raw) {
+        // Instructions from disabled extensions are reported by the decoder instead.
+        Some((mnemonic, features))
+            if X::X_LEN < required_xlen(features) && features.iter().all(|f| f.is_enabled()) =>
+        {
+            Err(InstructionDecodeError {
+                raw,
+                kind: DecodeErrorKind::XlenTooNarrow { mnemonic, xlen: X::X_LEN },
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns the narrowest register width that implements an instruction requiring `features`.
+fn required_xlen(features: &[Feature]) -> usize {
+    if features.contains(&Feature::Rv128) {
+        128
+    } else if features.contains(&Feature::Rv64) {
+        64
+    } else {
+        32
+    }
+}
+
+/// Recognizes encodings of instructions that only some builds of the decoder support, returning
+/// the mnemonic of the instruction and the features that it requires.
+///
+/// This is independent of the features enabled in the current build, so that an encoding that
+/// fails to decode can be explained. Returns [None] for base RV32I instructions and for encodings
+/// that no build supports.
+pub(crate) const fn gated_instruction(raw: Word) -> Option<(&'static str, &'static [Feature])> {
+    if raw & 0b11 != 0b11 {
+        return gated_compressed_instruction(raw);
+    }
+
+    const RV64: &[Feature] = &[Feature::Rv64];
+    const M: &[Feature] = &[Feature::M];
+    const RV64_M: &[Feature] = &[Feature::Rv64, Feature::M];
+    const RV128: &[Feature] = &[Feature::Rv128];
+    const RV128_M: &[Feature] = &[Feature::Rv128, Feature::M];
+
+    let opcode = raw & 0x7F;
+    let funct3 = (raw >> 12) & 0x7;
+    let funct7 = raw >> 25;
+    let mnemonic = match (opcode, funct3, funct7) {
+        (0b000_0011, 0b110, _) => ("lwu", RV64),
+        (0b000_0011, 0b011, _) => ("ld", RV64),
+        (0b000_0011, 0b111, _) => ("ldu", RV128),
+        (0b000_1111, 0b010, _) => ("lq", RV128),
+        (0b010_0011, 0b011, _) => ("sd", RV64),
+        (0b010_0011, 0b100, _) => ("sq", RV128),
+        (0b001_0011, 0b001, 0b000_0001) => ("slli", RV64),
+        (0b001_0011, 0b001, 0b000_0010 | 0b000_0011) => ("slli", RV128),
+        (0b001_0011, 0b101, 0b000_0001) => ("srli", RV64),
+        (0b001_0011, 0b101, 0b000_0010 | 0b000_0011) => ("srli", RV128),
+        (0b001_0011, 0b101, 0b010_0001) => ("srai", RV64),
+        (0b001_0011, 0b101, 0b010_0010 | 0b010_0011) => ("srai", RV128),
+        (0b011_0011, 0b000, 0b000_0001) => ("mul", M),
+        (0b011_0011, 0b001, 0b000_0001) => ("mulh", M),
+        (0b011_0011, 0b010, 0b000_0001) => ("mulhsu", M),
+        (0b011_0011, 0b011, 0b000_0001) => ("mulhu", M),
+        (0b011_0011, 0b100, 0b000_0001) => ("div", M),
+        (0b011_0011, 0b101, 0b000_0001) => ("divu", M),
+        (0b011_0011, 0b110, 0b000_0001) => ("rem", M),
+        (0b011_0011, 0b111, 0b000_0001) => ("remu", M),
+        (0b001_1011, 0b000, _) => ("addiw", RV64),
+        (0b001_1011, 0b001, 0b000_0000) => ("slliw", RV64),
+        (0b001_1011, 0b101, 0b000_0000) => ("srliw", RV64),
+        (0b001_1011, 0b101, 0b010_0000) => ("sraiw", RV64),
+        (0b011_1011, 0b000, 0b000_0000) => ("addw", RV64),
+        (0b011_1011, 0b000, 0b010_0000) => ("subw", RV64),
+        (0b011_1011, 0b001, 0b000_0000) => ("sllw", RV64),
+        (0b011_1011, 0b101, 0b000_0000) => ("srlw", RV64),
+        (0b011_1011, 0b101, 0b010_0000) => ("sraw", RV64),
+        (0b011_1011, 0b000, 0b000_0001) => ("mulw", RV64_M),
+        (0b011_1011, 0b100, 0b000_0001) => ("divw", RV64_M),
+        (0b011_1011, 0b101, 0b000_0001) => ("divuw", RV64_M),
+        (0b011_1011, 0b110, 0b000_0001) => ("remw", RV64_M),
+        (0b011_1011, 0b111, 0b000_0001) => ("remuw", RV64_M),
+        (0b101_1011, 0b000, _) => ("addid", RV128),
+        (0b101_1011, 0b001, 0b000_0000) => ("sllid", RV128),
+        (0b101_1011, 0b101, 0b000_0000) => ("srlid", RV128),
+        (0b101_1011, 0b101, 0b010_0000) => ("sraid", RV128),
+        (0b111_1011, 0b000, 0b000_0000) => ("addd", RV128),
+        (0b111_1011, 0b000, 0b010_0000) => ("subd", RV128),
+        (0b111_1011, 0b001, 0b000_0000) => ("slld", RV128),
+        (0b111_1011, 0b101, 0b000_0000) => ("srld", RV128),
+        (0b111_1011, 0b101, 0b010_0000) => ("srad", RV128),
+        (0b111_1011, 0b000, 0b000_0001) => ("muld", RV128_M),
+        (0b111_1011, 0b100, 0b000_0001) => ("divd", RV128_M),
+        (0b111_1011, 0b101, 0b000_0001) => ("divud", RV128_M),
+        (0b111_1011, 0b110, 0b000_0001) => ("remd", RV128_M),
+        (0b111_1011, 0b111, 0b000_0001) => ("remud", RV128_M),
+        (0b010_1111, 0b010 | 0b011, _) => return gated_amo_instruction(raw),
+        _ => return None,
+    };
+    Some(mnemonic)
+}
+
+/// Recognizes the `lr`, `sc` and `amo*` instructions of the A extension.
+const fn gated_amo_instruction(raw: Word) -> Option<(&'static str, &'static [Feature])> {
+    const A: &[Feature] = &[Feature::A];
+    const RV64_A: &[Feature] = &[Feature::Rv64, Feature::A];
+
+    let (word, double) = match raw >> 27 {
+        0b00010 => ("lr.w", "lr.d"),
+        0b00011 => ("sc.w", "sc.d"),
+        0b00001 => ("amoswap.w", "amoswap.d"),
+        0b00000 => ("amoadd.w", "amoadd.d"),
+        0b00100 => ("amoxor.w", "amoxor.d"),
+        0b01100 => ("amoand.w", "amoand.d"),
+        0b01000 => ("amoor.w", "amoor.d"),
+        0b10000 => ("amomin.w", "amomin.d"),
+        0b10100 => ("amomax.w", "amomax.d"),
+        0b11000 => ("amominu.w", "amominu.d"),
+        0b11100 => ("amomaxu.w", "amomaxu.d"),
+        _ => return None,
+    };
+    if (raw >> 12) & 0x7 == 0b011 {
+        Some((double, RV64_A))
+    } else {
+        Some((word, A))
+    }
+}
+
+/// Recognizes the 16-bit instructions of the C extension.
+const fn gated_compressed_instruction(raw: Word) -> Option<(&'static str, &'static [Feature])> {
+    const C: &[Feature] = &[Feature::C];
+    const C_RV64: &[Feature] = &[Feature::C, Feature::Rv64];
+
+    let quadrant = raw & 0b11;
+    let funct3 = (raw >> 13) & 0x7;
+    let bit12 = (raw >> 12) & 0b1 != 0;
+    let rd = (raw >> 7) & 0x1F;
+    let rs2 = (raw >> 2) & 0x1F;
+    let mnemonic = match (quadrant, funct3) {
+        (0b00, 0b000) => ("c.addi4spn", C),
+        (0b00, 0b010) => ("c.lw", C),
+        (0b00, 0b011) => ("c.ld", C_RV64),
+        (0b00, 0b110) => ("c.sw", C),
+        (0b00, 0b111) => ("c.sd", C_RV64),
+        (0b01, 0b000) => ("c.addi", C),
+        (0b01, 0b001) if cfg!(feature = "64-bit") => ("c.addiw", C_RV64),
+        (0b01, 0b001) => ("c.jal", C),
+        (0b01, 0b010) => ("c.li", C),
+        (0b01, 0b011) if rd == 2 => ("c.addi16sp", C),
+        (0b01, 0b011) => ("c.lui", C),
+        (0b01, 0b100) => match ((raw >> 10) & 0b11, bit12, (raw >> 5) & 0b11) {
+            (0b00, false, _) => ("c.srli", C),
+            (0b00, true, _) => ("c.srli", C_RV64),
+            (0b01, false, _) => ("c.srai", C),
+            (0b01, true, _) => ("c.srai", C_RV64),
+            (0b10, _, _) => ("c.andi", C),
+            (0b11, false, 0b00) => ("c.sub", C),
+            (0b11, false, 0b01) => ("c.xor", C),
+            (0b11, false, 0b10) => ("c.or", C),
+            (0b11, false, _) => ("c.and", C),
+            (_, true, 0b00) => ("c.subw", C_RV64),
+            (_, true, 0b01) => ("c.addw", C_RV64),
+            _ => return None,
+        },
+        (0b01, 0b101) => ("c.j", C),
+        (0b01, 0b110) => ("c.beqz", C),
+        (0b01, 0b111) => ("c.bnez", C),
+        (0b10, 0b000) if bit12 => ("c.slli", C_RV64),
+        (0b10, 0b000) => ("c.slli", C),
+        (0b10, 0b010) => ("c.lwsp", C),
+        (0b10, 0b011) => ("c.ldsp", C_RV64),
+        (0b10, 0b100) => match (bit12, rd, rs2) {
+            (false, _, 0) => ("c.jr", C),
+            (false, _, _) => ("c.mv", C),
+            (true, 0, 0) => ("c.ebreak", C),
+            (true, _, 0) => ("c.jalr", C),
+            (true, _, _) => ("c.add", C),
+        },
+        (0b10, 0b110) => ("c.swsp", C),
+        (0b10, 0b111) => ("c.sdsp", C_RV64),
+        _ => return None,
+    };
+    Some(mnemonic)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    // add a0, a1, a2
+    #[case(0x00c58533, None)]
+    // csrr t5, mcause
+    #[case(0x34202f73, None)]
+    // mul a0, a1, a2
+    #[case(0x02c58533, Some(("mul", &[Feature::M][..])))]
+    // mulw a0, a1, a2
+    #[case(0x02c5853b, Some(("mulw", &[Feature::Rv64, Feature::M][..])))]
+    // ld a0, 0(sp)
+    #[case(0x00013503, Some(("ld", &[Feature::Rv64][..])))]
+    // srai a0, a0, 32
+    #[case(0x42055513, Some(("srai", &[Feature::Rv64][..])))]
+    // amoadd.w a0, a2, (a1)
+    #[case(0x00c5a52f, Some(("amoadd.w", &[Feature::A][..])))]
+    // lr.d a0, (a1)
+    #[case(0x1005b52f, Some(("lr.d", &[Feature::Rv64, Feature::A][..])))]
+    // c.li a0, 1
+    #[case(0x4505, Some(("c.li", &[Feature::C][..])))]
+    // c.ldsp ra, 8(sp)
+    #[case(0x60a2, Some(("c.ldsp", &[Feature::C, Feature::Rv64][..])))]
+    // c.addw a0, a1
+    #[case(0x9d2d, Some(("c.addw", &[Feature::C, Feature::Rv64][..])))]
+    // c.sub a0, a1
+    #[case(0x8d0d, Some(("c.sub", &[Feature::C][..])))]
+    // c.fld fa0, 0(a0)
+    #[case(0x2108, None)]
+    // lq a0, 16(sp)
+    #[case(0x0101250f, Some(("lq", &[Feature::Rv128][..])))]
+    // slli a0, a0, 64
+    #[case(0x04051513, Some(("slli", &[Feature::Rv128][..])))]
+    // muld a0, a1, a2
+    #[case(0x02c5857b, Some(("muld", &[Feature::Rv128, Feature::M][..])))]
+    fn test_gated_instruction(
+        #[case] raw: Word,
+        #[case] expected: Option<(&'static str, &'static [Feature])>,
+    ) {
+        assert_eq!(gated_instruction(raw), expected);
+    }
+}
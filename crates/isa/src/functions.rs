@@ -1,6 +1,6 @@
 //! Contains function definitions for the ISA.
 
-use crate::{bits, BType, IType, InstructionDecodeError, RType, SType};
+use crate::{bits, BType, DecodeErrorKind, IType, RType, SType};
 
 /// Functions for Integer Register-Register Instructions.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -51,8 +51,42 @@ pub enum RegisterArithmeticFunction {
     Remu,
 }
 
+impl RegisterArithmeticFunction {
+    /// Returns the `funct3` and `funct7` fields that encode the function.
+    pub const fn encoding(&self) -> (u8, u8) {
+        match self {
+            Self::Add => (0x00, 0x00),
+            Self::Sub => (0x00, 0x20),
+            Self::Xor => (0x04, 0x00),
+            Self::Or => (0x06, 0x00),
+            Self::And => (0x07, 0x00),
+            Self::Sll => (0x01, 0x00),
+            Self::Srl => (0x05, 0x00),
+            Self::Sra => (0x05, 0x20),
+            Self::Slt => (0x02, 0x00),
+            Self::Sltu => (0x03, 0x00),
+            #[cfg(feature = "m")]
+            Self::Mul => (0x00, 0x01),
+            #[cfg(feature = "m")]
+            Self::Mulh => (0x01, 0x01),
+            #[cfg(feature = "m")]
+            Self::Mulhsu => (0x02, 0x01),
+            #[cfg(feature = "m")]
+            Self::Mulhu => (0x03, 0x01),
+            #[cfg(feature = "m")]
+            Self::Div => (0x04, 0x01),
+            #[cfg(feature = "m")]
+            Self::Divu => (0x05, 0x01),
+            #[cfg(feature = "m")]
+            Self::Rem => (0x06, 0x01),
+            #[cfg(feature = "m")]
+            Self::Remu => (0x07, 0x01),
+        }
+    }
+}
+
 impl TryFrom<&RType> for RegisterArithmeticFunction {
-    type Error = InstructionDecodeError;
+    type Error = DecodeErrorKind;
 
     fn try_from(value: &RType) -> Result<Self, Self::Error> {
         match (value.funct3, value.funct7) {
@@ -82,10 +116,7 @@ impl TryFrom<&RType> for RegisterArithmeticFunction {
             (0x06, 0x01) => Ok(Self::Rem),
             #[cfg(feature = "m")]
             (0x07, 0x01) => Ok(Self::Remu),
-            _ => Err(InstructionDecodeError::InvalidFunction {
-                q_a: value.funct3,
-                q_b: value.funct7,
-            }),
+            _ => Err(DecodeErrorKind::InvalidFunction { q_a: value.funct3, q_b: value.funct7 }),
         }
     }
 }
@@ -121,9 +152,33 @@ pub enum RegisterArithmeticWordFunction {
     Remuw,
 }
 
+#[cfg(feature = "64-bit")]
+impl RegisterArithmeticWordFunction {
+    /// Returns the `funct3` and `funct7` fields that encode the function.
+    pub const fn encoding(&self) -> (u8, u8) {
+        match self {
+            Self::Addw => (0x00, 0x00),
+            Self::Subw => (0x00, 0x20),
+            Self::Sllw => (0x01, 0x00),
+            Self::Srlw => (0x05, 0x00),
+            Self::Sraw => (0x05, 0x20),
+            #[cfg(feature = "m")]
+            Self::Mulw => (0x00, 0x01),
+            #[cfg(feature = "m")]
+            Self::Divw => (0x04, 0x01),
+            #[cfg(feature = "m")]
+            Self::Divuw => (0x05, 0x01),
+            #[cfg(feature = "m")]
+            Self::Remw => (0x06, 0x01),
+            #[cfg(feature = "m")]
+            Self::Remuw => (0x07, 0x01),
+        }
+    }
+}
+
 #[cfg(feature = "64-bit")]
 impl TryFrom<&RType> for RegisterArithmeticWordFunction {
-    type Error = InstructionDecodeError;
+    type Error = DecodeErrorKind;
 
     fn try_from(value: &RType) -> Result<Self, Self::Error> {
         match (value.funct3, value.funct7) {
@@ -142,10 +197,88 @@ impl TryFrom<&RType> for RegisterArithmeticWordFunction {
             (0x06, 0x01) => Ok(Self::Remw),
             #[cfg(feature = "m")]
             (0x07, 0x01) => Ok(Self::Remuw),
-            _ => Err(InstructionDecodeError::InvalidFunction {
-                q_a: value.funct3,
-                q_b: value.funct7,
-            }),
+            _ => Err(DecodeErrorKind::InvalidFunction { q_a: value.funct3, q_b: value.funct7 }),
+        }
+    }
+}
+
+/// Functions for Integer Register-Register Double Word Instructions.
+#[cfg(feature = "128-bit")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RegisterArithmeticDoubleFunction {
+    /// The `ADDD` function.
+    Addd,
+    /// The `SUBD` function.
+    Subd,
+    /// The `SLLD` function.
+    Slld,
+    /// The `SRLD` function.
+    Srld,
+    /// The `SRAD` function.
+    Srad,
+    /// The `MULD` function.
+    #[cfg(feature = "m")]
+    Muld,
+    /// The `DIVD` function.
+    #[cfg(feature = "m")]
+    Divd,
+    /// The `DIVUD` function.
+    #[cfg(feature = "m")]
+    Divud,
+    /// The `REMD` function.
+    #[cfg(feature = "m")]
+    Remd,
+    /// The `REMUD` function.
+    #[cfg(feature = "m")]
+    Remud,
+}
+
+#[cfg(feature = "128-bit")]
+impl RegisterArithmeticDoubleFunction {
+    /// Returns the `funct3` and `funct7` fields that encode the function.
+    pub const fn encoding(&self) -> (u8, u8) {
+        match self {
+            Self::Addd => (0x00, 0x00),
+            Self::Subd => (0x00, 0x20),
+            Self::Slld => (0x01, 0x00),
+            Self::Srld => (0x05, 0x00),
+            Self::Srad => (0x05, 0x20),
+            #[cfg(feature = "m")]
+            Self::Muld => (0x00, 0x01),
+            #[cfg(feature = "m")]
+            Self::Divd => (0x04, 0x01),
+            #[cfg(feature = "m")]
+            Self::Divud => (0x05, 0x01),
+            #[cfg(feature = "m")]
+            Self::Remd => (0x06, 0x01),
+            #[cfg(feature = "m")]
+            Self::Remud => (0x07, 0x01),
+        }
+    }
+}
+
+#[cfg(feature = "128-bit")]
+impl TryFrom<&RType> for RegisterArithmeticDoubleFunction {
+    type Error = DecodeErrorKind;
+
+    fn try_from(value: &RType) -> Result<Self, Self::Error> {
+        match (value.funct3, value.funct7) {
+            (0x00, 0x00) => Ok(Self::Addd),
+            (0x00, 0x20) => Ok(Self::Subd),
+            (0x01, 0x00) => Ok(Self::Slld),
+            (0x05, 0x00) => Ok(Self::Srld),
+            (0x05, 0x20) => Ok(Self::Srad),
+            #[cfg(feature = "m")]
+            (0x00, 0x01) => Ok(Self::Muld),
+            #[cfg(feature = "m")]
+            (0x04, 0x01) => Ok(Self::Divd),
+            #[cfg(feature = "m")]
+            (0x05, 0x01) => Ok(Self::Divud),
+            #[cfg(feature = "m")]
+            (0x06, 0x01) => Ok(Self::Remd),
+            #[cfg(feature = "m")]
+            (0x07, 0x01) => Ok(Self::Remud),
+            _ => Err(DecodeErrorKind::InvalidFunction { q_a: value.funct3, q_b: value.funct7 }),
         }
     }
 }
@@ -173,8 +306,24 @@ pub enum ImmediateArithmeticFunction {
     Sltiu,
 }
 
+impl ImmediateArithmeticFunction {
+    /// Returns the `funct3` field that encodes the function.
+    pub const fn funct3(&self) -> u8 {
+        match self {
+            Self::Addi => 0x00,
+            Self::Slli => 0x01,
+            Self::Slti => 0x02,
+            Self::Sltiu => 0x03,
+            Self::Xori => 0x04,
+            Self::Srli | Self::Srai => 0x05,
+            Self::Ori => 0x06,
+            Self::Andi => 0x07,
+        }
+    }
+}
+
 impl TryFrom<&IType> for ImmediateArithmeticFunction {
-    type Error = InstructionDecodeError;
+    type Error = DecodeErrorKind;
 
     fn try_from(value: &IType) -> Result<Self, Self::Error> {
         match value.funct3 {
@@ -187,13 +336,17 @@ impl TryFrom<&IType> for ImmediateArithmeticFunction {
             0x05 if bits!(u8, value.imm, 5..12) == 0 => Ok(Self::Srli),
             #[cfg(not(feature = "64-bit"))]
             0x05 if bits!(u8, value.imm, 5..12) == 0x20 => Ok(Self::Srai),
-            #[cfg(feature = "64-bit")]
+            #[cfg(all(feature = "64-bit", not(feature = "128-bit")))]
             0x05 if bits!(u8, value.imm, 6..12) == 0 => Ok(Self::Srli),
-            #[cfg(feature = "64-bit")]
+            #[cfg(all(feature = "64-bit", not(feature = "128-bit")))]
             0x05 if bits!(u8, value.imm, 6..12) == 0x10 => Ok(Self::Srai),
+            #[cfg(feature = "128-bit")]
+            0x05 if bits!(u8, value.imm, 7..12) == 0 => Ok(Self::Srli),
+            #[cfg(feature = "128-bit")]
+            0x05 if bits!(u8, value.imm, 7..12) == 0x08 => Ok(Self::Srai),
             0x06 => Ok(Self::Ori),
             0x07 => Ok(Self::Andi),
-            _ => Err(InstructionDecodeError::InvalidFunction {
+            _ => Err(DecodeErrorKind::InvalidFunction {
                 q_a: value.funct3,
                 q_b: bits!(u8, value.imm, 5..12),
             }),
@@ -215,9 +368,21 @@ pub enum ImmediateArithmeticWordFunction {
     Sraiw,
 }
 
+#[cfg(feature = "64-bit")]
+impl ImmediateArithmeticWordFunction {
+    /// Returns the `funct3` field that encodes the function.
+    pub const fn funct3(&self) -> u8 {
+        match self {
+            Self::Addiw => 0x00,
+            Self::Slliw => 0x01,
+            Self::Srliw | Self::Sraiw => 0x05,
+        }
+    }
+}
+
 #[cfg(feature = "64-bit")]
 impl TryFrom<&IType> for ImmediateArithmeticWordFunction {
-    type Error = InstructionDecodeError;
+    type Error = DecodeErrorKind;
 
     fn try_from(value: &IType) -> Result<Self, Self::Error> {
         match value.funct3 {
@@ -225,7 +390,51 @@ impl TryFrom<&IType> for ImmediateArithmeticWordFunction {
             0x01 if bits!(u8, value.imm, 5..12) == 0 => Ok(Self::Slliw),
             0x05 if bits!(u8, value.imm, 5..12) == 0 => Ok(Self::Srliw),
             0x05 if bits!(u8, value.imm, 5..12) == 0x20 => Ok(Self::Sraiw),
-            _ => Err(InstructionDecodeError::InvalidFunction {
+            _ => Err(DecodeErrorKind::InvalidFunction {
+                q_a: value.funct3,
+                q_b: bits!(u8, value.imm, 5..12),
+            }),
+        }
+    }
+}
+
+/// Functions for Integer Register-Immediate Double Word Instructions.
+#[cfg(feature = "128-bit")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImmediateArithmeticDoubleFunction {
+    /// The `ADDID` function.
+    Addid,
+    /// The `SLLID` function.
+    Sllid,
+    /// The `SRLID` function.
+    Srlid,
+    /// The `SRAID` function.
+    Sraid,
+}
+
+#[cfg(feature = "128-bit")]
+impl ImmediateArithmeticDoubleFunction {
+    /// Returns the `funct3` field that encodes the function.
+    pub const fn funct3(&self) -> u8 {
+        match self {
+            Self::Addid => 0x00,
+            Self::Sllid => 0x01,
+            Self::Srlid | Self::Sraid => 0x05,
+        }
+    }
+}
+
+#[cfg(feature = "128-bit")]
+impl TryFrom<&IType> for ImmediateArithmeticDoubleFunction {
+    type Error = DecodeErrorKind;
+
+    fn try_from(value: &IType) -> Result<Self, Self::Error> {
+        match value.funct3 {
+            0x00 => Ok(Self::Addid),
+            0x01 if bits!(u8, value.imm, 6..12) == 0 => Ok(Self::Sllid),
+            0x05 if bits!(u8, value.imm, 6..12) == 0 => Ok(Self::Srlid),
+            0x05 if bits!(u8, value.imm, 6..12) == 0x10 => Ok(Self::Sraid),
+            _ => Err(DecodeErrorKind::InvalidFunction {
                 q_a: value.funct3,
                 q_b: bits!(u8, value.imm, 5..12),
             }),
@@ -252,10 +461,37 @@ pub enum LoadFunction {
     /// The `LD` function.
     #[cfg(feature = "64-bit")]
     Ld,
+    /// The `LDU` function.
+    #[cfg(feature = "128-bit")]
+    Ldu,
+    /// The `LQ` function. Unlike the other loads, it is encoded in the `MISC-MEM` major opcode.
+    #[cfg(feature = "128-bit")]
+    Lq,
+}
+
+impl LoadFunction {
+    /// Returns the `funct3` field that encodes the function.
+    pub const fn funct3(&self) -> u8 {
+        match self {
+            Self::Lb => 0x00,
+            Self::Lh => 0x01,
+            Self::Lw => 0x02,
+            Self::Lbu => 0x04,
+            Self::Lhu => 0x05,
+            #[cfg(feature = "64-bit")]
+            Self::Lwu => 0x06,
+            #[cfg(feature = "64-bit")]
+            Self::Ld => 0x03,
+            #[cfg(feature = "128-bit")]
+            Self::Ldu => 0x07,
+            #[cfg(feature = "128-bit")]
+            Self::Lq => 0x02,
+        }
+    }
 }
 
 impl TryFrom<&IType> for LoadFunction {
-    type Error = InstructionDecodeError;
+    type Error = DecodeErrorKind;
 
     fn try_from(value: &IType) -> Result<Self, Self::Error> {
         match value.funct3 {
@@ -268,7 +504,9 @@ impl TryFrom<&IType> for LoadFunction {
             0x06 => Ok(Self::Lwu),
             #[cfg(feature = "64-bit")]
             0x03 => Ok(Self::Ld),
-            _ => Err(InstructionDecodeError::InvalidFunction { q_a: value.funct3, q_b: 0 }),
+            #[cfg(feature = "128-bit")]
+            0x07 => Ok(Self::Ldu),
+            _ => Err(DecodeErrorKind::InvalidFunction { q_a: value.funct3, q_b: 0 }),
         }
     }
 }
@@ -285,10 +523,28 @@ pub enum StoreFunction {
     /// The `SD` function.
     #[cfg(feature = "64-bit")]
     Sd,
+    /// The `SQ` function.
+    #[cfg(feature = "128-bit")]
+    Sq,
+}
+
+impl StoreFunction {
+    /// Returns the `funct3` field that encodes the function.
+    pub const fn funct3(&self) -> u8 {
+        match self {
+            Self::Sb => 0x00,
+            Self::Sh => 0x01,
+            Self::Sw => 0x02,
+            #[cfg(feature = "64-bit")]
+            Self::Sd => 0x03,
+            #[cfg(feature = "128-bit")]
+            Self::Sq => 0x04,
+        }
+    }
 }
 
 impl TryFrom<&SType> for StoreFunction {
-    type Error = InstructionDecodeError;
+    type Error = DecodeErrorKind;
 
     fn try_from(value: &SType) -> Result<Self, Self::Error> {
         match value.funct3 {
@@ -297,7 +553,9 @@ impl TryFrom<&SType> for StoreFunction {
             0x02 => Ok(Self::Sw),
             #[cfg(feature = "64-bit")]
             0x03 => Ok(Self::Sd),
-            _ => Err(InstructionDecodeError::InvalidFunction { q_a: value.funct3, q_b: 0 }),
+            #[cfg(feature = "128-bit")]
+            0x04 => Ok(Self::Sq),
+            _ => Err(DecodeErrorKind::InvalidFunction { q_a: value.funct3, q_b: 0 }),
         }
     }
 }
@@ -319,8 +577,22 @@ pub enum BranchFunction {
     Bgeu,
 }
 
+impl BranchFunction {
+    /// Returns the `funct3` field that encodes the function.
+    pub const fn funct3(&self) -> u8 {
+        match self {
+            Self::Beq => 0x00,
+            Self::Bne => 0x01,
+            Self::Blt => 0x04,
+            Self::Bge => 0x05,
+            Self::Bltu => 0x06,
+            Self::Bgeu => 0x07,
+        }
+    }
+}
+
 impl TryFrom<&BType> for BranchFunction {
-    type Error = InstructionDecodeError;
+    type Error = DecodeErrorKind;
 
     fn try_from(value: &BType) -> Result<Self, Self::Error> {
         match value.funct3 {
@@ -330,7 +602,7 @@ impl TryFrom<&BType> for BranchFunction {
             0x05 => Ok(Self::Bge),
             0x06 => Ok(Self::Bltu),
             0x07 => Ok(Self::Bgeu),
-            _ => Err(InstructionDecodeError::InvalidFunction { q_a: value.funct3, q_b: 0 }),
+            _ => Err(DecodeErrorKind::InvalidFunction { q_a: value.funct3, q_b: 0 }),
         }
     }
 }
@@ -345,13 +617,13 @@ pub enum EnvironmentFunction {
 }
 
 impl TryFrom<&IType> for EnvironmentFunction {
-    type Error = InstructionDecodeError;
+    type Error = DecodeErrorKind;
 
     fn try_from(value: &IType) -> Result<Self, Self::Error> {
         match value.funct3 {
             0x00 if value.imm == 0 => Ok(Self::Ecall),
             _ => Ok(Self::Ebreak),
-            // _ => Err(InstructionDecodeError::InvalidFunction { q_a: value.funct3, q_b: 0 }),
+            // _ => Err(DecodeErrorKind::InvalidFunction { q_a: value.funct3, q_b: 0 }),
         }
     }
 }
@@ -384,9 +656,29 @@ pub enum AmoFunction {
     Amomaxu,
 }
 
+#[cfg(feature = "a")]
+impl AmoFunction {
+    /// Returns the `funct5` field (the upper bits of `funct7`) that encodes the function.
+    pub const fn funct5(&self) -> u8 {
+        match self {
+            Self::Lr => 0b00010,
+            Self::Sc => 0b00011,
+            Self::Amoswap => 0b00001,
+            Self::Amoadd => 0b00000,
+            Self::Amoxor => 0b00100,
+            Self::Amoand => 0b01100,
+            Self::Amoor => 0b01000,
+            Self::Amomin => 0b10000,
+            Self::Amomax => 0b10100,
+            Self::Amominu => 0b11000,
+            Self::Amomaxu => 0b11100,
+        }
+    }
+}
+
 #[cfg(feature = "a")]
 impl TryFrom<&RType> for AmoFunction {
-    type Error = InstructionDecodeError;
+    type Error = DecodeErrorKind;
 
     fn try_from(value: &RType) -> Result<Self, Self::Error> {
         let afunct5 = bits!(u8, value.funct7, 2..7);
@@ -402,7 +694,7 @@ impl TryFrom<&RType> for AmoFunction {
             0b10100 => Ok(Self::Amomax),
             0b11000 => Ok(Self::Amominu),
             0b11100 => Ok(Self::Amomaxu),
-            _ => Err(InstructionDecodeError::InvalidFunction { q_a: afunct5, q_b: 0 }),
+            _ => Err(DecodeErrorKind::InvalidFunction { q_a: afunct5, q_b: 0 }),
         }
     }
 }
@@ -1,8 +1,9 @@
 //! RISC-V Instruction Types
 
 use crate::{
-    arch::Word, BranchFunction, EnvironmentFunction, ImmediateArithmeticFunction,
-    InstructionDecodeError, LoadFunction, RegisterArithmeticFunction, StoreFunction, XWord,
+    arch::Word, feature::check_xlen, BranchFunction, DecodeErrorKind, EnvironmentFunction,
+    ImmediateArithmeticFunction, InstructionDecodeError, LoadFunction, RegisterArithmeticFunction,
+    StoreFunction, XWord, Xlen, SHIFT_MASK,
 };
 
 mod b_type;
@@ -23,6 +24,8 @@ pub use s_type::SType;
 mod u_type;
 pub use u_type::UType;
 
+mod strict;
+
 #[cfg(feature = "c")]
 mod rvc;
 #[cfg(feature = "c")]
@@ -63,6 +66,14 @@ pub enum Instruction {
     /// Register arithmetic word operations (RV64I)
     #[cfg(feature = "64-bit")]
     RegisterArithmeticWord(RType, crate::functions::RegisterArithmeticWordFunction) = 0b011_1011,
+    /// Immediate arithmetic double word operations (RV128I)
+    #[cfg(feature = "128-bit")]
+    ImmediateArithmeticDouble(IType, crate::functions::ImmediateArithmeticDoubleFunction) =
+        0b101_1011,
+    /// Register arithmetic double word operations (RV128I)
+    #[cfg(feature = "128-bit")]
+    RegisterArithmeticDouble(RType, crate::functions::RegisterArithmeticDoubleFunction) =
+        0b111_1011,
     /// AMO operations (RV32A)
     #[cfg(feature = "a")]
     Amo(RType, crate::functions::AmoFunction) = 0b010_1111,
@@ -83,6 +94,10 @@ impl Instruction {
             Self::ImmediateArithmeticWord(i_type, _) => Some(i_type.rs1),
             #[cfg(feature = "64-bit")]
             Self::RegisterArithmeticWord(r_type, _) => Some(r_type.rs1),
+            #[cfg(feature = "128-bit")]
+            Self::ImmediateArithmeticDouble(i_type, _) => Some(i_type.rs1),
+            #[cfg(feature = "128-bit")]
+            Self::RegisterArithmeticDouble(r_type, _) => Some(r_type.rs1),
             #[cfg(feature = "a")]
             Self::Amo(r_type, _) => Some(r_type.rs1),
             _ => None,
@@ -97,6 +112,8 @@ impl Instruction {
             Self::RegisterArithmetic(r_type, _) => Some(r_type.rs2),
             #[cfg(feature = "64-bit")]
             Self::RegisterArithmeticWord(r_type, _) => Some(r_type.rs2),
+            #[cfg(feature = "128-bit")]
+            Self::RegisterArithmeticDouble(r_type, _) => Some(r_type.rs2),
             #[cfg(feature = "a")]
             Self::Amo(r_type, _) => Some(r_type.rs2),
             _ => None,
@@ -118,6 +135,10 @@ impl Instruction {
             Self::ImmediateArithmeticWord(i_type, _) => Some(i_type.rd),
             #[cfg(feature = "64-bit")]
             Self::RegisterArithmeticWord(r_type, _) => Some(r_type.rd),
+            #[cfg(feature = "128-bit")]
+            Self::ImmediateArithmeticDouble(i_type, _) => Some(i_type.rd),
+            #[cfg(feature = "128-bit")]
+            Self::RegisterArithmeticDouble(r_type, _) => Some(r_type.rd),
             #[cfg(feature = "a")]
             Self::Amo(r_type, _) => Some(r_type.rd),
             _ => None,
@@ -138,6 +159,8 @@ impl Instruction {
             Self::Environment(i_type, _) => Some(i_type.imm),
             #[cfg(feature = "64-bit")]
             Self::ImmediateArithmeticWord(i_type, _) => Some(i_type.imm),
+            #[cfg(feature = "128-bit")]
+            Self::ImmediateArithmeticDouble(i_type, _) => Some(i_type.imm),
             _ => None,
         }
     }
@@ -146,6 +169,132 @@ impl Instruction {
     pub const fn is_system_call(&self) -> bool {
         matches!(self, Self::Environment(_, EnvironmentFunction::Ecall))
     }
+
+    /// Returns the 7-bit major opcode of the instruction.
+    pub const fn opcode(&self) -> u8 {
+        match self {
+            #[cfg(feature = "128-bit")]
+            Self::MemoryLoad(_, LoadFunction::Lq) => 0b000_1111,
+            Self::MemoryLoad(_, _) => 0b000_0011,
+            Self::MemoryStore(_, _) => 0b010_0011,
+            Self::Branch(_, _) => 0b110_0011,
+            Self::ImmediateArithmetic(_, _) => 0b001_0011,
+            Self::RegisterArithmetic(_, _) => 0b011_0011,
+            Self::Lui(_) => 0b011_0111,
+            Self::Auipc(_) => 0b001_0111,
+            Self::Jal(_) => 0b110_1111,
+            Self::Jalr(_) => 0b110_0111,
+            Self::Environment(_, _) => 0b111_0011,
+            Self::Fence => 0b000_1111,
+            #[cfg(feature = "64-bit")]
+            Self::ImmediateArithmeticWord(_, _) => 0b001_1011,
+            #[cfg(feature = "64-bit")]
+            Self::RegisterArithmeticWord(_, _) => 0b011_1011,
+            #[cfg(feature = "128-bit")]
+            Self::ImmediateArithmeticDouble(_, _) => 0b101_1011,
+            #[cfg(feature = "128-bit")]
+            Self::RegisterArithmeticDouble(_, _) => 0b111_1011,
+            #[cfg(feature = "a")]
+            Self::Amo(_, _) => 0b010_1111,
+        }
+    }
+
+    /// Encodes the instruction into its canonical 32-bit [Word].
+    ///
+    /// The function qualifiers (`funct3`, `funct7`) are derived from the instruction's function
+    /// rather than from the fields of the embedded type, so instructions that were expanded from
+    /// their compressed form encode to the equivalent standard-length instruction. [Self::Fence]
+    /// encodes as `fence iorw, iorw`.
+    pub fn encode(&self) -> Word {
+        let opcode = self.opcode();
+        match *self {
+            Self::MemoryLoad(i_type, funct) => {
+                IType { funct3: funct.funct3(), ..i_type }.encode(opcode)
+            }
+            Self::MemoryStore(s_type, funct) => {
+                SType { funct3: funct.funct3(), ..s_type }.encode(opcode)
+            }
+            Self::Branch(b_type, funct) => {
+                BType { funct3: funct.funct3(), ..b_type }.encode(opcode)
+            }
+            Self::ImmediateArithmetic(i_type, funct) => {
+                let imm = match funct {
+                    ImmediateArithmeticFunction::Slli | ImmediateArithmeticFunction::Srli => {
+                        i_type.imm & SHIFT_MASK
+                    }
+                    ImmediateArithmeticFunction::Srai => (i_type.imm & SHIFT_MASK) | 0x400,
+                    _ => i_type.imm,
+                };
+                IType { funct3: funct.funct3(), imm, ..i_type }.encode(opcode)
+            }
+            Self::RegisterArithmetic(r_type, funct) => {
+                let (funct3, funct7) = funct.encoding();
+                RType { funct3, funct7, ..r_type }.encode(opcode)
+            }
+            Self::Lui(u_type) | Self::Auipc(u_type) => u_type.encode(opcode),
+            Self::Jal(j_type) => j_type.encode(opcode),
+            Self::Jalr(i_type) => IType { funct3: 0, ..i_type }.encode(opcode),
+            Self::Environment(_, EnvironmentFunction::Ecall) => 0x0000_0073,
+            Self::Environment(_, EnvironmentFunction::Ebreak) => 0x0010_0073,
+            Self::Fence => 0x0FF0_000F,
+            #[cfg(feature = "64-bit")]
+            Self::ImmediateArithmeticWord(i_type, funct) => {
+                use crate::functions::ImmediateArithmeticWordFunction as F;
+
+                let imm = match funct {
+                    F::Slliw | F::Srliw => i_type.imm & 0x1F,
+                    F::Sraiw => (i_type.imm & 0x1F) | 0x400,
+                    F::Addiw => i_type.imm,
+                };
+                IType { funct3: funct.funct3(), imm, ..i_type }.encode(opcode)
+            }
+            #[cfg(feature = "64-bit")]
+            Self::RegisterArithmeticWord(r_type, funct) => {
+                let (funct3, funct7) = funct.encoding();
+                RType { funct3, funct7, ..r_type }.encode(opcode)
+            }
+            #[cfg(feature = "128-bit")]
+            Self::ImmediateArithmeticDouble(i_type, funct) => {
+                use crate::functions::ImmediateArithmeticDoubleFunction as F;
+
+                let imm = match funct {
+                    F::Sllid | F::Srlid => i_type.imm & 0x3F,
+                    F::Sraid => (i_type.imm & 0x3F) | 0x400,
+                    F::Addid => i_type.imm,
+                };
+                IType { funct3: funct.funct3(), imm, ..i_type }.encode(opcode)
+            }
+            #[cfg(feature = "128-bit")]
+            Self::RegisterArithmeticDouble(r_type, funct) => {
+                let (funct3, funct7) = funct.encoding();
+                RType { funct3, funct7, ..r_type }.encode(opcode)
+            }
+            #[cfg(feature = "a")]
+            Self::Amo(r_type, funct) => {
+                let funct7 = (funct.funct5() << 2) | (r_type.funct7 & 0b11);
+                RType { funct7, ..r_type }.encode(opcode)
+            }
+        }
+    }
+}
+
+impl Instruction {
+    /// Decodes an [Instruction] for a hart with the register width `X`, rejecting instructions
+    /// that are only available on wider harts. [Instruction::try_from] decodes for [NativeXlen].
+    ///
+    /// [NativeXlen]: crate::NativeXlen
+    pub fn decode_for<X: Xlen>(value: Word) -> Result<Self, InstructionDecodeError> {
+        #[cfg(feature = "c")]
+        if is_compressed(value) {
+            return CompressedInstruction::decode_for::<X>(value as crate::HalfWord)
+                .map(CompressedInstruction::expand);
+        }
+
+        if X::X_LEN < crate::X_LEN {
+            check_xlen::<X>(value)?;
+        }
+        Self::try_from(value)
+    }
 }
 
 impl TryFrom<Word> for Instruction {
@@ -163,7 +312,7 @@ impl TryFrom<Word> for Instruction {
         let opcode = (value & 0x7F) as u8;
 
         // Use a direct jump table based on opcodes for faster dispatch
-        match opcode {
+        let result = match opcode {
             0b000_0011 => {
                 // Memory load operations - decode once and reuse
                 let i_type = IType::decode(value);
@@ -212,6 +361,11 @@ impl TryFrom<Word> for Instruction {
                 let i_type = IType::decode(value);
                 EnvironmentFunction::try_from(&i_type).map(|f| Self::Environment(i_type, f))
             }
+            #[cfg(feature = "128-bit")]
+            0b000_1111 if (value >> 12) & 0x7 == 0b010 => {
+                // LQ shares the MISC-MEM opcode with FENCE
+                Ok(Self::MemoryLoad(IType::decode(value), LoadFunction::Lq))
+            }
             0b000_1111 => Ok(Self::Fence),
 
             // Feature-gated instructions
@@ -227,13 +381,26 @@ impl TryFrom<Word> for Instruction {
                 crate::functions::RegisterArithmeticWordFunction::try_from(&r_type)
                     .map(|f| Self::RegisterArithmeticWord(r_type, f))
             }
+            #[cfg(feature = "128-bit")]
+            0b101_1011 => {
+                let i_type = IType::decode(value);
+                crate::functions::ImmediateArithmeticDoubleFunction::try_from(&i_type)
+                    .map(|f| Self::ImmediateArithmeticDouble(i_type, f))
+            }
+            #[cfg(feature = "128-bit")]
+            0b111_1011 => {
+                let r_type = RType::decode(value);
+                crate::functions::RegisterArithmeticDoubleFunction::try_from(&r_type)
+                    .map(|f| Self::RegisterArithmeticDouble(r_type, f))
+            }
             #[cfg(feature = "a")]
             0b010_1111 => {
                 let r_type = RType::decode(value);
                 crate::functions::AmoFunction::try_from(&r_type).map(|f| Self::Amo(r_type, f))
             }
-            _ => Err(InstructionDecodeError::InvalidOpcode(opcode)),
-        }
+            _ => Err(DecodeErrorKind::InvalidOpcode(opcode)),
+        };
+        result.map_err(|kind| InstructionDecodeError::new(value, kind))
     }
 }
 
@@ -263,4 +430,50 @@ mod test {
             panic!("Expected ImmediateArithmetic instruction");
         }
     }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let raws: &[Word] = &[
+            0x01010513, // addi a0, sp, 16
+            0x40455513, // srai a0, a0, 4
+            0x40b50533, // sub a0, a0, a1
+            0xfea42c23, // sw a0, -8(s0)
+            0xfe050ee3, // beqz a0, .-4
+            0x12345537, // lui a0, 0x12345
+            0x0100006f, // j .+16
+            0x004500e7, // jalr ra, 4(a0)
+            0x00000073, // ecall
+            0x00100073, // ebreak
+            0x0ff0000f, // fence
+        ];
+        for &raw in raws {
+            assert_eq!(Instruction::try_from(raw).unwrap().encode(), raw, "{raw:#010x}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "128-bit")]
+    fn test_encode_round_trip_rv128() {
+        let raws: &[Word] = &[
+            0x0101250f, // lq a0, 16(sp)
+            0x00a14823, // sq a0, 16(sp)
+            0x00817503, // ldu a0, 8(sp)
+            0x04051513, // slli a0, a0, 64
+            0x4405d513, // srai a0, a1, 64
+            0x0015855b, // addid a0, a1, 1
+            0x4205d55b, // sraid a0, a1, 32
+            0x40c5857b, // subd a0, a1, a2
+        ];
+        for &raw in raws {
+            assert_eq!(Instruction::try_from(raw).unwrap().encode(), raw, "{raw:#010x}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "c")]
+    fn test_encode_expanded_compressed() {
+        // c.srai a0, 4 expands to srai a0, a0, 4
+        let expanded = CompressedInstruction::decode(0x8511).unwrap().expand();
+        assert_eq!(expanded.encode(), 0x40455513);
+    }
 }
@@ -25,6 +25,18 @@ impl BType {
             imm: sign_extend(twiddle!(XWord, instruction, 31..32, 7..8, 25..31, 8..12) << 1, 12),
         }
     }
+
+    /// Encodes the [BType] instruction into a 32-bit [Word] with the given `opcode`.
+    pub fn encode(&self, opcode: u8) -> Word {
+        (bits!(Word, self.imm, 12..13) << 31) |
+            (bits!(Word, self.imm, 5..11) << 25) |
+            ((self.rs2 as Word & 0x1F) << 20) |
+            ((self.rs1 as Word & 0x1F) << 15) |
+            ((self.funct3 as Word & 0x7) << 12) |
+            (bits!(Word, self.imm, 1..5) << 8) |
+            (bits!(Word, self.imm, 11..12) << 7) |
+            (opcode as Word & 0x7F)
+    }
 }
 
 #[cfg(test)]
@@ -52,4 +64,10 @@ mod test {
         assert_eq!(btype.rs2, 0b11111);
         assert_eq!(btype.imm, sign_extend(0b1_1_010110_0010 << 1, 12));
     }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let instruction = 0b1_010110_11111_01010_101_0010_1_1100011;
+        assert_eq!(BType::decode(instruction).encode(0b1100011), instruction);
+    }
 }
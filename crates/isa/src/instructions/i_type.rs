@@ -25,6 +25,15 @@ impl IType {
             imm: sign_extend(bits!(XWord, instruction, 20..32), 11),
         }
     }
+
+    /// Encodes the [IType] instruction into a 32-bit [Word] with the given `opcode`.
+    pub fn encode(&self, opcode: u8) -> Word {
+        (bits!(Word, self.imm, 0..12) << 20) |
+            ((self.rs1 as Word & 0x1F) << 15) |
+            ((self.funct3 as Word & 0x7) << 12) |
+            ((self.rd as Word & 0x1F) << 7) |
+            (opcode as Word & 0x7F)
+    }
 }
 
 #[cfg(test)]
@@ -52,4 +61,10 @@ mod test {
         assert_eq!(itype.rs1, 0b11000);
         assert_eq!(itype.imm, sign_extend(0b110011001000, 11));
     }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let instruction = 0b010011001000_11000_010_00100_0010011;
+        assert_eq!(IType::decode(instruction).encode(0b0010011), instruction);
+    }
 }
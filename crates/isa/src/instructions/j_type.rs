@@ -19,6 +19,16 @@ impl JType {
             imm: sign_extend(twiddle!(XWord, instruction, 31..32, 12..20, 20..21, 21..31) << 1, 20),
         }
     }
+
+    /// Encodes the [JType] instruction into a 32-bit [Word] with the given `opcode`.
+    pub fn encode(&self, opcode: u8) -> Word {
+        (bits!(Word, self.imm, 20..21) << 31) |
+            (bits!(Word, self.imm, 1..11) << 21) |
+            (bits!(Word, self.imm, 11..12) << 20) |
+            (bits!(Word, self.imm, 12..20) << 12) |
+            ((self.rd as Word & 0x1F) << 7) |
+            (opcode as Word & 0x7F)
+    }
 }
 
 #[cfg(test)]
@@ -42,4 +52,10 @@ mod test {
         assert_eq!(jtype.rd, 0b11111);
         assert_eq!(jtype.imm, sign_extend(0b1_00000000_1_0101010101 << 1, 20));
     }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let instruction = 0b1_0101010101_1_00000000_11111_1101111;
+        assert_eq!(JType::decode(instruction).encode(0b1101111), instruction);
+    }
 }
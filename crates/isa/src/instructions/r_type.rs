@@ -28,6 +28,16 @@ impl RType {
             funct7: bits!(u8, instruction, 25..32),
         }
     }
+
+    /// Encodes the [RType] instruction into a 32-bit [Word] with the given `opcode`.
+    pub const fn encode(&self, opcode: u8) -> Word {
+        ((self.funct7 as Word & 0x7F) << 25) |
+            ((self.rs2 as Word & 0x1F) << 20) |
+            ((self.rs1 as Word & 0x1F) << 15) |
+            ((self.funct3 as Word & 0x7) << 12) |
+            ((self.rd as Word & 0x1F) << 7) |
+            (opcode as Word & 0x7F)
+    }
 }
 
 #[cfg(test)]
@@ -45,4 +55,10 @@ mod test {
         assert_eq!(rtype.rs2, 0b01000);
         assert_eq!(rtype.funct7, 0b1100110);
     }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let instruction = 0b1100110_01000_11000_010_00100_0110011;
+        assert_eq!(RType::decode(instruction).encode(0b0110011), instruction);
+    }
 }
@@ -0,0 +1,458 @@
+//! Compression of regular RISC-V instructions into their `c` extension forms.
+
+use super::{CompressedInstruction, C_REG_OFFSET};
+use crate::{
+    feature::check_xlen, twiddle, BranchFunction, EnvironmentFunction, HalfWord,
+    ImmediateArithmeticFunction, Instruction, LoadFunction, NativeXlen, RegisterArithmeticFunction,
+    StoreFunction, Word, XWord, Xlen, REG_RA, REG_SP, REG_ZERO, SHIFT_MASK, X_LEN,
+};
+
+#[cfg(feature = "64-bit")]
+use crate::{ImmediateArithmeticWordFunction, RegisterArithmeticWordFunction};
+
+const ZERO: u8 = REG_ZERO as u8;
+const RA: u8 = REG_RA as u8;
+const SP: u8 = REG_SP as u8;
+
+impl Instruction {
+    /// Returns the [CompressedInstruction] that encodes this instruction, if one exists for
+    /// [NativeXlen]. [Self::compress_for] compresses for a narrower hart.
+    ///
+    /// The expansion of the returned instruction is equivalent to, but not always identical to,
+    /// `self`: `addi rd, rs1, 0` compresses to `c.mv`, which expands to `add rd, x0, rs1`. HINT
+    /// encodings (such as `c.addi x0, imm`) are never produced.
+    pub fn compress(&self) -> Option<CompressedInstruction> {
+        self.compress_for::<NativeXlen>()
+    }
+
+    /// Returns the [CompressedInstruction] that encodes this instruction on a hart with the
+    /// register width `X`, if one exists, like [Self::compress]. `jal ra` compresses to `c.jal`
+    /// on RV32 only, whose encoding is taken by `c.addiw` on wider harts, and instructions that are
+    /// only available on wider harts never compress.
+    pub fn compress_for<X: Xlen>(&self) -> Option<CompressedInstruction> {
+        check_xlen::<X>(self.encode()).ok()?;
+
+        let raw = match *self {
+            Self::ImmediateArithmetic(i, funct) => {
+                let (rd, rs1, imm) = (i.rd, i.rs1, signed(i.imm));
+                // The compressed shifts hold a 6-bit shift amount, so RV128 shifts by 64 or more
+                // stay uncompressed, as do the shifts by XLEN or more that RV32 reserves.
+                let shamt = (i.imm & SHIFT_MASK) as Word;
+                let short_shamt = shamt != 0 && (shamt as usize) < X::X_LEN.min(64);
+                match funct {
+                    ImmediateArithmeticFunction::Addi => match (rd, rs1, imm) {
+                        // C.NOP
+                        (ZERO, ZERO, 0) => 0x0001,
+                        // C.MV
+                        (rd, rs1, 0) if rd != ZERO && rs1 != ZERO => cr(0b1000, rd, rs1),
+                        // C.ADDI
+                        (rd, rs1, imm) if rd == rs1 && rd != ZERO && fits(imm, 6) => {
+                            ci(0b000, imm, rd) | 0b01
+                        }
+                        // C.ADDI16SP
+                        (SP, SP, imm) if imm % 16 == 0 && fits(imm, 10) => {
+                            let nzimm = twiddle!(Word, imm as Word, 4..5, 6..7, 7..9, 5..6);
+                            (0b011 << 13) |
+                                (bits(imm, 9) << 12) |
+                                ((SP as Word) << 7) |
+                                (nzimm << 2) |
+                                0b01
+                        }
+                        // C.LI
+                        (rd, ZERO, imm) if rd != ZERO && fits(imm, 6) => ci(0b010, imm, rd) | 0b01,
+                        // C.ADDI4SPN
+                        (rd, SP, imm) if imm > 0 && imm % 4 == 0 && imm < 1024 => {
+                            let nzuimm = twiddle!(Word, imm as Word, 4..6, 6..10, 2..3, 3..4);
+                            (nzuimm << 5) | ((creg(rd)? as Word) << 2)
+                        }
+                        _ => return None,
+                    },
+                    // C.SLLI
+                    ImmediateArithmeticFunction::Slli if rd == rs1 && rd != ZERO && short_shamt => {
+                        ci(0b000, shamt as i64, rd) | 0b10
+                    }
+                    // C.SRLI, C.SRAI
+                    ImmediateArithmeticFunction::Srli | ImmediateArithmeticFunction::Srai
+                        if rd == rs1 && short_shamt =>
+                    {
+                        let funct2 = (funct == ImmediateArithmeticFunction::Srai) as Word;
+                        cb_arith(funct2, shamt as i64, rd)?
+                    }
+                    // C.ANDI
+                    ImmediateArithmeticFunction::Andi if rd == rs1 && fits(imm, 6) => {
+                        cb_arith(0b10, imm, rd)?
+                    }
+                    _ => return None,
+                }
+            }
+            Self::RegisterArithmetic(r, funct) => {
+                let (rd, rs1, rs2) = (r.rd, r.rs1, r.rs2);
+                match funct {
+                    RegisterArithmeticFunction::Add if rd != ZERO => match (rs1, rs2) {
+                        // C.MV
+                        (ZERO, rs2) if rs2 != ZERO => cr(0b1000, rd, rs2),
+                        // C.ADD
+                        (rs1, rs2) if rs1 == rd && rs2 != ZERO => cr(0b1001, rd, rs2),
+                        (rs1, rs2) if rs2 == rd && rs1 != ZERO => cr(0b1001, rd, rs1),
+                        _ => return None,
+                    },
+                    // C.SUB
+                    RegisterArithmeticFunction::Sub if rd == rs1 => ca(0, 0b00, rd, rs2)?,
+                    // C.XOR, C.OR, C.AND
+                    RegisterArithmeticFunction::Xor |
+                    RegisterArithmeticFunction::Or |
+                    RegisterArithmeticFunction::And => {
+                        let funct2 = match funct {
+                            RegisterArithmeticFunction::Xor => 0b01,
+                            RegisterArithmeticFunction::Or => 0b10,
+                            _ => 0b11,
+                        };
+                        commutative(rd, rs1, rs2, |rs2| ca(0, funct2, rd, rs2))?
+                    }
+                    _ => return None,
+                }
+            }
+            // C.LUI
+            Self::Lui(u) => {
+                let imm = signed(u.imm) >> 12;
+                if u.rd == ZERO || u.rd == SP || imm == 0 || !fits(imm, 6) {
+                    return None;
+                }
+                ci(0b011, imm, u.rd) | 0b01
+            }
+            Self::MemoryLoad(i, funct) => {
+                let offset = signed(i.imm);
+                match funct {
+                    // C.LWSP
+                    LoadFunction::Lw if i.rs1 == SP && i.rd != ZERO => {
+                        let uimm = uimm(offset, 4, 256)?;
+                        (0b010 << 13) |
+                            (bits(uimm, 5) << 12) |
+                            ((i.rd as Word) << 7) |
+                            (twiddle!(Word, uimm as Word, 2..5, 6..8) << 2) |
+                            0b10
+                    }
+                    // C.LW
+                    LoadFunction::Lw => {
+                        let uimm = uimm(offset, 4, 128)?;
+                        cl_cs(0b010, twiddle!(Word, uimm as Word, 2..3, 6..7), uimm, i.rs1, i.rd)?
+                    }
+                    // C.LDSP
+                    #[cfg(feature = "64-bit")]
+                    LoadFunction::Ld if i.rs1 == SP && i.rd != ZERO => {
+                        let uimm = uimm(offset, 8, 512)?;
+                        (0b011 << 13) |
+                            (bits(uimm, 5) << 12) |
+                            ((i.rd as Word) << 7) |
+                            (twiddle!(Word, uimm as Word, 3..5, 6..9) << 2) |
+                            0b10
+                    }
+                    // C.LD
+                    #[cfg(feature = "64-bit")]
+                    LoadFunction::Ld => {
+                        let uimm = uimm(offset, 8, 256)?;
+                        cl_cs(0b011, twiddle!(Word, uimm as Word, 6..8), uimm, i.rs1, i.rd)?
+                    }
+                    _ => return None,
+                }
+            }
+            Self::MemoryStore(s, funct) => {
+                let offset = signed(s.imm);
+                match funct {
+                    // C.SWSP
+                    StoreFunction::Sw if s.rs1 == SP => {
+                        let uimm = uimm(offset, 4, 256)?;
+                        (0b110 << 13) |
+                            (twiddle!(Word, uimm as Word, 2..6, 6..8) << 7) |
+                            ((s.rs2 as Word) << 2) |
+                            0b10
+                    }
+                    // C.SW
+                    StoreFunction::Sw => {
+                        let uimm = uimm(offset, 4, 128)?;
+                        cl_cs(0b110, twiddle!(Word, uimm as Word, 2..3, 6..7), uimm, s.rs1, s.rs2)?
+                    }
+                    // C.SDSP
+                    #[cfg(feature = "64-bit")]
+                    StoreFunction::Sd if s.rs1 == SP => {
+                        let uimm = uimm(offset, 8, 512)?;
+                        (0b111 << 13) |
+                            (twiddle!(Word, uimm as Word, 3..6, 6..9) << 7) |
+                            ((s.rs2 as Word) << 2) |
+                            0b10
+                    }
+                    // C.SD
+                    #[cfg(feature = "64-bit")]
+                    StoreFunction::Sd => {
+                        let uimm = uimm(offset, 8, 256)?;
+                        cl_cs(0b111, twiddle!(Word, uimm as Word, 6..8), uimm, s.rs1, s.rs2)?
+                    }
+                    _ => return None,
+                }
+            }
+            // C.J, and C.JAL on RV32.
+            Self::Jal(j) => {
+                let offset = signed(j.imm);
+                let funct3 = match j.rd {
+                    ZERO => 0b101,
+                    RA if X::X_LEN == 32 => 0b001,
+                    _ => return None,
+                };
+                if offset % 2 != 0 || !fits(offset, 12) {
+                    return None;
+                }
+                let target = twiddle!(
+                    Word,
+                    offset as Word,
+                    11..12,
+                    4..5,
+                    8..10,
+                    10..11,
+                    6..7,
+                    7..8,
+                    1..4,
+                    5..6
+                );
+                (funct3 << 13) | (target << 2) | 0b01
+            }
+            // C.JR, C.JALR
+            Self::Jalr(i) if i.imm == 0 && i.rs1 != ZERO => match i.rd {
+                ZERO => cr(0b1000, i.rs1, ZERO),
+                RA => cr(0b1001, i.rs1, ZERO),
+                _ => return None,
+            },
+            // C.BEQZ, C.BNEZ
+            Self::Branch(b, funct) if b.rs2 == ZERO => {
+                let funct3 = match funct {
+                    BranchFunction::Beq => 0b110,
+                    BranchFunction::Bne => 0b111,
+                    _ => return None,
+                };
+                let offset = signed(b.imm);
+                if offset % 2 != 0 || !fits(offset, 9) {
+                    return None;
+                }
+                (funct3 << 13) |
+                    (twiddle!(Word, offset as Word, 8..9, 3..5) << 10) |
+                    ((creg(b.rs1)? as Word) << 7) |
+                    (twiddle!(Word, offset as Word, 6..8, 1..3, 5..6) << 2) |
+                    0b01
+            }
+            // C.EBREAK
+            Self::Environment(_, EnvironmentFunction::Ebreak) => 0x9002,
+            // C.ADDIW
+            #[cfg(feature = "64-bit")]
+            Self::ImmediateArithmeticWord(i, ImmediateArithmeticWordFunction::Addiw)
+                if i.rd == i.rs1 && i.rd != ZERO && fits(signed(i.imm), 6) =>
+            {
+                ci(0b001, signed(i.imm), i.rd) | 0b01
+            }
+            // C.SUBW, C.ADDW
+            #[cfg(feature = "64-bit")]
+            Self::RegisterArithmeticWord(r, funct) => match funct {
+                RegisterArithmeticWordFunction::Subw if r.rd == r.rs1 => ca(1, 0b00, r.rd, r.rs2)?,
+                RegisterArithmeticWordFunction::Addw => {
+                    commutative(r.rd, r.rs1, r.rs2, |rs2| ca(1, 0b01, r.rd, rs2))?
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        CompressedInstruction::decode_for::<X>(raw as HalfWord).ok()
+    }
+}
+
+/// Interprets a sign-extended immediate as a signed value.
+const fn signed(imm: XWord) -> i64 {
+    let unused = 128 - X_LEN as u32;
+    (((imm as i128) << unused) >> unused) as i64
+}
+
+/// Returns `true` if `value` fits in a `bits`-bit two's complement field.
+const fn fits(value: i64, bits: u32) -> bool {
+    value >= -(1 << (bits - 1)) && value < (1 << (bits - 1))
+}
+
+/// Returns bit `index` of `value`.
+const fn bits(value: i64, index: u32) -> Word {
+    ((value >> index) & 1) as Word
+}
+
+/// Returns `offset` if it is a multiple of `scale` within `0..limit`.
+const fn uimm(offset: i64, scale: i64, limit: i64) -> Option<i64> {
+    if offset >= 0 && offset < limit && offset % scale == 0 {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+/// Maps a regular register index to its 3-bit compressed form, if it is one of `x8`-`x15`.
+const fn creg(reg: u8) -> Option<u8> {
+    if reg >= C_REG_OFFSET && reg < C_REG_OFFSET + 8 {
+        Some(reg - C_REG_OFFSET)
+    } else {
+        None
+    }
+}
+
+/// Encodes a CR-type instruction in the C2 quadrant.
+const fn cr(funct4: Word, rs1_rd: u8, rs2: u8) -> Word {
+    (funct4 << 12) | ((rs1_rd as Word) << 7) | ((rs2 as Word) << 2) | 0b10
+}
+
+/// Encodes a CI-type instruction with a 6-bit immediate, excluding the quadrant bits.
+const fn ci(funct3: Word, imm: i64, rs1_rd: u8) -> Word {
+    (funct3 << 13) | (bits(imm, 5) << 12) | ((rs1_rd as Word) << 7) | (((imm as Word) & 0x1F) << 2)
+}
+
+/// Encodes a CA-type arithmetic instruction in the C1 quadrant.
+fn ca(word: Word, funct2: Word, rd: u8, rs2: u8) -> Option<Word> {
+    Some(
+        (0b100 << 13) |
+            (word << 12) |
+            (0b11 << 10) |
+            ((creg(rd)? as Word) << 7) |
+            (funct2 << 5) |
+            ((creg(rs2)? as Word) << 2) |
+            0b01,
+    )
+}
+
+/// Encodes a CB-type arithmetic instruction (`c.srli`, `c.srai`, `c.andi`) in the C1 quadrant.
+fn cb_arith(funct2: Word, imm: i64, rd: u8) -> Option<Word> {
+    Some(
+        (0b100 << 13) |
+            (bits(imm, 5) << 12) |
+            (funct2 << 10) |
+            ((creg(rd)? as Word) << 7) |
+            (((imm as Word) & 0x1F) << 2) |
+            0b01,
+    )
+}
+
+/// Encodes a CL-type or CS-type instruction in the C0 quadrant, given the immediate bits placed
+/// at `[6:5]`.
+fn cl_cs(funct3: Word, low: Word, uimm: i64, rs1: u8, rd_rs2: u8) -> Option<Word> {
+    Some(
+        (funct3 << 13) |
+            (twiddle!(Word, uimm as Word, 3..6) << 10) |
+            ((creg(rs1)? as Word) << 7) |
+            (low << 5) |
+            ((creg(rd_rs2)? as Word) << 2),
+    )
+}
+
+/// Encodes a commutative operation `rd = rs1 op rs2` where `rd` must equal one of the sources.
+fn commutative(rd: u8, rs1: u8, rs2: u8, encode: impl Fn(u8) -> Option<Word>) -> Option<Word> {
+    if rd == rs1 {
+        encode(rs2)
+    } else if rd == rs2 {
+        encode(rs1)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CompressedInstruction, HalfWord, Instruction, NativeXlen, Rv32, Word, Xlen};
+    use rstest::rstest;
+
+    #[cfg(feature = "64-bit")]
+    use crate::Rv64;
+
+    #[rstest]
+    #[case::c_nop(0x00000013, Some(0x0001))]
+    #[case::c_addi(0xFFF50513, Some(0x157D))]
+    #[case::c_li(0x00500593, Some(0x4595))]
+    #[case::c_mv(0x00058513, Some(0x852E))]
+    #[case::c_add(0x00B50533, Some(0x952E))]
+    #[case::c_add_commuted(0x00A58533, Some(0x952E))]
+    #[case::c_addi_sp(0xFE010113, Some(0x1101))]
+    #[case::c_addi16sp(0xFC010113, Some(0x7139))]
+    #[case::c_addi4spn(0x00810513, Some(0x0028))]
+    #[case::c_lui(0x00001537, Some(0x6505))]
+    #[case::c_lw(0x0045A503, Some(0x41C8))]
+    #[case::c_sw(0x00A5A223, Some(0xC1C8))]
+    #[case::c_lwsp(0x00412503, Some(0x4512))]
+    #[case::c_swsp(0x00A12223, Some(0xC22A))]
+    #[case::c_j(0x0100006F, Some(0xA801))]
+    #[case::c_jr(0x00008067, Some(0x8082))]
+    #[case::c_jalr(0x000500E7, Some(0x9502))]
+    #[case::c_beqz(0x00050463, Some(0xC501))]
+    #[case::c_bnez(0xFE051EE3, Some(0xFD75))]
+    #[case::c_srli(0x00155513, Some(0x8105))]
+    #[case::c_srai(0x40155513, Some(0x8505))]
+    #[case::c_andi(0x00F57513, Some(0x893D))]
+    #[case::c_sub(0x40B50533, Some(0x8D0D))]
+    #[case::c_and(0x00A5F533, Some(0x8D6D))]
+    #[case::c_slli(0x00251513, Some(0x050A))]
+    #[case::c_ebreak(0x00100073, Some(0x9002))]
+    #[case::addi_large(0x7FF50513, None)]
+    #[case::lw_high_reg(0x0045A283, None)]
+    #[case::lw_misaligned(0x0025A503, None)]
+    #[case::sub_not_rd(0x40B60533, None)]
+    #[case::ecall(0x00000073, None)]
+    #[case::blt(0x00B54463, None)]
+    #[case::hint_addi_x0(0x00100013, None)]
+    fn test_compress(#[case] word: Word, #[case] expected: Option<HalfWord>) {
+        let instruction = Instruction::try_from(word).unwrap();
+        assert_eq!(instruction.compress().map(|c| c.encode()), expected);
+    }
+
+    #[test]
+    fn test_compress_xlen() {
+        let jal_ra = Instruction::try_from(0x008000EF).unwrap();
+        assert_eq!(jal_ra.compress_for::<Rv32>().map(|c| c.encode()), Some(0x2021));
+
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "64-bit")] {
+                // `c.jal` is replaced by `c.addiw` on RV64, and `c.ld` takes the place of `c.flw`.
+                assert_eq!(jal_ra.compress_for::<Rv64>(), None);
+                let addiw = Instruction::try_from(0x0015051B).unwrap();
+                assert_eq!(addiw.compress_for::<Rv64>().map(|c| c.encode()), Some(0x2505));
+                assert_eq!(addiw.compress_for::<Rv32>(), None);
+                let ld = Instruction::try_from(0x0085B503).unwrap();
+                assert_eq!(ld.compress_for::<Rv64>().map(|c| c.encode()), Some(0x6588));
+                assert_eq!(ld.compress_for::<Rv32>(), None);
+
+                // slli a0, a0, 32
+                let slli = Instruction::try_from(0x02051513).unwrap();
+                assert_eq!(slli.compress_for::<Rv64>().map(|c| c.encode()), Some(0x1502));
+                assert_eq!(slli.compress_for::<Rv32>(), None);
+            } else {
+                assert_eq!(jal_ra.compress().map(|c| c.encode()), Some(0x2021));
+            }
+        }
+    }
+
+    #[test]
+    fn test_compress_round_trip_exhaustive() {
+        round_trip::<Rv32>();
+        round_trip::<NativeXlen>();
+    }
+
+    /// Checks that every compressed instruction of a hart with the register width `X` compresses
+    /// back to an equivalent one once expanded.
+    fn round_trip<X: Xlen>() {
+        for raw in 0..=HalfWord::MAX {
+            let Ok(compressed) = CompressedInstruction::decode_strict_for::<X>(raw) else {
+                continue;
+            };
+            assert_eq!(compressed.encode(), raw, "encode(decode({raw:#06x}))");
+
+            let expanded = compressed.expand();
+            let Some(recompressed) = expanded.compress_for::<X>() else {
+                continue;
+            };
+            let re_expanded = recompressed.expand();
+            assert!(
+                re_expanded == expanded || expanded.encode() & 0x707F == 0x0013,
+                "RV{}, {raw:#06x}: {expanded:?} compressed to {recompressed:?}",
+                X::X_LEN
+            );
+        }
+    }
+}
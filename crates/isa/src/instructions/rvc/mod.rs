@@ -2,9 +2,10 @@
 
 use super::{BType, JType, UType};
 use crate::{
-    bits, sign_extend, twiddle, BranchFunction, EnvironmentFunction, HalfWord, IType,
-    ImmediateArithmeticFunction, Instruction, InstructionDecodeError, LoadFunction, RType,
-    RegisterArithmeticFunction, SType, StoreFunction, Word, XWord, REG_RA, REG_SP, REG_ZERO,
+    bits, feature::check_xlen, sign_extend, twiddle, BranchFunction, DecodeErrorKind,
+    EnvironmentFunction, HalfWord, IType, ImmediateArithmeticFunction, Instruction,
+    InstructionDecodeError, LoadFunction, RType, RegisterArithmeticFunction, SType, StoreFunction,
+    Word, XWord, Xlen, REG_RA, REG_SP, REG_ZERO, X_LEN,
 };
 use cfg_if::cfg_if;
 
@@ -14,6 +15,8 @@ use crate::{ImmediateArithmeticWordFunction, RegisterArithmeticWordFunction};
 mod types;
 pub use types::*;
 
+mod compress;
+
 /// C_REGISTER_OFFSET is the offset of the register mapping from the `C` instructions to regular
 /// 32 bit instructions. In the `C` extension, register fields are only allotted 3 bits,
 /// allowing for 8 possible register designations.
@@ -55,10 +58,29 @@ impl CompressedInstruction {
             0b00 => Ok(Self::C0(C0::decode(instruction)?)),
             0b01 => Ok(Self::C1(C1::decode(instruction)?)),
             0b10 => Ok(Self::C2(C2::decode(instruction)?)),
-            _ => Err(InstructionDecodeError::InvalidOpcode(opcode as u8)),
+            _ => Err(InstructionDecodeError::new(
+                instruction as Word,
+                DecodeErrorKind::InvalidOpcode(opcode as u8),
+            )),
         }
     }
 
+    /// Decodes a [CompressedInstruction] for a hart with the register width `X`, rejecting
+    /// instructions that are only available on wider harts. [Self::decode] decodes for
+    /// [NativeXlen].
+    ///
+    /// [NativeXlen]: crate::NativeXlen
+    pub fn decode_for<X: Xlen>(instruction: HalfWord) -> Result<Self, InstructionDecodeError> {
+        if X::X_LEN < X_LEN {
+            // `c.jal` shares its encoding with `c.addiw`, which replaces it on RV64 and RV128.
+            if X::X_LEN == 32 && instruction & 0xE003 == 0x2001 {
+                return Ok(Self::C1(C1::CJal(CJType::decode(instruction))));
+            }
+            check_xlen::<X>(instruction as Word)?;
+        }
+        Self::decode(instruction)
+    }
+
     /// Maps the [CompressedInstruction] to a regular RISC-V [Instruction].
     pub fn expand(self) -> Instruction {
         match self {
@@ -67,6 +89,15 @@ impl CompressedInstruction {
             Self::C2(c2) => c2.expand(),
         }
     }
+
+    /// Encodes the [CompressedInstruction] into a 16-bit [HalfWord].
+    pub fn encode(&self) -> HalfWord {
+        match self {
+            Self::C0(c0) => c0.encode(),
+            Self::C1(c1) => c1.encode() | 0b01,
+            Self::C2(c2) => c2.encode() | 0b10,
+        }
+    }
 }
 
 /// A RISC-V C0 instruction.
@@ -98,7 +129,10 @@ impl C0 {
             0b011 => Ok(Self::CLd(CLType::decode(instruction))),
             #[cfg(feature = "64-bit")]
             0b111 => Ok(Self::CSd(CSType::decode(instruction))),
-            _ => Err(InstructionDecodeError::InvalidFunction { q_a: funct3, q_b: 0 }),
+            _ => Err(InstructionDecodeError::new(
+                instruction as Word,
+                DecodeErrorKind::InvalidFunction { q_a: funct3, q_b: 0 },
+            )),
         }
     }
 
@@ -160,6 +194,19 @@ impl C0 {
             }
         }
     }
+
+    /// Encodes the compressed instruction into a 16-bit [HalfWord].
+    pub fn encode(&self) -> HalfWord {
+        match self {
+            Self::CAddi4spn(ciw) => ciw.encode(),
+            Self::CLw(cl) => cl.encode(),
+            Self::CSw(cs) => cs.encode(),
+            #[cfg(feature = "64-bit")]
+            Self::CLd(cl) => cl.encode(),
+            #[cfg(feature = "64-bit")]
+            Self::CSd(cs) => cs.encode(),
+        }
+    }
 }
 
 /// A RISC-V C1 instruction.
@@ -201,7 +248,7 @@ impl C1 {
                     // In 64-bit mode, C.ADDIW is used instead of C.JAL.
                     if #[cfg(feature = "64-bit")] {
                         if rs1_rd == 0 {
-                            Err(InstructionDecodeError::InvalidFunction { q_a: funct3, q_b: 0 })
+                            Err(InstructionDecodeError::new(instruction as Word, DecodeErrorKind::InvalidFunction { q_a: funct3, q_b: 0 }))
                         } else {
                             Ok(Self::CAddiw(CIType::decode(instruction)))
                         }
@@ -212,19 +259,21 @@ impl C1 {
             }
             0b010 => Ok(Self::CLi(CIType::decode(instruction))),
             0b011 => {
+                // C.LUI with `rd = x0` is a HINT.
                 if rs1_rd == 2 {
                     Ok(Self::CAddi16sp(CIType::decode(instruction)))
-                } else if rs1_rd != 0 {
-                    Ok(Self::CLui(CIType::decode(instruction)))
                 } else {
-                    Err(InstructionDecodeError::InvalidFunction { q_a: funct3, q_b: 0 })
+                    Ok(Self::CLui(CIType::decode(instruction)))
                 }
             }
             0b100 => Ok(Self::SubFunct(C1SubFunct::decode(instruction)?)),
             0b101 => Ok(Self::CJ(CJType::decode(instruction))),
             0b110 => Ok(Self::CBeqz(CBType::decode(instruction))),
             0b111 => Ok(Self::CBnez(CBType::decode(instruction))),
-            _ => Err(InstructionDecodeError::InvalidFunction { q_a: funct3, q_b: 0 }),
+            _ => Err(InstructionDecodeError::new(
+                instruction as Word,
+                DecodeErrorKind::InvalidFunction { q_a: funct3, q_b: 0 },
+            )),
         }
     }
 
@@ -337,6 +386,18 @@ impl C1 {
             }
         }
     }
+
+    /// Encodes the compressed instruction into a 16-bit [HalfWord], excluding the quadrant bits.
+    pub fn encode(&self) -> HalfWord {
+        match self {
+            Self::CAddi(ci) | Self::CLi(ci) | Self::CAddi16sp(ci) | Self::CLui(ci) => ci.encode(),
+            Self::CJal(cj) | Self::CJ(cj) => cj.encode(),
+            Self::SubFunct(sub_funct) => sub_funct.encode(),
+            Self::CBeqz(cb) | Self::CBnez(cb) => cb.encode(),
+            #[cfg(feature = "64-bit")]
+            Self::CAddiw(ci) => ci.encode(),
+        }
+    }
 }
 
 /// Sub-functions of the [C1] `4` funct3.
@@ -385,12 +446,16 @@ impl C1SubFunct {
                     0b00 if arch_sel == 1 => Ok(Self::CSubw(CSType::decode(instruction))),
                     #[cfg(feature = "64-bit")]
                     0b01 if arch_sel == 1 => Ok(Self::CAddw(CSType::decode(instruction))),
-                    _ => {
-                        Err(InstructionDecodeError::InvalidFunction { q_a: funct2, q_b: arch_sel })
-                    }
+                    _ => Err(InstructionDecodeError::new(
+                        instruction as Word,
+                        DecodeErrorKind::InvalidFunction { q_a: funct2, q_b: arch_sel },
+                    )),
                 }
             }
-            _ => Err(InstructionDecodeError::InvalidFunction { q_a: funct6_low, q_b: 0 }),
+            _ => Err(InstructionDecodeError::new(
+                instruction as Word,
+                DecodeErrorKind::InvalidFunction { q_a: funct6_low, q_b: 0 },
+            )),
         }
     }
 
@@ -497,6 +562,16 @@ impl C1SubFunct {
             }
         }
     }
+
+    /// Encodes the compressed instruction into a 16-bit [HalfWord], excluding the quadrant bits.
+    pub fn encode(&self) -> HalfWord {
+        match self {
+            Self::CSrli(cb) | Self::CSrai(cb) | Self::CAndi(cb) => cb.encode(),
+            Self::CSub(cs) | Self::CXor(cs) | Self::COr(cs) | Self::CAnd(cs) => cs.encode(),
+            #[cfg(feature = "64-bit")]
+            Self::CSubw(cs) | Self::CAddw(cs) => cs.encode(),
+        }
+    }
 }
 
 /// A RISC-V C2 instruction.
@@ -525,7 +600,8 @@ impl C2 {
         let rd = bits!(u8, instruction, 7..12);
 
         match funct3 {
-            0b000 if rd != 0 => Ok(Self::CSlli(CIType::decode(instruction))),
+            // C.SLLI with `rd = x0` is a HINT.
+            0b000 => Ok(Self::CSlli(CIType::decode(instruction))),
             0b010 if rd != 0 => Ok(Self::CLwsp(CIType::decode(instruction))),
             0b100 => Ok(Self::SubFunct(C2SubFunct::decode(instruction)?)),
             0b110 => Ok(Self::CSwsp(CSSType::decode(instruction))),
@@ -533,7 +609,10 @@ impl C2 {
             0b011 if rd != 0 => Ok(Self::CLdsp(CIType::decode(instruction))),
             #[cfg(feature = "64-bit")]
             0b111 => Ok(Self::CSdsp(CSSType::decode(instruction))),
-            _ => Err(InstructionDecodeError::InvalidFunction { q_a: funct3, q_b: 0 }),
+            _ => Err(InstructionDecodeError::new(
+                instruction as Word,
+                DecodeErrorKind::InvalidFunction { q_a: funct3, q_b: 0 },
+            )),
         }
     }
 
@@ -595,6 +674,19 @@ impl C2 {
             }
         }
     }
+
+    /// Encodes the compressed instruction into a 16-bit [HalfWord], excluding the quadrant bits.
+    pub fn encode(&self) -> HalfWord {
+        match self {
+            Self::CSlli(ci) | Self::CLwsp(ci) => ci.encode(),
+            Self::CSwsp(css) => css.encode(),
+            Self::SubFunct(sf) => sf.encode(),
+            #[cfg(feature = "64-bit")]
+            Self::CLdsp(ci) => ci.encode(),
+            #[cfg(feature = "64-bit")]
+            Self::CSdsp(css) => css.encode(),
+        }
+    }
 }
 
 /// Sub-functions of the [C2] `4` funct3.
@@ -626,10 +718,13 @@ impl C2SubFunct {
             1 => match (cr.rs2, cr.rs1_rd) {
                 (0, 0) => Ok(Self::CEBreak),
                 (0, rs1_rd) if rs1_rd != 0 => Ok(Self::CJalr(cr)),
-                (rs2, rs1_rd) if rs2 != 0 && rs1_rd != 0 => Ok(Self::CAdd(cr)),
-                _ => Err(InstructionDecodeError::InvalidFunction { q_a: sel, q_b: 0 }),
+                // C.ADD with `rd = x0` is a HINT.
+                _ => Ok(Self::CAdd(cr)),
             },
-            _ => Err(InstructionDecodeError::InvalidFunction { q_a: sel, q_b: 0 }),
+            _ => Err(InstructionDecodeError::new(
+                instruction as Word,
+                DecodeErrorKind::InvalidFunction { q_a: sel, q_b: 0 },
+            )),
         }
     }
 
@@ -664,4 +759,12 @@ impl C2SubFunct {
             }
         }
     }
+
+    /// Encodes the compressed instruction into a 16-bit [HalfWord], excluding the quadrant bits.
+    pub const fn encode(&self) -> HalfWord {
+        match self {
+            Self::CJr(cr) | Self::CMv(cr) | Self::CJalr(cr) | Self::CAdd(cr) => cr.encode(),
+            Self::CEBreak => 0b1001 << 12,
+        }
+    }
 }
@@ -22,6 +22,13 @@ impl CRType {
             funct4: bits!(u8, instruction, 12..16),
         }
     }
+
+    /// Encodes the [CRType] instruction into a 16-bit [HalfWord], excluding the quadrant bits.
+    pub const fn encode(&self) -> HalfWord {
+        ((self.funct4 as HalfWord & 0xF) << 12) |
+            ((self.rs1_rd as HalfWord & 0x1F) << 7) |
+            ((self.rs2 as HalfWord & 0x1F) << 2)
+    }
 }
 
 /// A RISC-V CI-Type instruction.
@@ -44,6 +51,14 @@ impl CIType {
             imm: twiddle!(HalfWord, instruction, 12..13, 2..7),
         }
     }
+
+    /// Encodes the [CIType] instruction into a 16-bit [HalfWord], excluding the quadrant bits.
+    pub fn encode(&self) -> HalfWord {
+        ((self.funct3 as HalfWord & 0x7) << 13) |
+            (bits!(HalfWord, self.imm, 5..6) << 12) |
+            ((self.rs1_rd as HalfWord & 0x1F) << 7) |
+            (bits!(HalfWord, self.imm, 0..5) << 2)
+    }
 }
 
 /// A RISC-V CSS-Type instruction.
@@ -66,6 +81,13 @@ impl CSSType {
             imm: bits!(HalfWord, instruction, 7..13),
         }
     }
+
+    /// Encodes the [CSSType] instruction into a 16-bit [HalfWord], excluding the quadrant bits.
+    pub fn encode(&self) -> HalfWord {
+        ((self.funct3 as HalfWord & 0x7) << 13) |
+            (bits!(HalfWord, self.imm, 0..6) << 7) |
+            ((self.rs2 as HalfWord & 0x1F) << 2)
+    }
 }
 
 /// A RISC-V CIW-Type instruction.
@@ -88,6 +110,13 @@ impl CIWType {
             imm: bits!(HalfWord, instruction, 5..13),
         }
     }
+
+    /// Encodes the [CIWType] instruction into a 16-bit [HalfWord], excluding the quadrant bits.
+    pub fn encode(&self) -> HalfWord {
+        ((self.funct3 as HalfWord & 0x7) << 13) |
+            (bits!(HalfWord, self.imm, 0..8) << 5) |
+            ((self.rd as HalfWord & 0x7) << 2)
+    }
 }
 
 /// A RISC-V CL-Type instruction.
@@ -113,6 +142,15 @@ impl CLType {
             imm: twiddle!(HalfWord, instruction, 10..13, 5..7),
         }
     }
+
+    /// Encodes the [CLType] instruction into a 16-bit [HalfWord], excluding the quadrant bits.
+    pub fn encode(&self) -> HalfWord {
+        ((self.funct3 as HalfWord & 0x7) << 13) |
+            (bits!(HalfWord, self.imm, 2..5) << 10) |
+            ((self.rs1 as HalfWord & 0x7) << 7) |
+            (bits!(HalfWord, self.imm, 0..2) << 5) |
+            ((self.rd as HalfWord & 0x7) << 2)
+    }
 }
 
 /// A RISC-V CS-Type instruction.
@@ -138,6 +176,15 @@ impl CSType {
             imm: twiddle!(HalfWord, instruction, 10..13, 5..7),
         }
     }
+
+    /// Encodes the [CSType] instruction into a 16-bit [HalfWord], excluding the quadrant bits.
+    pub fn encode(&self) -> HalfWord {
+        ((self.funct3 as HalfWord & 0x7) << 13) |
+            (bits!(HalfWord, self.imm, 2..5) << 10) |
+            ((self.rs1 as HalfWord & 0x7) << 7) |
+            (bits!(HalfWord, self.imm, 0..2) << 5) |
+            ((self.rs2 as HalfWord & 0x7) << 2)
+    }
 }
 
 /// A RISC-V CB-Type instruction.
@@ -160,6 +207,14 @@ impl CBType {
             offset: twiddle!(HalfWord, instruction, 10..13, 2..7),
         }
     }
+
+    /// Encodes the [CBType] instruction into a 16-bit [HalfWord], excluding the quadrant bits.
+    pub fn encode(&self) -> HalfWord {
+        ((self.funct3 as HalfWord & 0x7) << 13) |
+            (bits!(HalfWord, self.offset, 5..8) << 10) |
+            ((self.rs1 as HalfWord & 0x7) << 7) |
+            (bits!(HalfWord, self.offset, 0..5) << 2)
+    }
 }
 
 /// A RISC-V CJ-Type instruction.
@@ -176,4 +231,9 @@ impl CJType {
     pub fn decode(instruction: HalfWord) -> Self {
         Self { funct3: bits!(u8, instruction, 13..16), target: bits!(HalfWord, instruction, 2..13) }
     }
+
+    /// Encodes the [CJType] instruction into a 16-bit [HalfWord], excluding the quadrant bits.
+    pub fn encode(&self) -> HalfWord {
+        ((self.funct3 as HalfWord & 0x7) << 13) | (bits!(HalfWord, self.target, 0..11) << 2)
+    }
 }
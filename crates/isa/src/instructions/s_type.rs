@@ -25,6 +25,16 @@ impl SType {
             imm: sign_extend(twiddle!(XWord, instruction, 25..32, 7..12), 11),
         }
     }
+
+    /// Encodes the [SType] instruction into a 32-bit [Word] with the given `opcode`.
+    pub fn encode(&self, opcode: u8) -> Word {
+        (bits!(Word, self.imm, 5..12) << 25) |
+            ((self.rs2 as Word & 0x1F) << 20) |
+            ((self.rs1 as Word & 0x1F) << 15) |
+            ((self.funct3 as Word & 0x7) << 12) |
+            (bits!(Word, self.imm, 0..5) << 7) |
+            (opcode as Word & 0x7F)
+    }
 }
 
 #[cfg(test)]
@@ -52,4 +62,10 @@ mod test {
         assert_eq!(stype.rs2, 0b00100);
         assert_eq!(stype.imm, sign_extend(0b111000111000, 11));
     }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let instruction = 0b1110001_00100_10101_111_11000_0100011;
+        assert_eq!(SType::decode(instruction).encode(0b0100011), instruction);
+    }
 }
@@ -0,0 +1,338 @@
+//! Strict decoding, which rejects every reserved or illegal encoding.
+//!
+//! The default decoder is permissive: it accepts some encodings that the specification reserves,
+//! such as unknown `SYSTEM` instructions (decoded as `ebreak`) or `fence.i`. This keeps programs
+//! that execute those encodings running. [Instruction::decode_strict] instead only accepts the
+//! encodings that the specification defines for the enabled extensions, including HINTs.
+
+use crate::{bits, DecodeErrorKind, Instruction, InstructionDecodeError, NativeXlen, Word, Xlen};
+
+#[cfg(feature = "c")]
+use crate::{is_compressed, CompressedInstruction, HalfWord};
+
+impl Instruction {
+    /// Decodes an [Instruction] from a [Word], rejecting every reserved or illegal encoding.
+    ///
+    /// Compressed instructions are decoded with [CompressedInstruction::decode_strict] and
+    /// expanded.
+    pub fn decode_strict(instruction: Word) -> Result<Self, InstructionDecodeError> {
+        Self::decode_strict_for::<NativeXlen>(instruction)
+    }
+
+    /// Decodes an [Instruction] like [Self::decode_strict], for a hart with the register width
+    /// `X`.
+    pub fn decode_strict_for<X: Xlen>(instruction: Word) -> Result<Self, InstructionDecodeError> {
+        #[cfg(feature = "c")]
+        if is_compressed(instruction) {
+            return CompressedInstruction::decode_strict_for::<X>(instruction as HalfWord)
+                .map(CompressedInstruction::expand);
+        }
+
+        if is_reserved::<X>(instruction) {
+            return Err(InstructionDecodeError::new(instruction, DecodeErrorKind::Reserved));
+        }
+        Self::decode_for::<X>(instruction)
+    }
+}
+
+/// Returns `true` if a 32-bit encoding is reserved on a hart with the register width `X`, but
+/// accepted by the permissive decoder.
+fn is_reserved<X: Xlen>(instruction: Word) -> bool {
+    let opcode = bits!(u8, instruction, 0..7);
+    let funct3 = bits!(u8, instruction, 12..15);
+    let shamt_bits = X::X_LEN.trailing_zeros() as usize;
+    match opcode {
+        // `slli` with bits above the shift amount set.
+        0b001_0011 if funct3 == 0b001 => instruction >> (20 + shamt_bits) != 0,
+        // `srli` and `srai` with a shift amount wider than the register.
+        0b001_0011 if funct3 == 0b101 => {
+            (instruction >> (20 + shamt_bits)) & ((1 << (10 - shamt_bits)) - 1) != 0
+        }
+        // `jalr` with a non-zero funct3.
+        0b110_0111 => funct3 != 0,
+        // `fence.i` and the reserved MISC-MEM encodings, except `lq` on RV128.
+        0b000_1111 => funct3 != 0 && !(X::X_LEN == 128 && funct3 == 0b010),
+        // CSR instructions, trap returns, `wfi` and the reserved SYSTEM encodings.
+        0b111_0011 => instruction != 0x0000_0073 && instruction != 0x0010_0073,
+        // AMOs with an unsupported width, and `lr` with a non-zero `rs2`.
+        #[cfg(feature = "a")]
+        0b010_1111 => {
+            let lr = bits!(u8, instruction, 27..32) == 0b00010;
+            let width_ok = funct3 == 0b010 || (X::X_LEN >= 64 && funct3 == 0b011);
+            !width_ok || (lr && bits!(u8, instruction, 20..25) != 0)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(feature = "c")]
+impl CompressedInstruction {
+    /// Decodes a [CompressedInstruction] from a 16-bit [HalfWord], rejecting every reserved or
+    /// illegal encoding.
+    ///
+    /// Rejected encodings include the all-zero halfword, `c.addi4spn`, `c.addi16sp` and `c.lui`
+    /// with a zero immediate, `c.jr x0`, and shifts with `shamt[5]` set on RV32. HINTs, such as
+    /// `c.li x0, imm`, are accepted and expand to instructions without architectural effect.
+    pub fn decode_strict(instruction: HalfWord) -> Result<Self, InstructionDecodeError> {
+        Self::decode_strict_for::<NativeXlen>(instruction)
+    }
+
+    /// Decodes a [CompressedInstruction] like [Self::decode_strict], for a hart with the register
+    /// width `X`.
+    pub fn decode_strict_for<X: Xlen>(
+        instruction: HalfWord,
+    ) -> Result<Self, InstructionDecodeError> {
+        if is_reserved_compressed::<X>(instruction) {
+            return Err(InstructionDecodeError::new(instruction as Word, DecodeErrorKind::Reserved));
+        }
+        Self::decode_for::<X>(instruction)
+    }
+}
+
+/// Returns `true` if a 16-bit encoding is reserved, but accepted by the permissive decoder.
+#[cfg(feature = "c")]
+fn is_reserved_compressed<X: Xlen>(instruction: HalfWord) -> bool {
+    let quadrant = bits!(u8, instruction, 0..2);
+    let funct3 = bits!(u8, instruction, 13..16);
+    let shamt_5 = bits!(u8, instruction, 12..13) == 1;
+    let ci_imm = bits!(u8, instruction, 2..7) | (u8::from(shamt_5) << 5);
+
+    match (quadrant, funct3) {
+        // `c.addi4spn` with a zero immediate, including the all-zero illegal instruction.
+        (0b00, 0b000) => bits!(u8, instruction, 5..13) == 0,
+        // `c.addi16sp` and `c.lui` with a zero immediate.
+        (0b01, 0b011) => ci_imm == 0,
+        (0b01, 0b100) => match bits!(u8, instruction, 10..12) {
+            // `c.srli` and `c.srai` with `shamt[5]` set on RV32.
+            0b00 | 0b01 => X::X_LEN == 32 && shamt_5,
+            // The reserved encodings alongside `c.subw` and `c.addw`.
+            0b11 => shamt_5 && bits!(u8, instruction, 5..7) >= 0b10,
+            _ => false,
+        },
+        // `c.slli` with `shamt[5]` set on RV32.
+        (0b10, 0b000) => X::X_LEN == 32 && shamt_5,
+        // `c.jr x0`.
+        (0b10, 0b100) => !shamt_5 && bits!(HalfWord, instruction, 2..12) == 0,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::ecall(0x00000073, true)]
+    #[case::ebreak(0x00100073, true)]
+    #[case::fence(0x0ff0000f, true)]
+    #[case::slli(0x00451513, true)]
+    #[case::jalr(0x000280e7, true)]
+    #[case::csrr(0x34202f73, false)]
+    #[case::mret(0x30200073, false)]
+    #[case::wfi(0x10500073, false)]
+    #[case::fence_i(0x0000100f, false)]
+    #[case::jalr_funct3(0x000290e7, false)]
+    #[case::slli_upper_bits(0x08451513, false)]
+    fn test_decode_strict(#[case] word: Word, #[case] valid: bool) {
+        assert_eq!(Instruction::decode_strict(word).is_ok(), valid, "{word:#010x}");
+        if !valid {
+            assert!(Instruction::try_from(word).is_ok());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "64-bit")]
+    fn test_decode_strict_rv32_shifts() {
+        // slli a0, a0, 32 and srai a0, a0, 32
+        for raw in [0x02051513, 0x42055513] {
+            assert!(Instruction::decode_strict_for::<NativeXlen>(raw).is_ok());
+            assert!(Instruction::decode_strict_for::<crate::Rv32>(raw).is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "128-bit")]
+    fn test_decode_strict_rv128() {
+        // lq a0, 16(sp)
+        assert!(Instruction::decode_strict(0x0101250f).is_ok());
+        assert!(Instruction::decode_strict_for::<crate::Rv64>(0x0101250f).is_err());
+        // slli a0, a0, 64
+        assert!(Instruction::decode_strict(0x04051513).is_ok());
+        assert!(Instruction::decode_strict_for::<crate::Rv64>(0x04051513).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "a")]
+    fn test_decode_strict_amo() {
+        // lr.w a0, (a1)
+        assert!(Instruction::decode_strict(0x1005a52f).is_ok());
+        // lr.w a0, (a1) with rs2 = a2
+        assert!(Instruction::decode_strict(0x10c5a52f).is_err());
+        // amoadd.b a0, a2, (a1)
+        assert!(Instruction::decode_strict(0x00c5852f).is_err());
+        // amoadd.d a0, a2, (a1)
+        assert_eq!(Instruction::decode_strict(0x00c5b52f).is_ok(), cfg!(feature = "64-bit"));
+    }
+
+    /// The classification of a 16-bit encoding by the specification.
+    #[cfg(feature = "c")]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Class {
+        /// A defined instruction.
+        Legal,
+        /// A HINT, which must execute without architectural effect.
+        Hint,
+        /// A reserved or illegal encoding.
+        Reserved,
+        /// An encoding from an extension that `brisc` does not implement (`f` / `d`).
+        Unsupported,
+        /// A 32-bit encoding.
+        Wide,
+    }
+
+    /// A `(mask, match, class)` table of every 16-bit encoding, transcribed from the RVC opcode
+    /// map and the constraints on each instruction in the specification, for a hart with the
+    /// register width `X`. The first matching entry classifies an encoding.
+    #[cfg(feature = "c")]
+    fn spec_table<X: Xlen>() -> Vec<(HalfWord, HalfWord, Class)> {
+        use Class::*;
+
+        let rv64 = X::X_LEN >= 64;
+        let mut table = vec![
+            (0x0003, 0x0003, Wide),
+            // Quadrant 0
+            (0xFFE3, 0x0000, Reserved),    // c.addi4spn, nzuimm = 0
+            (0xE003, 0x0000, Legal),       // c.addi4spn
+            (0xE003, 0x2000, Unsupported), // c.fld
+            (0xE003, 0x4000, Legal),       // c.lw
+            (0xE003, 0x6000, if rv64 { Legal } else { Unsupported }), // c.ld / c.flw
+            (0xE003, 0x8000, Reserved),
+            (0xE003, 0xA000, Unsupported), // c.fsd
+            (0xE003, 0xC000, Legal),       // c.sw
+            (0xE003, 0xE000, if rv64 { Legal } else { Unsupported }), // c.sd / c.fsw
+            // Quadrant 1
+            (0xFFFF, 0x0001, Legal), // c.nop
+            (0xEF83, 0x0001, Hint),  // c.nop, nzimm != 0
+            (0xF07F, 0x0001, Hint),  // c.addi, nzimm = 0
+            (0xE003, 0x0001, Legal), // c.addi
+        ];
+        if rv64 {
+            table.extend([
+                (0xEF83, 0x2001, Reserved), // c.addiw, rd = 0
+                (0xE003, 0x2001, Legal),    // c.addiw
+            ]);
+        } else {
+            table.push((0xE003, 0x2001, Legal)); // c.jal
+        }
+        table.extend([
+            (0xEF83, 0x4001, Hint),     // c.li, rd = 0
+            (0xE003, 0x4001, Legal),    // c.li
+            (0xFFFF, 0x6101, Reserved), // c.addi16sp, nzimm = 0
+            (0xEF83, 0x6101, Legal),    // c.addi16sp
+            (0xF07F, 0x6001, Reserved), // c.lui, nzimm = 0
+            (0xEF83, 0x6001, Hint),     // c.lui, rd = 0
+            (0xE003, 0x6001, Legal),    // c.lui
+        ]);
+        for base in [0x8001, 0x8401] {
+            if !rv64 {
+                table.push((0xFC03, base | 0x1000, Reserved)); // shamt[5] = 1 on RV32
+            }
+            table.extend([
+                (0xFC7F, base, Hint),  // c.srli / c.srai, shamt = 0
+                (0xEC03, base, Legal), // c.srli / c.srai
+            ]);
+        }
+        table.extend([
+            (0xEC03, 0x8801, Legal),                               // c.andi
+            (0xFC03, 0x8C01, Legal),                               // c.sub, c.xor, c.or, c.and
+            (0xFC63, 0x9C01, if rv64 { Legal } else { Reserved }), // c.subw
+            (0xFC63, 0x9C21, if rv64 { Legal } else { Reserved }), // c.addw
+            (0xFC43, 0x9C41, Reserved),
+            (0xE003, 0xA001, Legal), // c.j
+            (0xE003, 0xC001, Legal), // c.beqz
+            (0xE003, 0xE001, Legal), // c.bnez
+        ]);
+        // Quadrant 2
+        if !rv64 {
+            table.push((0xF003, 0x1002, Reserved)); // c.slli, shamt[5] = 1 on RV32
+        }
+        table.extend([
+            (0xEF83, 0x0002, Hint),        // c.slli, rd = 0
+            (0xF07F, 0x0002, Hint),        // c.slli, shamt = 0
+            (0xE003, 0x0002, Legal),       // c.slli
+            (0xE003, 0x2002, Unsupported), // c.fldsp
+            (0xEF83, 0x4002, Reserved),    // c.lwsp, rd = 0
+            (0xE003, 0x4002, Legal),       // c.lwsp
+        ]);
+        if rv64 {
+            table.extend([
+                (0xEF83, 0x6002, Reserved), // c.ldsp, rd = 0
+                (0xE003, 0x6002, Legal),    // c.ldsp
+            ]);
+        } else {
+            table.push((0xE003, 0x6002, Unsupported)); // c.flwsp
+        }
+        table.extend([
+            (0xFFFF, 0x8002, Reserved),                               // c.jr x0
+            (0xF07F, 0x8002, Legal),                                  // c.jr
+            (0xFF83, 0x8002, Hint),                                   // c.mv, rd = 0
+            (0xF003, 0x8002, Legal),                                  // c.mv
+            (0xFFFF, 0x9002, Legal),                                  // c.ebreak
+            (0xF07F, 0x9002, Legal),                                  // c.jalr
+            (0xFF83, 0x9002, Hint),                                   // c.add, rd = 0
+            (0xF003, 0x9002, Legal),                                  // c.add
+            (0xE003, 0xA002, Unsupported),                            // c.fsdsp
+            (0xE003, 0xC002, Legal),                                  // c.swsp
+            (0xE003, 0xE002, if rv64 { Legal } else { Unsupported }), // c.sdsp / c.fswsp
+        ]);
+        table
+    }
+
+    #[test]
+    #[cfg(feature = "c")]
+    fn test_decode_strict_compressed_exhaustive() {
+        decode_strict_compressed_exhaustive::<crate::Rv32>();
+        #[cfg(feature = "64-bit")]
+        decode_strict_compressed_exhaustive::<crate::Rv64>();
+    }
+
+    /// Checks every 16-bit encoding against [spec_table] for a hart with the register width `X`.
+    #[cfg(feature = "c")]
+    fn decode_strict_compressed_exhaustive<X: Xlen>() {
+        let table = spec_table::<X>();
+        for raw in 0..=HalfWord::MAX {
+            let class = table
+                .iter()
+                .find(|&&(mask, value, _)| raw & mask == value)
+                .map(|&(_, _, class)| class)
+                .unwrap_or_else(|| panic!("{raw:#06x} is not covered by the table"));
+
+            let strict = CompressedInstruction::decode_strict_for::<X>(raw);
+            match class {
+                Class::Legal | Class::Hint => {
+                    let decoded = strict.unwrap_or_else(|e| panic!("{raw:#06x} ({class:?}): {e}"));
+                    let decoded_for = CompressedInstruction::decode_for::<X>(raw);
+                    assert_eq!(decoded_for, Ok(decoded), "{raw:#06x}");
+                    assert_eq!(decoded.encode(), raw, "{raw:#06x}");
+                }
+                Class::Reserved | Class::Unsupported | Class::Wide => {
+                    assert!(strict.is_err(), "{raw:#06x} ({class:?}) decoded as {strict:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "c")]
+    fn test_decode_strict_compressed_word() {
+        // c.li a0, 1
+        assert!(Instruction::decode_strict(0x4505).is_ok());
+        // c.jr x0
+        assert_eq!(
+            Instruction::decode_strict(0x8002),
+            Err(InstructionDecodeError { raw: 0x8002, kind: DecodeErrorKind::Reserved })
+        );
+        assert!(Instruction::decode_strict(0x0000).is_err());
+    }
+}
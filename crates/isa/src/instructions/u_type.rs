@@ -19,6 +19,13 @@ impl UType {
             imm: sign_extend(bits!(XWord, instruction, 12..32) << 12, 31),
         }
     }
+
+    /// Encodes the [UType] instruction into a 32-bit [Word] with the given `opcode`.
+    pub fn encode(&self, opcode: u8) -> Word {
+        (bits!(Word, self.imm, 12..32) << 12) |
+            ((self.rd as Word & 0x1F) << 7) |
+            (opcode as Word & 0x7F)
+    }
 }
 
 #[cfg(test)]
@@ -42,4 +49,10 @@ mod test {
         assert_eq!(utype.rd, 0b01010);
         assert_eq!(utype.imm, sign_extend(0b10000100100100010000 << 12, 31));
     }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let instruction = 0b10000100100100010000_01010_0110111;
+        assert_eq!(UType::decode(instruction).encode(0b0110111), instruction);
+    }
 }
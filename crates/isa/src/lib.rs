@@ -9,7 +9,10 @@ mod bits;
 pub use bits::sign_extend;
 
 mod errors;
-pub use errors::InstructionDecodeError;
+pub use errors::{DecodeErrorKind, InstructionDecodeError, IsaParseError};
+
+mod feature;
+pub use feature::Feature;
 
 mod instructions;
 pub use instructions::*;
@@ -19,3 +22,28 @@ pub use functions::*;
 
 mod arch;
 pub use arch::*;
+
+mod xlen;
+#[cfg(feature = "128-bit")]
+pub use xlen::Rv128;
+#[cfg(feature = "64-bit")]
+pub use xlen::Rv64;
+pub use xlen::{NativeXlen, Rv32, Xlen};
+
+mod decoded;
+pub use decoded::{DecodedInstruction, InstructionLength};
+
+mod config;
+pub use config::IsaConfig;
+
+mod metadata;
+pub use metadata::{Extension, Format, InstructionClass, MemoryWidth, RegisterSet};
+
+mod disasm;
+pub use disasm::{Disassembly, DisplayOptions, REGISTER_ABI_NAMES};
+
+#[cfg(feature = "asm")]
+extern crate alloc;
+
+#[cfg(feature = "asm")]
+pub mod asm;
@@ -0,0 +1,587 @@
+//! Static metadata about decoded instructions.
+//!
+//! Schedulers, profilers and hazard detectors often need to know facts about an instruction that
+//! are implied by its encoding, such as the registers it reads and writes or the width of its
+//! memory access. The methods in this module derive them from an [Instruction] without executing
+//! it.
+
+use crate::{
+    EnvironmentFunction, Feature, Instruction, LoadFunction, RegisterArithmeticFunction,
+    StoreFunction,
+};
+
+#[cfg(feature = "64-bit")]
+use crate::RegisterArithmeticWordFunction;
+
+#[cfg(feature = "128-bit")]
+use crate::RegisterArithmeticDoubleFunction;
+
+/// The RISC-V extension that an instruction originates from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Extension {
+    /// The base integer instruction set (`RV32I` / `RV64I` / `RV128I`).
+    I,
+    /// The integer multiplication and division extension.
+    M,
+    /// The atomic instruction extension.
+    A,
+    /// The compressed instruction extension.
+    C,
+}
+
+/// The encoding format of an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// Register-register operations.
+    R,
+    /// Short immediates and loads.
+    I,
+    /// Stores.
+    S,
+    /// Conditional branches.
+    B,
+    /// Long immediates.
+    U,
+    /// Unconditional jumps.
+    J,
+    /// Compressed register operations.
+    #[cfg(feature = "c")]
+    CR,
+    /// Compressed immediate operations.
+    #[cfg(feature = "c")]
+    CI,
+    /// Compressed stack-relative stores.
+    #[cfg(feature = "c")]
+    CSS,
+    /// Compressed wide immediate operations.
+    #[cfg(feature = "c")]
+    CIW,
+    /// Compressed loads.
+    #[cfg(feature = "c")]
+    CL,
+    /// Compressed stores.
+    #[cfg(feature = "c")]
+    CS,
+    /// Compressed register-register arithmetic. These are decoded with [crate::CSType].
+    #[cfg(feature = "c")]
+    CA,
+    /// Compressed branches and immediate arithmetic.
+    #[cfg(feature = "c")]
+    CB,
+    /// Compressed jumps.
+    #[cfg(feature = "c")]
+    CJ,
+}
+
+/// The broad class of operation that an instruction performs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstructionClass {
+    /// Integer arithmetic, logic and upper immediate instructions.
+    Alu,
+    /// Conditional branches.
+    Branch,
+    /// Unconditional jumps (`jal` and `jalr`).
+    Jump,
+    /// Memory loads.
+    Load,
+    /// Memory stores.
+    Store,
+    /// Atomic memory operations, including `lr` and `sc`.
+    Amo,
+    /// Memory ordering fences.
+    Fence,
+    /// Environment calls and breakpoints.
+    System,
+}
+
+/// The width of a memory access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum MemoryWidth {
+    /// A single byte.
+    Byte = 1,
+    /// A 2-byte half word.
+    HalfWord = 2,
+    /// A 4-byte word.
+    Word = 4,
+    /// An 8-byte double word.
+    DoubleWord = 8,
+    /// A 16-byte quad word.
+    #[cfg(feature = "128-bit")]
+    QuadWord = 16,
+}
+
+impl Extension {
+    /// Returns the letter that names the extension in ISA strings and the `misa` CSR.
+    pub const fn letter(self) -> char {
+        match self {
+            Self::I => 'i',
+            Self::M => 'm',
+            Self::A => 'a',
+            Self::C => 'c',
+        }
+    }
+
+    /// Returns the crate feature that compiles in support for the extension, or [None] for the
+    /// base integer instruction set.
+    pub const fn feature(self) -> Option<Feature> {
+        match self {
+            Self::I => None,
+            Self::M => Some(Feature::M),
+            Self::A => Some(Feature::A),
+            Self::C => Some(Feature::C),
+        }
+    }
+}
+
+impl MemoryWidth {
+    /// Returns the number of bytes accessed.
+    pub const fn bytes(self) -> usize {
+        self as usize
+    }
+}
+
+/// A set of integer registers, stored as a bitmask indexed by register number.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegisterSet(u32);
+
+impl RegisterSet {
+    /// The empty set.
+    pub const EMPTY: Self = Self(0);
+
+    /// Returns a copy of the set with `reg` added. The zero register is hardwired, and never
+    /// carries a dependency, so it is never added.
+    pub const fn with(self, reg: u8) -> Self {
+        if reg == 0 {
+            self
+        } else {
+            Self(self.0 | (1 << (reg & 0x1F)))
+        }
+    }
+
+    /// Returns `true` if the set contains `reg`.
+    pub const fn contains(&self, reg: u8) -> bool {
+        self.0 & (1 << (reg & 0x1F)) != 0
+    }
+
+    /// Returns `true` if the set has no registers in common with `other`.
+    pub const fn is_disjoint(&self, other: &Self) -> bool {
+        self.0 & other.0 == 0
+    }
+
+    /// Returns the number of registers in the set.
+    pub const fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Returns `true` if the set is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the bitmask of the set, where bit `n` is set if register `xn` is in the set.
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns an iterator over the registers in the set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (1..32).filter(|&reg| self.contains(reg))
+    }
+}
+
+impl Instruction {
+    /// Returns the extension that the instruction originates from.
+    ///
+    /// Instructions that were expanded from their compressed form report the extension of the
+    /// expanded instruction. Use `CompressedInstruction::extension` to classify the
+    /// compressed form itself.
+    pub const fn extension(&self) -> Extension {
+        match self {
+            Self::RegisterArithmetic(_, funct) => match funct {
+                RegisterArithmeticFunction::Add |
+                RegisterArithmeticFunction::Sub |
+                RegisterArithmeticFunction::Xor |
+                RegisterArithmeticFunction::Or |
+                RegisterArithmeticFunction::And |
+                RegisterArithmeticFunction::Sll |
+                RegisterArithmeticFunction::Srl |
+                RegisterArithmeticFunction::Sra |
+                RegisterArithmeticFunction::Slt |
+                RegisterArithmeticFunction::Sltu => Extension::I,
+                #[cfg(feature = "m")]
+                _ => Extension::M,
+            },
+            #[cfg(feature = "64-bit")]
+            Self::RegisterArithmeticWord(_, funct) => match funct {
+                RegisterArithmeticWordFunction::Addw |
+                RegisterArithmeticWordFunction::Subw |
+                RegisterArithmeticWordFunction::Sllw |
+                RegisterArithmeticWordFunction::Srlw |
+                RegisterArithmeticWordFunction::Sraw => Extension::I,
+                #[cfg(feature = "m")]
+                _ => Extension::M,
+            },
+            #[cfg(feature = "128-bit")]
+            Self::RegisterArithmeticDouble(_, funct) => match funct {
+                RegisterArithmeticDoubleFunction::Addd |
+                RegisterArithmeticDoubleFunction::Subd |
+                RegisterArithmeticDoubleFunction::Slld |
+                RegisterArithmeticDoubleFunction::Srld |
+                RegisterArithmeticDoubleFunction::Srad => Extension::I,
+                #[cfg(feature = "m")]
+                _ => Extension::M,
+            },
+            #[cfg(feature = "a")]
+            Self::Amo(_, _) => Extension::A,
+            _ => Extension::I,
+        }
+    }
+
+    /// Returns the encoding format of the instruction.
+    pub const fn format(&self) -> Format {
+        match self {
+            Self::MemoryStore(_, _) => Format::S,
+            Self::Branch(_, _) => Format::B,
+            Self::RegisterArithmetic(_, _) => Format::R,
+            Self::Lui(_) | Self::Auipc(_) => Format::U,
+            Self::Jal(_) => Format::J,
+            #[cfg(feature = "64-bit")]
+            Self::RegisterArithmeticWord(_, _) => Format::R,
+            #[cfg(feature = "128-bit")]
+            Self::RegisterArithmeticDouble(_, _) => Format::R,
+            #[cfg(feature = "a")]
+            Self::Amo(_, _) => Format::R,
+            _ => Format::I,
+        }
+    }
+
+    /// Returns the class of operation that the instruction performs.
+    pub const fn class(&self) -> InstructionClass {
+        match self {
+            Self::MemoryLoad(_, _) => InstructionClass::Load,
+            Self::MemoryStore(_, _) => InstructionClass::Store,
+            Self::Branch(_, _) => InstructionClass::Branch,
+            Self::Jal(_) | Self::Jalr(_) => InstructionClass::Jump,
+            Self::Environment(_, _) => InstructionClass::System,
+            Self::Fence => InstructionClass::Fence,
+            #[cfg(feature = "a")]
+            Self::Amo(_, _) => InstructionClass::Amo,
+            _ => InstructionClass::Alu,
+        }
+    }
+
+    /// Returns `true` if the instruction may redirect control flow.
+    pub const fn is_control_flow(&self) -> bool {
+        matches!(self.class(), InstructionClass::Branch | InstructionClass::Jump)
+    }
+
+    /// Returns `true` if the instruction accesses memory.
+    pub const fn accesses_memory(&self) -> bool {
+        self.memory_width().is_some()
+    }
+
+    /// Returns the width of the instruction's memory access, if it accesses memory.
+    pub const fn memory_width(&self) -> Option<MemoryWidth> {
+        match self {
+            Self::MemoryLoad(_, funct) => Some(match funct {
+                LoadFunction::Lb | LoadFunction::Lbu => MemoryWidth::Byte,
+                LoadFunction::Lh | LoadFunction::Lhu => MemoryWidth::HalfWord,
+                LoadFunction::Lw => MemoryWidth::Word,
+                #[cfg(feature = "64-bit")]
+                LoadFunction::Lwu => MemoryWidth::Word,
+                #[cfg(feature = "64-bit")]
+                LoadFunction::Ld => MemoryWidth::DoubleWord,
+                #[cfg(feature = "128-bit")]
+                LoadFunction::Ldu => MemoryWidth::DoubleWord,
+                #[cfg(feature = "128-bit")]
+                LoadFunction::Lq => MemoryWidth::QuadWord,
+            }),
+            Self::MemoryStore(_, funct) => Some(match funct {
+                StoreFunction::Sb => MemoryWidth::Byte,
+                StoreFunction::Sh => MemoryWidth::HalfWord,
+                StoreFunction::Sw => MemoryWidth::Word,
+                #[cfg(feature = "64-bit")]
+                StoreFunction::Sd => MemoryWidth::DoubleWord,
+                #[cfg(feature = "128-bit")]
+                StoreFunction::Sq => MemoryWidth::QuadWord,
+            }),
+            #[cfg(feature = "a")]
+            Self::Amo(r_type, _) => match r_type.funct3 {
+                0b011 => Some(MemoryWidth::DoubleWord),
+                _ => Some(MemoryWidth::Word),
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the set of registers that the instruction reads, including implicit reads.
+    ///
+    /// `ecall` follows the Linux system call convention, reading the system call number from
+    /// `a7` and its arguments from `a0`-`a5`. The zero register is never included.
+    pub const fn reads(&self) -> RegisterSet {
+        let set = match self.rs1() {
+            Some(rs1) => RegisterSet::EMPTY.with(rs1),
+            None => RegisterSet::EMPTY,
+        };
+        let set = match self.rs2() {
+            Some(rs2) => set.with(rs2),
+            None => set,
+        };
+
+        match self {
+            // `lr` has no second source operand.
+            #[cfg(feature = "a")]
+            Self::Amo(r_type, crate::AmoFunction::Lr) => RegisterSet::EMPTY.with(r_type.rs1),
+            Self::Environment(_, EnvironmentFunction::Ecall) => RegisterSet::EMPTY
+                .with(crate::REG_A0 as u8)
+                .with(crate::REG_A1 as u8)
+                .with(crate::REG_A2 as u8)
+                .with(crate::REG_A3 as u8)
+                .with(crate::REG_A4 as u8)
+                .with(crate::REG_A5 as u8)
+                .with(crate::REG_A7 as u8),
+            Self::Environment(_, EnvironmentFunction::Ebreak) => RegisterSet::EMPTY,
+            _ => set,
+        }
+    }
+
+    /// Returns the set of registers that the instruction writes, including implicit writes.
+    ///
+    /// `ecall` follows the Linux system call convention, returning its result in `a0`. The zero
+    /// register is never included, so instructions that discard their result (such as `j`) write
+    /// nothing.
+    pub const fn writes(&self) -> RegisterSet {
+        match self {
+            Self::Environment(_, EnvironmentFunction::Ecall) => {
+                RegisterSet::EMPTY.with(crate::REG_A0 as u8)
+            }
+            Self::Environment(_, EnvironmentFunction::Ebreak) => RegisterSet::EMPTY,
+            _ => match self.rd() {
+                Some(rd) => RegisterSet::EMPTY.with(rd),
+                None => RegisterSet::EMPTY,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "c")]
+mod compressed {
+    use super::{Format, InstructionClass, MemoryWidth, RegisterSet};
+    use crate::{C1SubFunct, CompressedInstruction, Extension, C0, C1, C2};
+
+    impl CompressedInstruction {
+        /// Returns the extension that the instruction originates from, which is always
+        /// [Extension::C].
+        pub const fn extension(&self) -> Extension {
+            Extension::C
+        }
+
+        /// Returns the compressed encoding format of the instruction.
+        pub const fn format(&self) -> Format {
+            match self {
+                Self::C0(C0::CAddi4spn(_)) => Format::CIW,
+                Self::C0(C0::CLw(_)) => Format::CL,
+                Self::C0(C0::CSw(_)) => Format::CS,
+                #[cfg(feature = "64-bit")]
+                Self::C0(C0::CLd(_)) => Format::CL,
+                #[cfg(feature = "64-bit")]
+                Self::C0(C0::CSd(_)) => Format::CS,
+                Self::C1(C1::CJal(_) | C1::CJ(_)) => Format::CJ,
+                Self::C1(C1::CBeqz(_) | C1::CBnez(_)) => Format::CB,
+                Self::C1(C1::SubFunct(
+                    C1SubFunct::CSrli(_) | C1SubFunct::CSrai(_) | C1SubFunct::CAndi(_),
+                )) => Format::CB,
+                Self::C1(C1::SubFunct(_)) => Format::CA,
+                Self::C1(_) => Format::CI,
+                Self::C2(C2::CSwsp(_)) => Format::CSS,
+                #[cfg(feature = "64-bit")]
+                Self::C2(C2::CSdsp(_)) => Format::CSS,
+                Self::C2(C2::SubFunct(_)) => Format::CR,
+                Self::C2(_) => Format::CI,
+            }
+        }
+
+        /// Returns the class of operation that the instruction performs.
+        pub fn class(&self) -> InstructionClass {
+            self.expand().class()
+        }
+
+        /// Returns the width of the instruction's memory access, if it accesses memory.
+        pub fn memory_width(&self) -> Option<MemoryWidth> {
+            self.expand().memory_width()
+        }
+
+        /// Returns the set of registers that the instruction reads, including implicit reads such
+        /// as `sp` for stack-relative loads and stores.
+        pub fn reads(&self) -> RegisterSet {
+            self.expand().reads()
+        }
+
+        /// Returns the set of registers that the instruction writes, including implicit writes
+        /// such as `ra` for `c.jal` and `c.jalr`.
+        pub fn writes(&self) -> RegisterSet {
+            self.expand().writes()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    /// Builds a [RegisterSet] from a list of registers.
+    fn set(regs: &[u8]) -> RegisterSet {
+        regs.iter().fold(RegisterSet::EMPTY, |set, &reg| set.with(reg))
+    }
+
+    #[rstest]
+    // add a0, a1, a2
+    #[case(0x00c58533, Extension::I, Format::R, InstructionClass::Alu, None, &[11, 12], &[10])]
+    // addi a0, sp, 16
+    #[case(0x01010513, Extension::I, Format::I, InstructionClass::Alu, None, &[2], &[10])]
+    // lui a0, 0x12345
+    #[case(0x12345537, Extension::I, Format::U, InstructionClass::Alu, None, &[], &[10])]
+    // lw a0, 8(sp)
+    #[case(
+        0x00812503,
+        Extension::I,
+        Format::I,
+        InstructionClass::Load,
+        Some(MemoryWidth::Word),
+        &[2],
+        &[10]
+    )]
+    // sb a1, 0(a0)
+    #[case(
+        0x00b50023,
+        Extension::I,
+        Format::S,
+        InstructionClass::Store,
+        Some(MemoryWidth::Byte),
+        &[10, 11],
+        &[]
+    )]
+    // beq a0, zero, .+8
+    #[case(0x00050463, Extension::I, Format::B, InstructionClass::Branch, None, &[10], &[])]
+    // jal ra, .+16
+    #[case(0x010000ef, Extension::I, Format::J, InstructionClass::Jump, None, &[], &[1])]
+    // j .+16
+    #[case(0x0100006f, Extension::I, Format::J, InstructionClass::Jump, None, &[], &[])]
+    // jalr ra, 0(t0)
+    #[case(0x000280e7, Extension::I, Format::I, InstructionClass::Jump, None, &[5], &[1])]
+    // ecall
+    #[case(
+        0x00000073,
+        Extension::I,
+        Format::I,
+        InstructionClass::System,
+        None,
+        &[10, 11, 12, 13, 14, 15, 17],
+        &[10]
+    )]
+    // ebreak
+    #[case(0x00100073, Extension::I, Format::I, InstructionClass::System, None, &[], &[])]
+    // fence
+    #[case(0x0ff0000f, Extension::I, Format::I, InstructionClass::Fence, None, &[], &[])]
+    fn test_metadata(
+        #[case] word: u32,
+        #[case] extension: Extension,
+        #[case] format: Format,
+        #[case] class: InstructionClass,
+        #[case] width: Option<MemoryWidth>,
+        #[case] reads: &[u8],
+        #[case] writes: &[u8],
+    ) {
+        let instruction = Instruction::try_from(word).unwrap();
+        assert_eq!(instruction.extension(), extension);
+        assert_eq!(instruction.format(), format);
+        assert_eq!(instruction.class(), class);
+        assert_eq!(instruction.memory_width(), width);
+        assert_eq!(instruction.reads(), set(reads));
+        assert_eq!(instruction.writes(), set(writes));
+    }
+
+    #[test]
+    #[cfg(feature = "m")]
+    fn test_metadata_m() {
+        // mul a0, a1, a2
+        let instruction = Instruction::try_from(0x02c58533).unwrap();
+        assert_eq!(instruction.extension(), Extension::M);
+        assert_eq!(instruction.format(), Format::R);
+        assert_eq!(instruction.reads(), set(&[11, 12]));
+    }
+
+    #[test]
+    #[cfg(feature = "a")]
+    fn test_metadata_a() {
+        // amoadd.w a0, a2, (a1)
+        let amo = Instruction::try_from(0x00c5a52f).unwrap();
+        assert_eq!(amo.extension(), Extension::A);
+        assert_eq!(amo.class(), InstructionClass::Amo);
+        assert_eq!(amo.memory_width(), Some(MemoryWidth::Word));
+        assert_eq!(amo.reads(), set(&[11, 12]));
+        assert_eq!(amo.writes(), set(&[10]));
+
+        // lr.w a0, (a1)
+        let lr = Instruction::try_from(0x1005a52f).unwrap();
+        assert_eq!(lr.reads(), set(&[11]));
+    }
+
+    #[test]
+    #[cfg(feature = "128-bit")]
+    fn test_metadata_rv128() {
+        // lq a0, 16(sp)
+        let lq = Instruction::try_from(0x0101250f).unwrap();
+        assert_eq!(lq.opcode(), 0b000_1111);
+        assert_eq!(lq.class(), InstructionClass::Load);
+        assert_eq!(lq.memory_width(), Some(MemoryWidth::QuadWord));
+        assert_eq!(lq.reads(), set(&[2]));
+        assert_eq!(lq.writes(), set(&[10]));
+
+        // subd a0, a1, a2
+        let subd = Instruction::try_from(0x40c5857b).unwrap();
+        assert_eq!(subd.extension(), Extension::I);
+        assert_eq!(subd.format(), Format::R);
+        assert_eq!(subd.reads(), set(&[11, 12]));
+    }
+
+    #[test]
+    #[cfg(feature = "c")]
+    fn test_metadata_compressed() {
+        use crate::CompressedInstruction;
+
+        // c.lwsp a0, 4(sp)
+        let lwsp = CompressedInstruction::decode(0x4512).unwrap();
+        assert_eq!(lwsp.extension(), Extension::C);
+        assert_eq!(lwsp.format(), Format::CI);
+        assert_eq!(lwsp.class(), InstructionClass::Load);
+        assert_eq!(lwsp.memory_width(), Some(MemoryWidth::Word));
+        assert_eq!(lwsp.reads(), set(&[2]));
+        assert_eq!(lwsp.writes(), set(&[10]));
+
+        // c.jalr a0
+        let jalr = CompressedInstruction::decode(0x9502).unwrap();
+        assert_eq!(jalr.format(), Format::CR);
+        assert_eq!(jalr.class(), InstructionClass::Jump);
+        assert_eq!(jalr.reads(), set(&[10]));
+        assert_eq!(jalr.writes(), set(&[1]));
+
+        // c.sub a0, a1
+        let sub = CompressedInstruction::decode(0x8d0d).unwrap();
+        assert_eq!(sub.format(), Format::CA);
+        assert_eq!(sub.reads(), set(&[10, 11]));
+    }
+
+    #[test]
+    fn test_register_set() {
+        let regs = set(&[0, 1, 10, 31]);
+        assert_eq!(regs.len(), 3);
+        assert!(!regs.contains(0));
+        assert!(regs.contains(31));
+        assert_eq!(regs.iter().collect::<Vec<_>>(), vec![1, 10, 31]);
+        assert!(regs.is_disjoint(&set(&[2, 3])));
+        assert!(!regs.is_disjoint(&set(&[10])));
+    }
+}
@@ -0,0 +1,117 @@
+//! The register width of a hart, selected by a type parameter.
+
+use crate::{sign_extend, SXWord, XWord, X_LEN};
+use core::{fmt::Debug, hash::Hash};
+
+/// The register width (XLEN) of a hart.
+///
+/// [XWord] is the widest register width compiled into this build, and is used to store register
+/// values for every [Xlen]. Narrower widths keep their values zero-extended in an [XWord], so that
+/// register values can be used as addresses and syscall arguments as-is. Operations whose result
+/// depends on the width, such as signed comparisons, interpret their operands with
+/// [Xlen::signed], and results are truncated with [Xlen::truncate] before they are written back.
+///
+/// [Rv32] is always available. [Rv64] requires the `64-bit` feature, and in that case a single
+/// build can emulate both RV32 and RV64 harts. The experimental `Rv128` requires the `128-bit`
+/// feature.
+pub trait Xlen:
+    Debug + Default + Clone + Copy + PartialEq + Eq + Hash + Send + Sync + 'static
+{
+    /// The width of the registers in bits.
+    const X_LEN: usize;
+
+    /// The mask for the shift amount of shift instructions.
+    const SHIFT_MASK: XWord = Self::X_LEN as XWord - 1;
+
+    /// The value of the `MXL` field of the `misa` CSR.
+    const MXL: u8;
+
+    /// Truncates a value to [Self::X_LEN] bits.
+    #[inline(always)]
+    fn truncate(value: XWord) -> XWord {
+        value & (XWord::MAX >> (X_LEN - Self::X_LEN))
+    }
+
+    /// Interprets the low [Self::X_LEN] bits of a value as a signed integer.
+    #[inline(always)]
+    fn signed(value: XWord) -> SXWord {
+        if Self::X_LEN == X_LEN {
+            value as SXWord
+        } else {
+            sign_extend(value, (Self::X_LEN - 1) as XWord) as SXWord
+        }
+    }
+}
+
+/// A 32-bit hart.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rv32;
+
+impl Xlen for Rv32 {
+    const X_LEN: usize = 32;
+    const MXL: u8 = 1;
+}
+
+/// A 64-bit hart.
+#[cfg(feature = "64-bit")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rv64;
+
+#[cfg(feature = "64-bit")]
+impl Xlen for Rv64 {
+    const X_LEN: usize = 64;
+    const MXL: u8 = 2;
+}
+
+/// A 128-bit hart, implementing the draft RV128I base.
+#[cfg(feature = "128-bit")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rv128;
+
+#[cfg(feature = "128-bit")]
+impl Xlen for Rv128 {
+    const X_LEN: usize = 128;
+    const MXL: u8 = 3;
+}
+
+/// The widest [Xlen] compiled into this build, which matches [X_LEN].
+#[cfg(feature = "128-bit")]
+pub type NativeXlen = Rv128;
+
+/// The widest [Xlen] compiled into this build, which matches [X_LEN].
+#[cfg(all(feature = "64-bit", not(feature = "128-bit")))]
+pub type NativeXlen = Rv64;
+
+/// The widest [Xlen] compiled into this build, which matches [X_LEN].
+#[cfg(not(feature = "64-bit"))]
+pub type NativeXlen = Rv32;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_native() {
+        assert_eq!(NativeXlen::X_LEN, X_LEN);
+        assert_eq!(NativeXlen::SHIFT_MASK, crate::SHIFT_MASK);
+        assert_eq!(NativeXlen::truncate(XWord::MAX), XWord::MAX);
+        assert_eq!(NativeXlen::signed(XWord::MAX), -1);
+    }
+
+    #[test]
+    fn test_rv32() {
+        assert_eq!(Rv32::SHIFT_MASK, 0x1F);
+        assert_eq!(Rv32::truncate(XWord::MAX), 0xFFFF_FFFF);
+        assert_eq!(Rv32::signed(0xFFFF_FFFF), -1);
+        assert_eq!(Rv32::signed(0x7FFF_FFFF), 0x7FFF_FFFF);
+    }
+
+    #[test]
+    #[cfg(feature = "128-bit")]
+    fn test_rv64_in_rv128_build() {
+        assert_eq!(Rv64::SHIFT_MASK, 0x3F);
+        assert_eq!(Rv64::truncate(XWord::MAX), u64::MAX as XWord);
+        assert_eq!(Rv64::signed(u64::MAX as XWord), -1);
+        assert_eq!(Rv128::signed(u64::MAX as XWord), u64::MAX as SXWord);
+    }
+}
@@ -0,0 +1,434 @@
+//! Rendering of ELF file layouts and disassembly listings.
+
+use brisc_emu::elf::load_segments;
+use brisc_isa::{
+    DecodeErrorKind, DecodedInstruction, DisplayOptions, Instruction, InstructionDecodeError,
+    InstructionLength, Rv32, Word, XWord, Xlen,
+};
+use elf::{
+    abi::{SHF_ALLOC, SHF_EXECINSTR, SHF_WRITE, SHN_UNDEF, SHT_NOBITS, STT_FILE, STT_SECTION},
+    endian::AnyEndian,
+    file::Class,
+    section::SectionHeader,
+    ElfBytes,
+};
+use std::{collections::BTreeMap, io::Write};
+use thiserror::Error;
+
+/// Options that control the output of [dump].
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Options {
+    /// Render registers by number rather than by ABI name.
+    pub(crate) numeric: bool,
+    /// Render canonical instructions rather than pseudo-instructions.
+    pub(crate) no_aliases: bool,
+    /// Only disassemble the section with this name.
+    pub(crate) section: Option<String>,
+    /// Disassemble `ELFCLASS64` files as RV128 programs. There is no ELF class for RV128, so
+    /// they are read as RV64 programs unless requested.
+    #[cfg(feature = "128-bit")]
+    pub(crate) rv128: bool,
+}
+
+/// An error that occurs while dumping an ELF file.
+#[derive(Error, Debug)]
+pub(crate) enum DumpError {
+    /// The ELF file could not be parsed.
+    #[error("Failed to parse ELF file: {0}")]
+    Parse(#[from] elf::ParseError),
+    /// The ELF file's program segments could not be loaded.
+    #[error("{0}")]
+    Load(String),
+    /// The requested section does not exist.
+    #[error("Section {0} not found")]
+    MissingSection(String),
+    /// The listing could not be written.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Writes the segment and section layout of a raw ELF file, followed by the disassembly of its
+/// executable sections.
+pub(crate) fn dump(
+    name: &str,
+    raw: &[u8],
+    options: &Options,
+    out: &mut impl Write,
+) -> Result<(), DumpError> {
+    let elf = ElfBytes::<AnyEndian>::minimal_parse(raw)?;
+    let width = if elf.ehdr.class == Class::ELF64 { 16 } else { 8 };
+    let (headers, strtab) = elf.section_headers_with_strtab()?;
+    let sections = match (headers, strtab) {
+        (Some(headers), Some(strtab)) => headers
+            .iter()
+            .map(|h| Ok((strtab.get(h.sh_name as usize)?.to_string(), h)))
+            .collect::<Result<Vec<_>, elf::ParseError>>()?,
+        _ => Vec::new(),
+    };
+    let symbols = symbols(&elf)?;
+
+    let format = if width == 16 { "elf64-littleriscv" } else { "elf32-littleriscv" };
+    writeln!(out, "\n{name}:     file format {format}")?;
+    writeln!(out, "start address 0x{:0width$x}", elf.ehdr.e_entry)?;
+
+    let (segments, _) = load_segments(raw).map_err(DumpError::Load)?;
+    writeln!(out, "\nLoaded segments:")?;
+    writeln!(out, "Idx Type         {:width$} FileSiz  MemSiz   Flg", "VirtAddr")?;
+    for segment in &segments {
+        writeln!(
+            out,
+            "{:3} {:12} {:0width$x} {:08x} {:08x} {}",
+            segment.index,
+            segment_type(segment.p_type),
+            segment.vaddr,
+            segment.data.len(),
+            segment.mem_size,
+            segment_flags(segment.flags)
+        )?;
+    }
+
+    writeln!(out, "\nSections:")?;
+    writeln!(out, "Idx Name                 Size     {:width$} Flg", "VMA")?;
+    for (i, (section_name, header)) in sections.iter().enumerate().skip(1) {
+        writeln!(
+            out,
+            "{:3} {:20} {:08x} {:0width$x} {}",
+            i,
+            section_name,
+            header.sh_size,
+            header.sh_addr,
+            section_flags(header.sh_flags)
+        )?;
+    }
+
+    if let Some(wanted) = &options.section {
+        if !sections.iter().any(|(section_name, _)| section_name == wanted) {
+            return Err(DumpError::MissingSection(wanted.clone()));
+        }
+    }
+
+    for (section_name, header) in &sections {
+        let selected = options.section.as_ref().map_or_else(
+            || header.sh_flags & SHF_EXECINSTR as u64 != 0,
+            |wanted| wanted == section_name,
+        );
+        if !selected || header.sh_type == SHT_NOBITS {
+            continue;
+        }
+
+        let (data, _) = elf.section_data(header)?;
+        writeln!(out, "\n\nDisassembly of section {section_name}:")?;
+        match elf.ehdr.class {
+            #[cfg(feature = "128-bit")]
+            Class::ELF64 if options.rv128 => disassemble_section::<brisc_isa::Rv128>(
+                section_name,
+                header,
+                data,
+                &symbols,
+                options,
+                width,
+                out,
+            )?,
+            #[cfg(feature = "64-bit")]
+            Class::ELF64 => disassemble_section::<brisc_isa::Rv64>(
+                section_name,
+                header,
+                data,
+                &symbols,
+                options,
+                width,
+                out,
+            )?,
+            _ => disassemble_section::<Rv32>(
+                section_name,
+                header,
+                data,
+                &symbols,
+                options,
+                width,
+                out,
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the disassembly listing of a single section, decoding instructions for a hart with
+/// register width `X`.
+fn disassemble_section<X: Xlen>(
+    section_name: &str,
+    header: &SectionHeader,
+    data: &[u8],
+    symbols: &BTreeMap<XWord, String>,
+    options: &Options,
+    width: usize,
+    out: &mut impl Write,
+) -> Result<(), DumpError> {
+    let base = header.sh_addr as XWord;
+    if !symbols.contains_key(&base) {
+        writeln!(out, "\n{base:0width$x} <{section_name}>:")?;
+    }
+
+    let mut offset = 0;
+    while offset < data.len() {
+        let address = base + offset as XWord;
+        if let Some(symbol) = symbols.get(&address) {
+            writeln!(out, "\n{address:0width$x} <{symbol}>:")?;
+        }
+
+        let remaining = &data[offset..];
+        if remaining.len() < 2 {
+            writeln!(out, "{address:>12x}:\t{:02x}\t.byte\t{:#04x}", remaining[0], remaining[0])?;
+            break;
+        }
+
+        let low = u16::from_le_bytes([remaining[0], remaining[1]]) as Word;
+        let compressed = InstructionLength::of(low) == Some(InstructionLength::Compressed);
+        let (raw, len) = if compressed || remaining.len() < 4 {
+            (low, 2)
+        } else {
+            (Word::from_le_bytes([remaining[0], remaining[1], remaining[2], remaining[3]]), 4)
+        };
+        let encoding = if len == 2 { format!("{raw:04x}") } else { format!("{raw:08x}") };
+        write!(out, "{address:>12x}:\t{encoding:<18}\t")?;
+
+        match decode::<X>(raw, len, address, options) {
+            Ok((text, target)) => {
+                let (mnemonic, operands) = text.split_once(' ').unwrap_or((&text, ""));
+                write!(out, "{mnemonic}")?;
+                if !operands.is_empty() {
+                    write!(out, "\t{operands}")?;
+                }
+                if let Some(target) = target {
+                    match symbols.range(..=target).next_back() {
+                        Some((&base, symbol)) if base == target => write!(out, " <{symbol}>")?,
+                        Some((&base, symbol)) => write!(out, " <{symbol}+{:#x}>", target - base)?,
+                        None => {}
+                    }
+                }
+                writeln!(out)?;
+            }
+            Err(err) => {
+                let directive = if len == 2 { ".half" } else { ".word" };
+                writeln!(
+                    out,
+                    "{directive}\t{raw:#0w$x} # {}",
+                    comment(&err.kind),
+                    w = len * 2 + 2
+                )?;
+            }
+        }
+
+        offset += len;
+    }
+
+    Ok(())
+}
+
+/// Decodes and renders an instruction, returning its assembly text along with the target of a
+/// branch or jump.
+fn decode<X: Xlen>(
+    raw: Word,
+    len: usize,
+    pc: XWord,
+    options: &Options,
+) -> Result<(String, Option<XWord>), InstructionDecodeError> {
+    let mut display = DisplayOptions::new().with_pc(pc);
+    if options.numeric {
+        display = display.with_numeric_registers();
+    }
+    if options.no_aliases {
+        display = display.without_aliases();
+    }
+
+    let decoded = DecodedInstruction::decode_strict_for::<X>(raw)?;
+    if decoded.size() != len {
+        // A standard-length instruction truncated by the end of the section.
+        return Err(InstructionDecodeError {
+            raw,
+            kind: DecodeErrorKind::InvalidOpcode((raw & 0x7F) as u8),
+        });
+    }
+    let target = branch_target(&decoded.instruction, pc).map(X::truncate);
+
+    #[cfg(feature = "c")]
+    if let Some(compressed) = decoded.compressed() {
+        return Ok((compressed.disassemble(display).to_string(), target));
+    }
+    Ok((decoded.instruction.disassemble(display).to_string(), target))
+}
+
+/// Returns the comment printed after an encoding that could not be decoded.
+fn comment(kind: &DecodeErrorKind) -> String {
+    match kind {
+        DecodeErrorKind::FeatureDisabled { .. } | DecodeErrorKind::XlenTooNarrow { .. } => {
+            kind.to_string()
+        }
+        _ => "unknown instruction".to_string(),
+    }
+}
+
+/// Returns the address targeted by a branch or direct jump.
+const fn branch_target(instruction: &Instruction, pc: XWord) -> Option<XWord> {
+    match instruction {
+        Instruction::Branch(b, _) => Some(pc.wrapping_add(b.imm)),
+        Instruction::Jal(j) => Some(pc.wrapping_add(j.imm)),
+        _ => None,
+    }
+}
+
+/// Collects the named symbols of an ELF file, keyed by address.
+fn symbols(elf: &ElfBytes<'_, AnyEndian>) -> Result<BTreeMap<XWord, String>, DumpError> {
+    let mut symbols = BTreeMap::new();
+    let Some((table, strtab)) = elf.symbol_table()? else {
+        return Ok(symbols);
+    };
+
+    for symbol in table.iter() {
+        if symbol.st_shndx == SHN_UNDEF ||
+            symbol.st_symtype() == STT_SECTION ||
+            symbol.st_symtype() == STT_FILE
+        {
+            continue;
+        }
+
+        // Skip empty names and mapping symbols such as `$x`.
+        let name = strtab.get(symbol.st_name as usize)?;
+        if name.is_empty() || name.starts_with('$') {
+            continue;
+        }
+        symbols.entry(symbol.st_value as XWord).or_insert_with(|| name.to_string());
+    }
+
+    Ok(symbols)
+}
+
+/// Returns the name of a program header type.
+fn segment_type(p_type: u32) -> String {
+    match p_type {
+        elf::abi::PT_NULL => "NULL".to_string(),
+        elf::abi::PT_LOAD => "LOAD".to_string(),
+        elf::abi::PT_DYNAMIC => "DYNAMIC".to_string(),
+        elf::abi::PT_INTERP => "INTERP".to_string(),
+        elf::abi::PT_NOTE => "NOTE".to_string(),
+        elf::abi::PT_PHDR => "PHDR".to_string(),
+        elf::abi::PT_TLS => "TLS".to_string(),
+        elf::abi::PT_GNU_STACK => "GNU_STACK".to_string(),
+        elf::abi::PT_GNU_RELRO => "GNU_RELRO".to_string(),
+        other => format!("{other:#x}"),
+    }
+}
+
+/// Renders program header flags in `rwx` form.
+fn segment_flags(flags: u32) -> String {
+    [(elf::abi::PF_R, 'r'), (elf::abi::PF_W, 'w'), (elf::abi::PF_X, 'x')]
+        .iter()
+        .map(|&(flag, c)| if flags & flag != 0 { c } else { '-' })
+        .collect()
+}
+
+/// Renders section header flags as `readelf` does.
+fn section_flags(flags: u64) -> String {
+    [(SHF_WRITE, 'W'), (SHF_ALLOC, 'A'), (SHF_EXECINSTR, 'X')]
+        .iter()
+        .filter(|&&(flag, _)| flags & flag as u64 != 0)
+        .map(|&(_, c)| c)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Returns the `(address, encoding)` pairs and symbol labels of an `objdump -d` listing.
+    fn listing(dump: &str) -> (HashSet<(String, String)>, HashSet<String>) {
+        let mut instructions = HashSet::new();
+        let mut labels = HashSet::new();
+        for line in dump.lines() {
+            if line.ends_with(">:") {
+                labels.insert(line.to_string());
+            } else if let Some((address, rest)) = line.trim_start().split_once(":\t") {
+                let encoding = rest.split_whitespace().next().unwrap_or_default();
+                instructions.insert((address.to_string(), encoding.to_string()));
+            }
+        }
+        (instructions, labels)
+    }
+
+    #[test]
+    fn test_dump_matches_objdump() {
+        let mut paths = vec!["../../rv-tests/bin/rv32ui-p-add"];
+        if cfg!(feature = "64-bit") {
+            paths.push("../../rv-tests/bin/rv64ui-p-add");
+        }
+
+        for path in paths {
+            let raw = std::fs::read(path).unwrap();
+            let expected = std::fs::read_to_string(format!("{path}.dump")).unwrap();
+
+            let mut out = Vec::new();
+            dump(path, &raw, &Options::default(), &mut out).unwrap();
+            let out = String::from_utf8(out).unwrap();
+
+            let (expected_instructions, expected_labels) = listing(&expected);
+            let (instructions, labels) = listing(&out);
+            assert!(!expected_instructions.is_empty());
+            assert!(expected_instructions.is_subset(&instructions));
+            assert!(expected_labels.is_subset(&labels));
+
+            // The first instruction jumps over the trap vector, which reads CSRs.
+            assert!(out.contains("80000000:\t0500006f          \tj\t0x80000050 <reset_vector>"));
+            assert!(out.contains(
+                "80000004:\t34202f73          \t.word\t0x34202f73 # unknown instruction"
+            ));
+            assert!(out.contains("LOAD"));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "64-bit")]
+    fn test_decode_rv32() {
+        // ld a0, 0(sp)
+        let err = decode::<Rv32>(0x00013503, 4, 0, &Options::default()).unwrap_err();
+        assert_eq!(comment(&err.kind), "`ld` is not available on RV32");
+    }
+
+    #[test]
+    #[cfg(feature = "128-bit")]
+    fn test_decode_rv128() {
+        // slli a0, a0, 64
+        let options = Options::default();
+        assert!(decode::<brisc_isa::Rv64>(0x04051513, 4, 0, &options).is_err());
+        let (text, _) = decode::<brisc_isa::Rv128>(0x04051513, 4, 0, &options).unwrap();
+        assert_eq!(text, "slli a0, a0, 64");
+
+        // ELFCLASS64 files are read as RV64 programs unless RV128 is requested.
+        let raw = std::fs::read("../../rv-tests/bin/rv64ui-p-add").unwrap();
+        let mut out = Vec::new();
+        dump("rv64ui-p-add", &raw, &Options { rv128: true, ..options }, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("file format elf64-littleriscv"));
+    }
+
+    #[test]
+    fn test_dump_missing_section() {
+        let raw = std::fs::read("../../rv-tests/bin/rv64ui-p-add").unwrap();
+        let options = Options { section: Some(".missing".to_string()), ..Default::default() };
+        let err = dump("rv64ui-p-add", &raw, &options, &mut Vec::new()).unwrap_err();
+        assert!(matches!(err, DumpError::MissingSection(_)));
+    }
+
+    #[test]
+    fn test_comment() {
+        // mul a0, a1, a2
+        let result = DecodedInstruction::decode_strict(0x02c58533);
+        assert_eq!(result.is_ok(), cfg!(feature = "m"));
+        if let Err(err) = result {
+            assert_eq!(comment(&err.kind), "`mul` requires the `m` feature");
+        }
+        // csrr t5, mcause
+        let err = DecodedInstruction::decode_strict(0x34202f73).unwrap_err();
+        assert_eq!(comment(&err.kind), "unknown instruction");
+    }
+}
@@ -0,0 +1,74 @@
+#![doc = include_str!("../README.md")]
+#![warn(missing_debug_implementations, unreachable_pub, rustdoc::all)]
+#![deny(unused_must_use, rust_2018_idioms)]
+
+use std::{env, fs, io, process::ExitCode};
+
+mod dump;
+use dump::{dump, Options};
+
+/// The usage message printed by `--help`.
+const USAGE: &str = "\
+Usage: brisc-objdump [options] <file>...
+
+Disassembles the executable sections of RISC-V ELF files.
+
+Options:
+  -M numeric, --numeric         Render registers by number rather than by ABI name
+  -M no-aliases, --no-aliases   Render canonical instructions rather than pseudo-instructions
+  -j, --section <name>          Only disassemble the named section
+      --rv128                   Disassemble ELFCLASS64 files as RV128 programs (requires the
+                                `128-bit` feature)
+  -h, --help                    Print this message
+";
+
+fn main() -> ExitCode {
+    match run(env::args().skip(1)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("brisc-objdump: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Parses the command line arguments and dumps each input file to stdout.
+fn run(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut options = Options::default();
+    let mut files = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            "--numeric" => options.numeric = true,
+            "--no-aliases" => options.no_aliases = true,
+            "-M" => match args.next().as_deref() {
+                Some("numeric") => options.numeric = true,
+                Some("no-aliases") => options.no_aliases = true,
+                Some(other) => return Err(format!("Unrecognized disassembler option: {other}")),
+                None => return Err("Missing disassembler option after -M".to_string()),
+            },
+            "-j" | "--section" => {
+                options.section = Some(args.next().ok_or("Missing section name after -j")?);
+            }
+            #[cfg(feature = "128-bit")]
+            "--rv128" => options.rv128 = true,
+            flag if flag.starts_with('-') => return Err(format!("Unrecognized option: {flag}")),
+            _ => files.push(arg),
+        }
+    }
+
+    if files.is_empty() {
+        return Err(format!("No input files\n\n{USAGE}"));
+    }
+
+    let mut stdout = io::stdout().lock();
+    for file in files {
+        let raw = fs::read(&file).map_err(|e| format!("{file}: {e}"))?;
+        dump(&file, &raw, &options, &mut stdout).map_err(|e| format!("{file}: {e}"))?;
+    }
+
+    Ok(())
+}